@@ -0,0 +1,163 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Benchmarks for updating the LED effects of a profile and for traversing the LEDs the effects
+//! produce, this is what the LED profile runner does for every frame of telemetry.
+
+use std::num::NonZeroUsize;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use lmx_cli::led::{
+    profiles::{rpm::RpmContainer, LedProfile},
+    state::{effect::Effect, groups::GroupState, rpm::gradient::RpmLedState, LedEffect},
+};
+use serde_json::json;
+use simetry::{Moment, RacingFlags};
+use uom::si::{angular_velocity::revolution_per_minute, f64::AngularVelocity};
+
+struct SimState {
+    rpm: f64,
+}
+
+impl Moment for SimState {
+    fn vehicle_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+        Some(AngularVelocity::new::<revolution_per_minute>(self.rpm))
+    }
+
+    fn vehicle_max_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+        Some(AngularVelocity::new::<revolution_per_minute>(8000.0))
+    }
+
+    fn flags(&self) -> Option<RacingFlags> {
+        Some(RacingFlags {
+            yellow: true,
+            ..Default::default()
+        })
+    }
+}
+
+fn rpm_container() -> serde_json::Value {
+    json!({
+        "UsePercent": false,
+        "PercentMin": 85.0,
+        "PercentMax": 95.0,
+        "RPMMin": 1000.0,
+        "RPMMax": 7800.0,
+        "BlinkEnabled": true,
+        "BlinkDelay": 100,
+        "StartColor": "Lime",
+        "EndColor": "Red",
+        "LedCount": 16,
+        "StartPosition": 1,
+        "ContainerType": "RPMContainer",
+        "IsEnabled": true
+    })
+}
+
+/// A profile with a RPM gradient and a nested group of flag containers on top of it.
+fn profile() -> LedProfile {
+    let flag = |color: &str, container_type: &str| {
+        json!({
+            "LedCount": 4,
+            "Color": color,
+            "BlinkEnabled": true,
+            "BlinkDelay": 150,
+            "StartPosition": 1,
+            "ContainerType": container_type,
+            "IsEnabled": true
+        })
+    };
+
+    let profile = json!({
+        "Name": "Benchmark",
+        "ProfileId": "c1f9e7a4-2b6d-4e0a-8f3c-5d7b9a1e2c48",
+        "GlobalBrightness": 1.0,
+        "UseProfileBrightness": false,
+        "LedContainers": [
+            rpm_container(),
+            {
+                "LedContainers": [
+                    flag("Yellow", "YellowFlagContainer"),
+                    flag("Blue", "BlueFlagContainer"),
+                    {
+                        "LedContainers": [flag("White", "WhiteFlagContainer")],
+                        "StartPosition": 1,
+                        "ContainerType": "GroupContainer",
+                        "IsEnabled": true
+                    }
+                ],
+                "StackLeftToRight": true,
+                "StartPosition": 1,
+                "ContainerType": "GroupContainer",
+                "IsEnabled": true
+            }
+        ]
+    });
+
+    serde_json::from_value(profile).expect("The benchmark profile should be valid")
+}
+
+fn group_update(c: &mut Criterion) {
+    let sim_state = SimState { rpm: 6500.0 };
+
+    let mut boxed = GroupState::root(profile());
+    c.bench_function("group update", |b| {
+        b.iter(|| boxed.update(black_box(&sim_state)))
+    });
+
+    let mut static_group: GroupState<Effect> =
+        GroupState::from_profile_at(profile(), NonZeroUsize::MIN);
+    c.bench_function("static group update", |b| {
+        b.iter(|| static_group.update(black_box(&sim_state)))
+    });
+}
+
+fn group_leds(c: &mut Criterion) {
+    let mut group = GroupState::root(profile());
+    group.update(&SimState { rpm: 6500.0 });
+
+    c.bench_function("group leds traversal", |b| {
+        b.iter(|| {
+            black_box(&group)
+                .leds()
+                .map(|state| state.leds().len())
+                .sum::<usize>()
+        })
+    });
+}
+
+fn rpm_gradient_update(c: &mut Criterion) {
+    let container: RpmContainer =
+        serde_json::from_value(rpm_container()).expect("The RPM container should be valid");
+    let mut state = RpmLedState::with_start_position(container, NonZeroUsize::MIN);
+
+    let mut rpm = 1000.0;
+
+    c.bench_function("rpm gradient update", |b| {
+        b.iter(|| {
+            // Sweep through the RPM range so every update changes the LEDs.
+            rpm = if rpm >= 8000.0 { 1000.0 } else { rpm + 50.0 };
+            state.update(black_box(&SimState { rpm }));
+        })
+    });
+}
+
+criterion_group!(benches, group_update, group_leds, rpm_gradient_update);
+criterion_main!(benches);
@@ -0,0 +1,180 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Arc, PrimitiveStyle},
+};
+
+/// A gauge in the shape of an arc, commonly used to display the RPM of the engine.
+///
+/// The gauge is drawn as a background arc spanning from the start to the end angle, on top of
+/// which a second arc is drawn which covers a part of the background arc proportional to the
+/// value of the gauge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArcGauge {
+    /// The center of the arc.
+    pub center: Point,
+    /// The outer diameter of the arc.
+    pub diameter: u32,
+    /// The width of the arc, in pixels.
+    pub thickness: u32,
+    /// The angle where the arc starts, zero degrees being at the 3 o'clock position, angles
+    /// increase clockwise.
+    pub start_angle: Angle,
+    /// The angle where the arc ends when the gauge is full.
+    pub end_angle: Angle,
+    /// The color of the part of the arc that represents the value.
+    pub color: Rgb565,
+    /// The color of the remainder of the arc.
+    pub background_color: Rgb565,
+}
+
+impl ArcGauge {
+    /// Draw the gauge with the given value onto the given draw target.
+    ///
+    /// The value should be between `0.0` and `1.0`, values outside of this range are clamped.
+    pub fn draw<D>(&self, value: f32, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let value = value.clamp(0.0, 1.0);
+        let sweep = self.end_angle - self.start_angle;
+        let sweep_for = |value: f32| Angle::from_degrees(sweep.to_degrees() * value);
+
+        // Embedded graphics strokes the arc around the given diameter, so shrink it to keep the
+        // whole gauge inside of the configured diameter.
+        let diameter = self.diameter.saturating_sub(self.thickness);
+
+        Arc::with_center(self.center, diameter, self.start_angle, sweep)
+            .into_styled(PrimitiveStyle::with_stroke(
+                self.background_color,
+                self.thickness,
+            ))
+            .draw(target)?;
+
+        if value > 0.0 {
+            Arc::with_center(self.center, diameter, self.start_angle, sweep_for(value))
+                .into_styled(PrimitiveStyle::with_stroke(self.color, self.thickness))
+                .draw(target)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::convert::Infallible;
+
+    use embedded_graphics::primitives::Rectangle;
+
+    use super::*;
+
+    /// A simple in-memory draw target.
+    pub struct Framebuffer {
+        size: Size,
+        pixels: Vec<Rgb565>,
+    }
+
+    impl Framebuffer {
+        pub fn new(size: Size) -> Self {
+            Self {
+                size,
+                pixels: vec![Rgb565::BLACK; (size.width * size.height) as usize],
+            }
+        }
+
+        /// Count the pixels which have the given color.
+        pub fn count(&self, color: Rgb565) -> usize {
+            self.pixels.iter().filter(|pixel| **pixel == color).count()
+        }
+
+        /// Get the pixels of the given area, row by row.
+        pub fn area(&self, area: &Rectangle) -> impl Iterator<Item = Rgb565> + '_ {
+            area.points()
+                .filter_map(|point| self.index(point).map(|index| self.pixels[index]))
+        }
+
+        fn index(&self, point: Point) -> Option<usize> {
+            let x = u32::try_from(point.x).ok()?;
+            let y = u32::try_from(point.y).ok()?;
+
+            (x < self.size.width && y < self.size.height)
+                .then_some((y * self.size.width + x) as usize)
+        }
+    }
+
+    impl OriginDimensions for Framebuffer {
+        fn size(&self) -> Size {
+            self.size
+        }
+    }
+
+    impl DrawTarget for Framebuffer {
+        type Color = Rgb565;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            for Pixel(point, color) in pixels {
+                if let Some(index) = self.index(point) {
+                    self.pixels[index] = color;
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn filled_pixels() {
+        let gauge = ArcGauge {
+            center: Point::new(32, 32),
+            diameter: 60,
+            thickness: 6,
+            start_angle: Angle::from_degrees(135.0),
+            end_angle: Angle::from_degrees(405.0),
+            color: Rgb565::RED,
+            background_color: Rgb565::WHITE,
+        };
+
+        let filled_pixels = [0.0, 0.5, 1.0].map(|value| {
+            let mut framebuffer = Framebuffer::new(Size::new(64, 64));
+            gauge
+                .draw(value, &mut framebuffer)
+                .expect("Drawing to a framebuffer should never fail");
+
+            framebuffer.count(Rgb565::RED)
+        });
+
+        assert_eq!(
+            filled_pixels[0], 0,
+            "An empty gauge should not fill any pixels"
+        );
+        assert!(
+            filled_pixels[0] < filled_pixels[1] && filled_pixels[1] < filled_pixels[2],
+            "The number of filled pixels should increase with the value: {filled_pixels:?}"
+        );
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
+use simetry::Moment;
+use uom::si::{
+    f64::Velocity,
+    velocity::{kilometer_per_hour, mile_per_hour},
+};
+
+use super::seven_segment::SevenSegmentStyle;
+use crate::led::state::MomentExt;
+
+/// The unit the speed should be displayed in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpeedUnit {
+    #[default]
+    KilometersPerHour,
+    MilesPerHour,
+}
+
+impl SpeedUnit {
+    fn convert(self, velocity: Velocity) -> f64 {
+        match self {
+            SpeedUnit::KilometersPerHour => velocity.get::<kilometer_per_hour>(),
+            SpeedUnit::MilesPerHour => velocity.get::<mile_per_hour>(),
+        }
+    }
+}
+
+/// A widget displaying the currently selected gear and the speed of the car using large
+/// seven-segment readouts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GearSpeedWidget {
+    /// The top left corner of the gear readout.
+    pub gear_position: Point,
+    /// The style of the gear readout.
+    pub gear_style: SevenSegmentStyle,
+    /// The top left corner of the speed readout.
+    pub speed_position: Point,
+    /// The style of the speed readout.
+    pub speed_style: SevenSegmentStyle,
+    /// The unit the speed should be displayed in.
+    pub speed_unit: SpeedUnit,
+}
+
+impl GearSpeedWidget {
+    fn format_speed(&self, velocity: Option<Velocity>) -> String {
+        match velocity {
+            Some(velocity) => format!("{:.0}", self.speed_unit.convert(velocity).abs()),
+            None => "-".to_owned(),
+        }
+    }
+
+    /// Draw the gear and speed of the given sim state onto the given draw target.
+    pub fn draw<D>(&self, sim_state: &dyn Moment, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let gear = sim_state.gear_label();
+        let speed = self.format_speed(sim_state.vehicle_velocity());
+
+        self.gear_style
+            .draw_text(&gear, self.gear_position, target)?;
+        self.speed_style
+            .draw_text(&speed, self.speed_position, target)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use embedded_graphics::primitives::Rectangle;
+    use similar_asserts::assert_eq;
+
+    use crate::dashboard::gauge::test::Framebuffer;
+
+    use super::*;
+
+    struct SimState;
+
+    impl Moment for SimState {
+        fn vehicle_gear(&self) -> Option<i8> {
+            Some(3)
+        }
+
+        fn vehicle_velocity(&self) -> Option<Velocity> {
+            Some(Velocity::new::<kilometer_per_hour>(123.4))
+        }
+    }
+
+    fn widget() -> GearSpeedWidget {
+        GearSpeedWidget {
+            gear_position: Point::new(10, 10),
+            gear_style: SevenSegmentStyle {
+                character_size: Size::new(40, 80),
+                thickness: 8,
+                color: Rgb565::WHITE,
+            },
+            speed_position: Point::new(80, 10),
+            speed_style: SevenSegmentStyle {
+                character_size: Size::new(20, 40),
+                thickness: 4,
+                color: Rgb565::YELLOW,
+            },
+            speed_unit: SpeedUnit::KilometersPerHour,
+        }
+    }
+
+    #[test]
+    fn formatting() {
+        let widget = widget();
+
+        let speed = Some(Velocity::new::<kilometer_per_hour>(100.0));
+        assert_eq!(widget.format_speed(speed), "100");
+
+        let widget = GearSpeedWidget {
+            speed_unit: SpeedUnit::MilesPerHour,
+            ..widget
+        };
+        assert_eq!(widget.format_speed(speed), "62");
+    }
+
+    #[test]
+    fn drawing() {
+        let widget = widget();
+        let mut framebuffer = Framebuffer::new(Size::new(160, 100));
+
+        widget
+            .draw(&SimState, &mut framebuffer)
+            .expect("Drawing to a framebuffer should never fail");
+
+        let gear_area = Rectangle::new(widget.gear_position, widget.gear_style.text_size("3"));
+        let speed_area = Rectangle::new(widget.speed_position, widget.speed_style.text_size("123"));
+
+        assert!(
+            framebuffer
+                .area(&gear_area)
+                .any(|pixel| pixel == Rgb565::WHITE),
+            "The gear should have been drawn in the gear area"
+        );
+        assert!(
+            framebuffer
+                .area(&speed_area)
+                .any(|pixel| pixel == Rgb565::YELLOW),
+            "The speed should have been drawn in the speed area"
+        );
+        assert_eq!(
+            framebuffer.count(Rgb565::WHITE),
+            framebuffer
+                .area(&gear_area)
+                .filter(|pixel| *pixel == Rgb565::WHITE)
+                .count(),
+            "Nothing should have been drawn outside of the gear area using the gear color"
+        );
+    }
+}
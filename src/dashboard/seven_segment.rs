@@ -0,0 +1,181 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+
+/// The segments of a seven-segment digit, in the usual `a` to `g` order.
+///
+/// The `a` segment is the top one, the segments then continue clockwise, `g` is the middle one.
+type Segments = [bool; 7];
+
+/// The style of a seven-segment readout, which is used to draw large numbers that remain legible
+/// from a distance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SevenSegmentStyle {
+    /// The size of a single character.
+    pub character_size: Size,
+    /// The width of a segment, in pixels.
+    pub thickness: u32,
+    /// The color of the segments that are turned on.
+    pub color: Rgb565,
+}
+
+impl SevenSegmentStyle {
+    /// Get the segments that need to be turned on to display the given character.
+    ///
+    /// Digits, the `-` sign, and the `N` and `R` letters are supported, `None` is returned for
+    /// every other character.
+    fn segments(character: char) -> Option<Segments> {
+        let segments = match character {
+            '0' => [true, true, true, true, true, true, false],
+            '1' => [false, true, true, false, false, false, false],
+            '2' => [true, true, false, true, true, false, true],
+            '3' => [true, true, true, true, false, false, true],
+            '4' => [false, true, true, false, false, true, true],
+            '5' => [true, false, true, true, false, true, true],
+            '6' => [true, false, true, true, true, true, true],
+            '7' => [true, true, true, false, false, false, false],
+            '8' => [true; 7],
+            '9' => [true, true, true, true, false, true, true],
+            '-' => [false, false, false, false, false, false, true],
+            'N' | 'n' => [false, false, true, false, true, false, true],
+            'R' | 'r' => [false, false, false, false, true, false, true],
+            _ => return None,
+        };
+
+        Some(segments)
+    }
+
+    /// Get the area each segment covers, for a character with the given top left corner.
+    fn segment_areas(&self, top_left: Point) -> [Rectangle; 7] {
+        let Size { width, height } = self.character_size;
+        let thickness = self.thickness;
+        let half = height / 2;
+
+        let horizontal = Size::new(width.saturating_sub(2 * thickness), thickness);
+        let upper_vertical = Size::new(thickness, half.saturating_sub(thickness));
+        let lower_vertical = Size::new(thickness, height.saturating_sub(thickness + half));
+
+        let point = |x: u32, y: u32| top_left + Point::new(x as i32, y as i32);
+        let right = width.saturating_sub(thickness);
+
+        [
+            Rectangle::new(point(thickness, 0), horizontal),
+            Rectangle::new(point(right, thickness), upper_vertical),
+            Rectangle::new(point(right, half), lower_vertical),
+            Rectangle::new(
+                point(thickness, height.saturating_sub(thickness)),
+                horizontal,
+            ),
+            Rectangle::new(point(0, half), lower_vertical),
+            Rectangle::new(point(0, thickness), upper_vertical),
+            Rectangle::new(
+                point(thickness, half.saturating_sub(thickness / 2)),
+                horizontal,
+            ),
+        ]
+    }
+
+    /// The size of the given text, if drawn using this style.
+    pub fn text_size(&self, text: &str) -> Size {
+        let count = text.chars().count() as u32;
+        let spacing = count.saturating_sub(1) * self.thickness;
+
+        Size::new(
+            count * self.character_size.width + spacing,
+            self.character_size.height,
+        )
+    }
+
+    /// Draw the given text with the top left corner at the given position.
+    ///
+    /// Unsupported characters are left blank.
+    pub fn draw_text<D>(&self, text: &str, position: Point, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let advance = (self.character_size.width + self.thickness) as i32;
+
+        for (index, character) in text.chars().enumerate() {
+            let Some(segments) = Self::segments(character) else {
+                continue;
+            };
+
+            let top_left = position + Point::new(index as i32 * advance, 0);
+
+            for (area, _) in self
+                .segment_areas(top_left)
+                .iter()
+                .zip(segments)
+                .filter(|(_, enabled)| *enabled)
+            {
+                area.into_styled(PrimitiveStyle::with_fill(self.color))
+                    .draw(target)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dashboard::gauge::test::Framebuffer;
+
+    use super::*;
+
+    #[test]
+    fn segment_count() {
+        let style = SevenSegmentStyle {
+            character_size: Size::new(10, 20),
+            thickness: 2,
+            color: Rgb565::WHITE,
+        };
+
+        let lit_pixels = |text: &str| {
+            let mut framebuffer = Framebuffer::new(Size::new(64, 32));
+            style
+                .draw_text(text, Point::zero(), &mut framebuffer)
+                .expect("Drawing to a framebuffer should never fail");
+
+            framebuffer.count(Rgb565::WHITE)
+        };
+
+        assert_eq!(
+            lit_pixels("1"),
+            2 * (2 * 8),
+            "A 1 consists of two 2x8 vertical segments"
+        );
+        assert!(
+            lit_pixels("8") > lit_pixels("0"),
+            "An 8 should light up more segments than a 0"
+        );
+        assert_eq!(
+            lit_pixels("?"),
+            0,
+            "Unsupported characters should be left blank"
+        );
+        assert_eq!(style.text_size("123"), Size::new(34, 20));
+    }
+}
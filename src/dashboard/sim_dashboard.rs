@@ -0,0 +1,783 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::{
+    future::Future,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context as _, Result};
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
+use simetry::{assetto_corsa_competizione, Moment};
+
+use super::gear_speed::GearSpeedWidget;
+use crate::{
+    devices::{FrameApplier, Heartbeat, LedOutput, Stats, USBD480Display},
+    led::{
+        profiles::LedProfile,
+        state::{
+            groups::{BuildWarningKind, GroupState},
+            idle::{IdleDetector, IdleLedState},
+            DriverAssists, LedEffect, SharedConfig, UpdateContext,
+        },
+    },
+};
+
+/// A source of sim states, for example the telemetry client of a game.
+pub trait MomentSource {
+    /// The type of the sim states this source produces.
+    type Moment: Moment;
+
+    /// Wait for the next sim state, returns `None` once the source is exhausted.
+    ///
+    /// The returned future might be dropped before it completes if no sim state arrives for a
+    /// while, so no sim state should get lost if that happens.
+    fn next_moment(&mut self) -> impl Future<Output = Option<Self::Moment>>;
+
+    /// Get the state of the driver assists from a sim state of this source.
+    ///
+    /// The [`Moment`] doesn't contain the state of the driver assists, sources which know about
+    /// them read it from their own sim states. By default the state is unknown and the assists
+    /// are considered to be disengaged.
+    fn driver_assists(_moment: &Self::Moment) -> Option<DriverAssists> {
+        None
+    }
+}
+
+impl MomentSource for assetto_corsa_competizione::Client {
+    type Moment = assetto_corsa_competizione::SimState;
+
+    async fn next_moment(&mut self) -> Option<Self::Moment> {
+        self.next_sim_state().await
+    }
+}
+
+/// A display showing the gear and speed of the car.
+struct Display<D> {
+    target: D,
+    widget: GearSpeedWidget,
+}
+
+impl<D> Display<D>
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Into<anyhow::Error>,
+{
+    fn draw(&mut self, sim_state: &dyn Moment) -> Result<()> {
+        self.target
+            .clear(Rgb565::BLACK)
+            .map_err(Into::into)
+            .context("Could not clear the display")?;
+        self.widget
+            .draw(sim_state, &mut self.target)
+            .map_err(Into::into)
+            .context("Could not draw the gear and speed on the display")
+    }
+}
+
+/// Copy the given stats into the shared stats handle, if there is one, see
+/// [`SimDashboard::with_shared_stats()`].
+fn share_stats(shared_stats: Option<&Arc<Mutex<Stats>>>, stats: Stats) {
+    if let Some(Ok(mut shared_stats)) = shared_stats.map(|shared_stats| shared_stats.lock()) {
+        *shared_stats = stats;
+    }
+}
+
+/// Create the effects of the given profile, using the containers of the given car if there is
+/// one, and log the problems found in the containers.
+///
+/// Layering effects is a normal part of most profiles, so overlaps are only logged at the debug
+/// level. Effects which don't fit on the output get truncated and the [`FrameApplier`] warns
+/// about them once they're applied.
+fn build_effects(profile: &LedProfile, car_id: Option<&str>) -> GroupState {
+    let (effects, warnings) = match car_id {
+        Some(car_id) => GroupState::for_car_with_warnings(profile.clone(), car_id),
+        None => GroupState::root_with_warnings(profile.clone()),
+    };
+
+    for warning in warnings {
+        match warning.kind {
+            BuildWarningKind::Overlap { .. } => tracing::debug!("{warning}"),
+            _ => tracing::warn!("{warning}"),
+        }
+    }
+
+    effects
+}
+
+/// The current time, taken from the clock of the async runtime so it can be paused in tests.
+fn now() -> Instant {
+    tokio::time::Instant::now().into_std()
+}
+
+/// A dashboard which ties a LED profile, a source of sim states, a LED device, and optionally a
+/// display together.
+///
+/// Every sim state the [`MomentSource`] produces updates the effects of the LED profile, the
+/// resulting LED states are applied to the [`LedOutput`] and the display is redrawn. While the
+/// source stalls, the effects keep running on the last sim state, and once no sim state was
+/// received for the idle timeout an idle animation takes over the LEDs.
+pub struct SimDashboard<S: MomentSource, O, D = USBD480Display> {
+    profile: LedProfile,
+    source: S,
+    output: O,
+    display: Option<Display<D>>,
+    effects: GroupState,
+    /// The configuration shared by all the effects, taken from the profile.
+    config: SharedConfig,
+    car_id: Option<String>,
+    frame_applier: FrameApplier,
+    heartbeat: Heartbeat<S::Moment>,
+    idle_detector: IdleDetector,
+    /// The animation shown while the dashboard is idle, `None` if the output has no LEDs.
+    idle_state: Option<IdleLedState>,
+    /// Were the effects paused since the dashboard became idle.
+    effects_paused: bool,
+    /// A handle which gets a copy of the [`Stats`] after every frame, see
+    /// [`SimDashboard::with_shared_stats()`].
+    shared_stats: Option<Arc<Mutex<Stats>>>,
+}
+
+impl<S, O> SimDashboard<S, O>
+where
+    S: MomentSource,
+    O: LedOutput,
+{
+    /// Create a new dashboard running the given LED profile on the given output, without a
+    /// display.
+    pub fn new(profile: LedProfile, source: S, output: O) -> Self {
+        let idle_state = NonZeroUsize::new(output.led_count()).map(|led_count| {
+            IdleLedState::new(NonZeroUsize::MIN, led_count, Self::IDLE_ANIMATION_CYCLE)
+        });
+
+        let mut idle_detector = IdleDetector::new(Self::DEFAULT_IDLE_TIMEOUT);
+        idle_detector.data_received(now());
+
+        Self {
+            effects: build_effects(&profile, None),
+            config: SharedConfig::from_profile(&profile),
+            profile,
+            source,
+            output,
+            display: None,
+            car_id: None,
+            frame_applier: FrameApplier::default(),
+            heartbeat: Heartbeat::default(),
+            idle_detector,
+            idle_state,
+            effects_paused: false,
+            shared_stats: None,
+        }
+    }
+}
+
+impl<S, O, D> SimDashboard<S, O, D>
+where
+    S: MomentSource,
+    O: LedOutput,
+{
+    /// How long we wait for telemetry before the idle animation takes over, unless a different
+    /// timeout is set using [`SimDashboard::with_idle_timeout()`].
+    pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+    /// How often the idle animation, or the effects using the last received sim state, are
+    /// updated while no telemetry is received.
+    const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+    /// How long it takes the idle animation to cycle through all the colors.
+    const IDLE_ANIMATION_CYCLE: Duration = Duration::from_secs(5);
+
+    /// Set how long the dashboard waits for telemetry before it shows an idle animation instead
+    /// of the last frame of the profile.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        let last_data = self.idle_detector.last_data();
+
+        self.idle_detector = IdleDetector::new(timeout);
+        self.idle_detector.data_received(last_data);
+
+        self
+    }
+
+    /// Set the minimal time between two frames which are sent to the output.
+    ///
+    /// The effects of the profile are still updated for every sim state, frames which change the
+    /// LEDs too soon after the last frame are dropped.
+    pub fn with_min_apply_interval(mut self, interval: Duration) -> Self {
+        self.frame_applier.set_min_interval(interval);
+        self
+    }
+
+    /// Copy the [`Stats`] of the dashboard into the given handle after every frame, so they can
+    /// be observed while the dashboard is running.
+    pub(crate) fn with_shared_stats(mut self, stats: Arc<Mutex<Stats>>) -> Self {
+        self.shared_stats = Some(stats);
+        self
+    }
+
+    /// Show the gear and speed of the car on the given display, using the given widget.
+    pub fn with_display<T>(self, display: T, widget: GearSpeedWidget) -> SimDashboard<S, O, T> {
+        SimDashboard {
+            profile: self.profile,
+            source: self.source,
+            output: self.output,
+            display: Some(Display {
+                target: display,
+                widget,
+            }),
+            effects: self.effects,
+            config: self.config,
+            car_id: self.car_id,
+            frame_applier: self.frame_applier,
+            heartbeat: self.heartbeat,
+            idle_detector: self.idle_detector,
+            idle_state: self.idle_state,
+            effects_paused: self.effects_paused,
+            shared_stats: self.shared_stats,
+        }
+    }
+
+    /// Switch to the car specific containers of the profile if the car changed.
+    fn update_car(&mut self, sim_state: &dyn Moment) {
+        let current_car_id = sim_state.vehicle_unique_id();
+
+        if current_car_id.as_deref() != self.car_id.as_deref() {
+            self.car_id = current_car_id.map(|id| id.to_string());
+
+            self.effects = build_effects(&self.profile, self.car_id.as_deref());
+            self.frame_applier.effects_replaced();
+        }
+    }
+
+    /// Forget the last sim state since the source was exhausted, the dashboard continues with a
+    /// new source afterwards, see [`SimDashboard::set_source()`].
+    pub(crate) fn connection_lost(&mut self) {
+        self.heartbeat.connection_lost();
+    }
+
+    /// Replace the source of the sim states, for example after reconnecting to the simulator.
+    pub(crate) fn set_source(&mut self, source: S) {
+        self.source = source;
+    }
+}
+
+impl<S, O, D> SimDashboard<S, O, D>
+where
+    S: MomentSource,
+    O: LedOutput,
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Into<anyhow::Error>,
+{
+    fn sim_state_received(&mut self, sim_state: S::Moment, now: Instant) -> Result<()> {
+        self.idle_detector.data_received(now);
+
+        if self.effects_paused {
+            self.effects.resume(now);
+            self.effects_paused = false;
+        }
+
+        self.update_car(&sim_state);
+
+        let assists = S::driver_assists(&sim_state);
+        let sim_state = self.heartbeat.set_moment(sim_state, assists);
+        let result = self.frame_applier.update_ctx_and_apply(
+            &mut self.output,
+            &mut self.effects,
+            &UpdateContext {
+                assists,
+                ..UpdateContext::at(sim_state, now).with_config(&self.config)
+            },
+        );
+        share_stats(self.shared_stats.as_ref(), self.frame_applier.stats);
+        result?;
+
+        if let Some(display) = &mut self.display {
+            display.draw(sim_state)?;
+        }
+
+        Ok(())
+    }
+
+    fn no_sim_state_received(&mut self, now: Instant) -> Result<()> {
+        if self.idle_detector.is_idle(now) {
+            // Freeze the blink timers of the profile at the point the telemetry stopped, so the
+            // profile resumes where it left off.
+            if !self.effects_paused {
+                self.effects.pause(self.idle_detector.last_data());
+                self.effects_paused = true;
+            }
+
+            if let Some(idle_state) = &mut self.idle_state {
+                idle_state.update();
+                self.output
+                    .apply_led_state(idle_state.state())
+                    .context("Could not show the idle animation")?;
+
+                // The LEDs don't show the last frame of the profile anymore, make sure the next
+                // frame gets applied once the telemetry resumes.
+                self.frame_applier.invalidate();
+            }
+        } else {
+            // Keep the blink timers running on the last sim state, the telemetry might just be
+            // stalling for a moment.
+            let result = self.heartbeat.beat(
+                &mut self.frame_applier,
+                &mut self.output,
+                &mut self.effects,
+                now,
+            );
+            share_stats(self.shared_stats.as_ref(), self.frame_applier.stats);
+            result?;
+        }
+
+        Ok(())
+    }
+
+    /// Drive the LEDs and the display using the sim states of the current source, until the
+    /// source is exhausted.
+    pub(crate) async fn run_source(&mut self) -> Result<()> {
+        loop {
+            let next_moment =
+                tokio::time::timeout(Self::IDLE_POLL_INTERVAL, self.source.next_moment()).await;
+            let now = now();
+
+            match next_moment {
+                Ok(Some(sim_state)) => self.sim_state_received(sim_state, now)?,
+                Ok(None) => return Ok(()),
+                Err(_) => self.no_sim_state_received(now)?,
+            }
+        }
+    }
+
+    /// Drive the LEDs and the display using the sim states of the source, until the source is
+    /// exhausted.
+    ///
+    /// Returns the [`Stats`] of the LED output once the source is exhausted.
+    pub async fn run(mut self) -> Result<Stats> {
+        self.run_source().await?;
+
+        Ok(self.frame_applier.stats)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{borrow::Cow, collections::VecDeque};
+
+    use serde_json::json;
+    use simetry::RacingFlags;
+    use similar_asserts::assert_eq;
+
+    use super::*;
+    use crate::{
+        dashboard::{
+            gauge::test::Framebuffer, gear_speed::SpeedUnit, seven_segment::SevenSegmentStyle,
+        },
+        led::state::LedState,
+        leds,
+    };
+
+    #[derive(Clone, Default)]
+    struct SimState {
+        car_id: Option<&'static str>,
+        gear: i8,
+        flags: RacingFlags,
+        abs_active: bool,
+    }
+
+    impl Moment for SimState {
+        fn vehicle_unique_id(&self) -> Option<Cow<'_, str>> {
+            self.car_id.map(Cow::Borrowed)
+        }
+
+        fn vehicle_gear(&self) -> Option<i8> {
+            Some(self.gear)
+        }
+
+        fn flags(&self) -> Option<RacingFlags> {
+            Some(self.flags.clone())
+        }
+    }
+
+    /// A source which produces a fixed list of sim states.
+    struct MockSource(VecDeque<SimState>);
+
+    impl MomentSource for MockSource {
+        type Moment = SimState;
+
+        async fn next_moment(&mut self) -> Option<Self::Moment> {
+            self.0.pop_front()
+        }
+    }
+
+    enum Step {
+        Moment(SimState),
+        /// Don't produce any sim state for the given amount of time.
+        Stall(Duration),
+    }
+
+    /// A source which produces sim states and stalls following a script.
+    struct ScriptedSource {
+        steps: VecDeque<Step>,
+        stalled_until: Option<tokio::time::Instant>,
+    }
+
+    impl MomentSource for ScriptedSource {
+        type Moment = SimState;
+
+        async fn next_moment(&mut self) -> Option<Self::Moment> {
+            while let Some(Step::Stall(duration)) = self.steps.front() {
+                // The future gets dropped if the dashboard stops waiting, so remember when the
+                // stall ends.
+                let until = *self
+                    .stalled_until
+                    .get_or_insert_with(|| tokio::time::Instant::now() + *duration);
+
+                tokio::time::sleep_until(until).await;
+
+                self.stalled_until = None;
+                self.steps.pop_front();
+            }
+
+            match self.steps.pop_front()? {
+                Step::Moment(sim_state) => Some(sim_state),
+                Step::Stall(_) => unreachable!("All the stalls should have been handled"),
+            }
+        }
+
+        fn driver_assists(moment: &Self::Moment) -> Option<DriverAssists> {
+            Some(DriverAssists {
+                abs_active: moment.abs_active,
+                tc_active: false,
+            })
+        }
+    }
+
+    #[derive(Default)]
+    struct MockOutput {
+        frames: Vec<Vec<LedState>>,
+        staged: Vec<LedState>,
+        cleared: usize,
+    }
+
+    impl LedOutput for MockOutput {
+        fn led_count(&self) -> usize {
+            2
+        }
+
+        fn stage(&mut self, led_state: &LedState) -> Result<usize> {
+            self.staged.push(led_state.clone());
+            Ok(led_state.leds().len())
+        }
+
+        fn present(&mut self) -> Result<()> {
+            self.frames.push(std::mem::take(&mut self.staged));
+            Ok(())
+        }
+
+        fn clear_all(&mut self) -> Result<()> {
+            self.cleared += 1;
+            Ok(())
+        }
+    }
+
+    /// A draw target which draws into a borrowed [`Framebuffer`], so the frame buffer can be
+    /// inspected once the dashboard is done.
+    struct BorrowedFramebuffer<'a>(&'a mut Framebuffer);
+
+    impl OriginDimensions for BorrowedFramebuffer<'_> {
+        fn size(&self) -> Size {
+            self.0.size()
+        }
+    }
+
+    impl DrawTarget for BorrowedFramebuffer<'_> {
+        type Color = Rgb565;
+        type Error = std::convert::Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            self.0.draw_iter(pixels)
+        }
+    }
+
+    fn flag(container_type: &str, color: &str) -> serde_json::Value {
+        json!({
+            "LedCount": 2,
+            "Color": color,
+            "BlinkEnabled": false,
+            "StartPosition": 1,
+            "ContainerType": container_type,
+            "IsEnabled": true
+        })
+    }
+
+    fn profile() -> LedProfile {
+        let profile = json!({
+            "Name": "Flags",
+            "ProfileId": "0d4b6f1e-2a3c-4e5f-8a9b-1c2d3e4f5a6b",
+            "GlobalBrightness": 1.0,
+            "UseProfileBrightness": false,
+            "LedContainers": [flag("YellowFlagContainer", "Yellow")],
+            "CarOverrides": {
+                "ferrari_296_gt3": [flag("BlueFlagContainer", "Blue")]
+            }
+        });
+
+        serde_json::from_value(profile).expect("We should be able to deserialize the profile")
+    }
+
+    fn widget() -> GearSpeedWidget {
+        GearSpeedWidget {
+            gear_position: Point::new(10, 10),
+            gear_style: SevenSegmentStyle {
+                character_size: Size::new(40, 80),
+                thickness: 8,
+                color: Rgb565::WHITE,
+            },
+            speed_position: Point::new(80, 10),
+            speed_style: SevenSegmentStyle {
+                character_size: Size::new(20, 40),
+                thickness: 4,
+                color: Rgb565::YELLOW,
+            },
+            speed_unit: SpeedUnit::KilometersPerHour,
+        }
+    }
+
+    #[tokio::test]
+    async fn run() {
+        let yellow_and_blue = RacingFlags {
+            yellow: true,
+            blue: true,
+            ..Default::default()
+        };
+
+        let states = VecDeque::from([
+            SimState {
+                gear: 1,
+                ..Default::default()
+            },
+            SimState {
+                gear: 2,
+                flags: yellow_and_blue.clone(),
+                ..Default::default()
+            },
+            SimState {
+                car_id: Some("ferrari_296_gt3"),
+                gear: 3,
+                flags: yellow_and_blue,
+                ..Default::default()
+            },
+        ]);
+
+        let mut output = MockOutput::default();
+        let mut display = Framebuffer::new(Size::new(200, 100));
+
+        let stats = SimDashboard::new(profile(), MockSource(states), &mut output)
+            .with_display(BorrowedFramebuffer(&mut display), widget())
+            .run()
+            .await
+            .expect("Running the dashboard with mock devices should never fail");
+
+        assert_eq!(
+            stats,
+            Stats {
+                frames_applied: 3,
+                frames_skipped: 0,
+                frames_throttled: 0,
+                errors: 0
+            }
+        );
+
+        assert_eq!(
+            output.frames,
+            vec![
+                vec![leds![off; 2]],
+                vec![leds!["yellow"; 2]],
+                vec![leds!["blue"; 2]],
+            ],
+            "Every sim state should have been applied, using the car specific containers once \
+             the car is known"
+        );
+        assert_eq!(
+            output.cleared, 1,
+            "The LEDs should have been cleared when switching to the car specific containers"
+        );
+
+        assert!(
+            display.count(Rgb565::WHITE) > 0,
+            "The gear of the last sim state should have been drawn on the display"
+        );
+    }
+
+    #[tokio::test]
+    async fn car_profile_which_doesnt_fit() {
+        let mut profile = profile();
+        let too_long = json!({
+            "LedCount": 3,
+            "Color": "Blue",
+            "BlinkEnabled": false,
+            "StartPosition": 1,
+            "ContainerType": "BlueFlagContainer",
+            "IsEnabled": true
+        });
+        profile.car_overrides.insert(
+            "ferrari_296_gt3".to_owned(),
+            vec![serde_json::from_value(too_long).unwrap()],
+        );
+
+        let blue = RacingFlags {
+            blue: true,
+            ..Default::default()
+        };
+
+        let states = VecDeque::from([
+            SimState::default(),
+            SimState {
+                car_id: Some("ferrari_296_gt3"),
+                flags: blue.clone(),
+                ..Default::default()
+            },
+            SimState {
+                car_id: Some("ferrari_296_gt3"),
+                flags: blue,
+                gear: 2,
+                ..Default::default()
+            },
+        ]);
+
+        let mut output = MockOutput::default();
+
+        let stats = SimDashboard::new(profile, MockSource(states), &mut output)
+            .run()
+            .await
+            .expect("A car profile which doesn't fit on the LEDs shouldn't stop the dashboard");
+
+        assert_eq!(
+            stats.frames_applied, 2,
+            "The frames of the car profile should have been applied"
+        );
+        assert_eq!(
+            output.frames.last(),
+            Some(&vec![leds!["blue"; 2]]),
+            "The LEDs of the car profile should have been truncated to the LEDs of the output"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_animation() {
+        let yellow = SimState {
+            flags: RacingFlags {
+                yellow: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let source = ScriptedSource {
+            steps: VecDeque::from([
+                Step::Moment(yellow.clone()),
+                Step::Stall(Duration::from_secs(2)),
+                Step::Moment(yellow),
+            ]),
+            stalled_until: None,
+        };
+
+        let mut output = MockOutput::default();
+
+        SimDashboard::new(profile(), source, &mut output)
+            .with_idle_timeout(Duration::from_secs(1))
+            .run()
+            .await
+            .expect("Running the dashboard with mock devices should never fail");
+
+        let yellow_frame = vec![leds!["yellow"; 2]];
+
+        assert_eq!(
+            output.frames.first(),
+            Some(&yellow_frame),
+            "The first sim state should have been applied"
+        );
+        assert!(
+            output.frames[1..output.frames.len() - 1]
+                .iter()
+                .all(|frame| *frame != yellow_frame),
+            "The idle animation should have taken over the LEDs while no sim states arrived"
+        );
+        assert!(
+            output.frames.len() > 2,
+            "The idle animation should have been shown while no sim states arrived"
+        );
+        assert_eq!(
+            output.frames.last(),
+            Some(&yellow_frame),
+            "The profile should have taken over the LEDs again once sim states arrived"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn assist_flash() {
+        let mut profile = profile();
+        profile.led_containers = vec![serde_json::from_value(json!({
+            "LedCount": 2,
+            "StartPosition": 1,
+            "Color": "Orange",
+            "Hold": 100,
+            "ContainerType": "AssistContainer",
+            "IsEnabled": true
+        }))
+        .expect("We should be able to deserialize the assist container")];
+
+        let abs_active = SimState {
+            abs_active: true,
+            ..Default::default()
+        };
+
+        let source = ScriptedSource {
+            steps: VecDeque::from([
+                Step::Moment(SimState::default()),
+                Step::Moment(abs_active),
+                Step::Moment(SimState::default()),
+                Step::Stall(Duration::from_millis(200)),
+            ]),
+            stalled_until: None,
+        };
+
+        let mut output = MockOutput::default();
+
+        SimDashboard::new(profile, source, &mut output)
+            .run()
+            .await
+            .expect("Running the dashboard with mock devices should never fail");
+
+        assert_eq!(
+            output.frames,
+            vec![
+                vec![leds![off; 2]],
+                vec![leds!["orange"; 2]],
+                vec![leds![off; 2]],
+            ],
+            "The LEDs should flash when the source reports an engaged ABS, and turn off once the \
+             hold duration passed"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Support for sending LED data to Art-Net nodes, as used by stage style LED installations.
+
+use std::net::{SocketAddr, UdpSocket};
+
+use anyhow::{ensure, Context as _, Result};
+
+use crate::led::state::LedConfiguration;
+
+/// The number of channels a single DMX universe contains.
+const CHANNELS_PER_UNIVERSE: usize = 512;
+/// The number of DMX channels a single RGB LED uses.
+const CHANNELS_PER_LED: usize = 3;
+/// The highest universe the 15 bit port address of an Art-Net packet can address.
+const MAX_UNIVERSE: u16 = 0x7fff;
+
+/// An output which sends LED data as DMX universes using the Art-Net protocol over UDP.
+///
+/// Every LED uses three consecutive DMX channels, in the red, green, and blue order. If the LEDs
+/// don't fit into a single universe, they continue in the following universes.
+pub struct ArtNetOutput {
+    socket: UdpSocket,
+    target: SocketAddr,
+    universe: u16,
+    start_channel: usize,
+    sequence: u8,
+}
+
+impl ArtNetOutput {
+    /// The UDP port Art-Net nodes listen on.
+    pub const PORT: u16 = 6454;
+
+    /// Create a new output which will send the LED data to the Art-Net node with the given
+    /// address.
+    ///
+    /// The first LED will be put into the given DMX channel of the given universe, DMX channels
+    /// are numbered starting from 1.
+    pub fn new(target: SocketAddr, universe: u16, start_channel: usize) -> Result<Self> {
+        ensure!(
+            (1..=CHANNELS_PER_UNIVERSE).contains(&start_channel),
+            "The DMX start channel needs to be between 1 and {CHANNELS_PER_UNIVERSE}, got \
+             {start_channel}"
+        );
+        ensure!(
+            universe <= MAX_UNIVERSE,
+            "Art-Net only supports universes up to {MAX_UNIVERSE}, got {universe}"
+        );
+
+        let socket =
+            UdpSocket::bind(("0.0.0.0", 0)).context("Could not bind the Art-Net UDP socket")?;
+
+        Ok(Self {
+            socket,
+            target,
+            universe,
+            start_channel,
+            sequence: 0,
+        })
+    }
+
+    /// Send a whole frame of LEDs, for example the output of [`GroupState::render()`], to the
+    /// Art-Net node.
+    ///
+    /// [`GroupState::render()`]: crate::led::state::groups::GroupState::render
+    pub fn send_frame(&mut self, leds: &[LedConfiguration]) -> Result<()> {
+        // A sequence number of 0 disables the reordering in the Art-Net node, so skip it.
+        self.sequence = self.sequence.checked_add(1).unwrap_or(1);
+
+        let channels = dmx_channels(leds, self.start_channel);
+
+        for (universe, data) in split_universes(&channels, self.universe)? {
+            let packet = build_dmx_packet(universe, self.sequence, data);
+
+            self.socket.send_to(&packet, self.target).with_context(|| {
+                format!("Could not send the Art-Net packet for universe {universe}")
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Convert the LEDs into a list of DMX channel values, starting at the first channel of the first
+/// universe.
+///
+/// The channels before the start channel are set to zero.
+fn dmx_channels(leds: &[LedConfiguration], start_channel: usize) -> Vec<u8> {
+    let mut channels = vec![0u8; start_channel - 1];
+    channels.reserve(leds.len() * CHANNELS_PER_LED);
+
+    for led in leds {
+        match led {
+            LedConfiguration::On { color, brightness } => {
+                let brightness = brightness.clamp(0.0, 1.0);
+                let [r, g, b, _] = color.to_rgba8();
+
+                channels.extend(
+                    [r, g, b]
+                        .into_iter()
+                        .map(|channel| (channel as f64 * brightness).round() as u8),
+                );
+            }
+            LedConfiguration::Off => channels.extend_from_slice(&[0, 0, 0]),
+        }
+    }
+
+    channels
+}
+
+/// Split the DMX channels into universes of 512 channels, starting with the given universe.
+///
+/// Fails if the channels need universes past the highest universe Art-Net supports.
+fn split_universes(channels: &[u8], first_universe: u16) -> Result<Vec<(u16, &[u8])>> {
+    channels
+        .chunks(CHANNELS_PER_UNIVERSE)
+        .enumerate()
+        .map(|(index, data)| {
+            let universe = u16::try_from(index)
+                .ok()
+                .and_then(|index| first_universe.checked_add(index))
+                .filter(|universe| *universe <= MAX_UNIVERSE)
+                .with_context(|| {
+                    format!(
+                        "The LEDs need more than the universes {first_universe} to {MAX_UNIVERSE}"
+                    )
+                })?;
+
+            Ok((universe, data))
+        })
+        .collect()
+}
+
+/// Build an ArtDmx packet containing the DMX data for the given universe.
+fn build_dmx_packet(universe: u16, sequence: u8, data: &[u8]) -> Vec<u8> {
+    const ID: &[u8] = b"Art-Net\0";
+    const OP_DMX: u16 = 0x5000;
+    const PROTOCOL_VERSION: u16 = 14;
+
+    // The length of the DMX data needs to be even and at least 2.
+    let length = data.len().max(2).next_multiple_of(2);
+
+    let mut packet = Vec::with_capacity(18 + length);
+
+    packet.extend_from_slice(ID);
+    packet.extend_from_slice(&OP_DMX.to_le_bytes());
+    packet.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    packet.push(sequence);
+    // The physical input port, this is purely informational.
+    packet.push(0);
+    // The 15 bit port address, the low byte contains the sub-net and universe, the high byte the
+    // net.
+    packet.extend_from_slice(&(universe & MAX_UNIVERSE).to_le_bytes());
+    packet.extend_from_slice(&(length as u16).to_be_bytes());
+    packet.extend_from_slice(data);
+    packet.resize(18 + length, 0);
+
+    packet
+}
+
+#[cfg(test)]
+mod test {
+    use similar_asserts::assert_eq;
+
+    use super::*;
+    use crate::led;
+
+    #[test]
+    fn dmx_packet() {
+        let packet = build_dmx_packet(0x0102, 7, &[0xff, 0x00, 0x80]);
+
+        assert_eq!(
+            packet,
+            vec![
+                b'A', b'r', b't', b'-', b'N', b'e', b't', 0x00, // ID
+                0x00, 0x50, // OpCode
+                0x00, 0x0e, // Protocol version
+                0x07, // Sequence
+                0x00, // Physical
+                0x02, 0x01, // Port address
+                0x00, 0x04, // Length
+                0xff, 0x00, 0x80, 0x00, // Data padded to an even length
+            ]
+        );
+    }
+
+    #[test]
+    fn channels() {
+        let leds = [led!("red"), led!(off), led!("blue")];
+
+        assert_eq!(
+            dmx_channels(&leds, 1),
+            vec![0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff]
+        );
+        assert_eq!(
+            dmx_channels(&leds[..1], 3),
+            vec![0x00, 0x00, 0xff, 0x00, 0x00],
+            "The LEDs should start at the start channel"
+        );
+    }
+
+    #[test]
+    fn dimmed_channels() {
+        let leds = [LedConfiguration::On {
+            color: csscolorparser::Color::from_rgba8(0xff, 0x80, 0x00, 0xff),
+            brightness: 0.5,
+        }];
+
+        assert_eq!(
+            dmx_channels(&leds, 1),
+            vec![0x80, 0x40, 0x00],
+            "The channels should be scaled by the brightness of the LED"
+        );
+    }
+
+    #[test]
+    fn universe_splitting() {
+        // 171 LEDs need 513 channels, the last channel needs to go into the next universe.
+        let leds = vec![led!("white"); 171];
+        let channels = dmx_channels(&leds, 1);
+
+        let universes = split_universes(&channels, 3).unwrap();
+
+        assert_eq!(universes.len(), 2);
+        assert_eq!(universes[0].0, 3);
+        assert_eq!(universes[0].1.len(), 512);
+        assert_eq!(universes[1].0, 4);
+        assert_eq!(universes[1].1, &[0xff]);
+
+        // 170 LEDs fit exactly into a single universe when starting at the third channel.
+        let channels = dmx_channels(&leds[..170], 3);
+        assert_eq!(split_universes(&channels, 0).unwrap().len(), 1);
+
+        let channels = dmx_channels(&leds[..170], 4);
+        assert_eq!(
+            split_universes(&channels, 0).unwrap().len(),
+            2,
+            "Moving the start channel should push the last channel into the next universe"
+        );
+    }
+
+    #[test]
+    fn universe_overflow() {
+        let leds = vec![led!("white"); 171];
+        let channels = dmx_channels(&leds, 1);
+
+        assert_eq!(
+            split_universes(&channels[..512], MAX_UNIVERSE).unwrap(),
+            [(MAX_UNIVERSE, &channels[..512])],
+            "A single universe should fit into the last universe"
+        );
+
+        split_universes(&channels, MAX_UNIVERSE)
+            .expect_err("Channels which need universes past the last one should be rejected");
+        split_universes(&channels, u16::MAX)
+            .expect_err("The universe counter shouldn't wrap around");
+    }
+}
@@ -19,10 +19,12 @@
 // SOFTWARE.
 
 use anyhow::{Context as _, Result};
-use hidapi::{HidApi, HidDevice};
+use hidapi::HidApi;
+
+use super::{checksum, LmxHidDevice};
 
 pub struct LmxButtonPlate {
-    inner: HidDevice,
+    inner: LmxHidDevice,
 }
 
 impl LmxButtonPlate {
@@ -34,13 +36,11 @@ impl LmxButtonPlate {
             .open(Self::VID, Self::PID)
             .context("Could not open the LM-X Wheel")?;
 
-        Ok(Self { inner })
-    }
+        tracing::debug!("Opened the LM-X button plate");
 
-    #[inline(always)]
-    fn calculate_checksum(bytes: &[u8]) -> u8 {
-        let sum: u32 = bytes.iter().map(|&byte| byte as u32).sum();
-        (sum % 256) as u8
+        Ok(Self {
+            inner: LmxHidDevice::new(Box::new(inner)),
+        })
     }
 
     pub fn set_color(&self, r: u8, g: u8, b: u8) -> Result<()> {
@@ -49,10 +49,10 @@ impl LmxButtonPlate {
             0x00, 0xff, 0xaa, 0x43, 0x01, 0x02, 0xe1, b, g, r, 0x00, 0x00, 0x00,
         ];
 
-        let checksum = Self::calculate_checksum(&package[..package.len() - 1]);
+        let checksum = checksum::sum8(&package[..package.len() - 1]);
         package[package.len() - 1] = checksum;
 
-        self.inner.write(&package).context(
+        self.inner.write_report(&package).context(
             "Couldn't send the HID package to set the RGB color of a button to the device",
         )?;
 
@@ -69,8 +69,7 @@ mod test {
     fn checksum() {
         const EXAMPLE_PAYLOAD: &[u8] = b"\xff\xaaC\x01\x02\xe3\xff\x05\xff\x00\x01\xd6";
 
-        let checksum =
-            LmxButtonPlate::calculate_checksum(&EXAMPLE_PAYLOAD[..EXAMPLE_PAYLOAD.len() - 1]);
+        let checksum = checksum::sum8(&EXAMPLE_PAYLOAD[..EXAMPLE_PAYLOAD.len() - 1]);
 
         assert_eq!(
             checksum,
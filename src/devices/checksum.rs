@@ -0,0 +1,85 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Checksums used by the HID protocols of the supported devices.
+
+/// Calculate the sum of all the bytes, modulo 256.
+pub fn sum8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// Calculate the XOR of all the bytes.
+pub fn xor8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |checksum, byte| checksum ^ byte)
+}
+
+/// Calculate the CRC-8 of the bytes, using the `0x07` polynomial and an initial value of zero,
+/// also known as CRC-8/SMBUS.
+pub fn crc8(bytes: &[u8]) -> u8 {
+    const POLYNOMIAL: u8 = 0x07;
+
+    bytes.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ POLYNOMIAL
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use similar_asserts::assert_eq;
+
+    use super::*;
+
+    /// The usual input used to check CRC implementations.
+    const CHECK_INPUT: &[u8] = b"123456789";
+
+    #[test]
+    fn sum() {
+        assert_eq!(sum8(&[]), 0);
+        assert_eq!(
+            sum8(&[0xff, 0x02]),
+            0x01,
+            "The sum should wrap around at 256"
+        );
+        assert_eq!(sum8(CHECK_INPUT), 0xdd);
+    }
+
+    #[test]
+    fn xor() {
+        assert_eq!(xor8(&[]), 0);
+        assert_eq!(xor8(&[0xf0, 0x0f, 0xff]), 0x00);
+        assert_eq!(xor8(CHECK_INPUT), 0x31);
+    }
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc8(&[]), 0);
+        assert_eq!(
+            crc8(CHECK_INPUT),
+            0xf4,
+            "The CRC should match the check value of CRC-8/SMBUS"
+        );
+    }
+}
@@ -20,7 +20,7 @@
 
 use std::{ffi::CStr, iter, time::Duration};
 
-use anyhow::Result;
+use anyhow::{bail, ensure, Result};
 use embedded_graphics::{
     draw_target::DrawTarget,
     pixelcolor::{raw::RawU16, Rgb565},
@@ -28,7 +28,6 @@ use embedded_graphics::{
     primitives::Rectangle,
     Pixel,
 };
-use itertools::Itertools;
 use rusb::{
     request_type, Context, DeviceHandle, Direction, Error, Recipient, RequestType, UsbContext,
 };
@@ -60,16 +59,93 @@ impl DeviceDetails {
             serial_number,
         })
     }
+
+    /// Get the dimensions of the screen the device reported.
+    ///
+    /// Falls back to the dimensions of the 480x272 USBD480 if the device didn't report any.
+    pub fn size(&self) -> Size {
+        if self.display_width == 0 || self.display_height == 0 {
+            Size::new(USBD480Display::WIDTH, USBD480Display::HEIGHT)
+        } else {
+            Size::new(self.display_width.into(), self.display_height.into())
+        }
+    }
+}
+
+/// The parameters of a USB control request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ControlRequest {
+    request_type: u8,
+    request: u8,
+    value: u16,
+    index: u16,
+}
+
+impl ControlRequest {
+    /// The vendor request to read the value of a configuration parameter, the ID of the parameter
+    /// is passed in the `wValue` field.
+    fn get_config_value(parameter_id: u16) -> Self {
+        const GET_CONFIG_VALUE: u8 = 0x83;
+
+        Self {
+            request_type: request_type(Direction::In, RequestType::Vendor, Recipient::Device),
+            request: GET_CONFIG_VALUE,
+            value: parameter_id,
+            index: 0,
+        }
+    }
+}
+
+/// The number of pixel bytes a single WRITE command of the stream decoder carries.
+///
+/// Different USB stacks perform better with different packet sizes, some fail to handle larger
+/// packets altogether.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkSizes {
+    /// The chunk size used by [`USBD480Display::write_bytes()`].
+    pub write_bytes: usize,
+    /// The chunk size used when drawing areas of the screen, e.g. using the [`DrawTarget`]
+    /// implementation.
+    pub fill_contiguous: usize,
+}
+
+impl Default for ChunkSizes {
+    fn default() -> Self {
+        Self {
+            write_bytes: 4096,
+            // Together with the 10 byte header of the WRITE command, this fills out 512 bytes.
+            fill_contiguous: 502,
+        }
+    }
+}
+
+impl ChunkSizes {
+    fn validate(&self) -> Result<()> {
+        for size in [self.write_bytes, self.fill_contiguous] {
+            ensure!(
+                size >= 2 && size % 2 == 0,
+                "Invalid chunk size {size}, a chunk needs to contain a whole number of RGB565 \
+                 pixels"
+            );
+        }
+
+        Ok(())
+    }
 }
 
 pub struct USBD480Display {
     handle: DeviceHandle<Context>,
+    chunk_sizes: ChunkSizes,
+    /// The dimensions of the screen, as reported by the device when it was opened.
+    size: Size,
 }
 
 impl USBD480Display {
-    /// The number horizontal pixels the screen contains.
+    /// The number horizontal pixels the screen of the most common USBD480 variant contains.
+    ///
+    /// The actual dimensions of an opened display are returned by [`OriginDimensions::size()`].
     pub const WIDTH: u32 = 480;
-    /// The number vertical pixels the screen contains.
+    /// The number vertical pixels the screen of the most common USBD480 variant contains.
     pub const HEIGHT: u32 = 272;
 
     /// The USB vendor ID of the display.
@@ -79,6 +155,10 @@ impl USBD480Display {
 
     /// The command identifier of the WRITE command for the stream decoder.
     const WRITE_COMMAND: u16 = 0x5B41;
+    /// The command identifier of the FRAMEBASE command for the stream decoder.
+    const FRAME_BASE_COMMAND: u16 = 0x5B42;
+    /// The command identifier of the WRAPLENGTH command for the stream decoder.
+    const WRAP_LENGTH_COMMAND: u16 = 0x5B43;
     /// The endpoint number of the bulk endpoint the stream decoder is using.
     const BULK_ENDPOINT: u8 = 2;
 
@@ -87,6 +167,10 @@ impl USBD480Display {
 
     const INTERFACE: u8 = 0x00;
 
+    /// The ID of the configuration parameter which holds the brightness of the backlight, the
+    /// value can be read using the GET_CONFIG_VALUE vendor request.
+    const BRIGHTNESS_PARAMETER: u16 = 20;
+
     /// Try to find a USBD480 display connected via USB.
     ///
     /// This will use the first such display that is found, other displays will be ignored.
@@ -98,10 +182,19 @@ impl USBD480Display {
                 let mut handle = device.open()?;
                 handle.set_auto_detach_kernel_driver(true)?;
                 handle.claim_interface(Self::INTERFACE)?;
-                let display = Self { handle };
+                let mut display = Self {
+                    handle,
+                    chunk_sizes: ChunkSizes::default(),
+                    size: Size::new(Self::WIDTH, Self::HEIGHT),
+                };
+
+                display.size = display.get_device_details()?.size();
 
                 display.enable_stream_decoder()?;
-                display.set_wrap_length(Self::WIDTH as u16)?;
+                display.set_wrap_length(display.size.width as u16)?;
+
+                let Size { width, height } = display.size;
+                tracing::debug!(width, height, "Opened the USBD480 display");
 
                 return Ok(display);
             }
@@ -128,25 +221,27 @@ impl USBD480Display {
         DeviceDetails::from_bytes(&data)
     }
 
-    pub fn get_config_value(&self) -> Result<()> {
-        const GET_CONFIG_VALUE: u8 = 0x83;
-        let parameter_id = 20;
-
-        let request_type = request_type(Direction::In, RequestType::Vendor, Recipient::Device);
+    /// Read the value of the configuration parameter with the given ID.
+    pub fn get_config_value(&self, parameter_id: u16) -> Result<u8> {
+        let request = ControlRequest::get_config_value(parameter_id);
         let mut data = [0u8; 1];
 
         self.handle.read_control(
-            request_type,
-            GET_CONFIG_VALUE,
-            parameter_id,
-            0,
+            request.request_type,
+            request.request,
+            request.value,
+            request.index,
             &mut data,
             Self::REQUEST_TIMEOUT,
         )?;
 
-        println!("Helloo {data:#?}");
+        Ok(data[0])
+    }
 
-        Ok(())
+    /// Get the current brightness of the backlight, as set by
+    /// [`USBD480Display::set_brightness()`].
+    pub fn get_brightness(&self) -> Result<u8> {
+        self.get_config_value(Self::BRIGHTNESS_PARAMETER)
     }
 
     pub fn set_brightness(&self, brightness: u8) -> Result<()> {
@@ -173,8 +268,8 @@ impl USBD480Display {
     ///
     /// The stream decoder has three subcommands:
     /// 1. WRITE - Write image data in the RGB565 format to the framebuffer of the display.
-    /// 2. FRAMEBASE - Set the start address for the visible frame. The display will read 480x272
-    ///    pixels starting from this address and display them on the screen. The address change is
+    /// 2. FRAMEBASE - Set the start address for the visible frame. The display will read a whole
+    ///    screen of pixels starting from this address and display them on the screen. The address change is
     ///    synchronized with the display refresh VSYNC.
     /// 3. WRAPLENGTH - This controls when the WRITE command should automatically move to the next
     ///    row, i.e. when this is set to 480 (the default value) the WRITE command will fill out a
@@ -218,93 +313,304 @@ impl USBD480Display {
     /// When writing data to the framebuffer the WRITE will automatically wrap to the next row
     /// after WRAPLENGTH number of pixels.
     fn set_wrap_length(&self, length: u16) -> Result<()> {
-        /// The command identifier for the WRAPLENGTH stream encoder command.
-        const WRAPLENGTH_COMMAND: u16 = 0x5B43;
+        self.write_to_bulk_endpoint(&Self::build_wrap_length_command(length))?;
 
+        Ok(())
+    }
+
+    /// Set the framebuffer address of the first pixel of the visible frame.
+    ///
+    /// The change takes effect with the next refresh of the display.
+    pub fn set_frame_base(&self, address: u32) -> Result<()> {
+        self.write_to_bulk_endpoint(&Self::build_frame_base_command(address))?;
+
+        Ok(())
+    }
+
+    /// Build a WRITE command which writes the given RGB565 pixel bytes to the framebuffer,
+    /// starting at the given address.
+    ///
+    /// The pixels need to contain at least one pixel.
+    fn build_write_command(address: u32, pixels: &[u8]) -> Vec<u8> {
+        // The WRITE command expects the number of pixels minus one, a value of 0 writes a single
+        // pixel.
+        let pixel_count_minus_one = (pixels.len() / 2).saturating_sub(1) as u32;
+
+        let mut command = Vec::with_capacity(pixels.len() + 10);
+
+        command.extend_from_slice(&Self::WRITE_COMMAND.to_le_bytes());
+        command.extend_from_slice(&address.to_le_bytes());
+        command.extend_from_slice(&pixel_count_minus_one.to_le_bytes());
+        command.extend_from_slice(pixels);
+
+        command
+    }
+
+    /// Build a WRAPLENGTH command which sets the wrap length to the given number of pixels.
+    fn build_wrap_length_command(length: u16) -> Vec<u8> {
         // The wrap length is defined in the spec to be the actual length - 1. A value of 479
         // means the full screen width, 0 is 1 pixel wide.
-        let wrap_length_minus_one = length - 1;
+        let wrap_length_minus_one = length.saturating_sub(1);
 
-        let mut command = vec![];
+        let mut command = Vec::with_capacity(4);
 
-        command.extend_from_slice(&WRAPLENGTH_COMMAND.to_le_bytes());
+        command.extend_from_slice(&Self::WRAP_LENGTH_COMMAND.to_le_bytes());
         command.extend_from_slice(&wrap_length_minus_one.to_le_bytes());
 
-        self.write_to_bulk_endpoint(&command)?;
+        command
+    }
 
-        Ok(())
+    /// Build a FRAMEBASE command which sets the start address of the visible frame.
+    fn build_frame_base_command(address: u32) -> Vec<u8> {
+        let mut command = Vec::with_capacity(6);
+
+        command.extend_from_slice(&Self::FRAME_BASE_COMMAND.to_le_bytes());
+        command.extend_from_slice(&address.to_le_bytes());
+
+        command
     }
 
     /// Write a single RGB565 pixel to the screen.
     fn write_pixel(&self, pixel: Pixel<Rgb565>) -> Result<()> {
         let Pixel(point, color) = pixel;
 
-        let address: u32 = point.y as u32 * 480 + point.x as u32;
+        let address: u32 = point.y as u32 * self.size.width + point.x as u32;
         let color = RawU16::from(color).into_inner();
-        let data_len: u32 = 0;
 
-        let mut command = Vec::with_capacity(16);
+        self.write_to_bulk_endpoint(&Self::build_write_command(address, &color.to_le_bytes()))?;
 
-        command.extend_from_slice(&Self::WRITE_COMMAND.to_le_bytes());
-        command.extend_from_slice(&address.to_le_bytes());
-        command.extend_from_slice(&data_len.to_le_bytes());
-        command.extend_from_slice(&color.to_le_bytes());
+        Ok(())
+    }
 
-        self.write_to_bulk_endpoint(&command)?;
+    /// Configure how many pixel bytes a single WRITE command should carry.
+    pub fn set_chunk_sizes(&mut self, chunk_sizes: ChunkSizes) -> Result<()> {
+        chunk_sizes.validate()?;
+        self.chunk_sizes = chunk_sizes;
 
         Ok(())
     }
 
+    /// Split the given pixels into chunks of the given size, each chunk is paired with the
+    /// framebuffer address it needs to be written to.
+    fn write_chunks(
+        start_address: u32,
+        pixels: &[u8],
+        chunk_size: usize,
+    ) -> impl Iterator<Item = (u32, &[u8])> {
+        pixels
+            .chunks(chunk_size)
+            .scan(start_address, |address, chunk| {
+                let chunk_address = *address;
+                *address += (chunk.len() / 2) as u32;
+
+                Some((chunk_address, chunk))
+            })
+    }
+
     fn write_bytes_impl(&self, start_address: u32, pixels: &[u8]) -> Result<()> {
-        const MAX_PACKET_SIZE: usize = 4096;
+        let chunk_size = self.chunk_sizes.write_bytes;
+
+        for (address, chunk) in Self::write_chunks(start_address, pixels, chunk_size) {
+            self.write_to_bulk_endpoint(&Self::build_write_command(address, chunk))?;
+        }
+
+        Ok(())
+    }
+
+    /// Check that the given buffer contains exactly enough RGB565 pixels to fill an area of the
+    /// given size.
+    fn validate_pixel_buffer(size: Size, pixels: &[u8]) -> Result<()> {
+        let pixel_count = size.width as usize * size.height as usize;
+        let expected_len = pixel_count * 2;
+
+        if pixels.len() == expected_len {
+            Ok(())
+        } else if pixels.len() == pixel_count * 3 {
+            bail!(
+                "The pixel buffer contains {} bytes, which matches a {}x{} RGB888 image, the \
+                 pixels need to be in the RGB565 format which requires {expected_len} bytes",
+                pixels.len(),
+                size.width,
+                size.height,
+            )
+        } else {
+            bail!(
+                "The pixel buffer contains {} bytes, a {}x{} RGB565 image requires {expected_len} \
+                 bytes",
+                pixels.len(),
+                size.width,
+                size.height,
+            )
+        }
+    }
 
-        let mut address: u32 = start_address;
-        let mut command = Vec::with_capacity(MAX_PACKET_SIZE);
+    /// Write a full frame of RGB565 pixels to the screen.
+    ///
+    /// The buffer needs to contain exactly as many pixels as the screen, see
+    /// [`OriginDimensions::size()`], each pixel taking up two bytes in little endian order,
+    /// otherwise an error is returned.
+    pub fn write_bytes(&self, pixels: &[u8]) -> Result<()> {
+        Self::validate_pixel_buffer(self.size(), pixels)?;
+
+        let start_address: u32 = 0;
+        self.write_bytes_impl(start_address, pixels)
+    }
+
+    /// Get the framebuffer address of the pixel with the given offset inside of the given area,
+    /// on a screen which is `screen_width` pixels wide.
+    ///
+    /// Pixels inside of the area are counted row by row, an offset equal to the number of pixels
+    /// in the area points to the first pixel after the area, in the row below it.
+    fn area_address(area: &Rectangle, screen_width: u32, offset: u32) -> u32 {
+        let width = area.size.width;
+        let x = area.top_left.x as u32 + offset % width;
+        let y = area.top_left.y as u32 + offset / width;
+
+        y * screen_width + x
+    }
+
+    /// Split the pixel bytes of the given on-screen area into chunks of the given size, each
+    /// chunk is paired with the framebuffer address of its first pixel.
+    ///
+    /// With the wrap length set to the width of the area, a chunk which starts at offset `n`
+    /// inside of the area continues where the previous chunk stopped. The invariant is that the
+    /// next chunk starts at the offset `n` plus the number of pixels in the current chunk, the
+    /// address is always computed from the offset and never from the previous address.
+    fn contiguous_chunks(
+        area: Rectangle,
+        screen_width: u32,
+        mut pixels: impl Iterator<Item = u8>,
+        chunk_size: usize,
+    ) -> impl Iterator<Item = (u32, Vec<u8>)> {
+        let mut offset = 0;
+
+        std::iter::from_fn(move || {
+            let chunk: Vec<u8> = pixels.by_ref().take(chunk_size).collect();
+
+            if chunk.is_empty() {
+                None
+            } else {
+                let address = Self::area_address(&area, screen_width, offset);
+                offset += (chunk.len() / 2) as u32;
+
+                Some((address, chunk))
+            }
+        })
+    }
+
+    /// Build the WRITE commands which fill the given area with the given colors, on a screen of
+    /// the given size.
+    ///
+    /// Pixels outside of the screen are skipped. Returns the part of the area which is on the
+    /// screen, the wrap length needs to be set to its width while the commands are written, and
+    /// the commands. `None` is returned if no part of the area is on the screen.
+    fn fill_commands<C>(
+        screen_size: Size,
+        area: &Rectangle,
+        colors: C,
+        chunk_size: usize,
+    ) -> Option<(Rectangle, impl Iterator<Item = Vec<u8>>)>
+    where
+        C: IntoIterator<Item = Rgb565>,
+    {
+        let drawable_area = area.intersection(&Rectangle::new(Point::zero(), screen_size));
+
+        if drawable_area.is_zero_sized() {
+            return None;
+        }
+
+        let pixels_inside_drawable_area = area
+            .points()
+            .zip(colors)
+            .filter(move |(pos, _)| drawable_area.contains(*pos))
+            .flat_map(|(_, color)| RawU16::from(color).into_inner().to_le_bytes());
 
-        for chunk in pixels.chunks(MAX_PACKET_SIZE) {
-            let pixel_count = (chunk.len() / 2) as u32 - 1;
+        let commands = Self::contiguous_chunks(
+            drawable_area,
+            screen_size.width,
+            pixels_inside_drawable_area,
+            chunk_size,
+        )
+        // Chunks are never empty, so the WRITE command always carries at least one pixel.
+        .map(|(address, chunk)| Self::build_write_command(address, &chunk));
 
-            command.extend_from_slice(&Self::WRITE_COMMAND.to_le_bytes());
-            command.extend_from_slice(&address.to_le_bytes());
-            command.extend_from_slice(&pixel_count.to_le_bytes());
-            command.extend_from_slice(chunk);
+        Some((drawable_area, commands))
+    }
 
-            self.write_to_bulk_endpoint(&command)?;
+    /// Fill the given area with the given colors, pixels outside of the screen are skipped.
+    fn fill_contiguous_impl<C>(&self, area: &Rectangle, colors: C) -> Result<()>
+    where
+        C: IntoIterator<Item = Rgb565>,
+    {
+        let Some((drawable_area, commands)) =
+            Self::fill_commands(self.size, area, colors, self.chunk_sizes.fill_contiguous)
+        else {
+            return Ok(());
+        };
 
-            command.clear();
+        // Set the wrap length to the width of the area, this ensures that we can just write the
+        // pixels to the framebuffer in a coniguous manner, the display will ensure that we go to
+        // the next row when we have written a `width` number of pixels.
+        self.set_wrap_length(drawable_area.size.width as u16)?;
 
-            address += pixel_count + 1;
+        for command in commands {
+            if let Err(e) = self.write_to_bulk_endpoint(&command) {
+                self.set_wrap_length(self.size.width as u16)?;
+                return Err(e);
+            }
         }
 
+        self.set_wrap_length(self.size.width as u16)?;
+
         Ok(())
     }
 
-    pub fn write_bytes(&self, pixels: &[u8]) -> Result<()> {
-        let start_address: u32 = 0;
-        self.write_bytes_impl(start_address, pixels)
+    /// Fill the given area of the screen with a single color, e.g. to clear a single field of a
+    /// dashboard before redrawing it.
+    ///
+    /// Parts of the area which aren't on the screen are skipped.
+    pub fn clear_area(&mut self, area: Rectangle, color: Rgb565) -> Result<()> {
+        self.fill_contiguous_impl(&area, iter::repeat(color))
+    }
+
+    /// Draw the given image onto the screen, with the top left corner of the image placed at the
+    /// given point.
+    ///
+    /// Parts of the image which don't fit on the screen are clipped.
+    #[cfg(feature = "image")]
+    pub fn draw_image(&self, image: &image::RgbImage, at: Point) -> Result<()> {
+        let area = Rectangle::new(at, Size::new(image.width(), image.height()));
+        self.fill_contiguous_impl(&area, image_to_rgb565(image))
     }
 
     fn write_pixels_contiguous(&self, area: &Rectangle, pixels: Vec<u8>) -> Result<()> {
         let top_left = area.top_left;
-        let start_address = top_left.y as u32 * 480 + top_left.x as u32;
+        let start_address = top_left.y as u32 * self.size.width + top_left.x as u32;
 
         self.write_bytes_impl(start_address, &pixels)
     }
 }
 
+/// Convert the pixels of the given image to the RGB565 format the display uses, row by row.
+#[cfg(feature = "image")]
+fn image_to_rgb565(image: &image::RgbImage) -> impl Iterator<Item = Rgb565> + '_ {
+    image
+        .pixels()
+        .map(|&image::Rgb([r, g, b])| embedded_graphics::pixelcolor::Rgb888::new(r, g, b).into())
+}
+
 impl Drop for USBD480Display {
     fn drop(&mut self) {
-        // TODO: Should we log a warning here? This could fail if the USB device was disconnected.
-        let _ = self.handle.release_interface(Self::INTERFACE);
+        // This could fail if the USB device was disconnected, there's nothing we can do about it.
+        if let Err(e) = self.handle.release_interface(Self::INTERFACE) {
+            tracing::warn!("Could not release the interface of the display: {e}");
+        }
     }
 }
 
 impl OriginDimensions for USBD480Display {
     fn size(&self) -> Size {
-        Size {
-            width: Self::WIDTH,
-            height: Self::HEIGHT,
-        }
+        self.size
     }
 }
 
@@ -328,78 +634,343 @@ impl DrawTarget for USBD480Display {
     where
         C: IntoIterator<Item = Self::Color>,
     {
-        let drawable_area = area.intersection(&Rectangle::new(Point::zero(), self.size()));
+        self.fill_contiguous_impl(area, colors)
+    }
 
-        if drawable_area.is_zero_sized() {
-            return Ok(());
-        }
+    fn clear(&mut self, color: Self::Color) -> std::result::Result<(), Self::Error> {
+        let Size { width, height } = self.size();
+        let drawable_area = Rectangle::new(Point::zero(), self.size());
 
-        let pixels_inside_drawable_area = area
-            .points()
-            .zip(colors)
-            .filter(|(pos, _)| drawable_area.contains(*pos))
-            .flat_map(|(_, color)| RawU16::from(color).into_inner().to_le_bytes());
+        let pixels: Vec<u8> = iter::repeat(RawU16::from(color).into_inner().to_le_bytes())
+            .flatten()
+            .take((width * height * 2) as usize)
+            .collect();
 
-        let width = area.size.width as u16;
-        let mut current_address = (area.top_left.y as u32 * 480) + area.top_left.x as u32;
+        self.write_pixels_contiguous(&drawable_area, pixels)?;
 
-        // Set the wrap length to the width of the area, this ensures that we can just write the
-        // pixels to the framebuffer in a coniguous manner, the display will ensure that we go to
-        // the next row when we have written a `width` number of pixels.
-        self.set_wrap_length(width)?;
+        Ok(())
+    }
+}
 
-        let mut command = Vec::with_capacity(512);
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn write_command() {
+        let pixels = [0x1f, 0x00, 0xe0, 0x07, 0x00, 0xf8];
+
+        assert_eq!(
+            USBD480Display::build_write_command(0x0001_e0ff, &pixels),
+            [
+                0x41, 0x5b, // WRITE
+                0xff, 0xe0, 0x01, 0x00, // Address
+                0x02, 0x00, 0x00, 0x00, // Three pixels, minus one
+                0x1f, 0x00, 0xe0, 0x07, 0x00, 0xf8, // Pixels
+            ],
+            "The WRITE command should contain the address and the pixel count minus one, in \
+             little endian order, followed by the pixels"
+        );
+
+        assert_eq!(
+            USBD480Display::build_write_command(42, &[0xff, 0xff])[6..10],
+            [0, 0, 0, 0],
+            "A single pixel should be written using a pixel count of zero"
+        );
+    }
 
-        for chunk in &pixels_inside_drawable_area.chunks(502) {
-            let chunk = chunk.collect_vec();
+    #[test]
+    fn clear_area() {
+        let screen_size = Size::new(8, 6);
+        let area = Rectangle::new(Point::new(5, 2), Size::new(6, 3));
+        let color = Rgb565::new(31, 0, 0);
 
-            let address = current_address.to_le_bytes();
+        // The area is clipped to the screen, so every row of the area is 3 pixels wide. Use a
+        // chunk per row, this way the wrap length doesn't matter for the simulated framebuffer.
+        let (drawable_area, commands) =
+            USBD480Display::fill_commands(screen_size, &area, iter::repeat(color), 6)
+                .expect("The area should be partially on the screen");
 
-            let pixel_count = (chunk.len() / 2) as u32 - 1;
-            let row_count = (pixel_count + 1) / width as u32;
-            let remainder = (pixel_count + 1) % width as u32;
+        assert_eq!(
+            drawable_area,
+            Rectangle::new(Point::new(5, 2), Size::new(3, 3)),
+            "The area should be clipped to the screen"
+        );
 
-            let old_remainder = current_address % width as u32;
-            let address_without_remainder = current_address - old_remainder;
+        let mut framebuffer = vec![0u16; 8 * 6];
 
-            let new_remainder = (remainder + old_remainder) % width as u32;
-            let remainder_row = (remainder + old_remainder) / width as u32;
+        for command in commands {
+            assert_eq!(command[..2], USBD480Display::WRITE_COMMAND.to_le_bytes());
 
-            let new_row_count = row_count + remainder_row;
-            let new_address =
-                address_without_remainder + (new_row_count * Self::WIDTH) + (new_remainder);
+            let address = u32::from_le_bytes(command[2..6].try_into().unwrap()) as usize;
+            let pixel_count = u32::from_le_bytes(command[6..10].try_into().unwrap()) as usize + 1;
+            let pixels = &command[10..];
 
-            command.extend_from_slice(&Self::WRITE_COMMAND.to_le_bytes());
-            command.extend_from_slice(&address);
-            command.extend_from_slice(&pixel_count.to_le_bytes());
-            command.extend_from_slice(&chunk);
+            assert_eq!(pixels.len(), pixel_count * 2);
 
-            if let Err(e) = self.write_to_bulk_endpoint(&command) {
-                self.set_wrap_length(Self::WIDTH as u16)?;
-                return Err(e);
+            for (index, pixel) in pixels.chunks_exact(2).enumerate() {
+                framebuffer[address + index] = u16::from_le_bytes([pixel[0], pixel[1]]);
             }
+        }
 
-            command.clear();
+        let red = RawU16::from(color).into_inner();
+        let expected: Vec<_> = (0..8 * 6)
+            .map(|address| {
+                let (x, y) = (address % 8, address / 8);
+                if x >= 5 && (2..5).contains(&y) {
+                    red
+                } else {
+                    0
+                }
+            })
+            .collect();
 
-            current_address = new_address;
-        }
+        assert_eq!(
+            framebuffer, expected,
+            "Only the pixels of the area which are on the screen should be written"
+        );
 
-        self.set_wrap_length(Self::WIDTH as u16)?;
+        assert!(
+            USBD480Display::fill_commands(screen_size, &area.translate(Point::new(4, 0)), [], 6)
+                .is_none(),
+            "Nothing should be written for an area which is completely off the screen"
+        );
+    }
 
-        Ok(())
+    #[test]
+    fn wrap_length_and_frame_base_commands() {
+        assert_eq!(
+            USBD480Display::build_wrap_length_command(480),
+            [0x43, 0x5b, 0xdf, 0x01],
+            "The wrap length should be sent as the length minus one"
+        );
+        assert_eq!(
+            USBD480Display::build_wrap_length_command(1),
+            [0x43, 0x5b, 0x00, 0x00],
+            "A wrap length of zero means a single pixel"
+        );
+        assert_eq!(
+            USBD480Display::build_frame_base_command(480 * 272),
+            [0x42, 0x5b, 0x00, 0xfe, 0x01, 0x00],
+            "The frame base should contain the address in little endian order"
+        );
     }
 
-    fn clear(&mut self, color: Self::Color) -> std::result::Result<(), Self::Error> {
-        let Size { width, height } = self.size();
-        let drawable_area = Rectangle::new(Point::zero(), self.size());
+    #[test]
+    fn write_chunks() {
+        let pixels = [0u8; 20];
 
-        let pixels: Vec<u8> = iter::repeat(RawU16::from(color).into_inner().to_le_bytes())
-            .flatten()
-            .take((width * height * 2) as usize)
+        let chunks: Vec<_> = USBD480Display::write_chunks(100, &pixels, 8)
+            .map(|(address, chunk)| (address, chunk.len()))
             .collect();
 
-        self.write_pixels_contiguous(&drawable_area, pixels)?;
+        assert_eq!(
+            chunks,
+            [(100, 8), (104, 8), (108, 4)],
+            "Every chunk should start at the address following the pixels of the previous chunk"
+        );
 
-        Ok(())
+        ChunkSizes {
+            write_bytes: 7,
+            ..Default::default()
+        }
+        .validate()
+        .expect_err("A chunk size which splits a pixel should be rejected");
+
+        ChunkSizes::default()
+            .validate()
+            .expect("The default chunk sizes should be valid");
+    }
+
+    /// Check that the chunks of the given area cover every pixel of the area exactly once, in
+    /// order, and return the addresses of the chunks.
+    fn chunk_addresses(area: Rectangle, chunk_size: usize) -> Vec<u32> {
+        let pixel_count = area.size.width * area.size.height;
+        let pixels = (0..pixel_count).flat_map(|pixel| (pixel as u16).to_le_bytes());
+
+        let mut expected_offset = 0;
+        let mut addresses = Vec::new();
+
+        for (address, chunk) in
+            USBD480Display::contiguous_chunks(area, USBD480Display::WIDTH, pixels, chunk_size)
+        {
+            // The pixels are numbered using 16 bits, so the numbers wrap around for large areas.
+            let first_pixel = u16::from_le_bytes([chunk[0], chunk[1]]);
+
+            assert_eq!(
+                first_pixel, expected_offset as u16,
+                "The chunks of {area:?} should contain the pixels in order"
+            );
+
+            addresses.push(address);
+            expected_offset += (chunk.len() / 2) as u32;
+        }
+
+        assert_eq!(
+            expected_offset, pixel_count,
+            "The chunks of {area:?} should contain all the pixels"
+        );
+
+        addresses
+    }
+
+    #[test]
+    fn contiguous_chunks() {
+        assert_eq!(
+            chunk_addresses(Rectangle::new(Point::new(0, 10), Size::new(480, 2)), 640),
+            [4800, 5120, 5440],
+            "The chunks of a full width area should wrap to the next row in the middle of a chunk"
+        );
+        assert_eq!(
+            chunk_addresses(Rectangle::new(Point::new(7, 5), Size::new(13, 9)), 40),
+            [2407, 2894, 3848, 4335, 5289, 5776],
+            "The chunks of an area which doesn't start at the left edge should wrap within the \
+             area"
+        );
+        assert_eq!(
+            chunk_addresses(Rectangle::new(Point::new(479, 271), Size::new(1, 1)), 502),
+            [130559],
+            "The last pixel of the screen should be written in a single chunk"
+        );
+        assert_eq!(
+            USBD480Display::area_address(
+                &Rectangle::new(Point::new(7, 5), Size::new(13, 9)),
+                USBD480Display::WIDTH,
+                117
+            ),
+            6727,
+            "After the last pixel of the area the address should point to the row below the area"
+        );
+
+        let screen = Size::new(USBD480Display::WIDTH, USBD480Display::HEIGHT);
+        chunk_addresses(Rectangle::new(Point::zero(), screen), 502);
+
+        // A simple xorshift generator, so the random rectangles are the same on every run.
+        let mut state: u32 = 0x2545_f491;
+        let mut random = |max: u32| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+
+            state % max
+        };
+
+        for _ in 0..200 {
+            let x = random(USBD480Display::WIDTH);
+            let y = random(USBD480Display::HEIGHT);
+            let width = random(USBD480Display::WIDTH - x) + 1;
+            let height = random(USBD480Display::HEIGHT - y) + 1;
+            let chunk_size = (random(300) as usize + 1) * 2;
+
+            let area = Rectangle::new(Point::new(x as i32, y as i32), Size::new(width, height));
+
+            chunk_addresses(area, chunk_size);
+        }
+    }
+
+    #[test]
+    fn reported_dimensions() {
+        let mut bytes = [0u8; 64];
+        bytes[..8].copy_from_slice(b"U480-WQ7");
+        bytes[20..22].copy_from_slice(&800u16.to_le_bytes());
+        bytes[22..24].copy_from_slice(&480u16.to_le_bytes());
+
+        let details = DeviceDetails::from_bytes(&bytes).unwrap();
+
+        assert_eq!(details.name, "U480-WQ7");
+        assert_eq!(
+            details.size(),
+            Size::new(800, 480),
+            "The size should use the dimensions the device reported"
+        );
+
+        let details = DeviceDetails {
+            display_width: 0,
+            display_height: 0,
+            ..details
+        };
+
+        assert_eq!(
+            details.size(),
+            Size::new(USBD480Display::WIDTH, USBD480Display::HEIGHT),
+            "A device which doesn't report its dimensions should use the default ones"
+        );
+    }
+
+    #[test]
+    fn address_on_a_wider_screen() {
+        let area = Rectangle::new(Point::new(10, 2), Size::new(4, 4));
+
+        assert_eq!(
+            USBD480Display::area_address(&area, 800, 0),
+            2 * 800 + 10,
+            "The address should use the width of the screen the area is on"
+        );
+        assert_eq!(
+            USBD480Display::area_address(&area, 800, 5),
+            3 * 800 + 11,
+            "The address should wrap to the next row of the screen after the width of the area"
+        );
+    }
+
+    #[test]
+    fn brightness_request() {
+        let request = ControlRequest::get_config_value(USBD480Display::BRIGHTNESS_PARAMETER);
+
+        assert_eq!(
+            request,
+            ControlRequest {
+                request_type: 0xC0,
+                request: 0x83,
+                value: 20,
+                index: 0,
+            },
+            "Reading the brightness should be a device-to-host vendor request for parameter 20"
+        );
+    }
+
+    #[test]
+    fn pixel_buffer_validation() {
+        let size = Size::new(USBD480Display::WIDTH, USBD480Display::HEIGHT);
+        let pixel_count = (USBD480Display::WIDTH * USBD480Display::HEIGHT) as usize;
+
+        USBD480Display::validate_pixel_buffer(size, &vec![0; pixel_count * 2])
+            .expect("A full frame of RGB565 pixels should be accepted");
+
+        let error = USBD480Display::validate_pixel_buffer(size, &vec![0; pixel_count * 3])
+            .expect_err("A RGB888 frame should be rejected");
+        assert!(
+            error.to_string().contains("RGB888"),
+            "The error should mention that the buffer looks like a RGB888 image: {error}"
+        );
+
+        USBD480Display::validate_pixel_buffer(size, &vec![0; pixel_count * 2 - 1])
+            .expect_err("A truncated frame should be rejected");
+        USBD480Display::validate_pixel_buffer(size, &[])
+            .expect_err("An empty buffer should be rejected");
+
+        USBD480Display::validate_pixel_buffer(Size::new(2, 3), &[0; 12])
+            .expect("The buffer should be validated against the given dimensions");
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn image_conversion() {
+        let image = image::RgbImage::from_fn(2, 2, |x, y| match (x, y) {
+            (0, 0) => image::Rgb([255, 0, 0]),
+            (1, 0) => image::Rgb([0, 255, 0]),
+            (0, 1) => image::Rgb([0, 0, 255]),
+            _ => image::Rgb([255, 255, 255]),
+        });
+
+        let bytes: Vec<u8> = image_to_rgb565(&image)
+            .flat_map(|color| RawU16::from(color).into_inner().to_le_bytes())
+            .collect();
+
+        assert_eq!(
+            bytes,
+            [0x00, 0xf8, 0xe0, 0x07, 0x1f, 0x00, 0xff, 0xff],
+            "The pixels should be converted to little endian RGB565, row by row"
+        );
     }
 }
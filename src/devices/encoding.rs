@@ -0,0 +1,294 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use csscolorparser::Color;
+
+/// The layout of the color channels of a single LED in the buffer of a device.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LedLayout {
+    /// Three bytes per LED, in the red, green, and blue order.
+    #[default]
+    Rgb,
+    /// Four bytes per LED, in the red, green, blue, and white order. This is used by RGBW strips,
+    /// like the SK6812 RGBW, which have a dedicated white LED. The white channel is derived from
+    /// the color using [`rgb_to_rgbw()`].
+    Rgbw,
+}
+
+impl LedLayout {
+    /// The number of bytes a single LED uses with this layout.
+    pub fn bytes_per_led(self) -> usize {
+        match self {
+            LedLayout::Rgb => 3,
+            LedLayout::Rgbw => 4,
+        }
+    }
+
+    /// Encode the color into the given buffer using this layout.
+    ///
+    /// The buffer needs to be at least [`LedLayout::bytes_per_led()`] bytes long.
+    pub fn encode(self, color: &Color, buffer: &mut [u8]) {
+        let [r, g, b, _] = color.to_rgba8();
+
+        match self {
+            LedLayout::Rgb => buffer[..3].copy_from_slice(&[r, g, b]),
+            LedLayout::Rgbw => buffer[..4].copy_from_slice(&rgb_to_rgbw([r, g, b])),
+        }
+    }
+
+    /// Decode a color which was encoded into the given buffer using this layout.
+    ///
+    /// The white channel of the [`LedLayout::Rgbw`] layout is added back to the color channels.
+    pub fn decode(self, buffer: &[u8]) -> Color {
+        let [r, g, b] = match self {
+            LedLayout::Rgb => [buffer[0], buffer[1], buffer[2]],
+            LedLayout::Rgbw => {
+                let white = buffer[3];
+
+                [
+                    buffer[0].saturating_add(white),
+                    buffer[1].saturating_add(white),
+                    buffer[2].saturating_add(white),
+                ]
+            }
+        };
+
+        Color::from_rgba8(r, g, b, 255)
+    }
+}
+
+/// A reduction of colors to the ones a device is able to represent.
+///
+/// Some LED controllers don't support the full 8 bits per color channel or only support a fixed
+/// set of colors. Quantizing the colors before they are encoded makes the LEDs of the device
+/// match what we think the device shows, for example when the LEDs are read back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorQuantization {
+    /// Every color channel only has the given number of bits, between 1 and 8.
+    BitDepth { red: u8, green: u8, blue: u8 },
+    /// The device can only show the colors of the given palette, every color is snapped to the
+    /// closest color of the palette.
+    Palette(Vec<Color>),
+}
+
+impl ColorQuantization {
+    /// The bit depth of the RGB565 format, which is used by the [`USBD480Display`].
+    ///
+    /// [`USBD480Display`]: super::USBD480Display
+    pub const RGB565: Self = Self::BitDepth {
+        red: 5,
+        green: 6,
+        blue: 5,
+    };
+
+    /// Snap the given color to the closest color the device can represent.
+    ///
+    /// The alpha channel of the color is left untouched. An empty palette leaves the color
+    /// untouched as well.
+    pub fn quantize(&self, color: &Color) -> Color {
+        let [r, g, b, a] = color.to_rgba8();
+
+        let [r, g, b] = match self {
+            ColorQuantization::BitDepth { red, green, blue } => [
+                quantize_channel(r, *red),
+                quantize_channel(g, *green),
+                quantize_channel(b, *blue),
+            ],
+            ColorQuantization::Palette(palette) => {
+                let distance = |color: &Color| {
+                    let [pr, pg, pb, _] = color.to_rgba8();
+
+                    [(r, pr), (g, pg), (b, pb)]
+                        .iter()
+                        .map(|&(a, b)| (a as i32 - b as i32).pow(2))
+                        .sum::<i32>()
+                };
+
+                match palette.iter().min_by_key(|color| distance(color)) {
+                    Some(closest) => {
+                        let [r, g, b, _] = closest.to_rgba8();
+                        [r, g, b]
+                    }
+                    None => [r, g, b],
+                }
+            }
+        };
+
+        Color::from_rgba8(r, g, b, a)
+    }
+}
+
+/// Reduce the 8 bit channel value to the given number of bits and scale it back to the 8 bit
+/// range, so the closest value the channel can represent is returned.
+fn quantize_channel(value: u8, bits: u8) -> u8 {
+    let bits = bits.clamp(1, 8);
+    let max = (1u32 << bits) - 1;
+
+    let quantized = (value as u32 * max + 127) / 255;
+
+    ((quantized * 255 + max / 2) / max) as u8
+}
+
+/// Convert a RGB color into a RGBW color.
+///
+/// The part of the color which all three channels have in common, `min(r, g, b)`, is moved into
+/// the white channel and subtracted from the color channels.
+pub fn rgb_to_rgbw([r, g, b]: [u8; 3]) -> [u8; 4] {
+    let white = r.min(g).min(b);
+
+    [r - white, g - white, b - white, white]
+}
+
+#[cfg(test)]
+mod test {
+    use similar_asserts::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn white_extraction() {
+        assert_eq!(rgb_to_rgbw([0, 0, 0]), [0, 0, 0, 0]);
+        assert_eq!(
+            rgb_to_rgbw([255, 255, 255]),
+            [0, 0, 0, 255],
+            "Pure white should only use the white channel"
+        );
+        assert_eq!(
+            rgb_to_rgbw([255, 0, 0]),
+            [255, 0, 0, 0],
+            "Saturated colors should not use the white channel"
+        );
+        assert_eq!(
+            rgb_to_rgbw([200, 150, 100]),
+            [100, 50, 0, 100],
+            "The smallest channel should be moved into the white channel"
+        );
+    }
+
+    #[test]
+    fn encoding() {
+        let color = Color::from_rgba8(255, 128, 64, 255);
+
+        let mut buffer = [0u8; 4];
+        LedLayout::Rgb.encode(&color, &mut buffer);
+        assert_eq!(buffer, [255, 128, 64, 0]);
+
+        LedLayout::Rgbw.encode(&color, &mut buffer);
+        assert_eq!(buffer, [191, 64, 0, 64]);
+    }
+
+    #[test]
+    fn quantize_gradient_to_five_bits() {
+        let quantization = ColorQuantization::BitDepth {
+            red: 5,
+            green: 5,
+            blue: 5,
+        };
+
+        let gradient: Vec<Color> = (0..=255)
+            .map(|value| Color::from_rgba8(value, 255 - value, value / 2, 255))
+            .collect();
+
+        let quantized: Vec<Color> = gradient.iter().map(|c| quantization.quantize(c)).collect();
+
+        // The 8-bit values of the 32 levels a 5-bit channel has, evenly spread out between 0 and
+        // 255.
+        let five_bit_levels: Vec<u8> = (0..32u32)
+            .map(|level| ((level * 255 + 15) / 31) as u8)
+            .collect();
+
+        for (color, quantized) in gradient.iter().zip(&quantized) {
+            let [r, g, b, _] = color.to_rgba8();
+            let [qr, qg, qb, _] = quantized.to_rgba8();
+
+            for (value, quantized) in [(r, qr), (g, qg), (b, qb)] {
+                assert!(
+                    (value as i32 - quantized as i32).abs() <= 4,
+                    "The quantized value {quantized} should be the closest 5-bit value to {value}"
+                );
+                assert!(
+                    five_bit_levels.contains(&quantized),
+                    "The quantized value {quantized} should be representable using 5 bits"
+                );
+            }
+
+            assert_eq!(
+                quantization.quantize(quantized).to_rgba8(),
+                quantized.to_rgba8(),
+                "Quantizing an already quantized color should not change it"
+            );
+        }
+
+        let mut levels: Vec<_> = quantized.iter().map(|c| c.to_rgba8()[0]).collect();
+        levels.dedup();
+
+        assert_eq!(levels.len(), 32, "A 5-bit channel should have 32 levels");
+        assert_eq!(levels.first(), Some(&0));
+        assert_eq!(levels.last(), Some(&255));
+    }
+
+    #[test]
+    fn quantize_to_palette() {
+        let palette = ColorQuantization::Palette(vec![
+            Color::from_html("red").unwrap(),
+            Color::from_html("lime").unwrap(),
+            Color::from_html("black").unwrap(),
+        ]);
+
+        assert_eq!(
+            palette
+                .quantize(&Color::from_rgba8(200, 60, 20, 255))
+                .to_rgba8(),
+            [255, 0, 0, 255],
+            "A reddish color should be snapped to red"
+        );
+        assert_eq!(
+            palette
+                .quantize(&Color::from_rgba8(20, 30, 10, 255))
+                .to_rgba8(),
+            [0, 0, 0, 255],
+            "A dark color should be snapped to black"
+        );
+
+        let color = Color::from_rgba8(20, 30, 10, 255);
+        assert_eq!(
+            ColorQuantization::Palette(Vec::new()).quantize(&color),
+            color,
+            "An empty palette should leave the color untouched"
+        );
+    }
+
+    #[test]
+    fn decoding() {
+        let color = Color::from_rgba8(255, 128, 64, 255);
+
+        for layout in [LedLayout::Rgb, LedLayout::Rgbw] {
+            let mut buffer = [0u8; 4];
+            layout.encode(&color, &mut buffer);
+
+            assert_eq!(
+                layout.decode(&buffer).to_rgba8(),
+                color.to_rgba8(),
+                "Decoding an encoded color should give back the same color using the {layout:?} \
+                 layout"
+            );
+        }
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use hidapi::HidDevice;
+
+/// The raw HID operations the LM-X devices need.
+///
+/// This is implemented for [`HidDevice`], it mainly exists so tests can inject a device which
+/// records the reports instead of talking to real hardware.
+pub trait HidTransport {
+    fn send_feature_report(&self, data: &[u8]) -> Result<()>;
+    fn write(&self, data: &[u8]) -> Result<usize>;
+}
+
+impl HidTransport for HidDevice {
+    fn send_feature_report(&self, data: &[u8]) -> Result<()> {
+        Ok(HidDevice::send_feature_report(self, data)?)
+    }
+
+    fn write(&self, data: &[u8]) -> Result<usize> {
+        Ok(HidDevice::write(self, data)?)
+    }
+}
+
+/// A HID device shared by the LM-X devices, which sends reports with consistent error context and
+/// optionally retries failed reports.
+pub struct LmxHidDevice {
+    transport: Box<dyn HidTransport>,
+    retries: u32,
+    retry_backoff: Duration,
+}
+
+impl LmxHidDevice {
+    /// How long we wait before the first retry of a failed report, unless a different backoff is
+    /// set using [`LmxHidDevice::with_retry_backoff()`].
+    pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(5);
+    /// The longest we wait before a retry, regardless of the number of retries.
+    const MAX_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+    pub fn new(transport: Box<dyn HidTransport>) -> Self {
+        Self {
+            transport,
+            retries: 0,
+            retry_backoff: Self::DEFAULT_RETRY_BACKOFF,
+        }
+    }
+
+    /// Retry failed reports up to the given number of times before giving up.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set how long we wait before the first retry of a failed report, the wait is doubled for
+    /// every further retry, up to a second, so a busy device gets some time to recover.
+    pub fn with_retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Get how long we wait before the given retry, the first retry being zero.
+    fn retry_delay(&self, retry: u32) -> Duration {
+        self.retry_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(Self::MAX_RETRY_DELAY)
+    }
+
+    fn retry<T>(&self, mut operation: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut attempt = 0;
+
+        loop {
+            match operation() {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.retries => {
+                    let delay = self.retry_delay(attempt);
+                    tracing::debug!("Retrying a failed HID report in {delay:?}: {e:#}");
+
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Send the given feature report, the first byte of the report is the report ID.
+    pub fn send_feature(&self, data: &[u8]) -> Result<()> {
+        self.retry(|| self.transport.send_feature_report(data))
+            .with_context(|| {
+                format!(
+                    "Could not send the HID feature report with the ID {:#04x}",
+                    data.first().copied().unwrap_or_default()
+                )
+            })
+    }
+
+    /// Write the given output report, the first byte of the report is the report ID.
+    pub fn write_report(&self, data: &[u8]) -> Result<()> {
+        self.retry(|| {
+            let written = self.transport.write(data)?;
+
+            anyhow::ensure!(
+                written == data.len(),
+                "Only {written} out of {} bytes were written",
+                data.len()
+            );
+
+            Ok(())
+        })
+        .with_context(|| {
+            format!(
+                "Could not write the HID output report with the ID {:#04x}",
+                data.first().copied().unwrap_or_default()
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, rc::Rc, time::Instant};
+
+    use similar_asserts::assert_eq;
+
+    use super::*;
+
+    /// A device which fails a number of times before it starts to accept reports.
+    #[derive(Clone, Default)]
+    struct FlakyDevice {
+        failures_left: Rc<Cell<u32>>,
+        attempts: Rc<Cell<u32>>,
+    }
+
+    impl FlakyDevice {
+        fn attempt(&self) -> Result<()> {
+            self.attempts.set(self.attempts.get() + 1);
+
+            match self.failures_left.get() {
+                0 => Ok(()),
+                failures_left => {
+                    self.failures_left.set(failures_left - 1);
+                    anyhow::bail!("The device is busy")
+                }
+            }
+        }
+    }
+
+    impl HidTransport for FlakyDevice {
+        fn send_feature_report(&self, _: &[u8]) -> Result<()> {
+            self.attempt()
+        }
+
+        fn write(&self, data: &[u8]) -> Result<usize> {
+            self.attempt().map(|_| data.len())
+        }
+    }
+
+    #[test]
+    fn retry() {
+        let transport = FlakyDevice::default();
+        transport.failures_left.set(2);
+
+        let device = LmxHidDevice::new(Box::new(transport.clone()))
+            .with_retries(2)
+            .with_retry_backoff(Duration::from_millis(10));
+
+        let start = Instant::now();
+
+        device
+            .send_feature(&[0x00, 0x09])
+            .expect("The report should succeed on the third attempt");
+        assert_eq!(transport.attempts.get(), 3);
+        assert!(
+            start.elapsed() >= Duration::from_millis(30),
+            "The retries should wait for the backoff, waited only {:?}",
+            start.elapsed()
+        );
+
+        transport.failures_left.set(2);
+        transport.attempts.set(0);
+
+        let device = LmxHidDevice::new(Box::new(transport.clone())).with_retries(1);
+
+        let error = device
+            .write_report(&[0x00, 0xff])
+            .expect_err("The report should fail if the device fails more often than we retry");
+        assert_eq!(transport.attempts.get(), 2);
+        assert_eq!(
+            error.to_string(),
+            "Could not write the HID output report with the ID 0x00",
+            "The error should contain the context of the report"
+        );
+    }
+
+    #[test]
+    fn retry_delay() {
+        let device = LmxHidDevice::new(Box::new(FlakyDevice::default()));
+
+        assert_eq!(
+            (0..4)
+                .map(|retry| device.retry_delay(retry))
+                .collect::<Vec<_>>(),
+            [5, 10, 20, 40].map(Duration::from_millis),
+            "The delay should double for every retry"
+        );
+        assert_eq!(
+            device.retry_delay(u32::MAX),
+            LmxHidDevice::MAX_RETRY_DELAY,
+            "The delay should be capped instead of overflowing"
+        );
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use anyhow::{ensure, Result};
+
+/// A mapping from logical LED indices to physical LED indices of a device.
+///
+/// The logical order of the LEDs is the left to right order the LED profiles assume, on some
+/// devices the LEDs are wired in a different order. This map is used to remap the logical index of
+/// a LED to the slot the LED has in the buffer of the device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexMap {
+    physical_indices: Vec<usize>,
+}
+
+impl IndexMap {
+    /// Create a map which maps every logical index to the same physical index.
+    pub fn identity(led_count: usize) -> Self {
+        Self {
+            physical_indices: (0..led_count).collect(),
+        }
+    }
+
+    /// Create a map from the given list of physical indices, the logical LED `n` will be mapped
+    /// to the physical index found at `physical_indices[n]`.
+    ///
+    /// The list needs to be a permutation of the numbers between `0` and `led_count - 1`,
+    /// otherwise an error is returned.
+    pub fn new(physical_indices: Vec<usize>, led_count: usize) -> Result<Self> {
+        ensure!(
+            physical_indices.len() == led_count,
+            "The LED index map contains {} entries, but the device has {led_count} LEDs",
+            physical_indices.len()
+        );
+
+        let mut seen = vec![false; led_count];
+
+        for &index in &physical_indices {
+            ensure!(
+                index < led_count,
+                "The LED index map contains the index {index}, but the device only has \
+                 {led_count} LEDs"
+            );
+            ensure!(
+                !seen[index],
+                "The LED index map contains the index {index} more than once"
+            );
+
+            seen[index] = true;
+        }
+
+        Ok(Self { physical_indices })
+    }
+
+    /// Get the physical index of the LED with the given logical index.
+    ///
+    /// Returns `None` if the logical index is out of bounds.
+    pub fn physical_index(&self, logical_index: usize) -> Option<usize> {
+        self.physical_indices.get(logical_index).copied()
+    }
+
+    /// The number of LEDs this map contains.
+    pub fn len(&self) -> usize {
+        self.physical_indices.len()
+    }
+
+    /// Does the map contain no LEDs at all?
+    pub fn is_empty(&self) -> bool {
+        self.physical_indices.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use similar_asserts::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn identity() {
+        let map = IndexMap::identity(4);
+
+        assert_eq!(
+            (0..5).map(|i| map.physical_index(i)).collect::<Vec<_>>(),
+            vec![Some(0), Some(1), Some(2), Some(3), None],
+            "The identity map should map every index to itself"
+        );
+    }
+
+    #[test]
+    fn reversed() {
+        let map = IndexMap::new(vec![3, 2, 1, 0], 4).expect("A reversed map should be valid");
+
+        assert_eq!(
+            (0..4).map(|i| map.physical_index(i)).collect::<Vec<_>>(),
+            vec![Some(3), Some(2), Some(1), Some(0)],
+            "The reversed map should map the first LED to the last one"
+        );
+    }
+
+    #[test]
+    fn shuffled() {
+        let map = IndexMap::new(vec![2, 0, 3, 1], 4).expect("A shuffled map should be valid");
+
+        assert_eq!(
+            (0..4).map(|i| map.physical_index(i)).collect::<Vec<_>>(),
+            vec![Some(2), Some(0), Some(3), Some(1)],
+        );
+    }
+
+    #[test]
+    fn invalid_permutation() {
+        IndexMap::new(vec![0, 1, 2], 4).expect_err("A map that is too short should be rejected");
+        IndexMap::new(vec![0, 1, 2, 4], 4)
+            .expect_err("A map containing an out of bounds index should be rejected");
+        IndexMap::new(vec![0, 1, 1, 3], 4)
+            .expect_err("A map containing a duplicate index should be rejected");
+    }
+}
@@ -18,17 +18,48 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use std::{
+    ops::Range,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
 use anyhow::{Context as _, Result};
 use csscolorparser::Color;
-use hidapi::{HidApi, HidDevice};
+use hidapi::HidApi;
 use simetry::assetto_corsa_competizione::Client;
 use strum::{EnumIter, IntoEnumIterator};
 
-use crate::led::state::{groups::GroupState, LedConfiguration, LedEffect, LedState};
+use super::{ColorQuantization, IndexMap, LedLayout, LedOutput, LmxHidDevice, Stats, ZoneMap};
+use crate::{
+    dashboard::sim_dashboard::{MomentSource, SimDashboard},
+    led::{
+        profiles::LedProfile,
+        state::{groups::GroupState, LedConfiguration, LedEffect, LedState},
+    },
+};
 
 pub struct LmxLeds {
-    device: HidDevice,
-    leds: [u8; 21 * 4],
+    device: LmxHidDevice,
+    led_count: usize,
+    leds: Vec<u8>,
+    /// The LED buffer as it was last committed to the device, `None` if we don't know what the
+    /// device is showing.
+    committed: Option<Vec<u8>>,
+    /// Which of the physical LEDs were staged since the LEDs were last presented.
+    staged: Vec<bool>,
+    index_map: IndexMap,
+    /// Should the LEDs be mirrored, see [`LmxLeds::set_mirrored()`].
+    mirrored: bool,
+    zone_map: ZoneMap,
+    quantization: Option<ColorQuantization>,
+    stats: Arc<Mutex<Stats>>,
+    /// How long to wait for telemetry before the idle animation takes over, see
+    /// [`LmxLeds::set_idle_timeout()`]. The default of the [`SimDashboard`] is used if not set.
+    idle_timeout: Option<Duration>,
+    /// The minimal time between two frames sent to the device, see
+    /// [`LmxLeds::set_min_apply_interval()`].
+    min_apply_interval: Duration,
 }
 
 #[derive(Debug, Clone, Copy, EnumIter)]
@@ -49,11 +80,7 @@ impl<'a> Led<'a> {
     }
 
     pub fn set_color(&mut self, color: &Color) {
-        let [r, g, b, _] = color.to_rgba8();
-
-        self.buffer[0] = r;
-        self.buffer[1] = g;
-        self.buffer[2] = b;
+        LedLayout::Rgb.encode(color, self.buffer);
     }
 
     pub fn set_brightness(&mut self, brightness: u8) {
@@ -63,7 +90,7 @@ impl<'a> Led<'a> {
 
 pub struct LedSegment<'a> {
     buffer: &'a mut [u8],
-    device: &'a HidDevice,
+    device: &'a LmxHidDevice,
 }
 
 impl<'a> LedSegment<'a> {
@@ -87,13 +114,11 @@ impl<'a> LedSegment<'a> {
     }
 
     pub fn commit_segment(&self) -> Result<()> {
-        self.device
-            .send_feature_report(self.as_bytes())
-            .with_context(|| {
-                let segment_id = self.buffer[1];
+        self.device.send_feature(self.as_bytes()).with_context(|| {
+            let segment_id = self.buffer[1];
 
-                format!("Could not commit the LED segment {segment_id:x}")
-            })
+            format!("Could not commit the LED segment {segment_id:x}")
+        })
     }
 }
 
@@ -102,33 +127,172 @@ impl LmxLeds {
     const PID: u16 = 0x32af;
 
     const COMMAND_BUFFER_SIZE: usize = 21;
-    const SEGMENT_COUNT: usize = 4;
+    /// The IDs of the LED segments, every segment controls four LEDs.
+    const SEGMENT_IDS: [u8; 4] = [0x02, 0x03, 0x07, 0x08];
+    const LEDS_PER_SEGMENT: usize = 4;
+
+    /// The number of RPM LEDs the device has, unless a different number was given when opening
+    /// the device.
+    pub const DEFAULT_LED_COUNT: usize = Self::SEGMENT_IDS.len() * Self::LEDS_PER_SEGMENT;
+
+    /// The brightness value the device uses for LEDs at full brightness.
+    const MAX_BRIGHTNESS: u8 = 0x04;
+
+    /// The minimal time between two frames sent to the device.
+    ///
+    /// Every frame is sent as a feature report for every LED segment, which takes a lot longer
+    /// than the simulators take to produce a new sim state.
+    const DEFAULT_MIN_APPLY_INTERVAL: Duration = Duration::from_millis(20);
 
     pub fn open(hidapi: &HidApi) -> Result<Self> {
+        Self::open_with_led_count(hidapi, Self::DEFAULT_LED_COUNT)
+    }
+
+    /// Open a variant of the RPM LEDs which has the given number of LEDs.
+    ///
+    /// The protocol doesn't allow us to ask the device how many LEDs it has. Variants with fewer
+    /// LEDs use fewer LED segments, the number of LEDs can't be larger than
+    /// [`LmxLeds::DEFAULT_LED_COUNT`].
+    pub fn open_with_led_count(hidapi: &HidApi, led_count: usize) -> Result<Self> {
         let inner = hidapi
             .open(Self::VID, Self::PID)
             .context("Could not open the LM-X RPM LEDs")?;
 
-        let mut leds = [0u8; Self::COMMAND_BUFFER_SIZE * Self::SEGMENT_COUNT];
+        tracing::debug!(led_count, "Opened the LM-X RPM LEDs");
 
-        for (i, chunk) in leds.chunks_exact_mut(Self::COMMAND_BUFFER_SIZE).enumerate() {
-            let segment_id = match i {
-                0 => 0x02,
-                1 => 0x03,
-                2 => 0x07,
-                3 => 0x08,
-                _ => unreachable!("We should only have 4 LED segments"),
-            };
+        Self::with_device(LmxHidDevice::new(Box::new(inner)), led_count)
+    }
 
+    fn with_device(device: LmxHidDevice, led_count: usize) -> Result<Self> {
+        anyhow::ensure!(
+            (1..=Self::DEFAULT_LED_COUNT).contains(&led_count),
+            "Invalid number of RPM LEDs {led_count}, the device supports between 1 and {} LEDs",
+            Self::DEFAULT_LED_COUNT
+        );
+
+        let segment_count = led_count.div_ceil(Self::LEDS_PER_SEGMENT);
+        let mut leds = vec![0u8; Self::COMMAND_BUFFER_SIZE * segment_count];
+
+        for (chunk, segment_id) in leds
+            .chunks_exact_mut(Self::COMMAND_BUFFER_SIZE)
+            .zip(Self::SEGMENT_IDS)
+        {
             chunk[1] = segment_id;
         }
 
         Ok(Self {
-            device: inner,
+            device,
+            led_count,
             leds,
+            committed: None,
+            staged: vec![false; led_count],
+            index_map: IndexMap::identity(led_count),
+            mirrored: false,
+            zone_map: ZoneMap::default(),
+            quantization: None,
+            stats: Default::default(),
+            idle_timeout: None,
+            min_apply_interval: Self::DEFAULT_MIN_APPLY_INTERVAL,
         })
     }
 
+    /// The number of RPM LEDs the device has.
+    pub fn led_count(&self) -> usize {
+        self.led_count
+    }
+
+    /// Get a handle to the [`Stats`] of the LED profile runner.
+    ///
+    /// The handle can be used to observe the stats while [`LmxLeds::run_led_profile()`] is
+    /// running.
+    pub fn stats(&self) -> Arc<Mutex<Stats>> {
+        self.stats.clone()
+    }
+
+    /// Set how long [`LmxLeds::run_led_profile()`] waits for telemetry before it shows an idle
+    /// animation instead of the last frame of the profile.
+    pub fn set_idle_timeout(&mut self, timeout: Duration) {
+        self.idle_timeout = Some(timeout);
+    }
+
+    /// Set the minimal time between two frames [`LmxLeds::run_led_profile()`] sends to the
+    /// device.
+    ///
+    /// The effects of the profile are still updated for every sim state, frames which change the
+    /// LEDs too soon after the last frame are dropped.
+    pub fn set_min_apply_interval(&mut self, interval: Duration) {
+        self.min_apply_interval = interval;
+    }
+
+    /// Set the map which remaps the logical LED positions of a [`LedState`] to the physical LEDs
+    /// of the device.
+    pub fn set_index_map(&mut self, index_map: IndexMap) -> Result<()> {
+        anyhow::ensure!(
+            index_map.len() == self.led_count,
+            "The LED index map contains {} LEDs, but the device has {} LEDs",
+            index_map.len(),
+            self.led_count
+        );
+
+        self.index_map = index_map;
+
+        Ok(())
+    }
+
+    /// Mirror all the LEDs, the first LED of a profile is shown on the last LED of the device.
+    ///
+    /// This is useful for devices which are mounted the other way around than the profiles
+    /// assume, instead of changing the direction of every container. The LEDs are mirrored
+    /// before the [`IndexMap`] gets applied.
+    pub fn set_mirrored(&mut self, mirrored: bool) {
+        self.mirrored = mirrored;
+    }
+
+    /// Get the physical index of the LED with the given logical index, taking the mirroring and
+    /// the [`IndexMap`] into account.
+    fn physical_index(&self, logical_index: usize) -> Option<usize> {
+        let logical_index = if self.mirrored {
+            self.led_count.checked_sub(logical_index + 1)?
+        } else {
+            logical_index
+        };
+
+        self.index_map.physical_index(logical_index)
+    }
+
+    /// Snap the colors of the LEDs to the colors the device is able to represent before they are
+    /// written into the LED buffer. Passing `None` disables the quantization.
+    pub fn set_color_quantization(&mut self, quantization: Option<ColorQuantization>) {
+        self.quantization = quantization;
+    }
+
+    /// Set the map of named LED zones, which allows LED states to be applied to a zone using
+    /// [`LmxLeds::apply_to_zone()`].
+    pub fn set_zone_map(&mut self, zone_map: ZoneMap) -> Result<()> {
+        zone_map
+            .validate(self.led_count)
+            .context("The LED zones don't fit on the RPM LEDs")?;
+
+        self.zone_map = zone_map;
+
+        Ok(())
+    }
+
+    /// Get the map of named LED zones of this device.
+    pub fn zone_map(&self) -> &ZoneMap {
+        &self.zone_map
+    }
+
+    /// Apply the given LED state to the zone with the given name, see
+    /// [`ZoneMap::apply_to_zone()`].
+    ///
+    /// Returns the number of LEDs that were written.
+    pub fn apply_to_zone(&mut self, name: &str, led_state: &LedState) -> Result<usize> {
+        let led_state = self.zone_map.apply_to_zone(name, led_state)?;
+
+        self.apply_led_state(&led_state)
+    }
+
     fn commit(&self) -> Result<()> {
         // Data for the LED commit command.
         const COMMIT_COMMAND: &[u8] = &[
@@ -137,7 +301,7 @@ impl LmxLeds {
         ];
 
         self.device
-            .send_feature_report(COMMIT_COMMAND)
+            .send_feature(COMMIT_COMMAND)
             .context("Could not commit the new LED data")?;
 
         Ok(())
@@ -158,69 +322,826 @@ impl LmxLeds {
         }
 
         self.commit()?;
+        self.committed = Some(self.leds.clone());
 
         Ok(())
     }
 
     pub fn segments(&mut self) -> impl Iterator<Item = LedSegment> {
-        self.leds.chunks_exact_mut(21).map(|buffer| LedSegment {
-            buffer,
-            device: &self.device,
-        })
+        self.leds
+            .chunks_exact_mut(Self::COMMAND_BUFFER_SIZE)
+            .map(|buffer| LedSegment {
+                buffer,
+                device: &self.device,
+            })
+    }
+
+    /// Convert the brightness of a [`LedConfiguration`] into the brightness value of the device.
+    ///
+    /// A brightness of zero would turn the LED off, so LEDs which are turned on are kept at the
+    /// lowest brightness the device supports.
+    fn brightness_value(brightness: f64) -> u8 {
+        let value = (brightness.clamp(0.0, 1.0) * Self::MAX_BRIGHTNESS as f64).round() as u8;
+
+        value.max(1)
+    }
+
+    /// Get the range of the buffer which contains the LED with the given physical index.
+    fn led_range(index: usize) -> Option<Range<usize>> {
+        let segment = index / LedNumber::iter().len();
+        let led = LedNumber::iter().nth(index % LedNumber::iter().len())? as usize;
+
+        let start = segment * Self::COMMAND_BUFFER_SIZE + led;
+
+        Some(start..start + LedSegment::BYTES_PER_LED)
+    }
+
+    fn led(&mut self, index: usize) -> Option<Led<'_>> {
+        if index >= self.led_count {
+            return None;
+        }
+
+        let buffer = self.leds.get_mut(Self::led_range(index)?)?;
+
+        Some(Led::new(buffer))
+    }
+
+    /// Read back the LEDs the device is currently showing, in the logical order of the
+    /// [`IndexMap`].
+    ///
+    /// The LEDs are read from the in-memory buffer, so this only contains the LEDs this instance
+    /// has written. LEDs with a brightness of zero are considered to be turned off.
+    pub fn led_configurations(&self) -> Vec<LedConfiguration> {
+        (0..self.led_count)
+            .map(|logical_index| {
+                let buffer = self
+                    .physical_index(logical_index)
+                    .and_then(Self::led_range)
+                    .and_then(|range| self.leds.get(range));
+
+                match buffer {
+                    Some(buffer) if buffer[3] != 0 => LedConfiguration::On {
+                        color: LedLayout::Rgb.decode(buffer),
+                        brightness: (buffer[3] as f64 / Self::MAX_BRIGHTNESS as f64).min(1.0),
+                    },
+                    _ => LedConfiguration::Off,
+                }
+            })
+            .collect()
     }
 
     pub fn leds(&mut self) -> impl Iterator<Item = Led> {
         self.leds
-            .chunks_exact_mut(21)
+            .chunks_exact_mut(Self::COMMAND_BUFFER_SIZE)
             .flat_map(|segment| segment[2..].chunks_exact_mut(LedSegment::BYTES_PER_LED))
+            .take(self.led_count)
             .map(Led::new)
     }
 
-    pub fn apply_led_state(&mut self, led_state: &LedState) -> Result<()> {
+    /// Apply the given LED state to the device.
+    ///
+    /// Returns the number of LEDs that were written. If the LED state doesn't fit on the device,
+    /// the LEDs past the end of the device are dropped and the returned number will be smaller
+    /// than the number of LEDs the state contains.
+    pub fn apply_led_state(&mut self, led_state: &LedState) -> Result<usize> {
+        let written = self.stage(led_state);
+        self.present()?;
+
+        Ok(written)
+    }
+
+    /// Write the given LED state into the LED buffer, without sending anything to the device.
+    ///
+    /// The staged LEDs are shown once [`LmxLeds::present()`] is called. Returns the number of
+    /// LEDs that were written, see [`LmxLeds::apply_led_state()`].
+    pub fn stage(&mut self, led_state: &LedState) -> usize {
         let start_led = led_state.start_position().get();
+        let mut written = 0;
+
+        for (logical_index, led_config) in (start_led - 1..).zip(led_state.leds()) {
+            let physical_index = self.physical_index(logical_index);
+
+            // A transparent LED which is turned off keeps whatever an earlier LED state staged
+            // for this frame.
+            let transparent =
+                led_state.is_transparent_when_off() && *led_config == LedConfiguration::Off;
+
+            if transparent
+                && physical_index.is_some_and(|index| self.staged.get(index) == Some(&true))
+            {
+                written += 1;
+                continue;
+            }
+
+            let color = match (led_config, &self.quantization) {
+                (LedConfiguration::On { color, .. }, Some(quantization)) => {
+                    Some(quantization.quantize(color))
+                }
+                (LedConfiguration::On { color, .. }, None) => Some(color.clone()),
+                (LedConfiguration::Off, _) => None,
+            };
 
-        for (mut led, led_config) in self.leds().skip(start_led - 1).zip(led_state.leds()) {
-            // TODO: Don't hardcode the brightness here.
-            match led_config {
-                LedConfiguration::On { color } => {
-                    led.set_color(color);
-                    led.set_brightness(0x04);
+            let Some((physical_index, mut led)) = physical_index.and_then(|physical_index| {
+                self.led(physical_index).map(|led| (physical_index, led))
+            }) else {
+                break;
+            };
+
+            written += 1;
+
+            match (led_config, color) {
+                (LedConfiguration::On { brightness, .. }, Some(color)) => {
+                    led.set_color(&color);
+                    led.set_brightness(Self::brightness_value(*brightness));
                 }
-                LedConfiguration::Off => led.set_brightness(0x00),
+                _ => led.set_brightness(0x00),
             }
+
+            self.staged[physical_index] = true;
         }
 
-        for segment in self.segments() {
-            segment
-                .commit_segment()
-                .context("Could not commit a LED segment while applying a new LED state")?;
+        written
+    }
+
+    /// Stage all the LEDs of the device as turned off, the LEDs are turned off once they're
+    /// presented.
+    pub fn clear_all(&mut self) {
+        for index in 0..self.led_count {
+            if let Some(mut led) = self.led(index) {
+                led.set_color(&Color::from_rgba8(0x00, 0x00, 0x00, 0x00));
+                led.set_brightness(0x00);
+            }
+        }
+    }
+
+    /// Send the LED buffer to the device and commit it, showing all the staged LEDs at once.
+    ///
+    /// Only the segments which changed since the last time the LEDs were presented are sent to
+    /// the device, the commit command is always sent.
+    pub fn present(&mut self) -> Result<()> {
+        // If sending a segment fails we don't know what the device shows, so all the segments
+        // will be sent the next time.
+        let committed = self.committed.take();
+        self.staged.fill(false);
+
+        for (number, segment) in self.segments().enumerate() {
+            let range =
+                number * Self::COMMAND_BUFFER_SIZE..(number + 1) * Self::COMMAND_BUFFER_SIZE;
+            let committed_segment = committed.as_ref().and_then(|c| c.get(range));
+
+            if committed_segment != Some(segment.as_bytes()) {
+                segment
+                    .commit_segment()
+                    .context("Could not commit a LED segment while applying a new LED state")?;
+            }
         }
 
         self.commit()
             .context("Could not commit the new LED data after applying a new LED state")?;
+        self.committed = Some(self.leds.clone());
 
         Ok(())
     }
 
-    pub async fn run_led_profile(&mut self, mut led_state: GroupState) -> Result<()> {
-        println!("Running RPM based LED configuration:\n\t",);
+    /// Check that all the LED states of the given group fit on the device.
+    pub fn validate_group(&self, group: &GroupState) -> Result<()> {
+        for state in group.leds() {
+            let last_led = state.start_position().get() - 1 + state.leds().len();
+
+            anyhow::ensure!(
+                last_led <= self.led_count,
+                "The LED state starting at position {} ends at the LED {last_led}, but the device \
+                 only has {} LEDs",
+                state.start_position(),
+                self.led_count
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Create a [`SimDashboard`] running the given LED profile on the given RPM LEDs, using the
+    /// idle timeout, the minimal apply interval, and the stats handle of the LEDs.
+    pub(crate) fn dashboard<S, O>(leds: O, profile: LedProfile, source: S) -> SimDashboard<S, O>
+    where
+        S: MomentSource,
+        O: LedOutput + std::borrow::Borrow<LmxLeds>,
+    {
+        let (idle_timeout, min_apply_interval, stats) = {
+            let leds: &LmxLeds = leds.borrow();
+            (
+                leds.idle_timeout,
+                leds.min_apply_interval,
+                leds.stats.clone(),
+            )
+        };
+
+        let dashboard = SimDashboard::new(profile, source, leds)
+            .with_min_apply_interval(min_apply_interval)
+            .with_shared_stats(stats);
+
+        match idle_timeout {
+            Some(idle_timeout) => dashboard.with_idle_timeout(idle_timeout),
+            None => dashboard,
+        }
+    }
+
+    /// Run the given LED profile, using the car specific containers of the profile if the current
+    /// car has any.
+    ///
+    /// The connection to the simulator is reestablished whenever it gets closed.
+    pub async fn run_led_profile(&mut self, profile: LedProfile) -> Result<()> {
+        tracing::info!(profile = profile.name, "Running the RPM based LED profile");
 
         self.turn_off()
             .context("Could not turn off the RPM LEDs to go back to the initial state")?;
 
+        let client = Client::try_connect()
+            .await
+            .context("Could not connect to the Assetto Corsa Competizione SHM file")?;
+
+        let mut dashboard = Self::dashboard(&mut *self, profile, client);
+
         loop {
-            let mut client = Client::try_connect()
+            dashboard.run_source().await?;
+            dashboard.connection_lost();
+
+            let client = Client::try_connect()
                 .await
                 .context("Could not connect to the Assetto Corsa Competizione SHM file")?;
+            dashboard.set_source(client);
+        }
+    }
+}
 
-            while let Some(sim_state) = client.next_sim_state().await {
-                led_state.update(&sim_state);
+impl LedOutput for LmxLeds {
+    fn led_count(&self) -> usize {
+        LmxLeds::led_count(self)
+    }
 
-                for state in led_state.leds() {
-                    self.apply_led_state(state)
-                        .context("Could not apply the new LED state")?;
-                }
+    fn stage(&mut self, led_state: &LedState) -> Result<usize> {
+        Ok(LmxLeds::stage(self, led_state))
+    }
+
+    fn present(&mut self) -> Result<()> {
+        LmxLeds::present(self)
+    }
+
+    fn clear_all(&mut self) -> Result<()> {
+        LmxLeds::clear_all(self);
+        Ok(())
+    }
+}
+
+impl Drop for LmxLeds {
+    fn drop(&mut self) {
+        // Try to leave the LEDs turned off, otherwise the last frame stays lit. This could fail
+        // if the USB device was disconnected, in which case there's nothing left to turn off.
+        if let Err(e) = self.turn_off() {
+            tracing::debug!("Could not turn off the RPM LEDs: {e:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, num::NonZeroUsize, rc::Rc};
+
+    use similar_asserts::assert_eq;
+
+    use super::*;
+    use crate::devices::hid::HidTransport;
+
+    #[derive(Clone, Default)]
+    struct MockDevice {
+        reports: Rc<RefCell<Vec<Vec<u8>>>>,
+    }
+
+    impl HidTransport for MockDevice {
+        fn send_feature_report(&self, data: &[u8]) -> Result<()> {
+            self.reports.borrow_mut().push(data.to_vec());
+            Ok(())
+        }
+
+        fn write(&self, _: &[u8]) -> Result<usize> {
+            unreachable!("The RPM LEDs only use feature reports")
+        }
+    }
+
+    fn mock_leds(device: &MockDevice) -> LmxLeds {
+        LmxLeds::with_device(
+            LmxHidDevice::new(Box::new(device.clone())),
+            LmxLeds::DEFAULT_LED_COUNT,
+        )
+        .expect("The default LED count should be valid")
+    }
+
+    #[test]
+    fn truncated_led_state() {
+        let mut leds = mock_leds(&MockDevice::default());
+
+        let red = Color::from_html("red").unwrap();
+        let start_position = NonZeroUsize::new(13).unwrap();
+        let led_count = NonZeroUsize::new(6).unwrap();
+
+        let written = leds
+            .apply_led_state(&LedState::with_color(red, start_position, led_count))
+            .expect("We should be able to apply a LED state to the mock device");
+
+        assert_eq!(
+            written, 4,
+            "Only the LEDs up to the last LED of the device should have been written"
+        );
+
+        let start_position = NonZeroUsize::new(LmxLeds::DEFAULT_LED_COUNT + 1).unwrap();
+
+        let written = leds
+            .apply_led_state(&LedState::new(start_position, led_count))
+            .expect("We should be able to apply a LED state to the mock device");
+
+        assert_eq!(
+            written, 0,
+            "A LED state starting past the last LED of the device should not write anything"
+        );
+    }
+
+    #[test]
+    fn reversed_index_map() {
+        let mut leds = mock_leds(&MockDevice::default());
+
+        let index_map = IndexMap::new(
+            (0..LmxLeds::DEFAULT_LED_COUNT).rev().collect(),
+            LmxLeds::DEFAULT_LED_COUNT,
+        )
+        .expect("A reversed index map should be valid");
+        leds.set_index_map(index_map)
+            .expect("We should be able to use an index map with the same number of LEDs");
+
+        let red = Color::from_html("red").unwrap();
+        let led_count = NonZeroUsize::new(2).unwrap();
+
+        leds.apply_led_state(&LedState::with_color(red, NonZeroUsize::MIN, led_count))
+            .expect("We should be able to apply a LED state to the mock device");
+
+        let brightness: Vec<u8> = leds.leds().map(|led| led.buffer[3]).collect();
+        let mut expected = vec![0x00; LmxLeds::DEFAULT_LED_COUNT];
+        expected[LmxLeds::DEFAULT_LED_COUNT - 2..].copy_from_slice(&[0x04, 0x04]);
+
+        assert_eq!(
+            brightness, expected,
+            "The first two logical LEDs should have been mapped to the last two physical LEDs"
+        );
+    }
+
+    #[test]
+    fn mirrored() {
+        let mut leds = mock_leds(&MockDevice::default());
+        leds.set_mirrored(true);
+
+        let pattern = crate::leds!["red", "lime", off, "blue"];
+        leds.apply_led_state(&pattern)
+            .expect("We should be able to apply a LED state to the mock device");
+
+        let colors: Vec<_> = leds
+            .leds()
+            .map(|led| (led.buffer[3] != 0).then(|| LedLayout::Rgb.decode(led.buffer)))
+            .collect();
+        let mut expected = vec![None; LmxLeds::DEFAULT_LED_COUNT];
+        expected[LmxLeds::DEFAULT_LED_COUNT - 4..].clone_from_slice(&[
+            Some(Color::from_html("blue").unwrap()),
+            None,
+            Some(Color::from_html("lime").unwrap()),
+            Some(Color::from_html("red").unwrap()),
+        ]);
+
+        assert_eq!(
+            colors, expected,
+            "The pattern should have been written in reverse, starting at the last LED"
+        );
+        assert_eq!(
+            leds.led_configurations()[..4],
+            pattern.leds()[..],
+            "Reading the LEDs back should return them in the order of the profile"
+        );
+
+        let written = leds.stage(&crate::leds![LmxLeds::DEFAULT_LED_COUNT; "red"; 2]);
+        assert_eq!(
+            written, 1,
+            "LEDs past the last LED shouldn't wrap around to the start of the mirrored LEDs"
+        );
+    }
+
+    #[test]
+    fn read_back_led_state() {
+        let mut leds = mock_leds(&MockDevice::default());
+
+        let index_map = IndexMap::new(
+            (0..LmxLeds::DEFAULT_LED_COUNT).rev().collect(),
+            LmxLeds::DEFAULT_LED_COUNT,
+        )
+        .expect("A reversed index map should be valid");
+        leds.set_index_map(index_map)
+            .expect("We should be able to use an index map with the same number of LEDs");
+
+        assert_eq!(
+            leds.led_configurations(),
+            vec![LedConfiguration::Off; LmxLeds::DEFAULT_LED_COUNT],
+            "All the LEDs should be turned off before anything was applied"
+        );
+
+        let state = crate::leds![3; "red", off, "lime", "#0080ff"];
+        leds.apply_led_state(&state)
+            .expect("We should be able to apply a LED state to the mock device");
+
+        let mut expected = vec![LedConfiguration::Off; LmxLeds::DEFAULT_LED_COUNT];
+        expected[2..6].clone_from_slice(state.leds());
+
+        assert_eq!(
+            leds.led_configurations(),
+            expected,
+            "Reading the LEDs back should give us the applied LED state in the logical order"
+        );
+    }
+
+    #[test]
+    fn per_led_brightness() {
+        let device = MockDevice::default();
+        let mut leds = mock_leds(&device);
+
+        let red = Color::from_html("red").unwrap();
+        let dimmed = |brightness| LedConfiguration::On {
+            color: red.clone(),
+            brightness,
+        };
+
+        let state = LedState::from_configs(
+            NonZeroUsize::MIN,
+            vec![dimmed(1.0), dimmed(0.5), dimmed(0.25), dimmed(0.01)],
+        );
+
+        leds.apply_led_state(&state)
+            .expect("We should be able to apply a LED state to the mock device");
+
+        let reports = device.reports.borrow();
+        let first_segment = &reports[0];
+
+        let brightness: Vec<u8> = LedNumber::iter()
+            .map(|led| first_segment[led as usize + 3])
+            .collect();
+
+        assert_eq!(
+            brightness,
+            vec![0x04, 0x02, 0x01, 0x01],
+            "The brightness of every LED should be scaled to the brightness range of the device, \
+             without turning dimmed LEDs off"
+        );
+    }
+
+    #[test]
+    fn custom_led_count() {
+        let device = MockDevice::default();
+        let mut leds = LmxLeds::with_device(LmxHidDevice::new(Box::new(device.clone())), 6)
+            .expect("A device with six LEDs should be valid");
+
+        assert_eq!(leds.led_count(), 6);
+        assert_eq!(leds.leds().count(), 6);
+
+        let red = Color::from_html("red").unwrap();
+        let state = LedState::with_color(red, NonZeroUsize::MIN, NonZeroUsize::new(8).unwrap());
+
+        let written = leds
+            .apply_led_state(&state)
+            .expect("We should be able to apply a LED state to the mock device");
+
+        assert_eq!(
+            written, 6,
+            "Only the LEDs of the device should have been written"
+        );
+        assert_eq!(
+            device.reports.borrow().len(),
+            3,
+            "Two segments should be committed, followed by the commit command"
+        );
+        assert_eq!(leds.led_configurations().len(), 6);
+
+        leds.set_index_map(IndexMap::identity(LmxLeds::DEFAULT_LED_COUNT))
+            .expect_err("The index map should be validated against the custom LED count");
+
+        assert!(
+            LmxLeds::with_device(LmxHidDevice::new(Box::new(device.clone())), 0).is_err(),
+            "A device without LEDs should be rejected"
+        );
+        assert!(
+            LmxLeds::with_device(
+                LmxHidDevice::new(Box::new(device)),
+                LmxLeds::DEFAULT_LED_COUNT + 1
+            )
+            .is_err(),
+            "A device with more LEDs than there are segments should be rejected"
+        );
+    }
+
+    #[test]
+    fn group_validation() {
+        let profile = |start_position: usize| {
+            serde_json::from_value(serde_json::json!({
+                "Name": "Flag",
+                "ProfileId": "2d7f3ab0-5b38-4ad3-9a6b-2f3f1e6c7a90",
+                "GlobalBrightness": 1.0,
+                "UseProfileBrightness": false,
+                "LedContainers": [
+                    {
+                        "LedCount": 4,
+                        "Color": "Yellow",
+                        "BlinkEnabled": false,
+                        "StartPosition": start_position,
+                        "ContainerType": "YellowFlagContainer",
+                        "IsEnabled": true
+                    }
+                ]
+            }))
+            .expect("We should be able to deserialize the profile")
+        };
+
+        let leds = LmxLeds::with_device(LmxHidDevice::new(Box::new(MockDevice::default())), 8)
+            .expect("A device with eight LEDs should be valid");
+
+        leds.validate_group(&GroupState::root(profile(5)))
+            .expect("A group ending at the last LED should fit on the device");
+        leds.validate_group(&GroupState::root(profile(6)))
+            .expect_err("A group ending past the last LED should be rejected");
+    }
+
+    #[test]
+    fn no_commit_before_present() {
+        let device = MockDevice::default();
+        let mut leds = mock_leds(&device);
+
+        leds.stage(&crate::leds![1; "red"; 4]);
+        leds.stage(&crate::leds![9; "lime"; 4]);
+
+        assert!(
+            device.reports.borrow().is_empty(),
+            "Staging LED states should not send anything to the device"
+        );
+
+        leds.present()
+            .expect("We should be able to present the LEDs on the mock device");
+
+        let reports = device.reports.borrow();
+
+        assert_eq!(
+            reports.len(),
+            LmxLeds::SEGMENT_IDS.len() + 1,
+            "Presenting should commit every segment once, followed by a single commit command"
+        );
+        assert_eq!(reports[LmxLeds::SEGMENT_IDS.len()][1], 0x09);
+        assert_eq!(
+            reports[0][LedNumber::One as usize + 3],
+            0x04,
+            "The first staged LED state should have been presented"
+        );
+        assert_eq!(
+            reports[2][LedNumber::One as usize + 3],
+            0x04,
+            "The second staged LED state should have been presented"
+        );
+    }
+
+    #[test]
+    fn only_changed_segments_are_committed() {
+        let device = MockDevice::default();
+        let mut leds = mock_leds(&device);
+
+        leds.apply_led_state(&crate::leds![1; "red"; 16])
+            .expect("We should be able to apply a LED state to the mock device");
+
+        assert_eq!(
+            device.reports.borrow().len(),
+            LmxLeds::SEGMENT_IDS.len() + 1,
+            "The first frame should commit every segment"
+        );
+
+        device.reports.borrow_mut().clear();
+        leds.apply_led_state(&crate::leds![6; "lime"; 1])
+            .expect("We should be able to apply a LED state to the mock device");
+
+        let reports = device.reports.borrow();
+
+        assert_eq!(
+            reports.len(),
+            2,
+            "Only the changed segment should be committed, followed by the commit command"
+        );
+        assert_eq!(
+            reports[0][1],
+            LmxLeds::SEGMENT_IDS[1],
+            "The sixth LED belongs to the second segment"
+        );
+        assert_eq!(
+            reports[1][1], 0x09,
+            "The last report should be the commit command"
+        );
+        drop(reports);
+
+        device.reports.borrow_mut().clear();
+        leds.apply_led_state(&crate::leds![6; "lime"; 1])
+            .expect("We should be able to apply a LED state to the mock device");
+
+        assert_eq!(
+            device.reports.borrow().len(),
+            1,
+            "If nothing changed, only the commit command should be sent"
+        );
+    }
+
+    #[test]
+    fn apply_to_zone() {
+        let mut leds = mock_leds(&MockDevice::default());
+
+        let zone_map: ZoneMap = serde_json::from_value(serde_json::json!({
+            "shift lights": { "StartPosition": 5, "LedCount": 8 },
+        }))
+        .expect("We should be able to deserialize the zone map");
+
+        leds.set_zone_map(zone_map)
+            .expect("The zones should fit on the RPM LEDs");
+
+        let written = leds
+            .apply_to_zone("shift lights", &crate::leds!["red"; 10])
+            .expect("We should be able to apply a LED state to a known zone");
+
+        assert_eq!(
+            written, 8,
+            "Only the LEDs of the zone should have been written"
+        );
+
+        let mut expected = vec![LedConfiguration::Off; LmxLeds::DEFAULT_LED_COUNT];
+        expected[4..12].fill(crate::led!("red"));
+
+        assert_eq!(leds.led_configurations(), expected);
+
+        leds.apply_to_zone("left cluster", &crate::leds!["red"; 2])
+            .expect_err("Applying a LED state to an unknown zone should fail");
+
+        let zone_map: ZoneMap = serde_json::from_value(serde_json::json!({
+            "shift lights": { "StartPosition": 10, "LedCount": 8 },
+        }))
+        .expect("We should be able to deserialize the zone map");
+
+        leds.set_zone_map(zone_map)
+            .expect_err("Zones which don't fit on the RPM LEDs should be rejected");
+    }
+
+    #[test]
+    fn color_quantization() {
+        let mut leds = mock_leds(&MockDevice::default());
+        let color = Color::from_rgba8(200, 100, 13, 255);
+
+        leds.apply_led_state(&LedState::with_color(
+            color.clone(),
+            NonZeroUsize::MIN,
+            NonZeroUsize::MIN,
+        ))
+        .expect("We should be able to apply a LED state to the mock device");
+
+        assert_eq!(
+            leds.led_configurations()[0],
+            LedConfiguration::on(color.clone())
+        );
+
+        leds.set_color_quantization(Some(ColorQuantization::RGB565));
+        leds.apply_led_state(&LedState::with_color(
+            color,
+            NonZeroUsize::MIN,
+            NonZeroUsize::MIN,
+        ))
+        .expect("We should be able to apply a LED state to the mock device");
+
+        assert_eq!(
+            leds.led_configurations()[0],
+            LedConfiguration::on(Color::from_rgba8(197, 101, 16, 255)),
+            "The color should have been snapped to the closest RGB565 color"
+        );
+    }
+
+    #[test]
+    fn transparent_led_states() {
+        let mut leds = mock_leds(&MockDevice::default());
+        let red = Color::from_html("red").unwrap();
+        let blue = Color::from_html("blue").unwrap();
+
+        let base = LedState::with_color(
+            red.clone(),
+            NonZeroUsize::MIN,
+            NonZeroUsize::new(2).unwrap(),
+        );
+        let mut overlay = LedState::from_configs(
+            NonZeroUsize::MIN,
+            vec![LedConfiguration::Off, LedConfiguration::on(blue.clone())],
+        );
+        overlay.set_transparent_when_off(true);
+
+        leds.stage(&base);
+        leds.stage(&overlay);
+        leds.present().unwrap();
+
+        assert_eq!(
+            leds.led_configurations()[..2],
+            [
+                LedConfiguration::on(red),
+                LedConfiguration::on(blue.clone())
+            ],
+            "The LEDs of the base should show through the transparent LEDs of the overlay"
+        );
+
+        assert_eq!(leds.stage(&overlay), 2);
+        leds.present().unwrap();
+
+        assert_eq!(
+            leds.led_configurations()[..2],
+            [LedConfiguration::Off, LedConfiguration::on(blue)],
+            "A transparent LED should be turned off if nothing else was staged below it"
+        );
+    }
+
+    #[test]
+    fn clear_all() {
+        let mut leds = mock_leds(&MockDevice::default());
+        let red = Color::from_html("red").unwrap();
+        let blue = Color::from_html("blue").unwrap();
+
+        leds.stage(&LedState::with_color(
+            red,
+            NonZeroUsize::MIN,
+            NonZeroUsize::new(4).unwrap(),
+        ));
+        leds.present().unwrap();
+
+        leds.clear_all();
+        leds.stage(&LedState::with_color(
+            blue.clone(),
+            NonZeroUsize::MIN,
+            NonZeroUsize::new(2).unwrap(),
+        ));
+        leds.present().unwrap();
+
+        assert_eq!(
+            leds.led_configurations()[..4],
+            [
+                LedConfiguration::on(blue.clone()),
+                LedConfiguration::on(blue),
+                LedConfiguration::Off,
+                LedConfiguration::Off,
+            ],
+            "The LEDs which aren't covered by the new LED state should have been turned off"
+        );
+    }
+
+    #[test]
+    fn invalid_index_map() {
+        let mut leds = mock_leds(&MockDevice::default());
+
+        leds.set_index_map(IndexMap::identity(LmxLeds::DEFAULT_LED_COUNT + 1))
+            .expect_err("An index map for a different number of LEDs should be rejected");
+    }
+
+    #[test]
+    fn turn_off_on_drop() {
+        let device = MockDevice::default();
+        let mut leds = mock_leds(&device);
+
+        let red = Color::from_html("red").unwrap();
+        let led_count = NonZeroUsize::new(16).unwrap();
+
+        leds.apply_led_state(&LedState::with_color(red, NonZeroUsize::MIN, led_count))
+            .expect("We should be able to apply a LED state to the mock device");
+        device.reports.borrow_mut().clear();
+
+        drop(leds);
+
+        let reports = device.reports.borrow();
+
+        assert_eq!(
+            reports.len(),
+            LmxLeds::SEGMENT_IDS.len() + 1,
+            "Dropping the LEDs should commit every segment and then the LED data"
+        );
+
+        for segment in &reports[..LmxLeds::SEGMENT_IDS.len()] {
+            for led in LedNumber::iter() {
+                assert_eq!(
+                    &segment[led as usize..led as usize + LedSegment::BYTES_PER_LED],
+                    &[0x00; 4],
+                    "Every LED should be turned off after the device has been dropped"
+                );
             }
         }
+
+        assert_eq!(
+            reports[LmxLeds::SEGMENT_IDS.len()][1],
+            0x09,
+            "The last report should be the commit command"
+        );
     }
 }
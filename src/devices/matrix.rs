@@ -0,0 +1,130 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use anyhow::Result;
+
+use crate::led::{matrix::GridPosition, state::LedConfiguration};
+
+/// A grid shaped RGB LED panel, as opposed to the strips of LEDs a [`LedOutput`] drives.
+///
+/// [`LedOutput`]: super::LedOutput
+pub trait MatrixLedDevice {
+    /// Apply the given LEDs to the device.
+    ///
+    /// The LEDs are given row by row, starting with the top left LED, the slice contains `width`
+    /// times `height` LEDs.
+    fn apply_matrix(&mut self, width: u32, height: u32, leds: &[LedConfiguration]) -> Result<()>;
+}
+
+/// Convert LEDs placed on a grid, e.g. by a [`MatrixLayout`], into the row by row list of LEDs a
+/// [`MatrixLedDevice`] expects.
+///
+/// LEDs outside of the grid are dropped, grid cells without a LED are turned off.
+///
+/// [`MatrixLayout`]: crate::led::matrix::MatrixLayout
+pub fn rasterize(
+    width: u32,
+    height: u32,
+    leds: &[(GridPosition, LedConfiguration)],
+) -> Vec<LedConfiguration> {
+    let mut grid = vec![LedConfiguration::Off; (width * height) as usize];
+
+    for (GridPosition { x, y }, led) in leds {
+        if *x < width && *y < height {
+            grid[(y * width + x) as usize] = led.clone();
+        }
+    }
+
+    grid
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use similar_asserts::assert_eq;
+
+    use crate::led;
+
+    use super::*;
+
+    /// A LED matrix which keeps the last applied frame around.
+    #[derive(Debug, Default)]
+    pub struct MockMatrix {
+        pub width: u32,
+        pub height: u32,
+        pub leds: Vec<LedConfiguration>,
+    }
+
+    impl MockMatrix {
+        pub fn led(&self, x: u32, y: u32) -> &LedConfiguration {
+            &self.leds[(y * self.width + x) as usize]
+        }
+    }
+
+    impl MatrixLedDevice for MockMatrix {
+        fn apply_matrix(
+            &mut self,
+            width: u32,
+            height: u32,
+            leds: &[LedConfiguration],
+        ) -> Result<()> {
+            anyhow::ensure!(
+                leds.len() == (width * height) as usize,
+                "A {width}x{height} matrix needs {} LEDs, got {}",
+                width * height,
+                leds.len()
+            );
+
+            self.width = width;
+            self.height = height;
+            self.leds = leds.to_vec();
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn diagonal_pattern() {
+        let diagonal: Vec<_> = (0..4)
+            .map(|i| (GridPosition { x: i, y: i }, led!("red")))
+            .chain([(GridPosition { x: 7, y: 0 }, led!("blue"))])
+            .collect();
+
+        let mut matrix = MockMatrix::default();
+        matrix
+            .apply_matrix(4, 3, &rasterize(4, 3, &diagonal))
+            .expect("The rasterized LEDs should fit the matrix");
+
+        for y in 0..3 {
+            for x in 0..4 {
+                let expected = if x == y { led!("red") } else { led!(off) };
+
+                assert_eq!(
+                    matrix.led(x, y),
+                    &expected,
+                    "Only the diagonal should be turned on, LED ({x}, {y}) doesn't match"
+                );
+            }
+        }
+
+        matrix
+            .apply_matrix(4, 4, &rasterize(4, 3, &diagonal))
+            .expect_err("The number of LEDs should match the size of the matrix");
+    }
+}
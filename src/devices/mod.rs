@@ -18,13 +18,357 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context as _, Result};
+use simetry::Moment;
+
+use crate::{
+    dashboard::{
+        gear_speed::GearSpeedWidget,
+        sim_dashboard::{MomentSource, SimDashboard},
+    },
+    led::{
+        profiles::LedProfile,
+        state::{
+            groups::GroupState, DriverAssists, LedConfiguration, LedEffect, LedState, UpdateContext,
+        },
+    },
+};
+
+#[cfg(feature = "artnet")]
+pub mod artnet;
 mod buttons;
-mod display;
+pub mod checksum;
+pub mod display;
+mod encoding;
+mod hid;
+mod index_map;
 mod leds;
+pub mod matrix;
+mod zone_map;
 
 pub use buttons::LmxButtonPlate;
 pub use display::USBD480Display;
+pub use encoding::{ColorQuantization, LedLayout};
+pub use hid::LmxHidDevice;
+pub use index_map::IndexMap;
 pub use leds::LmxLeds;
+pub use zone_map::{Zone, ZoneMap};
+
+/// A device which is able to display the [`LedState`]s our LED effects produce.
+///
+/// Showing LED states happens in two phases, LED states are first staged in the buffer of the
+/// device and then all of them are presented at once. This way a frame made out of multiple LED
+/// states never shows up partially on the device.
+pub trait LedOutput {
+    /// The number of LEDs the device has.
+    fn led_count(&self) -> usize;
+
+    /// Write the LED state into the buffer of the device, without showing it. Returns the number
+    /// of LEDs that were actually written.
+    fn stage(&mut self, led_state: &LedState) -> Result<usize>;
+
+    /// Show all the LED states which were staged since the last time the LEDs were presented.
+    fn present(&mut self) -> Result<()>;
+
+    /// Stage every LED of the device as turned off, without showing it.
+    ///
+    /// LED states staged afterwards are drawn on top, so a frame which starts with this turns off
+    /// all the LEDs the frame doesn't cover.
+    fn clear_all(&mut self) -> Result<()>;
+
+    /// Stage the LED state and present it right away, returns the number of LEDs that were
+    /// actually written.
+    fn apply_led_state(&mut self, led_state: &LedState) -> Result<usize> {
+        let written = self.stage(led_state)?;
+        self.present()?;
+
+        Ok(written)
+    }
+}
+
+impl<T: LedOutput + ?Sized> LedOutput for &mut T {
+    fn led_count(&self) -> usize {
+        (**self).led_count()
+    }
+
+    fn stage(&mut self, led_state: &LedState) -> Result<usize> {
+        (**self).stage(led_state)
+    }
+
+    fn present(&mut self) -> Result<()> {
+        (**self).present()
+    }
+
+    fn clear_all(&mut self) -> Result<()> {
+        (**self).clear_all()
+    }
+}
+
+/// Counters describing the work an LED profile runner has done.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of frames that were applied to the device.
+    pub frames_applied: u64,
+    /// The number of frames that were not applied since none of the LEDs changed.
+    pub frames_skipped: u64,
+    /// The number of frames that were not applied since the previous frame was applied too
+    /// recently, see [`FrameApplier::set_min_interval()`].
+    pub frames_throttled: u64,
+    /// The number of frames that failed to be applied to the device.
+    pub errors: u64,
+}
+
+/// Helper which applies the LED states of a [`GroupState`] to a [`LedOutput`] and keeps track of
+/// the [`Stats`] of the runner.
+#[derive(Debug, Default)]
+pub(crate) struct FrameApplier {
+    last_frame: Vec<LedConfiguration>,
+    /// Should all the LEDs be cleared before the next frame is applied, see
+    /// [`FrameApplier::effects_replaced()`].
+    clear_pending: bool,
+    /// The minimal time between two applied frames.
+    min_interval: Duration,
+    /// The time at which the last frame was applied.
+    last_applied: Option<Instant>,
+    /// Was a warning about LED states which don't fit on the output already emitted for the
+    /// current effects.
+    truncation_warned: bool,
+    pub(crate) stats: Stats,
+}
+
+impl FrameApplier {
+    /// Stage the flattened frame of the effects, see [`LedEffect::flattened()`], and present
+    /// it.
+    fn apply(&self, output: &mut impl LedOutput, frame: &[LedConfiguration]) -> Result<()> {
+        if self.clear_pending {
+            output
+                .clear_all()
+                .context("Could not clear the LEDs of the previous effects")?;
+        }
+
+        output
+            .stage(&LedState::from_configs(NonZeroUsize::MIN, frame.to_vec()))
+            .context("Could not stage the new LED state")?;
+
+        output
+            .present()
+            .context("Could not present the new LED states")
+    }
+
+    /// Warn if any of the LED states of the effects doesn't fit on the output.
+    ///
+    /// The warning is only emitted once for every set of effects, see
+    /// [`FrameApplier::effects_replaced()`], otherwise it would show up for every frame.
+    fn warn_about_truncation(&mut self, output: &impl LedOutput, effects: &GroupState) {
+        if self.truncation_warned {
+            return;
+        }
+
+        for state in effects.leds() {
+            let end = state.start_position().get() - 1 + state.leds().len();
+
+            if end > output.led_count() {
+                self.truncation_warned = true;
+
+                tracing::warn!(
+                    "The LED state starting at position {} contains {} LEDs but only {} fit on \
+                     the device, is the LED profile configured for a different device?",
+                    state.start_position(),
+                    state.leds().len(),
+                    output
+                        .led_count()
+                        .saturating_sub(state.start_position().get() - 1),
+                );
+            }
+        }
+    }
+
+    /// Forget the last applied frame, so the next frame gets applied even if it's the same one.
+    ///
+    /// This needs to be called if something else modified the LEDs of the output.
+    pub(crate) fn invalidate(&mut self) {
+        self.last_frame.clear();
+    }
+
+    /// Set the minimal time that needs to pass between two applied frames.
+    ///
+    /// The effects are still updated for every frame, so their timers keep running, but changed
+    /// frames which come in too quickly after the last applied frame aren't sent to the output.
+    /// This is useful for outputs which are a lot slower to update than the simulator produces
+    /// new states. By default every changed frame is applied.
+    pub(crate) fn set_min_interval(&mut self, min_interval: Duration) {
+        self.min_interval = min_interval;
+    }
+
+    /// Tell the applier that the effects were replaced, e.g. by the effects of a different car.
+    ///
+    /// The next frame starts by turning off all the LEDs of the output, so LEDs which the new
+    /// effects don't cover don't keep showing what the old effects left on them.
+    pub(crate) fn effects_replaced(&mut self) {
+        self.clear_pending = true;
+        self.truncation_warned = false;
+        self.invalidate();
+    }
+
+    /// Update the LED effects using the given sim state and apply the resulting LED states to the
+    /// output.
+    ///
+    /// If none of the LEDs changed since the last applied frame, nothing is sent to the output.
+    pub(crate) fn update_and_apply(
+        &mut self,
+        output: &mut impl LedOutput,
+        effects: &mut GroupState,
+        sim_state: &dyn Moment,
+    ) -> Result<()> {
+        self.update_ctx_and_apply(output, effects, &UpdateContext::new(sim_state))
+    }
+
+    /// Update the LED effects using the given [`UpdateContext`] and apply the resulting LED
+    /// states to the output, see [`FrameApplier::update_and_apply()`].
+    pub(crate) fn update_ctx_and_apply(
+        &mut self,
+        output: &mut impl LedOutput,
+        effects: &mut GroupState,
+        ctx: &UpdateContext<'_>,
+    ) -> Result<()> {
+        let ctx = match self.last_applied {
+            Some(last_applied) => ctx.with_last_applied(last_applied),
+            None => *ctx,
+        };
+
+        effects.update_ctx(&ctx);
+
+        let frame = effects.flattened(output.led_count());
+
+        if frame == self.last_frame {
+            self.stats.frames_skipped += 1;
+            return Ok(());
+        }
+
+        if self.last_applied.is_some_and(|last_applied| {
+            ctx.now.saturating_duration_since(last_applied) < self.min_interval
+        }) {
+            self.stats.frames_throttled += 1;
+            return Ok(());
+        }
+
+        self.warn_about_truncation(output, effects);
+
+        if let Err(e) = self.apply(output, &frame) {
+            // We don't know which LED states made it to the device, so make sure that the next
+            // frame gets applied.
+            self.last_frame.clear();
+            self.stats.errors += 1;
+
+            return Err(e);
+        }
+
+        self.last_frame = frame;
+        self.last_applied = Some(ctx.now);
+        self.clear_pending = false;
+        self.stats.frames_applied += 1;
+
+        tracing::trace!(
+            frames_applied = self.stats.frames_applied,
+            "Applied a frame"
+        );
+
+        Ok(())
+    }
+}
+
+/// Keeps the LED effects running on the last sim state while the telemetry stalls.
+///
+/// The blink timers of the effects only advance when the effects get updated. Without new sim
+/// states a blinking LED would freeze in whatever phase it was in, so the runner periodically
+/// updates the effects with the last sim state it received.
+pub(crate) struct Heartbeat<M> {
+    last_moment: Option<M>,
+    /// The state of the driver assists reported together with the last sim state.
+    last_assists: Option<DriverAssists>,
+}
+
+impl<M> Default for Heartbeat<M> {
+    fn default() -> Self {
+        Self {
+            last_moment: None,
+            last_assists: None,
+        }
+    }
+}
+
+impl<M: Moment> Heartbeat<M> {
+    /// Remember the given sim state, and the state of the driver assists the source reported for
+    /// it, as the last one that was received.
+    pub(crate) fn set_moment(&mut self, moment: M, assists: Option<DriverAssists>) -> &M {
+        self.last_assists = assists;
+        self.last_moment.insert(moment)
+    }
+
+    /// Forget the last sim state since the connection to the simulator was closed, the runner
+    /// reconnects to the simulator afterwards.
+    pub(crate) fn connection_lost(&mut self) {
+        tracing::warn!("The connection to the simulator was closed, reconnecting");
+        self.last_moment = None;
+        self.last_assists = None;
+    }
+
+    /// Update the effects with the last sim state at the given time, and apply the resulting
+    /// LED states to the output.
+    ///
+    /// Nothing happens if no sim state was received yet.
+    pub(crate) fn beat(
+        &self,
+        frame_applier: &mut FrameApplier,
+        output: &mut impl LedOutput,
+        effects: &mut GroupState,
+        now: Instant,
+    ) -> Result<()> {
+        let Some(moment) = &self.last_moment else {
+            return Ok(());
+        };
+
+        let ctx = UpdateContext {
+            assists: self.last_assists,
+            ..UpdateContext::at(moment, now)
+        };
+
+        frame_applier.update_ctx_and_apply(output, effects, &ctx)
+    }
+}
+
+/// Run the LED effects for every sim state the given iterator yields, blocking the current thread.
+///
+/// This is the synchronous counterpart of [`LmxLeds::run_led_profile()`], useful if the sim
+/// states come from somewhere else than a simetry client or if no async runtime is available.
+///
+/// Plain sim states don't tell us the state of the driver assists, so effects reacting to them
+/// stay off. The [`SimDashboard`] takes them from its [`MomentSource`] instead, see
+/// [`MomentSource::driver_assists()`].
+///
+/// Returns the [`Stats`] of the run once the iterator is exhausted.
+///
+/// [`SimDashboard`]: crate::dashboard::sim_dashboard::SimDashboard
+/// [`MomentSource`]: crate::dashboard::sim_dashboard::MomentSource
+/// [`MomentSource::driver_assists()`]: crate::dashboard::sim_dashboard::MomentSource::driver_assists
+pub fn run_profile_blocking(
+    output: &mut impl LedOutput,
+    effects: &mut GroupState,
+    states: impl Iterator<Item = impl Moment>,
+) -> Result<Stats> {
+    let mut frame_applier = FrameApplier::default();
+
+    for sim_state in states {
+        frame_applier.update_and_apply(output, effects, &sim_state)?;
+    }
+
+    Ok(frame_applier.stats)
+}
 
 pub struct LmxWheel {
     buttons: LmxButtonPlate,
@@ -56,6 +400,10 @@ impl LmxWheel {
         &self.buttons
     }
 
+    pub fn rpm_leds(&self) -> &LmxLeds {
+        &self.rpm_leds
+    }
+
     pub fn rpm_leds_mut(&mut self) -> &mut LmxLeds {
         &mut self.rpm_leds
     }
@@ -63,4 +411,540 @@ impl LmxWheel {
     pub fn display(&self) -> &USBD480Display {
         &self.display
     }
+
+    /// Turn the wheel into a [`SimDashboard`] which runs the given LED profile on the RPM LEDs
+    /// and shows the gear and speed on the display.
+    pub fn into_dashboard<S: MomentSource>(
+        self,
+        profile: LedProfile,
+        source: S,
+        widget: GearSpeedWidget,
+    ) -> SimDashboard<S, LmxLeds> {
+        LmxLeds::dashboard(self.rpm_leds, profile, source).with_display(self.display, widget)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use similar_asserts::assert_eq;
+
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use crate::led::{
+        profiles::{LedContainer, LedProfile},
+        state::flag::test::SimState,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MockOutput {
+        applied: Vec<LedState>,
+        presented: usize,
+        cleared: usize,
+    }
+
+    /// The number of LEDs of the mock outputs, the LEDs of the test profile end at the last one.
+    const LED_COUNT: usize = 4;
+
+    impl LedOutput for MockOutput {
+        fn led_count(&self) -> usize {
+            LED_COUNT
+        }
+
+        fn stage(&mut self, led_state: &LedState) -> Result<usize> {
+            self.applied.push(led_state.clone());
+            Ok(led_state.leds().len())
+        }
+
+        fn present(&mut self) -> Result<()> {
+            self.presented += 1;
+            Ok(())
+        }
+
+        fn clear_all(&mut self) -> Result<()> {
+            self.cleared += 1;
+            Ok(())
+        }
+    }
+
+    struct FailingOutput;
+
+    impl LedOutput for FailingOutput {
+        fn led_count(&self) -> usize {
+            LED_COUNT
+        }
+
+        fn stage(&mut self, _: &LedState) -> Result<usize> {
+            anyhow::bail!("The device has been disconnected")
+        }
+
+        fn present(&mut self) -> Result<()> {
+            anyhow::bail!("The device has been disconnected")
+        }
+
+        fn clear_all(&mut self) -> Result<()> {
+            anyhow::bail!("The device has been disconnected")
+        }
+    }
+
+    fn profile() -> LedProfile {
+        let profile = json!({
+            "Name": "Yellow flag",
+            "ProfileId": "4c3fca87-2ba4-46e0-9e5e-ac1a8ba1fd1f",
+            "GlobalBrightness": 1.0,
+            "UseProfileBrightness": false,
+            "LedContainers": [
+                {
+                    "LedCount": 2,
+                    "Color": "Yellow",
+                    "BlinkEnabled": false,
+                    "StartPosition": 3,
+                    "ContainerType": "YellowFlagContainer",
+                    "IsEnabled": true
+                }
+            ]
+        });
+
+        serde_json::from_value(profile).expect("We should be able to deserialize the profile")
+    }
+
+    #[test]
+    fn blocking_runner() {
+        let mut output = MockOutput::default();
+        let mut effects = GroupState::root(profile());
+
+        let mut yellow_flag = SimState::new();
+        yellow_flag.inner.yellow = true;
+
+        let states = vec![SimState::new(), yellow_flag, SimState::new()];
+
+        let stats = run_profile_blocking(&mut output, &mut effects, states.into_iter())
+            .expect("Running the profile with a mock output should never fail");
+
+        assert_eq!(
+            stats,
+            Stats {
+                frames_applied: 3,
+                frames_skipped: 0,
+                frames_throttled: 0,
+                errors: 0
+            }
+        );
+
+        assert_eq!(
+            output.applied,
+            vec![
+                crate::leds![off; 4],
+                crate::leds![off, off, "yellow", "yellow"],
+                crate::leds![off; 4],
+            ],
+            "Every sim state should have produced and applied a new LED state"
+        );
+    }
+
+    #[test]
+    fn single_present_per_frame() {
+        let mut profile = profile();
+        let container = profile.led_containers[0].clone();
+        profile
+            .led_containers
+            .extend([container.clone(), container]);
+
+        let mut output = MockOutput::default();
+        let mut effects = GroupState::root(profile);
+
+        FrameApplier::default()
+            .update_and_apply(&mut output, &mut effects, &SimState::new())
+            .expect("Applying a frame to a mock output should never fail");
+
+        assert_eq!(
+            output.applied,
+            vec![crate::leds![off; 4]],
+            "The LED states should have been flattened into a single frame"
+        );
+        assert_eq!(
+            output.presented, 1,
+            "The LED states of a frame should be presented all at once"
+        );
+    }
+
+    #[test]
+    fn overrides_reach_the_output() {
+        let mut output = MockOutput::default();
+        let mut effects = GroupState::root(profile());
+        effects.set_override(0, crate::led!("blue"));
+        effects.set_override(3, crate::led!(off));
+
+        let mut yellow_flag = SimState::new();
+        yellow_flag.inner.yellow = true;
+
+        FrameApplier::default()
+            .update_and_apply(&mut output, &mut effects, &yellow_flag)
+            .expect("Applying a frame to a mock output should never fail");
+
+        assert_eq!(
+            output.applied,
+            vec![crate::leds!["blue", off, "yellow", off]],
+            "The overrides should have been applied on top of the LED states of the effects"
+        );
+    }
+
+    #[test]
+    fn pit_lane_background_reaches_the_output() {
+        struct PitLaneState;
+
+        impl Moment for PitLaneState {
+            fn is_vehicle_in_pit_lane(&self) -> Option<bool> {
+                Some(true)
+            }
+
+            fn flags(&self) -> Option<simetry::RacingFlags> {
+                Some(simetry::RacingFlags {
+                    yellow: true,
+                    ..Default::default()
+                })
+            }
+        }
+
+        let mut output = MockOutput::default();
+        let mut effects = GroupState::root(profile());
+        effects.set_pit_lane_background(Some(csscolorparser::Color::from_html("purple").unwrap()));
+
+        FrameApplier::default()
+            .update_and_apply(&mut output, &mut effects, &PitLaneState)
+            .expect("Applying a frame to a mock output should never fail");
+
+        assert_eq!(
+            output.applied,
+            vec![crate::leds!["purple", "purple", "yellow", "yellow"]],
+            "The LEDs which aren't turned on by the effects should take on the pit lane background"
+        );
+    }
+
+    #[test]
+    fn heartbeat() {
+        let mut profile = profile();
+        let LedContainer::YellowFlag(container) = &mut profile.led_containers[0] else {
+            unreachable!("The test profile should contain a yellow flag container");
+        };
+        container.blink_enabled = true;
+        container.blink_delay = Duration::from_millis(100);
+        let delay = container.blink_delay;
+
+        let mut output = MockOutput::default();
+        let mut effects = GroupState::root(profile);
+        let mut frame_applier = FrameApplier::default();
+        let mut heartbeat = Heartbeat::default();
+        let start = Instant::now();
+
+        heartbeat
+            .beat(&mut frame_applier, &mut output, &mut effects, start)
+            .unwrap();
+        assert!(
+            output.applied.is_empty(),
+            "Nothing should be applied before the first sim state arrives"
+        );
+
+        let mut yellow_flag = SimState::new();
+        yellow_flag.inner.yellow = true;
+        heartbeat.set_moment(yellow_flag, None);
+
+        for (now, expected) in [
+            (start, crate::leds![off, off, "yellow", "yellow"]),
+            (start + delay, crate::leds![off; 4]),
+            (
+                start + delay * 2,
+                crate::leds![off, off, "yellow", "yellow"],
+            ),
+        ] {
+            heartbeat
+                .beat(&mut frame_applier, &mut output, &mut effects, now)
+                .expect("Applying a frame to a mock output should never fail");
+
+            assert_eq!(
+                output.applied.last(),
+                Some(&expected),
+                "The blinking should advance without new sim states"
+            );
+        }
+
+        assert_eq!(frame_applier.stats.frames_applied, 3);
+    }
+
+    /// A writer collecting the output of a [`tracing_subscriber`] in memory.
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn reconnect_warning() {
+        let logs = CapturedLogs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer({
+                let logs = logs.clone();
+                move || logs.clone()
+            })
+            .finish();
+
+        let mut output = MockOutput::default();
+        let mut effects = GroupState::root(profile());
+        let mut frame_applier = FrameApplier::default();
+        let mut heartbeat = Heartbeat::default();
+        heartbeat.set_moment(SimState::new(), None);
+
+        tracing::subscriber::with_default(subscriber, || heartbeat.connection_lost());
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+
+        assert!(
+            logs.contains("WARN") && logs.contains("reconnecting"),
+            "Losing the connection should emit a warning, got: {logs}"
+        );
+
+        heartbeat
+            .beat(
+                &mut frame_applier,
+                &mut output,
+                &mut effects,
+                Instant::now(),
+            )
+            .unwrap();
+        assert!(
+            output.applied.is_empty(),
+            "The last sim state should be forgotten once the connection is lost"
+        );
+    }
+
+    #[test]
+    fn truncation_warning() {
+        let logs = CapturedLogs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer({
+                let logs = logs.clone();
+                move || logs.clone()
+            })
+            .finish();
+
+        let mut profile = profile();
+        let LedContainer::YellowFlag(container) = &mut profile.led_containers[0] else {
+            unreachable!("The test profile should contain a yellow flag container");
+        };
+        container.led_count = NonZeroUsize::new(3).unwrap();
+
+        let mut output = MockOutput::default();
+        let mut effects = GroupState::root(profile);
+        let mut frame_applier = FrameApplier::default();
+
+        let mut yellow_flag = SimState::new();
+        yellow_flag.inner.yellow = true;
+
+        tracing::subscriber::with_default(subscriber, || {
+            for sim_state in [SimState::new(), yellow_flag] {
+                frame_applier
+                    .update_and_apply(&mut output, &mut effects, &sim_state)
+                    .expect("Applying a frame to a mock output should never fail");
+            }
+
+            frame_applier.effects_replaced();
+            frame_applier
+                .update_and_apply(&mut output, &mut effects, &SimState::new())
+                .expect("Applying a frame to a mock output should never fail");
+        });
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+
+        assert_eq!(
+            logs.matches("only 2 fit on the device").count(),
+            2,
+            "The truncation should be reported once for every set of effects, got: {logs}"
+        );
+    }
+
+    #[test]
+    fn throttled_frames() {
+        let mut output = MockOutput::default();
+        let mut effects = GroupState::root(profile());
+        let mut frame_applier = FrameApplier::default();
+        frame_applier.set_min_interval(Duration::from_millis(20));
+
+        let mut yellow_flag = SimState::new();
+        yellow_flag.inner.yellow = true;
+        let no_flag = SimState::new();
+
+        let start = Instant::now();
+        let frames: [(&SimState, u64); 4] = [
+            (&no_flag, 0),
+            (&yellow_flag, 5),
+            (&no_flag, 10),
+            (&yellow_flag, 15),
+        ];
+
+        for (sim_state, millis) in frames {
+            let ctx = UpdateContext::at(sim_state, start + Duration::from_millis(millis));
+            frame_applier
+                .update_ctx_and_apply(&mut output, &mut effects, &ctx)
+                .expect("Applying a frame to a mock output should never fail");
+        }
+
+        assert_eq!(
+            output.presented, 1,
+            "Only the first frame should have been presented, the others came in too quickly"
+        );
+        assert_eq!(
+            (
+                frame_applier.stats.frames_throttled,
+                frame_applier.stats.frames_skipped
+            ),
+            (2, 1),
+            "The frames with the yellow flag should have been throttled, the frame without any \
+             flag didn't change the LEDs of the last applied frame"
+        );
+        assert!(
+            effects
+                .leds()
+                .any(|state| state.leds().iter().all(|led| *led != crate::led!(off))),
+            "The effects should have been updated even if the frames were throttled"
+        );
+
+        let ctx = UpdateContext::at(&yellow_flag, start + Duration::from_millis(20));
+        frame_applier
+            .update_ctx_and_apply(&mut output, &mut effects, &ctx)
+            .expect("Applying a frame to a mock output should never fail");
+
+        assert_eq!(
+            output.presented, 2,
+            "The latest frame should be applied once the minimal interval has passed"
+        );
+        assert_eq!(
+            output.applied.last(),
+            Some(&crate::leds![off, off, "yellow", "yellow"]),
+            "The applied frame should contain the latest state of the effects"
+        );
+    }
+
+    #[test]
+    fn throttled_blinking() {
+        let mut profile = profile();
+        let LedContainer::YellowFlag(container) = &mut profile.led_containers[0] else {
+            unreachable!("The test profile should contain a yellow flag container");
+        };
+        container.blink_enabled = true;
+        container.blink_delay = Duration::from_millis(5);
+
+        let mut output = MockOutput::default();
+        let mut effects = GroupState::root(profile);
+        let mut frame_applier = FrameApplier::default();
+        frame_applier.set_min_interval(Duration::from_millis(20));
+
+        let mut yellow_flag = SimState::new();
+        yellow_flag.inner.yellow = true;
+
+        let start = Instant::now();
+
+        for millis in (0..=40).step_by(5) {
+            let ctx = UpdateContext::at(&yellow_flag, start + Duration::from_millis(millis));
+            frame_applier
+                .update_ctx_and_apply(&mut output, &mut effects, &ctx)
+                .expect("Applying a frame to a mock output should never fail");
+        }
+
+        assert_eq!(
+            output.applied,
+            vec![
+                crate::leds![off, off, "yellow", "yellow"],
+                crate::leds![off; 4],
+                crate::leds![off, off, "yellow", "yellow"],
+            ],
+            "Every blink phase should have been applied, even if the blink delay is shorter than \
+             the minimal interval between two applied frames"
+        );
+    }
+
+    #[test]
+    fn clear_after_replacing_the_effects() {
+        let mut output = MockOutput::default();
+        let mut effects = GroupState::root(profile());
+        let mut frame_applier = FrameApplier::default();
+
+        frame_applier
+            .update_and_apply(&mut output, &mut effects, &SimState::new())
+            .unwrap();
+        assert_eq!(
+            output.cleared, 0,
+            "The LEDs shouldn't be cleared for every frame"
+        );
+
+        frame_applier.effects_replaced();
+
+        for _ in 0..2 {
+            frame_applier
+                .update_and_apply(&mut output, &mut effects, &SimState::new())
+                .unwrap();
+        }
+
+        assert_eq!(
+            output.cleared, 1,
+            "The LEDs should be cleared once, before the first frame of the new effects"
+        );
+        assert_eq!(
+            frame_applier.stats.frames_applied, 2,
+            "The first frame of the new effects should be applied even if it's the same as the \
+             last frame"
+        );
+    }
+
+    #[test]
+    fn stats() {
+        let mut output = MockOutput::default();
+        let mut effects = GroupState::root(profile());
+        let mut frame_applier = FrameApplier::default();
+
+        let mut yellow_flag = SimState::new();
+        yellow_flag.inner.yellow = true;
+
+        for sim_state in [SimState::new(), SimState::new(), yellow_flag] {
+            frame_applier
+                .update_and_apply(&mut output, &mut effects, &sim_state)
+                .expect("Applying a frame to a mock output should never fail");
+        }
+
+        assert_eq!(
+            frame_applier.stats,
+            Stats {
+                frames_applied: 2,
+                frames_skipped: 1,
+                frames_throttled: 0,
+                errors: 0,
+            },
+            "The second frame didn't change any LEDs so it should have been skipped"
+        );
+        assert_eq!(output.applied.len(), 2);
+        assert_eq!(output.presented, 2);
+
+        frame_applier
+            .update_and_apply(&mut FailingOutput, &mut effects, &SimState::new())
+            .expect_err("Applying a frame to a disconnected device should fail");
+
+        assert_eq!(
+            frame_applier.stats.errors, 1,
+            "The failed frame should have been counted as an error"
+        );
+    }
 }
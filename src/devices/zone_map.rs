@@ -0,0 +1,216 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::{collections::BTreeMap, num::NonZeroUsize, ops::Range};
+
+use anyhow::{ensure, Context as _, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::led::{
+    profiles::{default_non_zero, led_count_from_int},
+    state::LedState,
+};
+
+/// A named group of neighbouring LEDs, for example the shift lights of a wheel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Zone {
+    /// The position of the first LED of the zone, starting at one like the start positions of
+    /// the LED profiles.
+    #[serde(default = "default_non_zero")]
+    pub start_position: NonZeroUsize,
+    /// The number of LEDs the zone contains.
+    #[serde(deserialize_with = "led_count_from_int")]
+    pub led_count: NonZeroUsize,
+}
+
+impl Zone {
+    /// Get the zero-based indices of the LEDs this zone covers.
+    pub fn range(&self) -> Range<usize> {
+        let start = self.start_position.get() - 1;
+
+        start..start + self.led_count.get()
+    }
+}
+
+/// A mapping from the names of LED zones, like `"left cluster"` or `"shift lights"`, to the LEDs
+/// of a device.
+///
+/// Zones allow LED states to be positioned relative to a part of the device instead of using
+/// absolute positions, this way the same LED states can be used on devices which have, for
+/// example, their shift lights at different positions.
+///
+/// The map can be deserialized from a config file, which maps every name to a [`Zone`]:
+///
+/// ```json
+/// {
+///     "shift lights": { "StartPosition": 1, "LedCount": 8 },
+///     "right cluster": { "StartPosition": 9, "LedCount": 4 }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct ZoneMap {
+    zones: BTreeMap<String, Zone>,
+}
+
+impl ZoneMap {
+    /// Create a map from the given zones, all the zones need to fit on a device with the given
+    /// number of LEDs, otherwise an error is returned.
+    pub fn new(zones: impl IntoIterator<Item = (String, Zone)>, led_count: usize) -> Result<Self> {
+        let map = Self {
+            zones: zones.into_iter().collect(),
+        };
+        map.validate(led_count)?;
+
+        Ok(map)
+    }
+
+    /// Check that all the zones of this map fit on a device with the given number of LEDs.
+    pub fn validate(&self, led_count: usize) -> Result<()> {
+        for (name, zone) in &self.zones {
+            ensure!(
+                zone.range().end <= led_count,
+                "The LED zone {name:?} ends at the LED {}, but the device only has {led_count} \
+                 LEDs",
+                zone.range().end
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Get the zone with the given name.
+    pub fn zone(&self, name: &str) -> Option<Zone> {
+        self.zones.get(name).copied()
+    }
+
+    /// Get the zero-based indices of the LEDs the zone with the given name covers.
+    pub fn range(&self, name: &str) -> Option<Range<usize>> {
+        self.zone(name).map(|zone| zone.range())
+    }
+
+    /// Get an iterator over the names of all the zones, in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.zones.keys().map(String::as_str)
+    }
+
+    /// Position the given LED state inside the zone with the given name.
+    ///
+    /// The start position of the LED state is treated as relative to the zone, a LED state
+    /// starting at position one starts at the first LED of the zone. LEDs which don't fit into
+    /// the zone are dropped. Returns an error if the map doesn't contain a zone with the given
+    /// name.
+    pub fn apply_to_zone(&self, name: &str, led_state: &LedState) -> Result<LedState> {
+        let zone = self
+            .zone(name)
+            .with_context(|| format!("The LED zone {name:?} doesn't exist"))?;
+
+        let offset = led_state.start_position().get() - 1;
+        let leds = led_state
+            .leds()
+            .iter()
+            .take(zone.led_count.get().saturating_sub(offset))
+            .cloned()
+            .collect();
+
+        let start_position = zone
+            .start_position
+            .checked_add(offset)
+            .context("The LED state is positioned past the end of the possible LED positions")?;
+
+        Ok(LedState::from_configs(start_position, leds))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use similar_asserts::assert_eq;
+
+    use super::*;
+    use crate::leds;
+
+    fn zone_map() -> ZoneMap {
+        serde_json::from_value(json!({
+            "shift lights": { "StartPosition": 1, "LedCount": 8 },
+            "right cluster": { "StartPosition": 13, "LedCount": 4 },
+        }))
+        .expect("We should be able to deserialize the zone map")
+    }
+
+    #[test]
+    fn resolve_zones() {
+        let map = zone_map();
+
+        assert_eq!(map.range("shift lights"), Some(0..8));
+        assert_eq!(map.range("right cluster"), Some(12..16));
+        assert_eq!(
+            map.range("left cluster"),
+            None,
+            "Unknown zones shouldn't resolve"
+        );
+        assert_eq!(
+            map.names().collect::<Vec<_>>(),
+            ["right cluster", "shift lights"]
+        );
+
+        map.validate(16)
+            .expect("All the zones should fit on a device with 16 LEDs");
+        map.validate(15)
+            .expect_err("The right cluster shouldn't fit on a device with 15 LEDs");
+    }
+
+    #[test]
+    fn new_validates_zones() {
+        let zone = Zone {
+            start_position: NonZeroUsize::new(5).unwrap(),
+            led_count: NonZeroUsize::new(4).unwrap(),
+        };
+
+        ZoneMap::new([("middle".to_owned(), zone)], 8)
+            .expect("A zone ending at the last LED should be accepted");
+        ZoneMap::new([("middle".to_owned(), zone)], 7)
+            .expect_err("A zone ending past the last LED should be rejected");
+    }
+
+    #[test]
+    fn apply_to_zone() {
+        let map = zone_map();
+
+        assert_eq!(
+            map.apply_to_zone("right cluster", &leds!["red", "lime"])
+                .expect("The zone should exist"),
+            leds![13; "red", "lime"],
+            "The LED state should start at the first LED of the zone"
+        );
+
+        assert_eq!(
+            map.apply_to_zone("right cluster", &leds![3; "red"; 4])
+                .expect("The zone should exist"),
+            leds![15; "red"; 2],
+            "The start position should be relative to the zone and LEDs past the end of the zone \
+             should be dropped"
+        );
+
+        map.apply_to_zone("left cluster", &leds!["red", "lime"])
+            .expect_err("Applying a LED state to an unknown zone should fail");
+    }
+}
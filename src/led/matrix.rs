@@ -0,0 +1,213 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Support for placing LEDs on a two dimensional LED matrix instead of a single strip of LEDs.
+
+use anyhow::{ensure, Result};
+
+use crate::led::{
+    profiles::rpm::{LedSegment, RpmSegmentsContainer},
+    state::{LedConfiguration, LedState},
+};
+
+/// The position of a LED on a LED matrix, the origin is the top left LED of the matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GridPosition {
+    pub x: u32,
+    pub y: u32,
+}
+
+/// The layout of the LEDs of a [`RpmSegmentsContainer`] on a LED matrix.
+///
+/// The layout is created from the [`SampleResult`] metadata of the segments. Every segment covers
+/// a block of the matrix, the top left corner of the block is given by the
+/// [`SampleResult::position`] and the block is [`SampleResult::width`] LEDs wide. The LEDs of the
+/// segment fill out the block row by row.
+///
+/// [`SampleResult`]: crate::led::profiles::rpm::SampleResult
+/// [`SampleResult::position`]: crate::led::profiles::rpm::SampleResult::position
+/// [`SampleResult::width`]: crate::led::profiles::rpm::SampleResult::width
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatrixLayout {
+    /// The position of every LED of the container, in the order of the segments.
+    positions: Vec<GridPosition>,
+}
+
+impl MatrixLayout {
+    /// Create the layout for all the segments of the given container.
+    pub fn for_segments(container: &RpmSegmentsContainer) -> Result<Self> {
+        let mut positions = Vec::with_capacity(container.total_led_count());
+
+        for segment in &container.segments {
+            positions.extend(Self::segment_positions(segment)?);
+        }
+
+        Ok(Self { positions })
+    }
+
+    /// Get the positions of the LEDs of a single segment on the matrix.
+    ///
+    /// A segment without a width is placed in a single row.
+    pub fn segment_positions(segment: &LedSegment) -> Result<Vec<GridPosition>> {
+        let sample = &segment.sample_result;
+        let led_count = segment.led_count.get() as u32;
+
+        ensure!(
+            sample.columns > 0,
+            "The LED matrix needs at least one column, got {}",
+            sample.columns
+        );
+
+        let columns = sample.columns as u32;
+        let width = if sample.width == 0 {
+            led_count
+        } else {
+            sample.width
+        };
+
+        let left = sample.position % columns;
+        let top = sample.position / columns;
+
+        ensure!(
+            left.checked_add(width)
+                .is_some_and(|right| right <= columns),
+            "A segment which is {width} LEDs wide and starts at column {left} doesn't fit on a \
+             LED matrix with {columns} columns"
+        );
+        ensure!(
+            top.checked_add((led_count - 1) / width).is_some(),
+            "A segment which starts at row {top} and has {led_count} LEDs doesn't fit on a LED \
+             matrix"
+        );
+
+        Ok((0..led_count)
+            .map(|led| GridPosition {
+                x: left + led % width,
+                y: top + led / width,
+            })
+            .collect())
+    }
+
+    /// Get the position of the LED with the given zero-based index.
+    pub fn position(&self, index: usize) -> Option<GridPosition> {
+        self.positions.get(index).copied()
+    }
+
+    /// Place the LEDs of the given LED states on the matrix.
+    ///
+    /// The LEDs are matched to the positions in order, LEDs past the end of the layout are
+    /// dropped.
+    pub fn place<'a>(
+        &self,
+        states: impl Iterator<Item = &'a LedState>,
+    ) -> Vec<(GridPosition, LedConfiguration)> {
+        self.positions
+            .iter()
+            .copied()
+            .zip(states.flat_map(|state| state.leds()).cloned())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use similar_asserts::assert_eq;
+
+    use crate::{led, leds};
+
+    use super::*;
+
+    fn segment(led_count: usize, width: u32, position: u32, columns: i32) -> LedSegment {
+        let segment = json!({
+            "StartValue": 50.0,
+            "EndValue": 80.0,
+            "NormalColor": "Lime",
+            "BlinkingColor": "Blue",
+            "UseBlinkingColor": true,
+            "LedCount": led_count,
+            "SampleResult": { "Width": width, "Position": position, "Columns": columns }
+        });
+
+        serde_json::from_value(segment).expect("We should be able to deserialize the LED segment")
+    }
+
+    fn positions(positions: &[(u32, u32)]) -> Vec<GridPosition> {
+        positions
+            .iter()
+            .map(|&(x, y)| GridPosition { x, y })
+            .collect()
+    }
+
+    #[test]
+    fn segment_positions() {
+        assert_eq!(
+            MatrixLayout::segment_positions(&segment(6, 3, 5, 4)).unwrap(),
+            positions(&[(1, 1), (2, 1), (3, 1), (1, 2), (2, 2), (3, 2)]),
+            "The segment should fill a 3 LED wide block starting at the second row and column"
+        );
+
+        assert_eq!(
+            MatrixLayout::segment_positions(&segment(3, 0, 2, 8)).unwrap(),
+            positions(&[(2, 0), (3, 0), (4, 0)]),
+            "A segment without a width should be placed in a single row"
+        );
+
+        MatrixLayout::segment_positions(&segment(3, 3, 2, 4))
+            .expect_err("A segment which is wider than the remaining columns should be rejected");
+        MatrixLayout::segment_positions(&segment(3, 3, 0, 0))
+            .expect_err("A matrix without columns should be rejected");
+        MatrixLayout::segment_positions(&segment(3, u32::MAX, 2, 4))
+            .expect_err("A width which overflows the column of the segment should be rejected");
+        MatrixLayout::segment_positions(&segment(4, 1, u32::MAX, 1))
+            .expect_err("A segment which overflows the rows of the matrix should be rejected");
+    }
+
+    #[test]
+    fn placing_leds() {
+        let container = json!({
+            "SegmentsCount": 2,
+            "IsEnabled": true,
+            "Segments": []
+        });
+        let mut container: RpmSegmentsContainer = serde_json::from_value(container)
+            .expect("We should be able to deserialize the RPM segments container");
+        container.segments = vec![segment(2, 1, 0, 2), segment(2, 1, 1, 2)];
+
+        let layout =
+            MatrixLayout::for_segments(&container).expect("The segments should fit on the matrix");
+
+        assert_eq!(layout.position(3), Some(GridPosition { x: 1, y: 1 }));
+        assert_eq!(layout.position(4), None);
+
+        let states = [leds!["red"; 2], leds![3; "lime", off]];
+
+        assert_eq!(
+            layout.place(states.iter()),
+            vec![
+                (GridPosition { x: 0, y: 0 }, led!("red")),
+                (GridPosition { x: 0, y: 1 }, led!("red")),
+                (GridPosition { x: 1, y: 0 }, led!("lime")),
+                (GridPosition { x: 1, y: 1 }, led!(off)),
+            ],
+            "Every segment should fill out one column of the matrix"
+        );
+    }
+}
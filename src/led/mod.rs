@@ -18,5 +18,8 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+pub mod matrix;
+#[cfg(feature = "image")]
+pub mod preview;
 pub mod profiles;
 pub mod state;
@@ -0,0 +1,203 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Render the LEDs of a profile into an image, for example to document how a profile looks at
+//! various RPMs.
+
+use image::{Rgb, RgbImage};
+
+use crate::led::state::LedConfiguration;
+
+/// The layout of a rendered strip of LEDs.
+///
+/// Every LED is drawn as a square cell, the cells are placed next to each other with some spacing
+/// in between. LEDs which are turned off are not drawn, leaving a gap of background in the strip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StripPreview {
+    /// The width and height of a single LED cell, in pixels.
+    pub cell_size: u32,
+    /// The space between two neighbouring LED cells and around the strip, in pixels.
+    pub spacing: u32,
+    /// The color of the space between the cells and of the LEDs which are turned off.
+    pub background: Rgb<u8>,
+}
+
+impl Default for StripPreview {
+    fn default() -> Self {
+        Self {
+            cell_size: 16,
+            spacing: 4,
+            background: Rgb([0, 0, 0]),
+        }
+    }
+}
+
+impl StripPreview {
+    /// Render the given, flattened, list of LEDs into a single row of cells.
+    pub fn render(&self, leds: &[LedConfiguration]) -> RgbImage {
+        let stride = self.cell_size + self.spacing;
+        let width = leds.len() as u32 * stride + self.spacing;
+        let height = self.cell_size + 2 * self.spacing;
+
+        let mut image = RgbImage::from_pixel(width, height, self.background);
+
+        for (index, led) in leds.iter().enumerate() {
+            let Some(color) = led_color(led) else {
+                continue;
+            };
+
+            let left = self.spacing + index as u32 * stride;
+
+            for x in left..left + self.cell_size {
+                for y in self.spacing..self.spacing + self.cell_size {
+                    image.put_pixel(x, y, color);
+                }
+            }
+        }
+
+        image
+    }
+}
+
+/// Get the color a LED should be drawn with, taking the brightness of the LED into account.
+fn led_color(led: &LedConfiguration) -> Option<Rgb<u8>> {
+    match led {
+        LedConfiguration::On { color, brightness } => {
+            let brightness = brightness.clamp(0.0, 1.0);
+            let [r, g, b, _] = color.to_rgba8();
+
+            Some(Rgb(
+                [r, g, b].map(|channel| (channel as f64 * brightness).round() as u8)
+            ))
+        }
+        LedConfiguration::Off => None,
+    }
+}
+
+/// Render the given LEDs into an image using the default [`StripPreview`] layout.
+pub fn render_strip(leds: &[LedConfiguration]) -> RgbImage {
+    StripPreview::default().render(leds)
+}
+
+#[cfg(test)]
+mod test {
+    use similar_asserts::assert_eq;
+
+    use super::*;
+    use crate::{
+        led,
+        led::{
+            profiles::LedProfile,
+            state::{groups::GroupState, rpm::gradient::test::RpmSimState},
+        },
+    };
+
+    #[test]
+    fn render_single_leds() {
+        let preview = StripPreview {
+            cell_size: 2,
+            spacing: 1,
+            background: Rgb([0, 0, 0]),
+        };
+
+        let leds = [
+            led!("red"),
+            led!(off),
+            LedConfiguration::On {
+                color: csscolorparser::Color::from_html("white").unwrap(),
+                brightness: 0.5,
+            },
+        ];
+
+        let image = preview.render(&leds);
+
+        assert_eq!((image.width(), image.height()), (10, 4));
+        assert_eq!(
+            image.get_pixel(0, 0),
+            &Rgb([0, 0, 0]),
+            "The border should be empty"
+        );
+        assert_eq!(image.get_pixel(1, 1), &Rgb([255, 0, 0]));
+        assert_eq!(image.get_pixel(2, 2), &Rgb([255, 0, 0]));
+        assert_eq!(
+            image.get_pixel(3, 1),
+            &Rgb([0, 0, 0]),
+            "The cells should be separated by the spacing"
+        );
+        assert_eq!(
+            image.get_pixel(4, 1),
+            &Rgb([0, 0, 0]),
+            "LEDs which are turned off should leave a gap"
+        );
+        assert_eq!(
+            image.get_pixel(7, 2),
+            &Rgb([128, 128, 128]),
+            "The brightness of the LED should be taken into account"
+        );
+    }
+
+    #[test]
+    fn render_profile_group() {
+        let profile: LedProfile =
+            serde_json::from_str(include_str!("../../tests/fixtures/profiles/rpm_sweep.json"))
+                .expect("The fixture profile should be valid");
+
+        let mut group = GroupState::root(profile);
+        let mut sim_state = RpmSimState::new(0.0, 8000.0);
+        sim_state.update_rpm(8000.0 * 0.84);
+        group.update(&sim_state);
+
+        let image = render_strip(&group.render(8));
+        let preview = StripPreview::default();
+
+        assert_eq!(
+            (image.width(), image.height()),
+            (8 * 20 + 4, 24),
+            "The image should contain a cell for every LED"
+        );
+
+        let cell_center = |index: u32| {
+            let stride = preview.cell_size + preview.spacing;
+            image.get_pixel(
+                preview.spacing + index * stride + preview.cell_size / 2,
+                preview.spacing + preview.cell_size / 2,
+            )
+        };
+
+        let expected = [
+            Rgb([0, 255, 0]),
+            preview.background,
+            preview.background,
+            preview.background,
+            preview.background,
+            Rgb([255, 255, 0]),
+            Rgb([255, 255, 0]),
+            preview.background,
+        ];
+
+        for (index, expected) in expected.iter().enumerate() {
+            assert_eq!(
+                cell_center(index as u32),
+                expected,
+                "The cell of the LED {index} should have the color of the LED"
+            );
+        }
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::{num::NonZeroUsize, time::Duration};
+
+use csscolorparser::Color;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    color_from_str, color_to_str, default_non_zero, duration_from_int_ms, duration_to_int_ms,
+    led_count_from_int,
+};
+
+/// The configuration for a LED profile container which flashes LEDs when a driver assist, the ABS
+/// or the traction control, engages.
+///
+/// Not a SimHub container.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AssistContainer {
+    /// The human readable description of the [`AssistContainer`].
+    #[serde(default)]
+    pub description: String,
+    /// Is this container enabled.
+    pub is_enabled: bool,
+    /// The number of the first LED this container should control.
+    #[serde(default = "default_non_zero")]
+    pub start_position: NonZeroUsize,
+    /// The total number of LEDs this container should control.
+    #[serde(deserialize_with = "led_count_from_int")]
+    pub led_count: NonZeroUsize,
+    /// The color the LEDs flash in.
+    #[serde(deserialize_with = "color_from_str", serialize_with = "color_to_str")]
+    pub color: Color,
+    /// The driver assists which should flash the LEDs.
+    #[serde(default)]
+    pub trigger: AssistTrigger,
+    /// How long the LEDs stay on after the assist disengaged.
+    ///
+    /// The ABS and the traction control often engage for a single frame, the LEDs would barely
+    /// be visible without holding them on for a while.
+    #[serde(
+        default = "default_hold",
+        deserialize_with = "duration_from_int_ms",
+        serialize_with = "duration_to_int_ms"
+    )]
+    pub hold: Duration,
+}
+
+impl AssistContainer {
+    /// The default value of the [`AssistContainer::hold`] setting.
+    pub const DEFAULT_HOLD: Duration = Duration::from_millis(150);
+}
+
+fn default_hold() -> Duration {
+    AssistContainer::DEFAULT_HOLD
+}
+
+/// The driver assists an [`AssistContainer`] reacts to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum AssistTrigger {
+    /// Flash when either the ABS or the traction control engages.
+    #[default]
+    Any,
+    /// Flash only when the ABS engages.
+    Abs,
+    /// Flash only when the traction control engages.
+    TractionControl,
+}
+
+impl AssistTrigger {
+    /// Should the LEDs flash given the state of the driver assists.
+    pub fn is_triggered(self, abs_active: bool, tc_active: bool) -> bool {
+        match self {
+            AssistTrigger::Any => abs_active || tc_active,
+            AssistTrigger::Abs => abs_active,
+            AssistTrigger::TractionControl => tc_active,
+        }
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::{num::NonZeroUsize, time::Duration};
+
+use csscolorparser::Color;
+
+use super::{flag::FlagContainer, redline::RedlineReachedContainer};
+
+/// The common shape of the containers which turn on a number of LEDs in a single color, and
+/// optionally make them blink.
+///
+/// The [`FlagContainer`] and the [`RedlineReachedContainer`] only differ in which of their fields
+/// are optional in a profile, once they're deserialized they can be converted into this shared
+/// container.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimpleBlinkContainer {
+    pub description: String,
+    pub is_enabled: bool,
+    pub led_count: NonZeroUsize,
+    pub start_position: NonZeroUsize,
+    pub color: Color,
+    pub blink_enabled: bool,
+    /// How long the LEDs stay on and off while blinking, unless dual timing is enabled.
+    pub blink_delay: Duration,
+    /// Should the LEDs use the separate [`SimpleBlinkContainer::on_delay`] and
+    /// [`SimpleBlinkContainer::off_delay`] instead of the [`SimpleBlinkContainer::blink_delay`].
+    pub dual_blink_timing_enabled: bool,
+    pub on_delay: Duration,
+    pub off_delay: Duration,
+}
+
+impl SimpleBlinkContainer {
+    /// Get the time the LEDs should stay on and off while blinking, in that order.
+    ///
+    /// The separate on and off delays are only used if dual timing is enabled, otherwise the
+    /// LEDs stay on and off for the blink delay.
+    pub fn blink_delays(&self) -> (Duration, Duration) {
+        if self.dual_blink_timing_enabled {
+            (self.on_delay, self.off_delay)
+        } else {
+            (self.blink_delay, self.blink_delay)
+        }
+    }
+}
+
+impl From<FlagContainer> for SimpleBlinkContainer {
+    fn from(container: FlagContainer) -> Self {
+        Self {
+            description: container.description,
+            is_enabled: container.is_enabled,
+            led_count: container.led_count,
+            start_position: container.start_position,
+            color: container.color,
+            blink_enabled: container.blink_enabled,
+            blink_delay: container.blink_delay,
+            dual_blink_timing_enabled: container.dual_blink_timing_enabled,
+            on_delay: container.on_delay,
+            off_delay: container.off_delay,
+        }
+    }
+}
+
+impl From<RedlineReachedContainer> for SimpleBlinkContainer {
+    fn from(container: RedlineReachedContainer) -> Self {
+        Self {
+            description: container.description,
+            is_enabled: container.is_enabled,
+            led_count: container.led_count,
+            start_position: container.start_position,
+            color: container.color,
+            blink_enabled: container.blink_enabled,
+            blink_delay: container.blink_delay,
+            dual_blink_timing_enabled: container.dual_blink_timing_enabled,
+            on_delay: container.on_delay,
+            off_delay: container.off_delay,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use similar_asserts::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn from_flag_container() {
+        let container: FlagContainer = serde_json::from_value(json!({
+            "LedCount": 3,
+            "Color": "Yellow",
+            "BlinkEnabled": true,
+            "BlinkDelay": 50,
+            "DualBlinkTimingEnabled": true,
+            "OffDelay": 75,
+            "OnDelay": 12,
+            "StartPosition": 14,
+            "ContainerType": "YellowFlagContainer",
+            "Description": "Yellow flag",
+            "IsEnabled": true
+        }))
+        .expect("We should be able to deserialize the flag container");
+
+        assert_eq!(
+            SimpleBlinkContainer::from(container),
+            SimpleBlinkContainer {
+                description: "Yellow flag".to_owned(),
+                is_enabled: true,
+                led_count: NonZeroUsize::new(3).unwrap(),
+                start_position: NonZeroUsize::new(14).unwrap(),
+                color: Color::from_html("yellow").unwrap(),
+                blink_enabled: true,
+                blink_delay: Duration::from_millis(50),
+                dual_blink_timing_enabled: true,
+                on_delay: Duration::from_millis(12),
+                off_delay: Duration::from_millis(75),
+            }
+        );
+    }
+
+    #[test]
+    fn from_minimal_flag_container() {
+        let container: FlagContainer = serde_json::from_value(json!({
+            "LedCount": 2,
+            "Color": "Blue",
+            "BlinkEnabled": false,
+            "ContainerType": "BlueFlagContainer",
+            "IsEnabled": false
+        }))
+        .expect("We should be able to deserialize the flag container");
+
+        assert_eq!(
+            SimpleBlinkContainer::from(container),
+            SimpleBlinkContainer {
+                description: String::new(),
+                is_enabled: false,
+                led_count: NonZeroUsize::new(2).unwrap(),
+                start_position: NonZeroUsize::MIN,
+                color: Color::from_html("blue").unwrap(),
+                blink_enabled: false,
+                blink_delay: Duration::ZERO,
+                dual_blink_timing_enabled: false,
+                on_delay: Duration::ZERO,
+                off_delay: Duration::ZERO,
+            },
+            "The fields missing from the flag container should take their default values"
+        );
+    }
+
+    #[test]
+    fn from_redline_reached_container() {
+        let container: RedlineReachedContainer = serde_json::from_value(json!({
+            "LedCount": 8,
+            "Color": "Red",
+            "DualBlinkTimingEnabled": false,
+            "OffDelay": 100,
+            "OnDelay": 200,
+            "StartPosition": 3,
+            "ContainerType": "RedlineReachedContainer",
+            "Description": "Redline",
+            "IsEnabled": true
+        }))
+        .expect("We should be able to deserialize the redline container");
+
+        let container = SimpleBlinkContainer::from(container);
+
+        assert_eq!(
+            container,
+            SimpleBlinkContainer {
+                description: "Redline".to_owned(),
+                is_enabled: true,
+                led_count: NonZeroUsize::new(8).unwrap(),
+                start_position: NonZeroUsize::new(3).unwrap(),
+                color: Color::from_html("red").unwrap(),
+                blink_enabled: false,
+                blink_delay: Duration::ZERO,
+                dual_blink_timing_enabled: false,
+                on_delay: Duration::from_millis(200),
+                off_delay: Duration::from_millis(100),
+            },
+            "The missing blink settings of the redline container should take their default values"
+        );
+        assert_eq!(
+            container.blink_delays(),
+            (Duration::ZERO, Duration::ZERO),
+            "The on and off delays should be ignored unless dual timing is enabled"
+        );
+    }
+}
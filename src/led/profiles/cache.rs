@@ -0,0 +1,162 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A compact binary format for [`LedProfile`]s, which is a lot faster to load than the JSON
+//! profiles SimHub writes.
+
+use std::io::{Read, Write};
+
+use anyhow::{Context, Result};
+
+use super::LedProfile;
+
+/// The bytes every profile cache starts with.
+const CACHE_MAGIC: [u8; 4] = *b"LMXP";
+
+/// The version of the cache format. This needs to be bumped whenever the layout of the
+/// [`LedProfile`] or of any of the containers changes, so caches written by an older version get
+/// rejected instead of being misinterpreted.
+const CACHE_VERSION: u32 = 3;
+
+impl LedProfile {
+    /// Write the profile into the given writer using the binary cache format.
+    ///
+    /// The cache can be loaded again using [`LedProfile::from_cache()`].
+    pub fn to_cache(&self, mut writer: impl Write) -> Result<()> {
+        writer
+            .write_all(&CACHE_MAGIC)
+            .and_then(|_| writer.write_all(&CACHE_VERSION.to_le_bytes()))
+            .context("Could not write the header of the profile cache")?;
+
+        bincode::serialize_into(writer, self).context("Could not write the profile cache")
+    }
+
+    /// Load a profile which was written using [`LedProfile::to_cache()`].
+    ///
+    /// Caches written by a different version of the cache format are rejected, the profile needs
+    /// to be loaded from its original file in that case.
+    pub fn from_cache(mut reader: impl Read) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        let mut version = [0u8; 4];
+
+        reader
+            .read_exact(&mut magic)
+            .and_then(|_| reader.read_exact(&mut version))
+            .context("Could not read the header of the profile cache")?;
+
+        anyhow::ensure!(magic == CACHE_MAGIC, "The file isn't a profile cache");
+
+        let version = u32::from_le_bytes(version);
+
+        anyhow::ensure!(
+            version == CACHE_VERSION,
+            "The profile cache uses version {version} of the cache format, but only version \
+             {CACHE_VERSION} is supported"
+        );
+
+        bincode::deserialize_from(reader).context("Could not read the profile cache")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use similar_asserts::assert_eq;
+
+    use super::*;
+    use crate::led::profiles::{supported_container_types, test::minimal_container};
+
+    fn profile() -> LedProfile {
+        let mut containers: Vec<_> = supported_container_types()
+            .iter()
+            .map(|container_type| minimal_container(container_type))
+            .collect();
+
+        containers.push(json!({
+            "ContainerType": "SimHub.Plugins.FancyNewContainer",
+            "StartPosition": 3,
+            "Speed": 1.5,
+        }));
+        containers.push(json!({
+            "LedContainers": [minimal_container("RPMContainer")],
+            "StackLeftToRight": true,
+            "ContainerType": "GroupContainer",
+            "IsEnabled": true,
+        }));
+
+        serde_json::from_value(json!({
+            "Name": "Cache",
+            "ProfileId": "0b8e0a4c-2f6d-4e1b-8c3a-7d5f9e1a2b3c",
+            "GlobalBrightness": 0.8,
+            "UseProfileBrightness": true,
+            "LedContainers": containers,
+            "CarOverrides": {
+                "bmw_m4_gt3": [minimal_container("BlueFlagContainer")],
+            },
+        }))
+        .expect("We should be able to deserialize the profile")
+    }
+
+    #[test]
+    fn round_trip() {
+        let profile = profile();
+
+        let mut cache = Vec::new();
+        profile
+            .to_cache(&mut cache)
+            .expect("We should be able to write the profile cache");
+
+        let cached = LedProfile::from_cache(cache.as_slice())
+            .expect("We should be able to load the profile cache");
+
+        assert_eq!(
+            format!("{cached:#?}"),
+            format!("{profile:#?}"),
+            "The cached profile should be the same as the original one"
+        );
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let mut cache = Vec::new();
+        profile()
+            .to_cache(&mut cache)
+            .expect("We should be able to write the profile cache");
+
+        cache[4..8].copy_from_slice(&(CACHE_VERSION + 1).to_le_bytes());
+
+        let error = LedProfile::from_cache(cache.as_slice())
+            .expect_err("A cache with a different version should be rejected");
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "The profile cache uses version {} of the cache format, but only version \
+                 {CACHE_VERSION} is supported",
+                CACHE_VERSION + 1
+            )
+        );
+
+        let error = LedProfile::from_cache(&b"{\"Name\": \"JSON\"}"[..])
+            .expect_err("A JSON profile isn't a valid cache");
+
+        assert_eq!(error.to_string(), "The file isn't a profile cache");
+    }
+}
@@ -21,28 +21,59 @@
 use std::{num::NonZeroUsize, time::Duration};
 
 use csscolorparser::Color;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use super::{color_from_str, default_non_zero, duration_from_int_ms};
+use super::{
+    color_from_str, color_to_str, default_non_zero, duration_from_int_ms, duration_to_int_ms,
+    led_count_from_int, optional_duration_from_int_ms, optional_duration_to_int_ms,
+};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct FlagContainer {
     #[serde(default)]
     pub description: String,
     pub is_enabled: bool,
+    #[serde(deserialize_with = "led_count_from_int")]
     pub led_count: NonZeroUsize,
     #[serde(default = "default_non_zero")]
     pub start_position: NonZeroUsize,
-    #[serde(deserialize_with = "color_from_str")]
+    #[serde(deserialize_with = "color_from_str", serialize_with = "color_to_str")]
     pub color: Color,
     pub blink_enabled: bool,
-    #[serde(default, deserialize_with = "duration_from_int_ms")]
+    #[serde(
+        default,
+        deserialize_with = "duration_from_int_ms",
+        serialize_with = "duration_to_int_ms"
+    )]
     pub blink_delay: Duration,
+    /// Should the LEDs use the separate [`FlagContainer::on_delay`] and
+    /// [`FlagContainer::off_delay`] instead of the [`FlagContainer::blink_delay`].
+    ///
+    /// This flag alone decides which timing is used. SimHub exports its default on and off
+    /// delays even if dual timing is disabled, so non-zero delays are ignored unless this is set.
     #[serde(default)]
     pub dual_blink_timing_enabled: bool,
-    #[serde(default, deserialize_with = "duration_from_int_ms")]
+    #[serde(
+        default,
+        deserialize_with = "duration_from_int_ms",
+        serialize_with = "duration_to_int_ms"
+    )]
     pub off_delay: Duration,
-    #[serde(default, deserialize_with = "duration_from_int_ms")]
+    #[serde(
+        default,
+        deserialize_with = "duration_from_int_ms",
+        serialize_with = "duration_to_int_ms"
+    )]
     pub on_delay: Duration,
+    /// How long the flag is considered to be waving after it was last seen.
+    ///
+    /// Some games drop a flag from the telemetry for a single frame, which would turn the LEDs
+    /// off for a moment. Not a SimHub setting, the flag is followed exactly if this isn't set.
+    #[serde(
+        default,
+        deserialize_with = "optional_duration_from_int_ms",
+        serialize_with = "optional_duration_to_int_ms"
+    )]
+    pub minimum_hold: Option<Duration>,
 }
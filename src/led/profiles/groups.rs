@@ -20,9 +20,9 @@
 
 use std::{num::NonZeroUsize, time::Duration};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use super::{default_non_zero, duration_from_int_ms, LedContainer};
+use super::{default_non_zero, duration_from_int_ms, duration_to_int_ms, LedContainer};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum StackingType {
@@ -46,7 +46,17 @@ impl<'de> Deserialize<'de> for StackingType {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl Serialize for StackingType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let stack_left_to_right = *self == StackingType::LeftToRight;
+        stack_left_to_right.serialize(serializer)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct SimpleGroupContainer {
     #[serde(default)]
@@ -59,14 +69,33 @@ pub struct SimpleGroupContainer {
     pub led_containers: Vec<LedContainer>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// When should a time limited group, which already ran once, be allowed to run again.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ReArmPolicy {
+    /// Run again once the engine of the car was stopped.
+    #[default]
+    OnEngineStop,
+    /// Run again once the car enters the pit lane, useful for cars which never report that the
+    /// engine was stopped.
+    OnPitLaneEntry,
+    /// Never run again.
+    Never,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct TimeLimitedGroupContainer {
     #[serde(default)]
     pub description: String,
     pub is_enabled: bool,
-    #[serde(deserialize_with = "duration_from_int_ms")]
+    #[serde(
+        deserialize_with = "duration_from_int_ms",
+        serialize_with = "duration_to_int_ms"
+    )]
     pub duration: Duration,
+    /// When should the group be allowed to run again after the duration has expired.
+    #[serde(default)]
+    pub re_arm_policy: ReArmPolicy,
     #[serde(default, rename = "StackLeftToRight")]
     pub stacking_type: StackingType,
     #[serde(default = "default_non_zero")]
@@ -74,13 +103,13 @@ pub struct TimeLimitedGroupContainer {
     pub led_containers: Vec<LedContainer>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Formula {
     pub expression: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct ConditionalGroupContainer {
     #[serde(default)]
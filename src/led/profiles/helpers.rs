@@ -20,8 +20,8 @@
 
 use std::{num::NonZeroUsize, time::Duration};
 
-use csscolorparser::Color;
-use serde::{Deserialize, Deserializer};
+use csscolorparser::{Color, ParseColorError};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// Helper to deserialize a integer containing milliseconds into a [`Duration`].
 pub fn duration_from_int_ms<'de, D>(deserializer: D) -> Result<Duration, D::Error>
@@ -31,15 +31,289 @@ where
     u64::deserialize(deserializer).map(Duration::from_millis)
 }
 
-/// Helper to deserialize a string containing a HTML color into a [`Color`].
+/// Helper to serialize a [`Duration`] into an integer containing milliseconds.
+pub fn duration_to_int_ms<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    // Profiles configure their durations in milliseconds, so nothing is lost here.
+    let ms = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+    ms.serialize(serializer)
+}
+
+/// Helper to deserialize an optional integer containing milliseconds into a [`Duration`].
+pub fn optional_duration_from_int_ms<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<u64>::deserialize(deserializer).map(|ms| ms.map(Duration::from_millis))
+}
+
+/// Helper to serialize an optional [`Duration`] into an optional integer containing milliseconds.
+pub fn optional_duration_to_int_ms<S>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match duration {
+        Some(duration) => serializer.serialize_some(&DurationMs(duration)),
+        None => serializer.serialize_none(),
+    }
+}
+
+struct DurationMs<'a>(&'a Duration);
+
+impl Serialize for DurationMs<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        duration_to_int_ms(self.0, serializer)
+    }
+}
+
+/// Helper to deserialize a string containing a color into a [`Color`], see [`parse_color()`] for
+/// the supported formats.
+///
+/// Some SimHub exports store the color as a packed ARGB integer instead of a string, those are
+/// accepted as well, see [`unpack_argb()`].
+///
+/// Binary formats store the color as its four components instead, see [`color_to_str()`].
 pub fn color_from_str<'de, D>(deserializer: D) -> Result<Color, D::Error>
 where
     D: Deserializer<'de>,
 {
-    String::deserialize(deserializer)
-        .and_then(|color| Color::from_html(color).map_err(serde::de::Error::custom))
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ColorValue {
+        Text(String),
+        Packed(i64),
+    }
+
+    if deserializer.is_human_readable() {
+        match ColorValue::deserialize(deserializer)? {
+            ColorValue::Text(color) => parse_color(&color).map_err(serde::de::Error::custom),
+            ColorValue::Packed(packed) => unpack_argb(packed).ok_or_else(|| {
+                serde::de::Error::custom(format!(
+                    "The packed color {packed} doesn't fit into a 32 bit ARGB value"
+                ))
+            }),
+        }
+    } else {
+        <[f64; 4]>::deserialize(deserializer).map(|[r, g, b, a]| Color::new(r, g, b, a))
+    }
+}
+
+/// Helper to serialize a [`Color`] into a hex color string.
+///
+/// Binary formats, which aren't meant to be edited by hand, get the exact components of the color
+/// instead.
+pub fn color_to_str<S>(color: &Color, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if serializer.is_human_readable() {
+        color.to_hex_string().serialize(serializer)
+    } else {
+        [color.r, color.g, color.b, color.a].serialize(serializer)
+    }
+}
+
+/// Helper to deserialize an optional string containing a color, see [`color_from_str()`].
+pub fn optional_color_from_str<'de, D>(deserializer: D) -> Result<Option<Color>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<ColorDeserializer>::deserialize(deserializer).map(|color| color.map(|c| c.0))
+}
+
+/// Helper to serialize an optional [`Color`], see [`color_to_str()`].
+pub fn optional_color_to_str<S>(color: &Option<Color>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match color {
+        Some(color) => serializer.serialize_some(&ColorStr(color)),
+        None => serializer.serialize_none(),
+    }
+}
+
+struct ColorStr<'a>(&'a Color);
+
+impl Serialize for ColorStr<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        color_to_str(self.0, serializer)
+    }
+}
+
+struct ColorDeserializer(Color);
+
+impl<'de> Deserialize<'de> for ColorDeserializer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        color_from_str(deserializer).map(Self)
+    }
+}
+
+/// Parse a color the way SimHub writes them.
+///
+/// Besides the HTML colors [`Color::from_html()`] understands, e.g. `Red`, `#ff0000` or
+/// `#ff000080`, SimHub uses comma separated lists of components, e.g. `255, 0, 0`. The list may
+/// contain a fourth component for the alpha channel. All the components go from 0 to 255.
+pub fn parse_color(color: &str) -> Result<Color, ParseColorError> {
+    match parse_component_list(color) {
+        Some(color) => Ok(color),
+        None => Color::from_html(color),
+    }
+}
+
+/// Decode a color packed into a 32 bit ARGB integer, the way .NET stores colors.
+///
+/// SimHub writes the integer as a signed number, so opaque colors are negative, e.g. `-16711936`
+/// is opaque green. Both the signed and the unsigned form are accepted, `None` is returned if the
+/// number doesn't fit into 32 bits.
+pub fn unpack_argb(packed: i64) -> Option<Color> {
+    let argb = match i32::try_from(packed) {
+        Ok(signed) => signed as u32,
+        Err(_) => u32::try_from(packed).ok()?,
+    };
+
+    let [a, r, g, b] = argb.to_be_bytes();
+
+    Some(Color::from_rgba8(r, g, b, a))
+}
+
+fn parse_component_list(color: &str) -> Option<Color> {
+    let components = color
+        .split(',')
+        .map(|component| component.trim().parse::<u8>())
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+
+    match components[..] {
+        [r, g, b] => Some(Color::from_rgba8(r, g, b, u8::MAX)),
+        [r, g, b, a] => Some(Color::from_rgba8(r, g, b, a)),
+        _ => None,
+    }
+}
+
+/// Helper to deserialize the number of LEDs a container controls.
+///
+/// Containers without any LEDs aren't supported, they are rejected with an error explaining why
+/// the profile couldn't be loaded.
+pub fn led_count_from_int<'de, D>(deserializer: D) -> Result<NonZeroUsize, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let led_count = usize::deserialize(deserializer)?;
+
+    NonZeroUsize::new(led_count).ok_or_else(|| {
+        serde::de::Error::custom(
+            "The LedCount of a container needs to be at least one, containers without any LEDs \
+             aren't supported",
+        )
+    })
 }
 
 pub fn default_non_zero() -> NonZeroUsize {
     NonZeroUsize::MIN
 }
+
+#[cfg(test)]
+mod test {
+    use similar_asserts::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn component_lists() {
+        assert_eq!(
+            parse_color("255, 128, 0").unwrap().to_rgba8(),
+            [255, 128, 0, 255],
+            "A list of three components should be an opaque color"
+        );
+        assert_eq!(
+            parse_color("255,128, 0 , 64").unwrap().to_rgba8(),
+            [255, 128, 0, 64],
+            "The fourth component of the list should be used as the alpha channel"
+        );
+
+        assert!(
+            parse_color("255, 128").is_err(),
+            "A list of two components isn't a valid color"
+        );
+        assert!(
+            parse_color("255, 128, 0, 64, 32").is_err(),
+            "A list of five components isn't a valid color"
+        );
+        assert!(
+            parse_color("256, 128, 0").is_err(),
+            "The components of the list can't be larger than 255"
+        );
+    }
+
+    #[test]
+    fn html_colors() {
+        assert_eq!(parse_color("Red").unwrap().to_rgba8(), [255, 0, 0, 255]);
+        assert_eq!(parse_color("#00ff00").unwrap().to_rgba8(), [0, 255, 0, 255]);
+        assert_eq!(
+            parse_color("#0000ff80").unwrap().to_rgba8(),
+            [0, 0, 255, 128],
+            "The last byte of a 8 digit hex color should be used as the alpha channel"
+        );
+        assert_eq!(
+            parse_color("rgb(1, 2, 3)").unwrap().to_rgba8(),
+            [1, 2, 3, 255],
+            "CSS functions with comma separated arguments should still be supported"
+        );
+    }
+
+    #[test]
+    fn packed_colors() {
+        #[derive(Deserialize)]
+        struct Container {
+            #[serde(deserialize_with = "color_from_str")]
+            color: Color,
+        }
+
+        let color = |value: serde_json::Value| {
+            serde_json::from_value::<Container>(serde_json::json!({ "color": value }))
+                .map(|container| container.color.to_rgba8())
+        };
+
+        assert_eq!(
+            color((-16711936).into()).unwrap(),
+            [0, 255, 0, 255],
+            "A negative packed integer should be decoded as an opaque ARGB color"
+        );
+        assert_eq!(
+            color(0xff00ff00u32.into()).unwrap(),
+            [0, 255, 0, 255],
+            "The unsigned form of a packed color should be accepted as well"
+        );
+        assert_eq!(
+            color(0x80ff0000u32.into()).unwrap(),
+            [255, 0, 0, 128],
+            "The highest byte of a packed color should be used as the alpha channel"
+        );
+        assert!(
+            color(0x1_0000_0000i64.into()).is_err(),
+            "Packed colors larger than 32 bits should be rejected"
+        );
+
+        assert_eq!(color("Green".into()).unwrap(), [0, 128, 0, 255]);
+        assert_eq!(color("#00ff00".into()).unwrap(), [0, 255, 0, 255]);
+        assert_eq!(color("0, 255, 0".into()).unwrap(), [0, 255, 0, 255]);
+        assert!(
+            color("not a color".into()).is_err(),
+            "Strings which aren't colors should still be rejected"
+        );
+    }
+}
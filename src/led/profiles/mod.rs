@@ -21,34 +21,55 @@
 //! Module containing parsers for popular LED light profile file formats for Sim racing which
 //! configure how LED lights on Sim racing dashboards and steering wheels should operate.
 
-use std::num::NonZeroUsize;
+use std::{
+    borrow::Borrow,
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufReader, Read},
+    num::NonZeroUsize,
+    path::Path,
+};
+
+use anyhow::Context as _;
 
-use serde::{Deserialize, Deserializer};
-use serde_json::value::RawValue;
+use serde::{
+    de::{DeserializeSeed, SeqAccess, Visitor},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::Value;
 use uuid::Uuid;
 
 use self::{
+    assist::AssistContainer,
     flag::FlagContainer,
-    groups::{ConditionalGroupContainer, SimpleGroupContainer, TimeLimitedGroupContainer},
+    groups::{
+        ConditionalGroupContainer, SimpleGroupContainer, StackingType, TimeLimitedGroupContainer,
+    },
     redline::RedlineReachedContainer,
     rpm::{RpmContainer, RpmSegmentsContainer},
     speed_limiter::SpeedLimiterAnimationContainer,
 };
+use crate::led::state::flag::FlagColor;
 
 pub use self::helpers::*;
+mod cache;
 mod helpers;
 
+pub mod assist;
+pub mod blink;
 pub mod flag;
 pub mod groups;
 pub mod redline;
 pub mod rpm;
+pub mod set;
 pub mod speed_limiter;
 
 /// The [`LedProfile`] struct contains configurations for controlling RGB LED lights.
 ///
 /// This struct collects configurations and definitions how LED lights on a steering wheel or data
 /// display unit should behave depending on the inputs of a simracing game.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct LedProfile {
     /// The name of the profile.
@@ -65,8 +86,18 @@ pub struct LedProfile {
     pub automatic_switch: bool,
     pub embedded_javascript: Option<String>,
     pub game_code: Option<String>,
+    /// How the top-level containers of the profile should be positioned. By default the
+    /// containers are layered on top of each other using their start positions, profiles can
+    /// instead place them one after another.
+    #[serde(default, rename = "StackLeftToRight")]
+    pub stacking_type: StackingType,
     /// A list of [`LedContainer`] values which configure a set of LEDs.
     pub led_containers: Vec<LedContainer>,
+    /// Car specific lists of [`LedContainer`] values, keyed by the unique ID of the car. If the
+    /// current car has an entry in this map, the containers of the entry are used instead of the
+    /// [`LedProfile::led_containers`].
+    #[serde(default)]
+    pub car_overrides: HashMap<String, Vec<LedContainer>>,
 }
 
 /// The [`LedContainer`] contains a single configuration for the behavior of a set of LED lights.
@@ -80,6 +111,7 @@ pub enum LedContainer {
     RpmSegments(RpmSegmentsContainer),
     RedlineReached(RedlineReachedContainer),
     SpeedLimiterAnimation(SpeedLimiterAnimationContainer),
+    Assist(AssistContainer),
     Group(GroupContainer),
     BlueFlag(FlagContainer),
     WhiteFlag(FlagContainer),
@@ -87,17 +119,379 @@ pub enum LedContainer {
     Unknown {
         start_position: NonZeroUsize,
         container_type: String,
-        content: Box<RawValue>,
+        /// The content of the container, kept as a JSON value independently of the format the
+        /// profile was written in.
+        content: Value,
     },
 }
 
+/// Evaluate an expression for the content of a known container, the content is bound to the given
+/// identifier. Unknown containers are matched against the given pattern instead.
+macro_rules! with_content {
+    ($container:expr, $c:ident => $call:expr, $unknown:pat => $fallback:expr) => {
+        match $container {
+            LedContainer::Rpm($c) => $call,
+            LedContainer::RpmSegments($c) => $call,
+            LedContainer::RedlineReached($c) => $call,
+            LedContainer::SpeedLimiterAnimation($c) => $call,
+            LedContainer::Assist($c) => $call,
+            LedContainer::Group(GroupContainer::Simple($c)) => $call,
+            LedContainer::Group(GroupContainer::GameRunning($c)) => $call,
+            LedContainer::Group(GroupContainer::CarStarted($c)) => $call,
+            LedContainer::Group(GroupContainer::Conditional($c)) => $call,
+            LedContainer::BlueFlag($c) => $call,
+            LedContainer::WhiteFlag($c) => $call,
+            LedContainer::YellowFlag($c) => $call,
+            $unknown => $fallback,
+        }
+    };
+}
+
 impl LedContainer {
+    /// Get the description of the container, as shown in the profile editor of SimHub.
+    pub fn description(&self) -> &str {
+        match self {
+            LedContainer::Rpm(c) => &c.description,
+            LedContainer::RpmSegments(c) => &c.description,
+            LedContainer::RedlineReached(c) => &c.description,
+            LedContainer::SpeedLimiterAnimation(c) => &c.description,
+            LedContainer::Assist(c) => &c.description,
+            LedContainer::Group(c) => c.description(),
+            LedContainer::BlueFlag(c)
+            | LedContainer::WhiteFlag(c)
+            | LedContainer::YellowFlag(c) => &c.description,
+            LedContainer::Unknown { content, .. } => content
+                .get("Description")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Is the container enabled in the profile.
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            LedContainer::Rpm(c) => c.is_enabled,
+            LedContainer::RpmSegments(c) => c.is_enabled,
+            LedContainer::RedlineReached(c) => c.is_enabled,
+            LedContainer::SpeedLimiterAnimation(c) => c.is_enabled,
+            LedContainer::Assist(c) => c.is_enabled,
+            LedContainer::Group(c) => c.is_enabled(),
+            LedContainer::BlueFlag(c)
+            | LedContainer::WhiteFlag(c)
+            | LedContainer::YellowFlag(c) => c.is_enabled,
+            LedContainer::Unknown { content, .. } => content
+                .get("IsEnabled")
+                .and_then(Value::as_bool)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The position of a single effect of a [`LedProfile`], see [`LedProfile::layout()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectPlacement {
+    /// The position of the first LED the effect controls.
+    pub start: NonZeroUsize,
+    /// The number of LEDs the effect controls.
+    pub count: usize,
+    /// The type of the container the effect was created from, e.g. `RPMContainer`.
+    pub kind: String,
+    /// The description of the container the effect was created from.
+    pub description: String,
+    /// Are the container and all the groups containing it enabled.
+    pub enabled: bool,
+}
+
+/// The byte order mark some Windows programs put at the start of UTF-8 encoded files.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+impl LedProfile {
+    /// Parse a JSON profile, like the ones SimHub writes, from the given reader.
+    ///
+    /// A leading UTF-8 byte order mark and trailing whitespace, which profiles exported on Windows
+    /// sometimes contain, are ignored.
+    pub fn from_reader(mut reader: impl Read) -> serde_json::Result<Self> {
+        let mut profile = Vec::new();
+        reader
+            .read_to_end(&mut profile)
+            .map_err(serde_json::Error::io)?;
+
+        let profile = profile.strip_prefix(UTF8_BOM).unwrap_or(&profile);
+
+        serde_json::from_slice(profile)
+    }
+
+    /// Parse the JSON profile stored in the file at the given path.
+    ///
+    /// See [`LedProfile::from_reader()`] for details.
+    pub fn from_path(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let profile = File::open(path).context("Couldn't open the LED profile")?;
+        let reader = BufReader::new(profile);
+
+        Self::from_reader(reader).context("Could not deserialize the LED profile")
+    }
+
+    /// Parse a profile written in YAML.
+    ///
+    /// The profile uses the same field names as the JSON profiles SimHub writes.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(profile: &str) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_str(profile)
+    }
+
+    /// Write the profile as YAML, it can be read back using [`LedProfile::from_yaml()`].
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(self)
+    }
+
+    /// Parse a profile written in RON.
+    ///
+    /// The profile uses the same field names as the JSON profiles SimHub writes. Since the type
+    /// of a container is only known once its `ContainerType` field has been read, containers need
+    /// to be written as RON maps instead of using the struct syntax.
+    #[cfg(feature = "ron")]
+    pub fn from_ron(profile: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(profile)
+    }
+
+    /// Write the profile as RON, it can be read back using [`LedProfile::from_ron()`].
+    ///
+    /// The containers are written as RON maps, see [`LedProfile::from_ron()`].
+    #[cfg(feature = "ron")]
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    /// Get the position of the highest LED any of the effects of this profile touches.
+    ///
+    /// Positions start at one, like the start positions of the containers. Returns zero if the
+    /// profile doesn't contain any effects with LEDs. The car specific overrides aren't taken
+    /// into account.
+    pub fn max_led_index(&self) -> usize {
+        max_led_position(&self.led_containers, NonZeroUsize::MIN, self.stacking_type)
+    }
+
+    /// Get the positions of all the effects of the profile, as a flat list.
+    ///
+    /// The containers of nested groups are included in place of the group, their positions are
+    /// resolved the same way a [`GroupState`] positions its effects. Containers without an effect,
+    /// see [`LedContainer::has_effect()`], are skipped.
+    ///
+    /// [`GroupState`]: crate::led::state::groups::GroupState
+    pub fn layout(&self) -> Vec<EffectPlacement> {
+        let mut placements = Vec::new();
+
+        collect_layout(
+            &self.led_containers,
+            NonZeroUsize::MIN,
+            self.stacking_type,
+            true,
+            &mut placements,
+        );
+
+        placements
+    }
+
+    /// Get the flags the containers of the profile react to.
+    ///
+    /// Containers inside of groups and the car specific containers are included as well.
+    pub fn referenced_flags(&self) -> HashSet<FlagColor> {
+        let mut flags = HashSet::new();
+
+        collect_flags(&self.led_containers, &mut flags);
+
+        for containers in self.car_overrides.values() {
+            collect_flags(containers, &mut flags);
+        }
+
+        flags
+    }
+}
+
+/// Resolve the start positions of the given containers of a group.
+///
+/// In a layered group the start position of a container is relative to the start of the group, in
+/// a group stacking from left to right each container starts right after the effect of the
+/// previous one. Nested groups are placed at their own start position, offset by the given base
+/// position.
+///
+/// This is used to create the effects of a [`GroupState`] as well as for the layout of a profile,
+/// so both always agree on where an effect is placed.
+///
+/// [`GroupState`]: crate::led::state::groups::GroupState
+pub(crate) fn resolve_positions<C: Borrow<LedContainer>>(
+    containers: impl IntoIterator<Item = C>,
+    group_start_position: NonZeroUsize,
+    base_position: NonZeroUsize,
+    stacking_type: StackingType,
+) -> impl Iterator<Item = (NonZeroUsize, C)> {
+    let mut next_position = group_start_position;
+
+    containers.into_iter().map(move |container| {
+        let led_container: &LedContainer = container.borrow();
+
+        let start_position = match (led_container, stacking_type) {
+            (LedContainer::Group(group), _) => {
+                base_position.saturating_add(group.start_position().get() - 1)
+            }
+            (_, StackingType::Layered) => {
+                group_start_position.saturating_add(led_container.start_position().get() - 1)
+            }
+            (_, StackingType::LeftToRight) => next_position,
+        };
+
+        if stacking_type == StackingType::LeftToRight {
+            next_position = next_position.saturating_add(led_container.effect_led_count());
+        }
+
+        (start_position, container)
+    })
+}
+
+/// Collect the positions of the effects of the given containers of a group, see
+/// [`LedProfile::layout()`].
+fn collect_layout(
+    containers: &[LedContainer],
+    group_start_position: NonZeroUsize,
+    stacking_type: StackingType,
+    group_enabled: bool,
+    placements: &mut Vec<EffectPlacement>,
+) {
+    let positions = resolve_positions(
+        containers,
+        group_start_position,
+        NonZeroUsize::MIN,
+        stacking_type,
+    );
+
+    for (start_position, container) in positions {
+        let enabled = group_enabled && container.is_enabled();
+
+        match container {
+            LedContainer::Group(group) => collect_layout(
+                group.led_containers(),
+                start_position,
+                group.stacking_type(),
+                enabled,
+                placements,
+            ),
+            container if container.has_effect() => {
+                if let Some(led_count) = container.led_count() {
+                    placements.push(EffectPlacement {
+                        start: start_position,
+                        count: led_count,
+                        kind: container.container_type().to_owned(),
+                        description: container.description().to_owned(),
+                        enabled,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Collect the flags the given containers, and the containers of any nested groups, react to.
+fn collect_flags(containers: &[LedContainer], flags: &mut HashSet<FlagColor>) {
+    for container in containers {
+        let flag = match container {
+            LedContainer::BlueFlag(_) => FlagColor::Blue,
+            LedContainer::WhiteFlag(_) => FlagColor::White,
+            LedContainer::YellowFlag(_) => FlagColor::Yellow,
+            LedContainer::Group(group) => {
+                collect_flags(group.led_containers(), flags);
+                continue;
+            }
+            LedContainer::Rpm(_)
+            | LedContainer::RpmSegments(_)
+            | LedContainer::RedlineReached(_)
+            | LedContainer::SpeedLimiterAnimation(_)
+            | LedContainer::Assist(_)
+            | LedContainer::Unknown { .. } => continue,
+        };
+
+        flags.insert(flag);
+    }
+}
+
+/// Get the position of the highest LED the effects of the given containers of a group touch, see
+/// [`resolve_positions()`].
+fn max_led_position(
+    containers: &[LedContainer],
+    group_start_position: NonZeroUsize,
+    stacking_type: StackingType,
+) -> usize {
+    resolve_positions(
+        containers,
+        group_start_position,
+        NonZeroUsize::MIN,
+        stacking_type,
+    )
+    .filter_map(|(start_position, container)| match container {
+        LedContainer::Group(group) => Some(max_led_position(
+            group.led_containers(),
+            start_position,
+            group.stacking_type(),
+        )),
+        container if container.has_effect() => container
+            .led_count()
+            .map(|led_count| start_position.get() + led_count - 1),
+        _ => None,
+    })
+    .max()
+    .unwrap_or_default()
+}
+
+impl LedContainer {
+    /// The number of LEDs this container controls.
+    ///
+    /// Returns `None` for groups, which don't control LEDs on their own, and for unknown
+    /// containers.
+    pub fn led_count(&self) -> Option<usize> {
+        Some(match self {
+            LedContainer::Rpm(c) => c.led_count.get(),
+            LedContainer::RpmSegments(c) => c.total_led_count(),
+            LedContainer::RedlineReached(c) => c.led_count.get(),
+            LedContainer::SpeedLimiterAnimation(c) => c.led_count.get(),
+            LedContainer::Assist(c) => c.led_count.get(),
+            LedContainer::BlueFlag(c)
+            | LedContainer::WhiteFlag(c)
+            | LedContainer::YellowFlag(c) => c.led_count.get(),
+            LedContainer::Group(_) | LedContainer::Unknown { .. } => return None,
+        })
+    }
+
+    /// Is an effect created for this container.
+    ///
+    /// No effects are created for `RedlineReached` containers and containers of an unknown type,
+    /// they are skipped when the effects of a group are created and don't take up any LEDs.
+    pub fn has_effect(&self) -> bool {
+        !matches!(
+            self,
+            LedContainer::RedlineReached(_) | LedContainer::Unknown { .. }
+        )
+    }
+
+    /// The number of LEDs the effect created for this container controls, including the effects
+    /// of nested groups.
+    ///
+    /// Returns zero if no effect is created for the container, see [`LedContainer::has_effect()`].
+    pub fn effect_led_count(&self) -> usize {
+        match self {
+            LedContainer::Group(group) => group.led_count(),
+            container if container.has_effect() => container.led_count().unwrap_or_default(),
+            _ => 0,
+        }
+    }
+
     pub fn start_position(&self) -> NonZeroUsize {
         match self {
             LedContainer::Rpm(c) => c.start_position,
             LedContainer::RpmSegments(c) => c.start_position,
             LedContainer::RedlineReached(c) => c.start_position,
             LedContainer::SpeedLimiterAnimation(c) => c.start_position,
+            LedContainer::Assist(c) => c.start_position,
             LedContainer::Group(c) => c.start_position(),
             LedContainer::BlueFlag(c) => c.start_position,
             LedContainer::WhiteFlag(c) => c.start_position,
@@ -124,6 +518,139 @@ impl GroupContainer {
             GroupContainer::Conditional(c) => c.start_position,
         }
     }
+
+    pub fn stacking_type(&self) -> StackingType {
+        match self {
+            GroupContainer::Simple(c) => c.stacking_type,
+            GroupContainer::GameRunning(c) => c.stacking_type,
+            GroupContainer::CarStarted(c) => c.stacking_type,
+            GroupContainer::Conditional(c) => c.stacking_type,
+        }
+    }
+
+    pub fn led_containers(&self) -> &[LedContainer] {
+        match self {
+            GroupContainer::Simple(c) => &c.led_containers,
+            GroupContainer::GameRunning(c) => &c.led_containers,
+            GroupContainer::CarStarted(c) => &c.led_containers,
+            GroupContainer::Conditional(c) => &c.led_containers,
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            GroupContainer::Simple(c) => &c.description,
+            GroupContainer::GameRunning(c) => &c.description,
+            GroupContainer::CarStarted(c) => &c.description,
+            GroupContainer::Conditional(c) => &c.description,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            GroupContainer::Simple(c) => c.is_enabled,
+            GroupContainer::GameRunning(c) => c.is_enabled,
+            GroupContainer::CarStarted(c) => c.is_enabled,
+            GroupContainer::Conditional(c) => c.is_enabled,
+        }
+    }
+
+    /// Get the position of the highest LED any of the effects of this group touch, see
+    /// [`LedProfile::max_led_index()`].
+    pub fn max_led_index(&self) -> usize {
+        max_led_position(
+            self.led_containers(),
+            self.start_position(),
+            self.stacking_type(),
+        )
+    }
+
+    /// The total number of LEDs the effects of the containers of this group control, including
+    /// the ones of nested groups, see [`LedContainer::effect_led_count()`].
+    pub fn led_count(&self) -> usize {
+        self.led_containers()
+            .iter()
+            .map(LedContainer::effect_led_count)
+            .sum()
+    }
+}
+
+/// Build the variant of [`LedContainer`] of an entry of [`container_types!`] around the given
+/// content, this works both as a pattern and as an expression.
+macro_rules! container_variant {
+    ($variant:ident, $content:tt) => {
+        LedContainer::$variant($content)
+    };
+    ($variant:ident($group:ident), $content:tt) => {
+        LedContainer::$variant(GroupContainer::$group($content))
+    };
+}
+
+/// Define the container types this version understands.
+///
+/// Every entry maps the name of a container type, as it's written in the `ContainerType` field of
+/// a container, to the variant of [`LedContainer`] holding the content of the container. The list
+/// of supported container types, [`LedContainer::container_type()`] and the deserialization of
+/// the content of a container are all generated from this single table.
+macro_rules! container_types {
+    ($($name:literal => $variant:ident $(($group:ident))?,)+) => {
+        const CONTAINER_TYPES: &[&str] = &[$($name),+];
+
+        impl LedContainer {
+            /// Get the type of the container, as it's written in the `ContainerType` field of the
+            /// container.
+            pub fn container_type(&self) -> &str {
+                match self {
+                    $(container_variant!($variant $(($group))?, _) => $name,)+
+                    LedContainer::Unknown { container_type, .. } => container_type,
+                }
+            }
+        }
+
+        /// Deserialize the content of a container with the given type, the type needs to be one
+        /// of the [`supported_container_types()`].
+        fn deserialize_content<'de, D>(
+            container_type: &str,
+            content: D,
+        ) -> Result<LedContainer, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            match container_type {
+                $($name => {
+                    let c = Deserialize::deserialize(content)?;
+                    Ok(container_variant!($variant $(($group))?, c))
+                })+
+                _ => Err(serde::de::Error::unknown_variant(
+                    container_type,
+                    CONTAINER_TYPES,
+                )),
+            }
+        }
+    };
+}
+
+container_types! {
+    "RPMContainer" => Rpm,
+    "RPMSegmentsContainer" => RpmSegments,
+    "RedlineReachedContainer" => RedlineReached,
+    "SpeedLimiterAnimationContainer" => SpeedLimiterAnimation,
+    "AssistContainer" => Assist,
+    "YellowFlagContainer" => YellowFlag,
+    "BlueFlagContainer" => BlueFlag,
+    "WhiteFlagContainer" => WhiteFlag,
+    "GroupContainer" => Group(Simple),
+    "GameRunningGroupContainer" => Group(GameRunning),
+    // Yes, this is a typo, we need to support it.
+    "GameCarStatedGroupContainer" => Group(CarStarted),
+    "CustomConditionalGroupContainer" => Group(Conditional),
+}
+
+/// Get the list of container types this version understands.
+///
+/// Containers with any other type are deserialized as [`LedContainer::Unknown`].
+pub fn supported_container_types() -> &'static [&'static str] {
+    CONTAINER_TYPES
 }
 
 impl<'de> Deserialize<'de> for LedContainer {
@@ -131,65 +658,696 @@ impl<'de> Deserialize<'de> for LedContainer {
     where
         D: Deserializer<'de>,
     {
-        fn from_str<'a, T, E>(string: &'a str) -> Result<T, E>
-        where
-            T: serde::Deserialize<'a>,
-            E: serde::de::Error,
-        {
-            serde_json::from_str(string).map_err(serde::de::Error::custom)
-        }
-
         #[derive(Debug, Deserialize)]
         #[serde(rename_all = "PascalCase")]
-        struct Helper<'a> {
-            container_type: &'a str,
+        struct Helper {
+            container_type: String,
             #[serde(default = "default_non_zero")]
             start_position: NonZeroUsize,
         }
 
-        let json = Box::<serde_json::value::RawValue>::deserialize(deserializer)?;
+        if !deserializer.is_human_readable() {
+            return deserializer.deserialize_tuple(2, BinaryContainerVisitor);
+        }
+
+        // Go through a JSON value, instead of a borrowed JSON string, so containers can be
+        // deserialized from any self-describing format, not just JSON.
+        let content = Value::deserialize(deserializer)?;
 
-        let helper: Helper<'_> =
-            serde_json::from_str(json.get()).map_err(serde::de::Error::custom)?;
+        let helper = Helper::deserialize(&content).map_err(|e| {
+            serde::de::Error::custom(format!(
+                "Couldn't determine the type of a LED container, containers need a string \
+                 ContainerType field and an optional StartPosition of at least one: {e}"
+            ))
+        })?;
 
         // The container type might have a dot delimited prefix or it might have just the container
         // type. This will handle both cases, and give us the container type in PascalCase.
-        let container_type = helper.container_type.split('.').last().ok_or_else(|| {
+        let container_type = helper
+            .container_type
+            .rsplit('.')
+            .next()
+            .filter(|t| !t.is_empty() && !t.contains(char::is_whitespace))
+            .ok_or_else(|| {
+                serde::de::Error::custom(format!(
+                    "The LED container at start position {} has an invalid ContainerType {:?}, \
+                     expected a name like \"RPMContainer\", optionally prefixed with a dot \
+                     delimited namespace like \"SimHub.Plugins.RPMContainer\"",
+                    helper.start_position, helper.container_type
+                ))
+            })?;
+
+        if !CONTAINER_TYPES.contains(&container_type) {
+            return Ok(LedContainer::Unknown {
+                start_position: helper.start_position,
+                container_type: container_type.to_string(),
+                content,
+            });
+        }
+
+        deserialize_content(container_type, &content).map_err(|e| {
             serde::de::Error::custom(format!(
-                "Container type doesn't have a falid form: {}",
-                helper.container_type
+                "Couldn't parse the {container_type} at start position {}: {e}",
+                helper.start_position
             ))
-        })?;
+        })
+    }
+}
 
-        let content = json.get();
+impl Serialize for LedContainer {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Binary formats can't figure out the type of a container by looking at its fields, so
+        // the type is written out in front of the content.
+        if !serializer.is_human_readable() {
+            let mut tuple = serializer.serialize_tuple(2)?;
+            tuple.serialize_element(self.container_type())?;
 
-        Ok(match container_type {
-            "RPMContainer" => LedContainer::Rpm(from_str(content)?),
-            "RPMSegmentsContainer" => LedContainer::RpmSegments(from_str(content)?),
-            "RedlineReachedContainer" => LedContainer::RedlineReached(from_str(content)?),
-            "SpeedLimiterAnimationContainer" => {
-                LedContainer::SpeedLimiterAnimation(from_str(content)?)
-            }
-            "YellowFlagContainer" => LedContainer::YellowFlag(from_str(content)?),
-            "BlueFlagContainer" => LedContainer::BlueFlag(from_str(content)?),
-            "WhiteFlagContainer" => LedContainer::WhiteFlag(from_str(content)?),
-            "GroupContainer" => LedContainer::Group(GroupContainer::Simple(from_str(content)?)),
-            "GameRunningGroupContainer" => {
-                LedContainer::Group(GroupContainer::GameRunning(from_str(content)?))
-            }
-            // Yes, this is a typo, we need to support it.
-            "GameCarStatedGroupContainer" => {
-                LedContainer::Group(GroupContainer::CarStarted(from_str(content)?))
-            }
-            "CustomConditionalGroupContainer" => {
-                LedContainer::Group(GroupContainer::Conditional(from_str(content)?))
-            }
+            with_content!(
+                self,
+                c => tuple.serialize_element(c)?,
+                LedContainer::Unknown { start_position, content, .. } => {
+                    tuple.serialize_element(&(start_position, content.to_string()))?
+                }
+            );
 
-            t => LedContainer::Unknown {
-                start_position: helper.start_position,
-                container_type: t.to_string(),
-                content: json,
+            return tuple.end();
+        }
+
+        let content = with_content!(
+            self,
+            c => serde_json::to_value(c).map_err(serde::ser::Error::custom)?,
+            LedContainer::Unknown { content, .. } => return content.serialize(serializer)
+        );
+
+        let Value::Object(mut content) = content else {
+            unreachable!("Containers should always be serialized as a JSON object");
+        };
+
+        content.insert("ContainerType".to_owned(), self.container_type().into());
+        content.serialize(serializer)
+    }
+}
+
+/// Visitor for containers written by a binary format, see the [`Serialize`] implementation of
+/// [`LedContainer`].
+struct BinaryContainerVisitor;
+
+impl<'de> Visitor<'de> for BinaryContainerVisitor {
+    type Value = LedContainer;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a container type followed by the content of the container")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        fn next<'de, T, A>(seq: &mut A, index: usize) -> Result<T, A::Error>
+        where
+            T: Deserialize<'de>,
+            A: SeqAccess<'de>,
+        {
+            seq.next_element()?
+                .ok_or_else(|| serde::de::Error::invalid_length(index, &BinaryContainerVisitor))
+        }
+
+        let container_type: String = next(&mut seq, 0)?;
+
+        if CONTAINER_TYPES.contains(&container_type.as_str()) {
+            return seq
+                .next_element_seed(ContentSeed(&container_type))?
+                .ok_or_else(|| serde::de::Error::invalid_length(1, &BinaryContainerVisitor));
+        }
+
+        let (start_position, content): (NonZeroUsize, String) = next(&mut seq, 1)?;
+
+        Ok(LedContainer::Unknown {
+            start_position,
+            container_type,
+            content: serde_json::from_str(&content).map_err(serde::de::Error::custom)?,
+        })
+    }
+}
+
+/// Deserializes the content of a container with a known type, written by a binary format.
+struct ContentSeed<'a>(&'a str);
+
+impl<'de> DeserializeSeed<'de> for ContentSeed<'_> {
+    type Value = LedContainer;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_content(self.0, deserializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+    use similar_asserts::assert_eq;
+
+    use super::*;
+
+    /// Create the smallest valid container of the given type.
+    pub(super) fn minimal_container(container_type: &str) -> Value {
+        let mut container = match container_type {
+            "RPMContainer" => json!({
+                "LedCount": 5,
+                "PercentMin": 80.0,
+                "PercentMax": 95.0,
+                "RPMMin": 1000.0,
+                "RPMMax": 8000.0,
+                "StartColor": "Lime",
+                "EndColor": "Red",
+                "BlinkDelay": 100,
+            }),
+            "RPMSegmentsContainer" => json!({ "SegmentsCount": 0, "Segments": [] }),
+            "RedlineReachedContainer" => json!({
+                "LedCount": 5,
+                "Color": "Red",
+                "OffDelay": 100,
+                "OnDelay": 100,
+            }),
+            "SpeedLimiterAnimationContainer" => json!({
+                "LedCount": 5,
+                "Color1Alternate": "Red",
+                "Color2Alternate": "Blue",
+                "Color1SingleColor": "Red",
+                "Color2SingleColor": "Blue",
+                "AlternateDelay": 100,
+                "AlternateEnabled": false,
+                "BlinkColor1Delay": 100,
+                "LimiterBehavior": 0,
+                "UseAlternate2": false,
+                "UseAlternate": false,
+                "Alternate2BlanckBackground": false,
+            }),
+            "AssistContainer" => json!({ "LedCount": 5, "Color": "Orange" }),
+            "YellowFlagContainer" | "BlueFlagContainer" | "WhiteFlagContainer" => json!({
+                "LedCount": 5,
+                "Color": "White",
+                "BlinkEnabled": false,
+            }),
+            "GroupContainer" | "GameRunningGroupContainer" => json!({ "LedContainers": [] }),
+            "GameCarStatedGroupContainer" => json!({ "LedContainers": [], "Duration": 1000 }),
+            "CustomConditionalGroupContainer" => json!({
+                "LedContainers": [],
+                "TriggerFormula": { "Expression": "true" },
+            }),
+            t => panic!("The test is missing a minimal container for the {t} container type"),
+        };
+
+        container["ContainerType"] = container_type.into();
+        container["IsEnabled"] = true.into();
+
+        container
+    }
+
+    #[test]
+    fn supported_container_types_parse() {
+        for container_type in supported_container_types() {
+            let container: LedContainer = serde_json::from_value(minimal_container(container_type))
+                .unwrap_or_else(|e| {
+                    panic!("We should be able to parse a minimal {container_type}: {e}")
+                });
+
+            assert!(
+                !matches!(container, LedContainer::Unknown { .. }),
+                "The supported container type {container_type} should not be parsed as unknown"
+            );
+        }
+    }
+
+    #[test]
+    fn serialize_containers() {
+        let mut containers: Vec<_> = supported_container_types()
+            .iter()
+            .map(|container_type| minimal_container(container_type))
+            .collect();
+        containers.push(json!({
+            "ContainerType": "SimHub.Plugins.FancyNewContainer",
+            "Speed": 1.5,
+        }));
+
+        for container in containers {
+            let container: LedContainer = serde_json::from_value(container)
+                .expect("We should be able to parse the container");
+
+            let json = serde_json::to_value(&container)
+                .expect("We should be able to serialize the container");
+
+            assert!(
+                json["ContainerType"]
+                    .as_str()
+                    .is_some_and(|t| t.ends_with(container.container_type())),
+                "The serialized container should contain its type: {json}"
+            );
+
+            let parsed: LedContainer = serde_json::from_value(json)
+                .expect("We should be able to parse the serialized container");
+
+            assert_eq!(
+                format!("{parsed:?}"),
+                format!("{container:?}"),
+                "Serializing the {} container should not lose any data",
+                container.container_type()
+            );
+        }
+    }
+
+    fn flag(start_position: usize, led_count: usize) -> Value {
+        json!({
+            "LedCount": led_count,
+            "Color": "Yellow",
+            "BlinkEnabled": false,
+            "StartPosition": start_position,
+            "ContainerType": "YellowFlagContainer",
+            "IsEnabled": true
+        })
+    }
+
+    fn profile(containers: Value) -> LedProfile {
+        serde_json::from_value(json!({
+            "Name": "Span",
+            "ProfileId": "e3b1a2c4-7d5f-4a6e-9b8c-0f1d2e3a4b5c",
+            "GlobalBrightness": 1.0,
+            "UseProfileBrightness": false,
+            "LedContainers": containers,
+        }))
+        .expect("We should be able to deserialize the profile")
+    }
+
+    #[test]
+    fn max_led_index_nested() {
+        assert_eq!(profile(json!([])).max_led_index(), 0);
+
+        let profile = profile(json!([
+            flag(1, 4),
+            flag(3, 2),
+            {
+                "LedContainers": [
+                    flag(1, 2),
+                    {
+                        "LedContainers": [flag(1, 3)],
+                        "StartPosition": 20,
+                        "ContainerType": "GroupContainer",
+                        "IsEnabled": true
+                    }
+                ],
+                "StartPosition": 10,
+                "ContainerType": "GroupContainer",
+                "IsEnabled": true
+            },
+            { "ContainerType": "FancyNewContainer", "StartPosition": 40 }
+        ]));
+
+        assert_eq!(
+            profile.max_led_index(),
+            22,
+            "The nested group should span from the LED 20 to the LED 22, unknown containers \
+             should be ignored"
+        );
+    }
+
+    #[test]
+    fn referenced_flags() {
+        assert!(
+            profile(json!([])).referenced_flags().is_empty(),
+            "An empty profile shouldn't react to any flags"
+        );
+
+        let profile = profile(json!([
+            flag(1, 4),
+            minimal_container("RPMContainer"),
+            {
+                "LedContainers": [minimal_container("WhiteFlagContainer"), flag(5, 2)],
+                "StartPosition": 10,
+                "ContainerType": "GroupContainer",
+                "IsEnabled": true
             },
+        ]));
+
+        assert_eq!(
+            profile.referenced_flags(),
+            HashSet::from([FlagColor::Yellow, FlagColor::White]),
+            "The yellow flag and the white flag of the nested group should be collected"
+        );
+    }
+
+    #[test]
+    fn max_led_index_left_to_right() {
+        let group = |stack_left_to_right: bool| {
+            profile(json!([{
+                "LedContainers": [flag(1, 4), flag(1, 3), flag(2, 2)],
+                "StackLeftToRight": stack_left_to_right,
+                "StartPosition": 5,
+                "ContainerType": "GroupContainer",
+                "IsEnabled": true
+            }]))
+        };
+
+        assert_eq!(
+            group(true).max_led_index(),
+            13,
+            "The containers should be placed one after another, starting at the LED 5"
+        );
+        assert_eq!(
+            group(false).max_led_index(),
+            8,
+            "The containers should be layered on top of each other, starting at the LED 5"
+        );
+    }
+
+    #[test]
+    fn max_led_index_left_to_right_root() {
+        let mut profile = profile(json!([flag(1, 4), flag(1, 3)]));
+        assert_eq!(profile.max_led_index(), 4);
+
+        profile.stacking_type = StackingType::LeftToRight;
+        assert_eq!(
+            profile.max_led_index(),
+            7,
+            "The top-level containers should be placed one after another"
+        );
+    }
+
+    #[test]
+    fn unknown_container_type() {
+        let container: LedContainer = serde_json::from_value(json!({
+            "ContainerType": "SimHub.Plugins.FancyNewContainer",
+            "StartPosition": 3,
+        }))
+        .expect("Unknown containers should still be parsed");
+
+        let LedContainer::Unknown {
+            start_position,
+            container_type,
+            ..
+        } = container
+        else {
+            panic!("The container should have been parsed as an unknown container");
+        };
+
+        assert_eq!(start_position.get(), 3);
+        assert_eq!(container_type, "FancyNewContainer");
+    }
+
+    #[test]
+    fn malformed_container_type() {
+        for container_type in ["", "SimHub.Plugins.", "RPM Container"] {
+            let error = serde_json::from_value::<LedContainer>(json!({
+                "ContainerType": container_type,
+                "StartPosition": 4,
+            }))
+            .expect_err("A malformed container type should be rejected")
+            .to_string();
+
+            assert!(
+                error.contains(&format!("invalid ContainerType {container_type:?}")),
+                "The error should contain the offending container type: {error}"
+            );
+            assert!(
+                error.contains("start position 4"),
+                "The error should contain the start position of the container: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn missing_container_type() {
+        let error = serde_json::from_value::<LedContainer>(json!({ "StartPosition": 4 }))
+            .expect_err("A container without a type should be rejected")
+            .to_string();
+
+        assert!(
+            error.contains("Couldn't determine the type of a LED container"),
+            "The error should explain what went wrong: {error}"
+        );
+        assert!(
+            error.contains("missing field `ContainerType`"),
+            "The error should contain the error of the underlying deserializer: {error}"
+        );
+    }
+
+    #[test]
+    fn invalid_known_container() {
+        let mut container = minimal_container("RPMContainer");
+        container["StartPosition"] = json!(7);
+        container["StartColor"] = json!("not a color");
+
+        let error = serde_json::from_value::<LedContainer>(container)
+            .expect_err("A container with an invalid color should be rejected")
+            .to_string();
+
+        assert!(
+            error.starts_with("Couldn't parse the RPMContainer at start position 7"),
+            "The error should contain the type and the start position of the container: {error}"
+        );
+    }
+
+    /// A small profile using a known, an unknown and a group container.
+    fn small_profile() -> Value {
+        json!({
+            "Name": "Small",
+            "ProfileId": "5f0c6a2e-8d1b-4c3a-9e7f-2b4d6c8a0e1f",
+            "GlobalBrightness": 0.5,
+            "UseProfileBrightness": true,
+            "LedContainers": [
+                flag(1, 4),
+                {
+                    "ContainerType": "SimHub.Plugins.FancyNewContainer",
+                    "StartPosition": 3,
+                    "Speed": 1.5,
+                },
+                {
+                    "LedContainers": [flag(1, 2)],
+                    "StackLeftToRight": true,
+                    "StartPosition": 5,
+                    "ContainerType": "GroupContainer",
+                    "IsEnabled": true
+                },
+            ],
         })
     }
+
+    #[test]
+    fn byte_order_mark() {
+        let mut profile = UTF8_BOM.to_vec();
+        profile.extend(serde_json::to_vec(&small_profile()).unwrap());
+
+        assert!(
+            serde_json::from_slice::<LedProfile>(&profile).is_err(),
+            "The JSON parser itself should reject the byte order mark"
+        );
+
+        let profile = LedProfile::from_reader(profile.as_slice())
+            .expect("We should be able to parse a profile starting with a byte order mark");
+
+        assert_eq!(profile.name, "Small");
+        assert_eq!(profile.led_containers.len(), 3);
+    }
+
+    #[test]
+    fn trailing_whitespace() {
+        let mut profile = serde_json::to_string_pretty(&small_profile()).unwrap();
+        profile.push_str("\r\n\n  \t\r\n");
+
+        let profile = LedProfile::from_reader(profile.as_bytes())
+            .expect("We should be able to parse a profile followed by whitespace");
+
+        assert_eq!(profile.led_containers.len(), 3);
+
+        let profile = format!("{}\r\n", serde_json::to_string(&small_profile()).unwrap());
+        let mut with_bom = UTF8_BOM.to_vec();
+        with_bom.extend(profile.as_bytes());
+
+        LedProfile::from_reader(with_bom.as_slice()).expect(
+            "We should be able to parse a profile with a byte order mark and trailing whitespace",
+        );
+
+        LedProfile::from_reader(format!("{profile} trailing").as_bytes())
+            .expect_err("Trailing data which isn't whitespace should still be rejected");
+    }
+
+    /// Check that the profile which was read back from the given format contains the same data
+    /// as the original profile.
+    #[cfg(any(feature = "yaml", feature = "ron"))]
+    fn assert_same_profile(read_back: &LedProfile, original: &LedProfile, format: &str) {
+        assert_eq!(
+            read_back.name, original.name,
+            "The {format} name should match"
+        );
+        assert_eq!(read_back.profile_id, original.profile_id);
+        assert_eq!(read_back.global_brightness, original.global_brightness);
+        assert_eq!(
+            read_back.use_profile_brightness,
+            original.use_profile_brightness
+        );
+        assert_eq!(read_back.stacking_type, original.stacking_type);
+
+        let containers = |profile: &LedProfile| {
+            profile
+                .led_containers
+                .iter()
+                .map(|container| {
+                    (
+                        container.container_type().to_owned(),
+                        container.start_position().get(),
+                        container.is_enabled(),
+                        container.effect_led_count(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            containers(read_back),
+            containers(original),
+            "The {format} profile should contain the same containers as the original one"
+        );
+
+        let unknown_content = |profile: &LedProfile| {
+            profile
+                .led_containers
+                .iter()
+                .filter_map(|container| match container {
+                    LedContainer::Unknown { content, .. } => Some(content.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            unknown_content(read_back),
+            unknown_content(original),
+            "The content of the unknown containers should survive the {format} round trip"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn yaml_round_trip() {
+        let profile: LedProfile = serde_json::from_value(small_profile())
+            .expect("We should be able to deserialize the JSON profile");
+
+        let yaml = profile
+            .to_yaml()
+            .expect("We should be able to write the profile as YAML");
+        let read_back =
+            LedProfile::from_yaml(&yaml).expect("We should be able to parse the YAML profile");
+
+        assert_same_profile(&read_back, &profile, "YAML");
+        assert!(
+            matches!(
+                &read_back.led_containers[1],
+                LedContainer::Unknown { content, .. } if content["Speed"] == 1.5
+            ),
+            "The unknown container should keep its content: {yaml}"
+        );
+
+        let yaml = LedProfile::from_yaml(
+            "
+            Name: Hand written
+            ProfileId: 5f0c6a2e-8d1b-4c3a-9e7f-2b4d6c8a0e1f
+            GlobalBrightness: 1.0
+            UseProfileBrightness: false
+            LedContainers:
+              - ContainerType: YellowFlagContainer
+                LedCount: 3
+                Color: Yellow
+                BlinkEnabled: false
+                StartPosition: 2
+                IsEnabled: true
+              - ContainerType: FancyNewContainer
+                Speed: 1.5
+            ",
+        )
+        .expect("We should be able to parse a hand written YAML profile");
+
+        let [LedContainer::YellowFlag(flag), LedContainer::Unknown { content, .. }] =
+            &yaml.led_containers[..]
+        else {
+            panic!("The YAML profile should contain a flag and an unknown container");
+        };
+
+        assert_eq!(flag.led_count.get(), 3);
+        assert_eq!(flag.start_position.get(), 2);
+        assert_eq!(
+            content["Speed"], 1.5,
+            "The content of unknown containers should be kept"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "ron")]
+    fn ron_profile() {
+        let json: LedProfile = serde_json::from_value(small_profile())
+            .expect("We should be able to deserialize the JSON profile");
+
+        // The profile itself uses the RON struct syntax, while the containers, which can have any
+        // type, need to be written as maps.
+        let ron = LedProfile::from_ron(
+            r#"(
+                Name: "Small",
+                ProfileId: "5f0c6a2e-8d1b-4c3a-9e7f-2b4d6c8a0e1f",
+                GlobalBrightness: 0.5,
+                UseProfileBrightness: true,
+                LedContainers: [
+                    {
+                        "LedCount": 4,
+                        "Color": "Yellow",
+                        "BlinkEnabled": false,
+                        "StartPosition": 1,
+                        "ContainerType": "YellowFlagContainer",
+                        "IsEnabled": true,
+                    },
+                    {
+                        "ContainerType": "SimHub.Plugins.FancyNewContainer",
+                        "StartPosition": 3,
+                        "Speed": 1.5,
+                    },
+                    {
+                        "LedContainers": [
+                            {
+                                "LedCount": 2,
+                                "Color": "Yellow",
+                                "BlinkEnabled": false,
+                                "StartPosition": 1,
+                                "ContainerType": "YellowFlagContainer",
+                                "IsEnabled": true,
+                            },
+                        ],
+                        "StackLeftToRight": true,
+                        "StartPosition": 5,
+                        "ContainerType": "GroupContainer",
+                        "IsEnabled": true,
+                    },
+                ],
+            )"#,
+        )
+        .expect("We should be able to parse the RON profile");
+
+        assert_same_profile(&ron, &json, "RON");
+    }
+
+    #[test]
+    #[cfg(feature = "ron")]
+    fn ron_round_trip() {
+        let profile: LedProfile = serde_json::from_value(small_profile())
+            .expect("We should be able to deserialize the JSON profile");
+
+        let ron = profile
+            .to_ron()
+            .expect("We should be able to write the profile as RON");
+        let read_back =
+            LedProfile::from_ron(&ron).expect("We should be able to parse the RON profile");
+
+        assert_same_profile(&read_back, &profile, "RON");
+    }
 }
@@ -21,29 +21,43 @@
 use std::{num::NonZeroUsize, time::Duration};
 
 use csscolorparser::Color;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use super::{color_from_str, default_non_zero, duration_from_int_ms};
+use super::{
+    color_from_str, color_to_str, default_non_zero, duration_from_int_ms, duration_to_int_ms,
+    led_count_from_int,
+};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct RedlineReachedContainer {
     #[serde(default)]
     pub description: String,
     pub is_enabled: bool,
+    #[serde(deserialize_with = "led_count_from_int")]
     pub led_count: NonZeroUsize,
     #[serde(default = "default_non_zero")]
     pub start_position: NonZeroUsize,
-    #[serde(deserialize_with = "color_from_str")]
+    #[serde(deserialize_with = "color_from_str", serialize_with = "color_to_str")]
     pub color: Color,
     #[serde(default)]
     pub blink_enabled: bool,
     #[serde(default)]
     pub dual_blink_timing_enabled: bool,
-    #[serde(deserialize_with = "duration_from_int_ms")]
+    #[serde(
+        deserialize_with = "duration_from_int_ms",
+        serialize_with = "duration_to_int_ms"
+    )]
     pub off_delay: Duration,
-    #[serde(deserialize_with = "duration_from_int_ms")]
+    #[serde(
+        deserialize_with = "duration_from_int_ms",
+        serialize_with = "duration_to_int_ms"
+    )]
     pub on_delay: Duration,
-    #[serde(default, deserialize_with = "duration_from_int_ms")]
+    #[serde(
+        default,
+        deserialize_with = "duration_from_int_ms",
+        serialize_with = "duration_to_int_ms"
+    )]
     pub blink_delay: Duration,
 }
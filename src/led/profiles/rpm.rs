@@ -21,13 +21,18 @@
 use std::{num::NonZeroUsize, time::Duration};
 
 use csscolorparser::Color;
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use uom::si::{
     angular_velocity::revolution_per_minute,
     f64::{AngularVelocity, Ratio},
+    ratio::ratio,
 };
 
-use super::{color_from_str, default_non_zero, duration_from_int_ms};
+use super::{
+    color_from_str, color_to_str, default_non_zero, duration_from_int_ms, duration_to_int_ms,
+    led_count_from_int, optional_color_from_str, optional_color_to_str,
+    optional_duration_from_int_ms, optional_duration_to_int_ms,
+};
 
 /// The configuration for a LED profile container which turns on LEDs based on the value of the RPM
 /// of the engine.
@@ -35,7 +40,7 @@ use super::{color_from_str, default_non_zero, duration_from_int_ms};
 /// As the RPM increases more LEDs will be turned on, the color of the LEDs will be configured to
 /// follow a color gradient beginning with the [`RpmContainer::start_color`] and ending in
 /// [`RpmContainer::end_color`].
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct RpmContainer {
     /// The human readable description of the [`RpmContainer`].
@@ -47,36 +52,60 @@ pub struct RpmContainer {
     #[serde(default = "default_non_zero")]
     pub start_position: NonZeroUsize,
     /// The total number of LEDs this container should control.
+    #[serde(deserialize_with = "led_count_from_int")]
     pub led_count: NonZeroUsize,
     /// Should we use the specified percentages to calculate how many LEDs need to be turned on
     /// instead of the raw [`RpmContainer::rpm_min`] and [`RpmContainer::rpm_max`] values?
     #[serde(default)]
     pub use_percent: bool,
     /// The percentage of the RPM that should start turning LEDs on.
+    ///
+    /// Profiles which don't use percentages might omit this, in which case it defaults to 0%.
+    #[serde(default = "default_percent_min")]
     pub percent_min: Ratio,
     /// The percentage of the RPM which should be considered the maximum RPM, or rather when the
     /// gradient should reach its end and all the LEDs should be turned on.
+    ///
+    /// Profiles which don't use percentages might omit this, in which case it defaults to 100%.
+    #[serde(default = "default_percent_max")]
     pub percent_max: Ratio,
     /// The value of the RPM that should start turning LEDs on.
+    ///
+    /// Profiles which use percentages might omit this, in which case it defaults to 0 RPM.
     #[serde(rename = "RPMMin")]
-    #[serde(deserialize_with = "rpm_from_float")]
+    #[serde(
+        default = "default_rpm",
+        deserialize_with = "rpm_from_float",
+        serialize_with = "rpm_to_float"
+    )]
     pub rpm_min: AngularVelocity,
     /// The value of the RPM which should be considered the maximum RPM, or rather when the
     /// gradient should reach its end and all the LEDs should be turned on.
+    ///
+    /// Profiles which use percentages might omit this, in which case it defaults to 0 RPM.
     #[serde(rename = "RPMMax")]
-    #[serde(deserialize_with = "rpm_from_float")]
+    #[serde(
+        default = "default_rpm",
+        deserialize_with = "rpm_from_float",
+        serialize_with = "rpm_to_float"
+    )]
     pub rpm_max: AngularVelocity,
     /// The first color in the gradient, the gradient will begin with this color and transition
     /// towards the [`RpmContainer::end_color`].
-    #[serde(deserialize_with = "color_from_str")]
+    #[serde(deserialize_with = "color_from_str", serialize_with = "color_to_str")]
     pub start_color: Color,
     /// The final color in the gradient.
-    #[serde(deserialize_with = "color_from_str")]
+    #[serde(deserialize_with = "color_from_str", serialize_with = "color_to_str")]
     pub end_color: Color,
     /// Should the LEDs be filled out from right to left instead of the usual left to right
     /// direction?
+    ///
+    /// This setting is ignored if the [`RpmContainer::fill_direction`] is set.
     #[serde(default)]
     pub right_to_left: bool,
+    /// The order in which the LEDs should be filled out.
+    #[serde(default)]
+    pub fill_direction: Option<FillDirection>,
     /// Should the LEDs blink when the maximum RPM of the car is reached, the so called redline.
     /// This is not the [`RpmContainer::rpm_max`] setting, the maximum RPM of the car is defined by
     /// the simulator.
@@ -84,11 +113,25 @@ pub struct RpmContainer {
     pub blink_enabled: bool,
     /// How long should the LED stay on and off when blinking, in other words how long do we wait
     /// before we change the state of the LED.
-    #[serde(deserialize_with = "duration_from_int_ms")]
+    #[serde(
+        deserialize_with = "duration_from_int_ms",
+        serialize_with = "duration_to_int_ms"
+    )]
     pub blink_delay: Duration,
     /// Should the LEDs also blink when the maximum RPM is reached in the last gear?
     #[serde(default)]
     pub blink_on_last_gear: bool,
+    /// The color all the LEDs flash in when it's time to shift.
+    ///
+    /// If set, the blinking at the redline doesn't toggle the gradient, instead every LED of the
+    /// container is turned on with this color, overriding the gradient. The flashing uses the
+    /// [`RpmContainer::blink_enabled`] and [`RpmContainer::blink_delay`] settings.
+    #[serde(
+        default,
+        deserialize_with = "optional_color_from_str",
+        serialize_with = "optional_color_to_str"
+    )]
+    pub shift_flash_color: Option<Color>,
     /// TODO: What does this setting do?
     #[serde(default)]
     pub use_led_dimming: bool,
@@ -100,6 +143,81 @@ pub struct RpmContainer {
     /// RPM. This only works in conjunction with the [`RpmContainer::gradient_on_all`] setting.
     #[serde(default)]
     pub fill_all_leds: bool,
+    /// The smoothing factor of the low-pass filter which is applied to the RPM, between `0.0` and
+    /// `1.0`. Smaller values smooth out more of the noise in the RPM telemetry but make the LEDs
+    /// react slower. The RPM isn't filtered if this isn't set.
+    #[serde(default)]
+    pub rpm_smoothing: Option<f64>,
+    /// Should all the LEDs be turned off while the car is in neutral or in reverse, so revving
+    /// the engine while standing doesn't sweep through the whole gradient?
+    #[serde(default)]
+    pub off_in_neutral_and_reverse: bool,
+    /// What should the LEDs do once the RPM goes past the maximum, the [`RpmContainer::percent_max`]
+    /// or the [`RpmContainer::rpm_max`] setting, before the redline of the car is reached.
+    #[serde(default)]
+    pub above_max_behavior: AboveMaxBehavior,
+}
+
+impl RpmContainer {
+    /// Get the order in which the LEDs should be filled out, falling back to the
+    /// [`RpmContainer::right_to_left`] setting if no explicit direction was configured.
+    pub fn fill_direction(&self) -> FillDirection {
+        self.fill_direction.unwrap_or(if self.right_to_left {
+            FillDirection::RightToLeft
+        } else {
+            FillDirection::LeftToRight
+        })
+    }
+}
+
+/// The behavior of the LEDs of a [`RpmContainer`] while the RPM is above the maximum of the
+/// container.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum AboveMaxBehavior {
+    /// Keep all the LEDs turned on.
+    #[default]
+    HoldOn,
+    /// Blink all the LEDs using the [`RpmContainer::blink_delay`].
+    Blink,
+    /// Turn all the LEDs off.
+    Off,
+}
+
+/// The order in which the LEDs of a [`RpmContainer`] are turned on as the RPM increases.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum FillDirection {
+    /// Start with the leftmost LED.
+    #[default]
+    LeftToRight,
+    /// Start with the rightmost LED.
+    RightToLeft,
+    /// Start with the LED in the center and fill out towards both edges.
+    CenterOut,
+    /// Start with the LEDs on both edges and fill out towards the center.
+    EdgesIn,
+}
+
+impl FillDirection {
+    /// Get the indices of the LEDs in the order they should be turned on.
+    pub fn led_order(self, led_count: usize) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..led_count).collect();
+
+        // Sorting by the distance to the center is stable, so from two LEDs with the same
+        // distance the left one is turned on first. The distance is doubled to avoid
+        // fractions for an even number of LEDs.
+        let distance_to_center = |index: &usize| (2 * index).abs_diff(led_count.saturating_sub(1));
+
+        match self {
+            FillDirection::LeftToRight => {}
+            FillDirection::RightToLeft => order.reverse(),
+            FillDirection::CenterOut => order.sort_by_key(distance_to_center),
+            FillDirection::EdgesIn => {
+                order.sort_by_key(|index| std::cmp::Reverse(distance_to_center(index)))
+            }
+        }
+
+        order
+    }
 }
 
 /// The configuration for a LED profile container which turns on segments of LEDs based on the value
@@ -107,7 +225,7 @@ pub struct RpmContainer {
 ///
 /// This container will divide a larger number of LEDs into smaller subsets or segments. Each
 /// segment can have a different configuration.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct RpmSegmentsContainer {
     /// The human readable description of the [`RpmContainer`].
@@ -119,45 +237,114 @@ pub struct RpmSegmentsContainer {
     #[serde(default = "default_non_zero")]
     pub start_position: NonZeroUsize,
     /// The number of segments this container has. This value isn't particularly useful since it's
-    /// better to take a look at [`RPMSegmentsContainer::segments::len()`]
+    /// better to take a look at [`RPMSegmentsContainer::segments::len()`], the number of LEDs the
+    /// segments control is given by [`RpmSegmentsContainer::total_led_count()`].
     pub segments_count: u32,
     /// Should the LEDs blink when TODO: When do we blink here exactly?
     #[serde(default)]
     pub blink_enabled: bool,
     /// How long should the LED stay on and off when blinking, in other words how long do we wait
     /// before we change the state of the LED.
-    #[serde(default, deserialize_with = "duration_from_int_ms")]
+    #[serde(
+        default,
+        deserialize_with = "duration_from_int_ms",
+        serialize_with = "duration_to_int_ms"
+    )]
     pub blink_delay: Duration,
     /// Should the LEDs only (or as well?) blink when the maximum RPM or percentage of it are
     /// reached in the last gear?
     #[serde(default)]
     pub blink_on_last_gear: bool,
+    /// Should the LEDs of the segments which are turned off let the effects below this container
+    /// show through?
+    ///
+    /// This allows layering the segments on top of another effect, e.g. a redline segment on top
+    /// of a [`RpmContainer`] gradient, without turning off the LEDs of the gradient while the
+    /// segments are off.
+    #[serde(default)]
+    pub transparent_when_off: bool,
+    /// Should the [`LedSegment::start_value`] and [`LedSegment::end_value`] percentages be
+    /// relative to the redline of the car, the RPM the simulator recommends to shift at, instead
+    /// of the maximum RPM of the car?
+    ///
+    /// Cars for which the simulator doesn't report a redline use the maximum RPM.
+    #[serde(default)]
+    pub relative_to_redline: bool,
     /// The list of LED segments.
     pub segments: Vec<LedSegment>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl RpmSegmentsContainer {
+    /// The total number of LEDs this container controls, the sum of the LEDs of all segments.
+    ///
+    /// The sum is capped at [`usize::MAX`], see [`RpmSegmentsContainer::checked_led_count()`].
+    pub fn total_led_count(&self) -> usize {
+        self.checked_led_count().unwrap_or(usize::MAX)
+    }
+
+    /// The total number of LEDs this container controls, or `None` if the sum of the LEDs of all
+    /// segments overflows.
+    pub fn checked_led_count(&self) -> Option<usize> {
+        self.segments.iter().try_fold(0usize, |sum, segment| {
+            sum.checked_add(segment.led_count.get())
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct LedSegment {
     pub start_value: Ratio,
     pub end_value: Ratio,
-    #[serde(deserialize_with = "color_from_str")]
+    #[serde(deserialize_with = "color_from_str", serialize_with = "color_to_str")]
     pub normal_color: Color,
-    #[serde(deserialize_with = "color_from_str")]
+    #[serde(deserialize_with = "color_from_str", serialize_with = "color_to_str")]
     pub blinking_color: Color,
     pub use_blinking_color: bool,
+    #[serde(deserialize_with = "led_count_from_int")]
     pub led_count: NonZeroUsize,
     pub sample_result: SampleResult,
+    /// How long should the LEDs of this segment stay on and off when blinking. If not set, the
+    /// [`RpmSegmentsContainer::blink_delay`] of the container is used.
+    #[serde(
+        default,
+        deserialize_with = "optional_duration_from_int_ms",
+        serialize_with = "optional_duration_to_int_ms"
+    )]
+    pub blink_delay: Option<Duration>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl LedSegment {
+    /// Get the blink delay of this segment, falling back to the blink delay of the container the
+    /// segment belongs to.
+    pub fn blink_delay_or(&self, container_delay: Duration) -> Duration {
+        self.blink_delay.unwrap_or(container_delay)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct SampleResult {
+    /// How many LEDs wide the segment is when placed on a LED matrix.
     pub width: u32,
+    /// The row-major index of the top left LED of the segment on a LED matrix.
     pub position: u32,
+    /// The number of columns of the LED matrix.
     pub columns: i32,
 }
 
+fn default_percent_min() -> Ratio {
+    Ratio::new::<ratio>(0.0)
+}
+
+fn default_percent_max() -> Ratio {
+    Ratio::new::<ratio>(100.0)
+}
+
+fn default_rpm() -> AngularVelocity {
+    AngularVelocity::new::<revolution_per_minute>(0.0)
+}
+
 /// Helper to deserialize a float containing a RPM value into a [`AngularVelocity`] type.
 pub fn rpm_from_float<'de, D>(deserializer: D) -> Result<AngularVelocity, D::Error>
 where
@@ -165,3 +352,152 @@ where
 {
     f64::deserialize(deserializer).map(AngularVelocity::new::<revolution_per_minute>)
 }
+
+/// Helper to serialize a [`AngularVelocity`] type into a float containing the RPM value.
+pub fn rpm_to_float<S>(rpm: &AngularVelocity, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    rpm.get::<revolution_per_minute>().serialize(serializer)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+    use similar_asserts::assert_eq;
+
+    use super::*;
+
+    fn rpm_container(range: Value) -> RpmContainer {
+        let mut container = json!({
+            "LedCount": 4,
+            "StartColor": "Lime",
+            "EndColor": "Red",
+            "BlinkDelay": 100,
+            "IsEnabled": true
+        });
+
+        container
+            .as_object_mut()
+            .unwrap()
+            .extend(range.as_object().unwrap().clone());
+
+        serde_json::from_value(container)
+            .expect("We should be able to deserialize a RPM container missing the unused range")
+    }
+
+    #[test]
+    fn percent_only_rpm_container() {
+        let container = rpm_container(json!({
+            "UsePercent": true,
+            "PercentMin": 80.0,
+            "PercentMax": 95.0,
+        }));
+
+        assert_eq!(container.percent_min.get::<ratio>(), 80.0);
+        assert_eq!(container.percent_max.get::<ratio>(), 95.0);
+        assert_eq!(
+            container.rpm_min.get::<revolution_per_minute>(),
+            0.0,
+            "The missing RPM range should default to zero"
+        );
+        assert_eq!(container.rpm_max.get::<revolution_per_minute>(), 0.0);
+    }
+
+    #[test]
+    fn rpm_only_rpm_container() {
+        let container = rpm_container(json!({
+            "RPMMin": 3000.0,
+            "RPMMax": 7500.0,
+        }));
+
+        assert!(!container.use_percent);
+        assert_eq!(
+            container.rpm_min,
+            AngularVelocity::new::<revolution_per_minute>(3000.0)
+        );
+        assert_eq!(
+            container.rpm_max,
+            AngularVelocity::new::<revolution_per_minute>(7500.0)
+        );
+        assert_eq!(
+            container.percent_min.get::<ratio>(),
+            0.0,
+            "The missing percentage range should cover the whole RPM range"
+        );
+        assert_eq!(container.percent_max.get::<ratio>(), 100.0);
+    }
+
+    #[test]
+    fn shift_flash_color() {
+        let container = rpm_container(json!({}));
+        assert_eq!(
+            container.shift_flash_color, None,
+            "The flash color should be optional"
+        );
+
+        let container = rpm_container(json!({ "ShiftFlashColor": "Blue" }));
+        assert_eq!(
+            container.shift_flash_color,
+            Some(Color::new(0.0, 0.0, 1.0, 1.0))
+        );
+
+        let serialized = serde_json::to_value(&container).unwrap();
+        assert_eq!(
+            serialized["ShiftFlashColor"], "#0000ff",
+            "The flash color should be serialized as a hex string"
+        );
+    }
+
+    #[test]
+    fn total_led_count() {
+        let segment = |led_count: usize| {
+            json!({
+                "StartValue": 50.0,
+                "EndValue": 80.0,
+                "NormalColor": "Lime",
+                "BlinkingColor": "Blue",
+                "UseBlinkingColor": true,
+                "LedCount": led_count,
+                "SampleResult": { "Width": 0, "Position": 0, "Columns": 0 }
+            })
+        };
+
+        let container = json!({
+            "SegmentsCount": 2,
+            "StartPosition": 1,
+            "IsEnabled": true,
+            "Segments": [segment(2), segment(3), segment(4)]
+        });
+
+        let container: RpmSegmentsContainer = serde_json::from_value(container)
+            .expect("We should be able to deserialize the RPM segments container");
+
+        assert_eq!(
+            container.total_led_count(),
+            9,
+            "The LED count should be the sum of all segments, regardless of the segments count"
+        );
+
+        let container = json!({
+            "SegmentsCount": 2,
+            "StartPosition": 1,
+            "IsEnabled": true,
+            "Segments": [segment(usize::MAX / 2), segment(usize::MAX / 2), segment(2)]
+        });
+
+        let container: RpmSegmentsContainer = serde_json::from_value(container)
+            .expect("We should be able to deserialize the RPM segments container");
+
+        assert_eq!(
+            container.checked_led_count(),
+            None,
+            "The checked LED count should report that the sum of the segments overflows"
+        );
+        assert_eq!(
+            container.total_led_count(),
+            usize::MAX,
+            "The LED count should be capped if the sum of the segments overflows"
+        );
+    }
+}
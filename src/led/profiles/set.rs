@@ -0,0 +1,151 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! A collection of [`LedProfile`]s, for example all the profiles SimHub exported, out of which a
+//! single profile gets selected.
+
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use super::LedProfile;
+
+/// A collection of [`LedProfile`]s out of which a profile can be selected using its unique ID.
+#[derive(Debug, Clone, Default)]
+pub struct ProfileSet {
+    profiles: Vec<LedProfile>,
+}
+
+impl ProfileSet {
+    /// Create a new [`ProfileSet`] containing the given profiles.
+    pub fn new(profiles: Vec<LedProfile>) -> Self {
+        Self { profiles }
+    }
+
+    /// Add a profile to the set.
+    pub fn push(&mut self, profile: LedProfile) {
+        self.profiles.push(profile);
+    }
+
+    /// Get all the profiles of the set, in the order they were added.
+    pub fn profiles(&self) -> &[LedProfile] {
+        &self.profiles
+    }
+
+    /// Get the profile with the given unique ID.
+    ///
+    /// If multiple profiles share the ID, the first one is returned, use
+    /// [`ProfileSet::validate()`] to detect this.
+    pub fn get(&self, profile_id: &Uuid) -> Option<&LedProfile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.profile_id == *profile_id)
+    }
+
+    /// Check that the profiles of the set can be selected unambiguously.
+    ///
+    /// Returns an error listing the IDs which are used by more than one profile, together with
+    /// the names of those profiles.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut names: HashMap<Uuid, Vec<&str>> = HashMap::new();
+
+        for profile in &self.profiles {
+            names
+                .entry(profile.profile_id)
+                .or_default()
+                .push(&profile.name);
+        }
+
+        // Go through the profiles instead of the map, so the IDs are listed in a stable order.
+        let duplicates: Vec<_> = self
+            .profiles
+            .iter()
+            .filter_map(|profile| {
+                let id = profile.profile_id;
+                let names = names.remove(&id).filter(|names| names.len() > 1)?;
+
+                Some(format!("{id} ({})", names.join(", ")))
+            })
+            .collect();
+
+        anyhow::ensure!(
+            duplicates.is_empty(),
+            "Multiple profiles share the same profile ID: {}",
+            duplicates.join("; ")
+        );
+
+        Ok(())
+    }
+}
+
+impl From<Vec<LedProfile>> for ProfileSet {
+    fn from(profiles: Vec<LedProfile>) -> Self {
+        Self::new(profiles)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use similar_asserts::assert_eq;
+
+    use super::*;
+
+    fn profile(name: &str, profile_id: &str) -> LedProfile {
+        serde_json::from_value(json!({
+            "Name": name,
+            "ProfileId": profile_id,
+            "GlobalBrightness": 1.0,
+            "UseProfileBrightness": false,
+            "LedContainers": [],
+        }))
+        .expect("We should be able to deserialize the profile")
+    }
+
+    #[test]
+    fn duplicate_profile_ids() {
+        let mut set = ProfileSet::new(vec![
+            profile("Formula", "9b2f5b6e-3c9c-4a53-8f1e-0a0c5f6e7d21"),
+            profile("GT3", "3f1c0d9a-6a55-4e0e-9d3b-2a9c4b7e1f00"),
+        ]);
+
+        set.validate()
+            .expect("Profiles with different IDs should be valid");
+
+        set.push(profile("GT3 copy", "3f1c0d9a-6a55-4e0e-9d3b-2a9c4b7e1f00"));
+
+        let error = set
+            .validate()
+            .expect_err("Two profiles sharing an ID should be rejected");
+
+        assert_eq!(
+            error.to_string(),
+            "Multiple profiles share the same profile ID: \
+             3f1c0d9a-6a55-4e0e-9d3b-2a9c4b7e1f00 (GT3, GT3 copy)",
+            "The error should list the duplicated ID and the profiles using it"
+        );
+        assert_eq!(
+            set.get(&"3f1c0d9a-6a55-4e0e-9d3b-2a9c4b7e1f00".parse().unwrap())
+                .map(|profile| profile.name.as_str()),
+            Some("GT3"),
+            "The first profile using a duplicated ID should be selected"
+        );
+    }
+}
@@ -18,38 +18,190 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use std::{num::NonZeroUsize, time::Duration};
+use std::{fmt, num::NonZeroUsize, time::Duration};
 
 use csscolorparser::Color;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use super::{color_from_str, default_non_zero, duration_from_int_ms};
+use super::{
+    color_from_str, color_to_str, default_non_zero, duration_from_int_ms, duration_to_int_ms,
+    led_count_from_int,
+};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct SpeedLimiterAnimationContainer {
     #[serde(default)]
     pub description: String,
     pub is_enabled: bool,
+    #[serde(deserialize_with = "led_count_from_int")]
     pub led_count: NonZeroUsize,
     #[serde(default = "default_non_zero")]
     pub start_position: NonZeroUsize,
-    #[serde(deserialize_with = "color_from_str")]
+    #[serde(deserialize_with = "color_from_str", serialize_with = "color_to_str")]
     pub color_1_alternate: Color,
-    #[serde(deserialize_with = "color_from_str")]
+    #[serde(deserialize_with = "color_from_str", serialize_with = "color_to_str")]
     pub color_2_alternate: Color,
-    #[serde(deserialize_with = "color_from_str")]
+    #[serde(deserialize_with = "color_from_str", serialize_with = "color_to_str")]
     pub color_1_single_color: Color,
-    #[serde(deserialize_with = "color_from_str")]
+    #[serde(deserialize_with = "color_from_str", serialize_with = "color_to_str")]
     pub color_2_single_color: Color,
-    #[serde(deserialize_with = "duration_from_int_ms")]
+    #[serde(
+        deserialize_with = "duration_from_int_ms",
+        serialize_with = "duration_to_int_ms"
+    )]
     pub alternate_delay: Duration,
     pub alternate_enabled: bool,
-    #[serde(deserialize_with = "duration_from_int_ms")]
+    #[serde(
+        deserialize_with = "duration_from_int_ms",
+        serialize_with = "duration_to_int_ms"
+    )]
     pub blink_color_1_delay: Duration,
-    pub limiter_behavior: u8,
+    pub limiter_behavior: LimiterBehavior,
+    /// Should a single lit LED sweep back and forth over the LEDs, see
+    /// [`SpeedLimiterAnimationContainer::use_alternate`].
     pub use_alternate_2: bool,
+    /// Should a single lit LED sweep back and forth over the LEDs, moving by one LED every
+    /// [`SpeedLimiterAnimationContainer::alternate_delay`], instead of blinking.
+    ///
+    /// The lit LED uses the [`SpeedLimiterAnimationContainer::color_1_alternate`], the other
+    /// LEDs use the [`SpeedLimiterAnimationContainer::color_2_alternate`] unless the
+    /// [`SpeedLimiterAnimationContainer::alternate_2_blank_background`] is set.
     pub use_alternate: bool,
+    /// Should the LEDs outside of the sweeping LED be turned off.
     #[serde(rename = "Alternate2BlanckBackground")]
     pub alternate_2_blank_background: bool,
 }
+
+/// When should the speed limiter animation be shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum LimiterBehavior {
+    /// The animation is always shown.
+    AlwaysOn,
+    /// The animation is shown while the pit limiter is engaged.
+    OnWhenLimiterEngaged,
+    /// The animation is shown while the car is in the pit lane.
+    OnInPitLane,
+}
+
+/// Error type for unknown [`LimiterBehavior`] values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownLimiterBehavior(pub u8);
+
+impl fmt::Display for UnknownLimiterBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unknown speed limiter behavior {}, expected 0 (always on), 1 (on when the pit \
+             limiter is engaged), or 2 (on in the pit lane)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownLimiterBehavior {}
+
+impl TryFrom<u8> for LimiterBehavior {
+    type Error = UnknownLimiterBehavior;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => LimiterBehavior::AlwaysOn,
+            1 => LimiterBehavior::OnWhenLimiterEngaged,
+            2 => LimiterBehavior::OnInPitLane,
+            v => return Err(UnknownLimiterBehavior(v)),
+        })
+    }
+}
+
+impl From<LimiterBehavior> for u8 {
+    fn from(behavior: LimiterBehavior) -> Self {
+        match behavior {
+            LimiterBehavior::AlwaysOn => 0,
+            LimiterBehavior::OnWhenLimiterEngaged => 1,
+            LimiterBehavior::OnInPitLane => 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use similar_asserts::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn limiter_behavior() {
+        assert_eq!(LimiterBehavior::try_from(0), Ok(LimiterBehavior::AlwaysOn));
+        assert_eq!(
+            LimiterBehavior::try_from(1),
+            Ok(LimiterBehavior::OnWhenLimiterEngaged)
+        );
+        assert_eq!(
+            LimiterBehavior::try_from(2),
+            Ok(LimiterBehavior::OnInPitLane)
+        );
+        assert_eq!(
+            LimiterBehavior::try_from(3),
+            Err(UnknownLimiterBehavior(3)),
+            "Unknown limiter behavior values should be rejected"
+        );
+    }
+
+    #[test]
+    fn zero_led_container() {
+        let container = json!({
+            "LedCount": 0,
+            "Color1Alternate": "Red",
+            "Color2Alternate": "Blue",
+            "Color1SingleColor": "Red",
+            "Color2SingleColor": "Black",
+            "AlternateDelay": 100,
+            "AlternateEnabled": false,
+            "BlinkColor1Delay": 100,
+            "LimiterBehavior": 0,
+            "UseAlternate2": false,
+            "UseAlternate": false,
+            "Alternate2BlanckBackground": false,
+            "IsEnabled": true,
+        });
+
+        let error = serde_json::from_value::<SpeedLimiterAnimationContainer>(container.clone())
+            .expect_err("A speed limiter container without any LEDs should be rejected");
+
+        assert!(
+            error
+                .to_string()
+                .contains("containers without any LEDs aren't supported"),
+            "The error should explain why the container was rejected, got: {error}"
+        );
+
+        let mut container = container;
+        container["ContainerType"] = "SpeedLimiterAnimationContainer".into();
+
+        let error = serde_json::from_value::<crate::led::profiles::LedContainer>(container)
+            .expect_err("The container should be rejected when it's part of a profile as well");
+
+        assert!(
+            error
+                .to_string()
+                .contains("containers without any LEDs aren't supported"),
+            "The error should explain why the container was rejected, got: {error}"
+        );
+    }
+
+    #[test]
+    fn invalid_limiter_behavior() {
+        let error = serde_json::from_value::<LimiterBehavior>(json!(7))
+            .expect_err("Deserializing an unknown limiter behavior should fail");
+
+        assert!(
+            error
+                .to_string()
+                .contains("Unknown speed limiter behavior 7"),
+            "The error should mention the unknown value, got: {error}"
+        );
+    }
+}
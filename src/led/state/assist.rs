@@ -0,0 +1,264 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::{num::NonZeroUsize, time::Instant};
+
+use simetry::Moment;
+
+use crate::led::profiles::assist::AssistContainer;
+
+use super::{LedConfiguration, LedEffect, LedState, PauseState, UpdateContext};
+
+/// An effect which flashes its LEDs when the ABS or the traction control engages, see
+/// [`AssistContainer`].
+#[derive(Debug)]
+pub struct AssistLedState {
+    container: AssistContainer,
+    state: LedState,
+    pause_state: PauseState,
+    /// The time until which the LEDs stay on after the assist disengaged, see
+    /// [`AssistContainer::hold`].
+    held_until: Option<Instant>,
+}
+
+impl AssistLedState {
+    pub fn with_start_position(container: AssistContainer, start_position: NonZeroUsize) -> Self {
+        Self {
+            state: LedState::new(start_position, container.led_count),
+            container,
+            pause_state: PauseState::default(),
+            held_until: None,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn new(container: AssistContainer) -> Self {
+        let start_position = container.start_position;
+        Self::with_start_position(container, start_position)
+    }
+
+    /// Get the configuration this effect was created from.
+    pub fn container(&self) -> &AssistContainer {
+        &self.container
+    }
+
+    pub fn update(&mut self, sim_state: &dyn Moment) {
+        self.update_ctx(&UpdateContext::new(sim_state))
+    }
+
+    /// Update the effect using the state of the driver assists from the given context.
+    ///
+    /// The [`Moment`] doesn't contain the state of the driver assists, the assists are considered
+    /// to be disengaged unless the context contains their state, see
+    /// [`UpdateContext::with_assists()`].
+    pub fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        let assists = ctx.assists.unwrap_or_default();
+
+        self.update_assists(assists.abs_active, assists.tc_active, ctx.now);
+    }
+
+    /// Update the effect using the given state of the ABS and the traction control.
+    pub fn update_assists(&mut self, abs_active: bool, tc_active: bool, now: Instant) {
+        let now = self.pause_state.now(now);
+
+        let enabled = if self.container.trigger.is_triggered(abs_active, tc_active) {
+            self.held_until = Some(now + self.container.hold);
+            true
+        } else {
+            self.held_until.is_some_and(|held_until| now < held_until)
+        };
+
+        let led = if enabled {
+            LedConfiguration::on(self.container.color.clone())
+        } else {
+            LedConfiguration::Off
+        };
+
+        self.state.leds.fill(led);
+    }
+}
+
+impl LedEffect for AssistLedState {
+    fn update(&mut self, sim_state: &dyn Moment) {
+        self.update(sim_state)
+    }
+
+    fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        self.update_ctx(ctx)
+    }
+
+    fn start_led(&self) -> NonZeroUsize {
+        self.state.start_position()
+    }
+
+    fn description(&self) -> &str {
+        &self.container.description
+    }
+
+    fn leds(&self) -> Box<dyn Iterator<Item = &LedState> + '_> {
+        Box::new(std::iter::once(&self.state))
+    }
+
+    fn disable(&mut self) {
+        self.held_until = None;
+        self.state.leds.fill(LedConfiguration::Off);
+    }
+
+    fn pause(&mut self, now: Instant) {
+        self.pause_state.pause(now);
+    }
+
+    fn resume(&mut self, now: Instant) {
+        let paused_for = self.pause_state.resume(now);
+        self.held_until = self.held_until.map(|held_until| held_until + paused_for);
+    }
+
+    fn led_count(&self) -> usize {
+        self.state.leds.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use serde_json::json;
+    use similar_asserts::assert_eq;
+
+    use crate::{
+        led::{
+            profiles::assist::{AssistContainer, AssistTrigger},
+            state::DriverAssists,
+        },
+        leds,
+    };
+
+    use super::*;
+
+    struct SimState;
+
+    impl Moment for SimState {}
+
+    fn container() -> AssistContainer {
+        let container = json!({
+            "LedCount": 2,
+            "StartPosition": 4,
+            "Color": "Orange",
+            "Hold": 100,
+            "ContainerType": "AssistContainer",
+            "IsEnabled": true
+        });
+
+        serde_json::from_value(container).expect("We should be able to deserialize the container")
+    }
+
+    #[test]
+    fn abs_activation() {
+        let mut effect = AssistLedState::new(container());
+        let hold = effect.container().hold;
+        let start = Instant::now();
+
+        effect.update_assists(false, false, start);
+        assert_eq!(
+            &leds![4; off; 2],
+            &effect.state,
+            "The LEDs should be off while no assist is active"
+        );
+
+        let engaged = start + Duration::from_millis(10);
+
+        for (now, abs_active, expected, message) in [
+            (
+                engaged,
+                true,
+                leds![4; "orange"; 2],
+                "The LEDs should flash when the ABS engages",
+            ),
+            (
+                engaged + Duration::from_millis(16),
+                false,
+                leds![4; "orange"; 2],
+                "The LEDs should be held on right after the ABS disengaged",
+            ),
+            (
+                engaged + hold,
+                false,
+                leds![4; off; 2],
+                "The LEDs should turn off once the hold duration passed",
+            ),
+            (
+                engaged + hold * 2,
+                true,
+                leds![4; "orange"; 2],
+                "The LEDs should flash again when the ABS engages again",
+            ),
+        ] {
+            effect.update_assists(abs_active, false, now);
+            assert_eq!(&expected, &effect.state, "{message}");
+        }
+    }
+
+    #[test]
+    fn trigger() {
+        let mut container = container();
+        container.trigger = AssistTrigger::TractionControl;
+
+        let mut effect = AssistLedState::new(container);
+        let now = Instant::now();
+
+        effect.update_assists(true, false, now);
+        assert_eq!(
+            &leds![4; off; 2],
+            &effect.state,
+            "The ABS shouldn't flash the LEDs of a traction control effect"
+        );
+
+        effect.update_assists(false, true, now);
+        assert_eq!(
+            &leds![4; "orange"; 2],
+            &effect.state,
+            "The traction control should flash the LEDs"
+        );
+    }
+
+    #[test]
+    fn assists_from_update_context() {
+        let mut effect = AssistLedState::new(container());
+        let now = Instant::now();
+
+        effect.update_ctx(&UpdateContext::at(&SimState, now));
+        assert_eq!(
+            &leds![4; off; 2],
+            &effect.state,
+            "The assists should be considered disengaged if the context doesn't contain them"
+        );
+
+        let assists = DriverAssists {
+            abs_active: true,
+            tc_active: false,
+        };
+        effect.update_ctx(&UpdateContext::at(&SimState, now).with_assists(assists));
+        assert_eq!(
+            &leds![4; "orange"; 2],
+            &effect.state,
+            "The LEDs should flash when the context reports an engaged ABS"
+        );
+    }
+}
@@ -0,0 +1,266 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::{num::NonZeroUsize, time::Instant};
+
+use simetry::Moment;
+
+use crate::led::profiles::LedContainer;
+
+use super::{
+    assist::AssistLedState,
+    flag::{FlagColor, FlagLedState},
+    groups::{FromContainer, GroupState},
+    rpm::{gradient::RpmLedState, segments::RpmSegmentsLedState},
+    speed_limiter::SpeedLimiterLedState,
+    LedEffect, LedState, UpdateContext,
+};
+
+/// All the LED effects this crate provides.
+///
+/// A [`GroupState`] using this enum for its effects dispatches the updates statically, instead of
+/// using a virtual call for every effect on every update. Use the boxed [`GroupState`] if you need
+/// to add your own effects to a group.
+#[derive(Debug)]
+pub enum Effect {
+    Rpm(RpmLedState),
+    RpmSegments(RpmSegmentsLedState),
+    SpeedLimiter(SpeedLimiterLedState),
+    Assist(AssistLedState),
+    Flag(FlagLedState),
+    Group(GroupState<Effect>),
+}
+
+/// Call the given method on the effect inside of the [`Effect`] enum.
+macro_rules! dispatch {
+    ($effect:expr, $e:ident => $call:expr) => {
+        match $effect {
+            Effect::Rpm($e) => $call,
+            Effect::RpmSegments($e) => $call,
+            Effect::SpeedLimiter($e) => $call,
+            Effect::Assist($e) => $call,
+            Effect::Flag($e) => $call,
+            Effect::Group($e) => $call,
+        }
+    };
+}
+
+impl LedEffect for Effect {
+    fn leds(&self) -> Box<dyn Iterator<Item = &LedState> + '_> {
+        dispatch!(self, e => LedEffect::leds(e))
+    }
+
+    fn update(&mut self, sim_state: &dyn Moment) {
+        dispatch!(self, e => LedEffect::update(e, sim_state))
+    }
+
+    fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        dispatch!(self, e => LedEffect::update_ctx(e, ctx))
+    }
+
+    fn disable(&mut self) {
+        dispatch!(self, e => LedEffect::disable(e))
+    }
+
+    fn pause(&mut self, now: Instant) {
+        dispatch!(self, e => LedEffect::pause(e, now))
+    }
+
+    fn resume(&mut self, now: Instant) {
+        dispatch!(self, e => LedEffect::resume(e, now))
+    }
+
+    fn sync_blink_phase(&mut self, state_change: Instant) {
+        dispatch!(self, e => LedEffect::sync_blink_phase(e, state_change))
+    }
+
+    fn start_led(&self) -> NonZeroUsize {
+        dispatch!(self, e => LedEffect::start_led(e))
+    }
+
+    fn description(&self) -> &str {
+        dispatch!(self, e => LedEffect::description(e))
+    }
+
+    fn led_count(&self) -> usize {
+        dispatch!(self, e => LedEffect::led_count(e))
+    }
+}
+
+impl FromContainer for Effect {
+    fn from_container(container: LedContainer, start_position: NonZeroUsize) -> Option<Self> {
+        Some(match container {
+            LedContainer::Rpm(c) => {
+                Effect::Rpm(RpmLedState::with_start_position(c, start_position))
+            }
+            LedContainer::SpeedLimiterAnimation(c) => {
+                Effect::SpeedLimiter(SpeedLimiterLedState::with_start_position(c, start_position))
+            }
+            LedContainer::RpmSegments(c) => {
+                Effect::RpmSegments(RpmSegmentsLedState::with_start_position(c, start_position))
+            }
+            LedContainer::Assist(c) => {
+                Effect::Assist(AssistLedState::with_start_position(c, start_position))
+            }
+            LedContainer::Group(c) => Effect::Group(GroupState::from_container(c)),
+            LedContainer::BlueFlag(c) => Effect::Flag(FlagLedState::with_start_position(
+                FlagColor::Blue,
+                c,
+                start_position,
+            )),
+            LedContainer::WhiteFlag(c) => Effect::Flag(FlagLedState::with_start_position(
+                FlagColor::White,
+                c,
+                start_position,
+            )),
+            LedContainer::YellowFlag(c) => Effect::Flag(FlagLedState::with_start_position(
+                FlagColor::Yellow,
+                c,
+                start_position,
+            )),
+            LedContainer::RedlineReached(_) | LedContainer::Unknown { .. } => return None,
+        })
+    }
+
+    fn from_group(group: GroupState<Self>) -> Self {
+        Effect::Group(group)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use serde_json::json;
+    use simetry::RacingFlags;
+    use similar_asserts::assert_eq;
+    use uom::si::{angular_velocity::revolution_per_minute, f64::AngularVelocity};
+
+    use super::*;
+    use crate::led::profiles::LedProfile;
+
+    struct SimState {
+        rpm: f64,
+        flags: RacingFlags,
+    }
+
+    impl Moment for SimState {
+        fn vehicle_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+            Some(AngularVelocity::new::<revolution_per_minute>(self.rpm))
+        }
+
+        fn vehicle_max_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+            Some(AngularVelocity::new::<revolution_per_minute>(8000.0))
+        }
+
+        fn flags(&self) -> Option<RacingFlags> {
+            Some(self.flags.clone())
+        }
+    }
+
+    fn profile() -> LedProfile {
+        let profile = json!({
+            "Name": "Static dispatch",
+            "ProfileId": "a5d3c1f0-8e4b-4f57-9c7e-6b2d1f0e3a94",
+            "GlobalBrightness": 1.0,
+            "UseProfileBrightness": false,
+            "LedContainers": [
+                {
+                    "UsePercent": false,
+                    "PercentMin": 85.0,
+                    "PercentMax": 95.0,
+                    "RPMMin": 1000.0,
+                    "RPMMax": 7800.0,
+                    "BlinkEnabled": true,
+                    "BlinkDelay": 100,
+                    "StartColor": "Lime",
+                    "EndColor": "Red",
+                    "LedCount": 8,
+                    "StartPosition": 1,
+                    "ContainerType": "RPMContainer",
+                    "IsEnabled": true
+                },
+                {
+                    "LedContainers": [
+                        {
+                            "LedCount": 4,
+                            "Color": "Yellow",
+                            "BlinkEnabled": true,
+                            "BlinkDelay": 150,
+                            "StartPosition": 1,
+                            "ContainerType": "YellowFlagContainer",
+                            "IsEnabled": true
+                        },
+                        {
+                            "LedCount": 4,
+                            "Color": "Blue",
+                            "BlinkEnabled": false,
+                            "StartPosition": 1,
+                            "ContainerType": "BlueFlagContainer",
+                            "IsEnabled": true
+                        }
+                    ],
+                    "StackLeftToRight": true,
+                    "StartPosition": 9,
+                    "ContainerType": "GroupContainer",
+                    "IsEnabled": true
+                }
+            ]
+        });
+
+        serde_json::from_value(profile).expect("We should be able to deserialize the profile")
+    }
+
+    #[test]
+    fn static_and_boxed_groups_agree() {
+        let mut boxed: GroupState = GroupState::from_profile_at(profile(), NonZeroUsize::MIN);
+        let mut static_group: GroupState<Effect> =
+            GroupState::from_profile_at(profile(), NonZeroUsize::MIN);
+
+        assert_eq!(boxed.led_count(), static_group.led_count());
+
+        let start = Instant::now();
+
+        for (frame, rpm) in [900.0, 2500.0, 5000.0, 7900.0, 7900.0, 7900.0, 3000.0]
+            .into_iter()
+            .enumerate()
+        {
+            let sim_state = SimState {
+                rpm,
+                flags: RacingFlags {
+                    yellow: frame >= 2,
+                    blue: frame % 2 == 0,
+                    ..Default::default()
+                },
+            };
+            let ctx =
+                UpdateContext::at(&sim_state, start + Duration::from_millis(60) * frame as u32);
+
+            boxed.update_ctx(&ctx);
+            static_group.update_ctx(&ctx);
+
+            crate::assert_led_states_eq!(
+                boxed.leds(),
+                static_group.leds(),
+                "The groups should produce the same LEDs in frame {frame}"
+            );
+        }
+    }
+}
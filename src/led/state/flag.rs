@@ -18,27 +18,62 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use std::{num::NonZeroUsize, time::Instant};
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
 
-use simetry::Moment;
+use simetry::{Moment, RacingFlags};
 
-use crate::led::profiles::flag::FlagContainer;
+use crate::led::profiles::{blink::SimpleBlinkContainer, flag::FlagContainer};
 
-use super::{BlinkState, LedConfiguration, LedEffect, LedState};
+use super::{BlinkState, LedConfiguration, LedEffect, LedState, PauseState, UpdateContext};
 
-#[derive(Debug)]
+/// The flags a [`FlagLedState`] can react to, one for every flag of the [`RacingFlags`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum FlagColor {
+    Green,
     White,
     Yellow,
     Blue,
+    Red,
+    Black,
+    Checkered,
+    /// The black and white flag, which warns drivers about unsportsmanlike behavior, for example
+    /// exceeding the track limits.
+    BlackAndWhite,
+    /// The black flag with an orange circle, which orders a car with a mechanical problem into the
+    /// pits.
+    Meatball,
+}
+
+/// Is the given flag currently being waved?
+pub fn is_flag_active(flags: &RacingFlags, flag: FlagColor) -> bool {
+    match flag {
+        FlagColor::Green => flags.green,
+        FlagColor::White => flags.white,
+        FlagColor::Yellow => flags.yellow,
+        FlagColor::Blue => flags.blue,
+        FlagColor::Red => flags.red,
+        FlagColor::Black => flags.black,
+        FlagColor::Checkered => flags.checkered,
+        FlagColor::BlackAndWhite => flags.black_and_white,
+        FlagColor::Meatball => flags.meatball,
+    }
 }
 
 #[derive(Debug)]
 pub struct FlagLedState {
     flag_color: FlagColor,
-    container: FlagContainer,
+    container: SimpleBlinkContainer,
+    /// See [`FlagContainer::minimum_hold`].
+    minimum_hold: Option<Duration>,
     state: LedState,
     blink_state: BlinkState,
+    pause_state: PauseState,
+    /// The time until which the flag is considered to be waving, even if the telemetry doesn't
+    /// report it anymore, see [`FlagContainer::minimum_hold`].
+    held_until: Option<Instant>,
 }
 
 impl FlagLedState {
@@ -47,13 +82,18 @@ impl FlagLedState {
         container: FlagContainer,
         start_position: NonZeroUsize,
     ) -> Self {
+        let minimum_hold = container.minimum_hold;
+        let container = SimpleBlinkContainer::from(container);
         let led_count = container.led_count;
 
         Self {
             flag_color,
             state: LedState::with_color(container.color.clone(), start_position, led_count),
             container,
+            minimum_hold,
             blink_state: BlinkState::default(),
+            pause_state: PauseState::default(),
+            held_until: None,
         }
     }
 
@@ -63,60 +103,55 @@ impl FlagLedState {
         Self::with_start_position(flag_color, container, start_position)
     }
 
-    fn calculate_next_blink_state(&self, is_flag_enabled: bool) -> BlinkState {
+    /// Get the blink configuration of the container this effect was created from.
+    pub fn container(&self) -> &SimpleBlinkContainer {
+        &self.container
+    }
+
+    /// Get the flag this effect reacts to.
+    pub fn flag_color(&self) -> FlagColor {
+        self.flag_color
+    }
+
+    /// Latch the flag for the [`FlagContainer::minimum_hold`] duration, returns if the flag
+    /// should be considered to be waving.
+    fn is_flag_held(&mut self, is_flag_active: bool, now: Instant) -> bool {
+        if is_flag_active {
+            self.held_until = self.minimum_hold.map(|hold| now + hold);
+            true
+        } else {
+            self.held_until.is_some_and(|held_until| now < held_until)
+        }
+    }
+
+    fn calculate_next_blink_state(
+        &self,
+        is_flag_enabled: bool,
+        now: Instant,
+        last_applied: Option<Instant>,
+    ) -> BlinkState {
         if self.container.blink_enabled && is_flag_enabled {
-            match self.blink_state {
-                BlinkState::NotBlinking => BlinkState::LedsTurnedOn {
-                    state_change: Instant::now(),
-                },
-                BlinkState::LedsTurnedOff { state_change } => {
-                    let delay = if self.container.dual_blink_timing_enabled {
-                        self.container.off_delay
-                    } else {
-                        self.container.blink_delay
-                    };
-
-                    if state_change.elapsed() >= delay {
-                        BlinkState::LedsTurnedOn {
-                            state_change: Instant::now(),
-                        }
-                    } else {
-                        self.blink_state
-                    }
-                }
-                BlinkState::LedsTurnedOn { state_change } => {
-                    let delay = if self.container.dual_blink_timing_enabled {
-                        self.container.on_delay
-                    } else {
-                        self.container.blink_delay
-                    };
-
-                    if state_change.elapsed() >= delay {
-                        BlinkState::LedsTurnedOff {
-                            state_change: Instant::now(),
-                        }
-                    } else {
-                        self.blink_state
-                    }
-                }
-            }
+            let (on_delay, off_delay) = self.container.blink_delays();
+            self.blink_state
+                .next(on_delay, off_delay, now, last_applied)
         } else {
             BlinkState::NotBlinking
         }
     }
 
-    pub fn update(&mut self, state: &dyn Moment) {
-        let Some(flags) = state.flags() else {
-            return;
-        };
+    pub fn update(&mut self, sim_state: &dyn Moment) {
+        self.update_ctx(&UpdateContext::new(sim_state))
+    }
 
-        let is_flag_enabled = match self.flag_color {
-            FlagColor::White => flags.white,
-            FlagColor::Yellow => flags.yellow,
-            FlagColor::Blue => flags.blue,
+    pub fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        let Some(flags) = ctx.sim_state.flags() else {
+            return;
         };
 
-        let next_blink_state = self.calculate_next_blink_state(is_flag_enabled);
+        let now = self.pause_state.now(ctx.now);
+        let is_flag_enabled = self.is_flag_held(is_flag_active(&flags, self.flag_color), now);
+        let next_blink_state =
+            self.calculate_next_blink_state(is_flag_enabled, now, ctx.last_applied);
 
         let leds_enabled = match next_blink_state {
             BlinkState::NotBlinking => is_flag_enabled,
@@ -126,9 +161,7 @@ impl FlagLedState {
 
         for led in &mut self.state.leds {
             *led = if leds_enabled {
-                LedConfiguration::On {
-                    color: self.container.color.clone(),
-                }
+                LedConfiguration::on(self.container.color.clone())
             } else {
                 LedConfiguration::Off
             };
@@ -143,6 +176,10 @@ impl LedEffect for FlagLedState {
         self.update(sim_state)
     }
 
+    fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        self.update_ctx(ctx)
+    }
+
     fn start_led(&self) -> NonZeroUsize {
         self.state.start_position()
     }
@@ -157,12 +194,27 @@ impl LedEffect for FlagLedState {
 
     fn disable(&mut self) {
         self.blink_state = BlinkState::NotBlinking;
+        self.held_until = None;
 
         for led in &mut self.state.leds {
             *led = LedConfiguration::Off;
         }
     }
 
+    fn pause(&mut self, now: Instant) {
+        self.pause_state.pause(now);
+    }
+
+    fn resume(&mut self, now: Instant) {
+        let paused_for = self.pause_state.resume(now);
+        self.blink_state = self.blink_state.delayed_by(paused_for);
+        self.held_until = self.held_until.map(|held_until| held_until + paused_for);
+    }
+
+    fn sync_blink_phase(&mut self, state_change: Instant) {
+        self.blink_state = self.blink_state.synced_to(state_change);
+    }
+
     fn led_count(&self) -> usize {
         self.state.leds.len()
     }
@@ -170,6 +222,9 @@ impl LedEffect for FlagLedState {
 
 #[cfg(test)]
 pub mod test {
+    use std::time::Duration;
+
+    use csscolorparser::Color;
     use serde_json::json;
     use simetry::RacingFlags;
     use similar_asserts::assert_eq;
@@ -178,6 +233,7 @@ pub mod test {
 
     use super::*;
 
+    #[derive(Default)]
     pub struct SimState {
         pub inner: RacingFlags,
     }
@@ -289,4 +345,298 @@ pub mod test {
             "The LEDs should be turned off if the flag stopped waving"
         );
     }
+
+    #[test]
+    fn blinking_with_injected_time() {
+        let container = container();
+        let delay = container.blink_delay;
+
+        let mut flags = SimState::new();
+        flags.inner.yellow = true;
+
+        let mut state = FlagLedState::new(FlagColor::Yellow, container);
+        let start = Instant::now();
+
+        let mut update_at = |now: Instant| {
+            state.update_ctx(&UpdateContext::at(&flags, now));
+            state.state.clone()
+        };
+
+        assert_eq!(
+            update_at(start),
+            leds![14; "Yellow"; 3],
+            "The LEDs should be turned on once the flag starts waving"
+        );
+        assert_eq!(
+            update_at(start + delay / 2),
+            leds![14; "Yellow"; 3],
+            "The LEDs should stay on until the blink delay has passed"
+        );
+        assert_eq!(
+            update_at(start + delay),
+            leds![14; off; 3],
+            "The LEDs should be turned off once the injected time passes the blink delay"
+        );
+        assert_eq!(
+            update_at(start + delay * 2),
+            leds![14; "Yellow"; 3],
+            "The LEDs should be turned on again without waiting for the real time to pass"
+        );
+    }
+
+    #[test]
+    fn minimum_hold() {
+        let mut container = container();
+        container.blink_enabled = false;
+        container.minimum_hold = Some(Duration::from_millis(200));
+        let hold = Duration::from_millis(200);
+
+        let mut flags = SimState::new();
+        let mut state = FlagLedState::new(FlagColor::Yellow, container);
+        let start = Instant::now();
+
+        let mut update_at = |yellow: bool, now: Instant| {
+            flags.inner.yellow = yellow;
+            state.update_ctx(&UpdateContext::at(&flags, now));
+            state.state.clone()
+        };
+
+        assert_eq!(
+            update_at(true, start),
+            leds![14; "Yellow"; 3],
+            "The LEDs should be turned on once the flag starts waving"
+        );
+        assert_eq!(
+            update_at(false, start + Duration::from_millis(16)),
+            leds![14; "Yellow"; 3],
+            "The LEDs should stay on if the flag disappears for a single frame"
+        );
+        assert_eq!(
+            update_at(false, start + hold - Duration::from_millis(1)),
+            leds![14; "Yellow"; 3],
+            "The LEDs should stay on until the hold duration has passed"
+        );
+        assert_eq!(
+            update_at(false, start + hold),
+            leds![14; off; 3],
+            "The LEDs should be turned off once the hold duration has passed"
+        );
+
+        update_at(true, start + hold * 2);
+
+        assert_eq!(
+            update_at(false, start + hold * 3 - Duration::from_millis(1)),
+            leds![14; "Yellow"; 3],
+            "The hold duration should start again from the last time the flag was seen"
+        );
+    }
+
+    #[test]
+    fn without_minimum_hold() {
+        let mut flags = SimState::new();
+        flags.inner.yellow = true;
+
+        let mut state = FlagLedState::new(FlagColor::Yellow, container());
+        let start = Instant::now();
+
+        state.update_ctx(&UpdateContext::at(&flags, start));
+        flags.inner.yellow = false;
+        state.update_ctx(&UpdateContext::at(&flags, start + Duration::from_millis(1)));
+
+        assert_eq!(
+            state.state,
+            leds![14; off; 3],
+            "The LEDs should follow the flag exactly without a minimum hold"
+        );
+    }
+
+    #[test]
+    fn asymmetric_delays_without_dual_timing() {
+        let container = container();
+        let delay = container.blink_delay;
+
+        assert!(
+            !container.dual_blink_timing_enabled && container.on_delay != container.off_delay,
+            "The default container should have asymmetric delays with dual timing disabled"
+        );
+
+        let mut flags = SimState::new();
+        flags.inner.yellow = true;
+
+        let mut state = FlagLedState::new(FlagColor::Yellow, container.clone());
+        let start = Instant::now();
+
+        let mut update_at = |now: Instant| {
+            state.update_ctx(&UpdateContext::at(&flags, now));
+            state.state.clone()
+        };
+
+        assert_eq!(update_at(start), leds![14; "Yellow"; 3]);
+        assert_eq!(
+            update_at(start + container.on_delay),
+            leds![14; "Yellow"; 3],
+            "The on delay should be ignored if dual timing is disabled"
+        );
+        assert_eq!(
+            update_at(start + delay),
+            leds![14; off; 3],
+            "The LEDs should be turned off after the blink delay"
+        );
+        assert_eq!(
+            update_at(start + delay + container.off_delay),
+            leds![14; "Yellow"; 3],
+            "The off delay should be ignored if dual timing is disabled"
+        );
+    }
+
+    #[test]
+    fn asymmetric_delays_with_dual_timing() {
+        let mut container = container();
+        container.dual_blink_timing_enabled = true;
+
+        let on_delay = container.on_delay;
+        let off_delay = container.off_delay;
+
+        let mut flags = SimState::new();
+        flags.inner.yellow = true;
+
+        let mut state = FlagLedState::new(FlagColor::Yellow, container);
+        let start = Instant::now();
+
+        let mut update_at = |now: Instant| {
+            state.update_ctx(&UpdateContext::at(&flags, now));
+            state.state.clone()
+        };
+
+        assert_eq!(update_at(start), leds![14; "Yellow"; 3]);
+        assert_eq!(
+            update_at(start + on_delay),
+            leds![14; off; 3],
+            "The LEDs should be turned off after the on delay"
+        );
+        assert_eq!(
+            update_at(start + on_delay + off_delay / 2),
+            leds![14; off; 3],
+            "The LEDs should stay off until the off delay has passed"
+        );
+        assert_eq!(
+            update_at(start + on_delay + off_delay),
+            leds![14; "Yellow"; 3],
+            "The LEDs should be turned on again after the off delay"
+        );
+    }
+
+    #[test]
+    fn sub_frame_blink_delay() {
+        let mut container = container();
+        container.blink_delay = Duration::from_micros(1);
+
+        let mut flags = SimState::new();
+        flags.inner.yellow = true;
+
+        let mut state = FlagLedState::new(FlagColor::Yellow, container);
+        let start = Instant::now();
+        let frame = Duration::from_millis(16);
+
+        for frame_number in 0..4 {
+            state.update_ctx(&UpdateContext::at(&flags, start + frame * frame_number));
+
+            let expected = if frame_number % 2 == 0 {
+                leds![14; "Yellow"; 3]
+            } else {
+                leds![14; off; 3]
+            };
+
+            assert_eq!(
+                state.state, expected,
+                "The LEDs should alternate every frame if the blink delay is shorter than a frame"
+            );
+        }
+    }
+
+    #[test]
+    fn read_back_container() {
+        let state = FlagLedState::new(FlagColor::Blue, container());
+
+        assert_eq!(state.flag_color(), FlagColor::Blue);
+        assert_eq!(state.container().led_count.get(), 3);
+        assert_eq!(state.container().color, Color::from_html("yellow").unwrap());
+        assert_eq!(state.container().blink_delay, Duration::from_millis(50));
+    }
+
+    #[test]
+    fn newly_supported_flags() {
+        let mut container = container();
+        container.blink_enabled = false;
+
+        for (flag, set_flag) in [
+            (
+                FlagColor::BlackAndWhite,
+                (|flags| flags.black_and_white = true) as fn(&mut RacingFlags),
+            ),
+            (FlagColor::Meatball, |flags| flags.meatball = true),
+            (FlagColor::Checkered, |flags| flags.checkered = true),
+        ] {
+            let mut sim_state = SimState::new();
+            sim_state.inner.yellow = true;
+
+            let mut state = FlagLedState::new(flag, container.clone());
+            state.update(&sim_state);
+
+            assert_eq!(
+                state.state,
+                leds![14; off; 3],
+                "The {flag:?} flag effect should ignore the other flags"
+            );
+
+            set_flag(&mut sim_state.inner);
+            state.update(&sim_state);
+
+            assert_eq!(
+                state.state,
+                leds![14; "Yellow"; 3],
+                "The {flag:?} flag effect should turn the LEDs on once its flag is waved"
+            );
+        }
+    }
+
+    #[test]
+    fn paused_blink_phase_is_preserved() {
+        let container = container();
+        let delay = container.blink_delay;
+
+        let mut flags = SimState::new();
+        flags.inner.yellow = true;
+
+        let mut state = FlagLedState::new(FlagColor::Yellow, container);
+        let start = Instant::now();
+
+        state.update_ctx(&UpdateContext::at(&flags, start));
+        state.pause(start + delay / 2);
+
+        let resumed = start + delay * 20;
+
+        state.update_ctx(&UpdateContext::at(&flags, resumed));
+        assert_eq!(
+            state.state,
+            leds![14; "Yellow"; 3],
+            "The blink timer should stand still while the effect is paused"
+        );
+
+        state.resume(resumed);
+
+        state.update_ctx(&UpdateContext::at(&flags, resumed + delay / 4));
+        assert_eq!(
+            state.state,
+            leds![14; "Yellow"; 3],
+            "The time the effect was paused shouldn't count towards the blink delay"
+        );
+
+        state.update_ctx(&UpdateContext::at(&flags, resumed + delay / 2));
+        assert_eq!(
+            state.state,
+            leds![14; off; 3],
+            "The LEDs should be turned off once the remainder of the blink delay has passed"
+        );
+    }
 }
@@ -19,21 +19,29 @@
 // SOFTWARE.
 
 use std::{
+    collections::HashMap,
+    fmt,
     num::NonZeroUsize,
+    ops::RangeInclusive,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
+use csscolorparser::Color;
+use serde_json::json;
 use simetry::Moment;
 
 use crate::led::profiles::{
-    groups::{Formula, StackingType},
-    GroupContainer, LedContainer, LedProfile,
+    groups::{Formula, ReArmPolicy, StackingType},
+    resolve_positions, GroupContainer, LedContainer, LedProfile,
 };
 
 use super::{
+    assist::AssistLedState,
     flag::{FlagColor, FlagLedState},
-    rpm::gradient::RpmLedState,
-    LedEffect, LedState, MomentExt,
+    rpm::{gradient::RpmLedState, segments::RpmSegmentsLedState},
+    speed_limiter::SpeedLimiterLedState,
+    LedConfiguration, LedEffect, LedState, MomentExt, UpdateContext,
 };
 
 #[derive(Debug)]
@@ -43,6 +51,10 @@ pub enum GroupCondition {
     CarStarted {
         duration: Duration,
         state: SimpleConditionstate,
+        re_arm_policy: ReArmPolicy,
+        /// Was the car in the pit lane during the last update, used to detect the pit lane entry
+        /// for the [`ReArmPolicy::OnPitLaneEntry`] policy.
+        was_in_pit_lane: bool,
     },
     Conditional {
         formula: Formula,
@@ -57,6 +69,8 @@ impl From<&GroupContainer> for GroupCondition {
             GroupContainer::CarStarted(c) => GroupCondition::CarStarted {
                 duration: c.duration,
                 state: Default::default(),
+                re_arm_policy: c.re_arm_policy,
+                was_in_pit_lane: false,
             },
             GroupContainer::Conditional(c) => GroupCondition::Conditional {
                 formula: c.trigger_formula.clone(),
@@ -75,50 +89,37 @@ pub enum SimpleConditionstate {
     Expired,
 }
 
-#[derive(Debug)]
-pub struct GroupState {
-    start_position: NonZeroUsize,
-    condition: GroupCondition,
-    states: Vec<Box<dyn LedEffect>>,
-}
-
-impl GroupState {
-    pub fn root(profile: LedProfile) -> Self {
-        let condition = GroupCondition::AlwaysOn;
-        let stacking_type = StackingType::Layered;
-        let start_position = NonZeroUsize::MIN;
-
-        let containers = profile.led_containers;
-
-        Self::new_helper(condition, start_position, stacking_type, containers)
-    }
-
-    pub fn new(container: GroupContainer) -> Self {
-        let condition = GroupCondition::from(&container);
-
-        let (stacking_type, start_position, containers) = match container {
-            GroupContainer::Simple(c) => (c.stacking_type, c.start_position, c.led_containers),
-            GroupContainer::GameRunning(c) => (c.stacking_type, c.start_position, c.led_containers),
-            GroupContainer::CarStarted(c) => (c.stacking_type, c.start_position, c.led_containers),
-            GroupContainer::Conditional(c) => (c.stacking_type, c.start_position, c.led_containers),
-        };
+/// A LED effect which can be created from a [`LedContainer`], this allows a [`GroupState`] to
+/// create the effects of its containers.
+pub trait FromContainer: LedEffect + Sized {
+    /// Create the effect for the given container, starting at the given LED position.
+    ///
+    /// Returns `None` if the container isn't supported.
+    fn from_container(container: LedContainer, start_position: NonZeroUsize) -> Option<Self>;
 
-        Self::new_helper(condition, start_position, stacking_type, containers)
-    }
+    /// Wrap an already created group of effects into an effect.
+    fn from_group(group: GroupState<Self>) -> Self;
+}
 
-    fn create_led_effect(
-        container: LedContainer,
-        start_position: NonZeroUsize,
-    ) -> Option<Box<dyn LedEffect>> {
+impl FromContainer for Box<dyn LedEffect> {
+    fn from_container(container: LedContainer, start_position: NonZeroUsize) -> Option<Self> {
         match container {
             LedContainer::Rpm(c) => Some(Box::new(RpmLedState::with_start_position(
                 c,
                 start_position,
             ))),
-            LedContainer::RpmSegments(_)
-            | LedContainer::RedlineReached(_)
-            | LedContainer::SpeedLimiterAnimation(_) => None,
-            LedContainer::Group(c) => Some(Box::new(Self::new(c))),
+            LedContainer::SpeedLimiterAnimation(c) => Some(Box::new(
+                SpeedLimiterLedState::with_start_position(c, start_position),
+            )),
+            LedContainer::RpmSegments(c) => Some(Box::new(
+                RpmSegmentsLedState::with_start_position(c, start_position),
+            )),
+            LedContainer::Assist(c) => Some(Box::new(AssistLedState::with_start_position(
+                c,
+                start_position,
+            ))),
+            LedContainer::RedlineReached(_) => None,
+            LedContainer::Group(c) => Some(Box::new(GroupState::new(c))),
             LedContainer::BlueFlag(c) => Some(Box::new(FlagLedState::with_start_position(
                 FlagColor::Blue,
                 c,
@@ -138,28 +139,291 @@ impl GroupState {
         }
     }
 
+    fn from_group(group: GroupState<Self>) -> Self {
+        Box::new(group)
+    }
+}
+
+/// A group of LED effects.
+///
+/// By default the effects are boxed trait objects, which allows any [`LedEffect`] to be part of
+/// the group. The [`Effect`] enum can be used instead to avoid the dynamic dispatch for every
+/// effect, see [`GroupState::from_profile_at()`].
+///
+/// [`Effect`]: super::effect::Effect
+#[derive(Debug)]
+pub struct GroupState<E = Box<dyn LedEffect>> {
+    start_position: NonZeroUsize,
+    condition: GroupCondition,
+    states: Vec<E>,
+    /// LEDs which are forced to a specific configuration when rendering, regardless of the
+    /// effects, keyed by the zero-based index of the LED.
+    overrides: HashMap<usize, LedConfiguration>,
+    /// A color which is multiplied into the colors of the effects when rendering.
+    tint: Option<Color>,
+    /// The color of the LEDs which aren't turned on by any effect while the car is in the pit
+    /// lane, see [`GroupState::set_pit_lane_background()`].
+    pit_lane_background: Option<Color>,
+    /// Was the car in the pit lane during the last update?
+    in_pit_lane: bool,
+    /// The brightness the LEDs are rendered with, taken from the last update which carried a
+    /// [`SharedConfig`](super::SharedConfig).
+    brightness: f64,
+}
+
+/// A problem with a container that was found while creating the effects of a profile.
+///
+/// The container is still part of the group, or skipped if it isn't supported, the warning only
+/// tells the author of the profile that the LEDs might not look like they intended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildWarning {
+    /// The type of the container, as it's written in the profile.
+    pub container_type: String,
+    /// The one-based position of the first LED of the container, after the offsets of the groups
+    /// containing it were applied.
+    pub start_position: NonZeroUsize,
+    pub kind: BuildWarningKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildWarningKind {
+    /// The container isn't supported and was skipped.
+    Unsupported,
+    /// The container doesn't control any LEDs.
+    NoLeds,
+    /// The LEDs of the container end past the largest possible LED position.
+    PositionOverflow,
+    /// The container covers some of the same LEDs as an earlier container of a layered group, and
+    /// is drawn on top of it.
+    ///
+    /// This is usually intended, e.g. flags drawn over the RPM LEDs, but it helps when a profile
+    /// doesn't look like its author expected.
+    Overlap {
+        /// The type of the earlier container.
+        other_container_type: String,
+        /// The one-based positions of the LEDs both containers cover.
+        leds: RangeInclusive<usize>,
+    },
+}
+
+impl fmt::Display for BuildWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let container_type = &self.container_type;
+        let start_position = self.start_position;
+
+        write!(f, "The {container_type} starting at LED {start_position} ")?;
+
+        match &self.kind {
+            BuildWarningKind::Unsupported => write!(f, "isn't supported and was skipped"),
+            BuildWarningKind::NoLeds => write!(f, "doesn't control any LEDs"),
+            BuildWarningKind::PositionOverflow => {
+                write!(f, "ends past the largest possible LED position")
+            }
+            BuildWarningKind::Overlap {
+                other_container_type,
+                leds,
+            } => write!(
+                f,
+                "is drawn on top of the {other_container_type} on the LEDs {} to {}",
+                leds.start(),
+                leds.end()
+            ),
+        }
+    }
+}
+
+impl GroupState {
+    pub fn root(profile: LedProfile) -> Self {
+        Self::root_at(profile, NonZeroUsize::MIN)
+    }
+
+    /// Create the root group for the given profile, like [`GroupState::root()`] does, and collect
+    /// the problems found in the containers of the profile, including the ones of nested groups.
+    pub fn root_with_warnings(profile: LedProfile) -> (Self, Vec<BuildWarning>) {
+        Self::from_profile_at_with_warnings(profile, NonZeroUsize::MIN)
+    }
+
+    /// Create the root group for the given profile using the containers configured for the car
+    /// with the given unique ID.
+    ///
+    /// If the profile doesn't contain an override for the car, the base containers of the profile
+    /// are used.
+    pub fn for_car(profile: LedProfile, car_id: &str) -> Self {
+        Self::for_car_with_warnings(profile, car_id).0
+    }
+
+    /// Create the root group for the given car, like [`GroupState::for_car()`] does, and collect
+    /// the problems found in the containers used for the car.
+    pub fn for_car_with_warnings(
+        mut profile: LedProfile,
+        car_id: &str,
+    ) -> (Self, Vec<BuildWarning>) {
+        if let Some(containers) = profile.car_overrides.remove(car_id) {
+            profile.led_containers = containers;
+        }
+
+        Self::root_with_warnings(profile)
+    }
+
+    /// Create the root group for the given profile, with all the top-level containers offset by
+    /// the given base position.
+    ///
+    /// This allows the same profile to drive LEDs starting at, for example, the fifth LED on one
+    /// device and the first LED on another one. A base position of one leaves the positions from
+    /// the profile untouched.
+    pub fn root_at(profile: LedProfile, base_position: NonZeroUsize) -> Self {
+        Self::from_profile_at(profile, base_position)
+    }
+
+    pub fn new(container: GroupContainer) -> Self {
+        Self::from_container(container)
+    }
+}
+
+impl<E: FromContainer> GroupState<E> {
+    /// Create the root group for the given profile, with all the top-level containers offset by
+    /// the given base position, see [`GroupState::root_at()`].
+    pub fn from_profile_at(profile: LedProfile, base_position: NonZeroUsize) -> Self {
+        Self::from_profile_at_with_warnings(profile, base_position).0
+    }
+
+    /// Create the root group for the given profile, see [`GroupState::from_profile_at()`], and
+    /// collect the problems found in the containers of the profile.
+    pub fn from_profile_at_with_warnings(
+        profile: LedProfile,
+        base_position: NonZeroUsize,
+    ) -> (Self, Vec<BuildWarning>) {
+        let condition = GroupCondition::AlwaysOn;
+        let stacking_type = profile.stacking_type;
+
+        let containers = profile.led_containers;
+        let mut warnings = Vec::new();
+
+        let group = Self::new_helper(
+            condition,
+            base_position,
+            base_position,
+            stacking_type,
+            containers,
+            &mut warnings,
+        );
+
+        (group, warnings)
+    }
+
+    /// Create the group for the given group container.
+    pub fn from_container(container: GroupContainer) -> Self {
+        Self::from_container_helper(container, NonZeroUsize::MIN, &mut Vec::new())
+    }
+
+    /// Create the group for the given group container, with the start position of the group and
+    /// the ones of its nested groups offset by the given base position, see
+    /// [`GroupState::root_at()`].
+    fn from_container_helper(
+        container: GroupContainer,
+        base_position: NonZeroUsize,
+        warnings: &mut Vec<BuildWarning>,
+    ) -> Self {
+        let condition = GroupCondition::from(&container);
+
+        let (stacking_type, start_position, containers) = match container {
+            GroupContainer::Simple(c) => (c.stacking_type, c.start_position, c.led_containers),
+            GroupContainer::GameRunning(c) => (c.stacking_type, c.start_position, c.led_containers),
+            GroupContainer::CarStarted(c) => (c.stacking_type, c.start_position, c.led_containers),
+            GroupContainer::Conditional(c) => (c.stacking_type, c.start_position, c.led_containers),
+        };
+
+        Self::new_helper(
+            condition,
+            base_position.saturating_add(start_position.get() - 1),
+            base_position,
+            stacking_type,
+            containers,
+            warnings,
+        )
+    }
+
     fn new_helper(
         condition: GroupCondition,
         group_start_position: NonZeroUsize,
+        base_position: NonZeroUsize,
         stacking_type: StackingType,
         containers: Vec<LedContainer>,
+        warnings: &mut Vec<BuildWarning>,
     ) -> Self {
         let mut states = Vec::with_capacity(containers.len());
+        let mut ranges: Vec<(String, RangeInclusive<usize>)> = Vec::new();
+
+        let positions = resolve_positions(
+            containers,
+            group_start_position,
+            base_position,
+            stacking_type,
+        );
+
+        for (start_position, container) in positions {
+            let container_type = container.container_type().to_owned();
+            let mut warn = |kind| {
+                warnings.push(BuildWarning {
+                    container_type: container_type.clone(),
+                    start_position,
+                    kind,
+                })
+            };
 
-        let mut start_position = group_start_position;
+            let segments_overflow = matches!(
+                &container,
+                LedContainer::RpmSegments(container) if container.checked_led_count().is_none()
+            );
 
-        for container in containers {
-            if stacking_type == StackingType::Layered {
-                start_position =
-                    group_start_position.saturating_add(container.start_position().get() - 1);
+            match container.led_count() {
+                Some(0) => warn(BuildWarningKind::NoLeds),
+                Some(_) if segments_overflow => warn(BuildWarningKind::PositionOverflow),
+                Some(led_count) if start_position.get().checked_add(led_count - 1).is_none() => {
+                    warn(BuildWarningKind::PositionOverflow)
+                }
+                _ => {}
             }
 
-            let Some(state) = Self::create_led_effect(container, start_position) else {
+            let state = match container {
+                // Groups are created here, instead of in `E::from_container()`, so the warnings
+                // of their containers get collected as well.
+                LedContainer::Group(group) => Some(E::from_group(
+                    GroupState::from_container_helper(group, base_position, warnings),
+                )),
+                container => E::from_container(container, start_position),
+            };
+
+            let Some(state) = state else {
+                warnings.push(BuildWarning {
+                    container_type,
+                    start_position,
+                    kind: BuildWarningKind::Unsupported,
+                });
+
                 continue;
             };
 
-            if stacking_type == StackingType::LeftToRight {
-                start_position = start_position.saturating_add(state.led_count());
+            if let Some(range) =
+                covered_leds(&state).filter(|_| stacking_type == StackingType::Layered)
+            {
+                for (other_type, other_range) in &ranges {
+                    let start = *range.start().max(other_range.start());
+                    let end = *range.end().min(other_range.end());
+
+                    if start <= end {
+                        warnings.push(BuildWarning {
+                            container_type: container_type.clone(),
+                            start_position,
+                            kind: BuildWarningKind::Overlap {
+                                other_container_type: other_type.clone(),
+                                leds: start..=end,
+                            },
+                        });
+                    }
+                }
+
+                ranges.push((container_type, range));
             }
 
             states.push(state);
@@ -167,56 +431,337 @@ impl GroupState {
 
         Self {
             condition,
-            start_position,
+            start_position: group_start_position,
             states,
+            overrides: HashMap::new(),
+            tint: None,
+            pit_lane_background: None,
+            in_pit_lane: false,
+            brightness: 1.0,
+        }
+    }
+}
+
+impl<E: LedEffect> GroupState<E> {
+    fn update_states(&mut self, ctx: &UpdateContext<'_>) {
+        for state in &mut self.states {
+            state.update_ctx(ctx);
+        }
+    }
+
+    /// Flatten the LED states of all the effects in this group into a single list of LEDs.
+    ///
+    /// The returned list contains `led_count` LEDs, the first element being the first LED. Effects
+    /// that come later in the group are drawn on top of the earlier ones, unless their LEDs are
+    /// turned off and [transparent](LedState::is_transparent_when_off). LEDs which aren't
+    /// covered by any effect are turned off, or take on the pit lane background while the car is in the
+    /// pit lane. The tint of the group is applied to the colors of the effects and the brightness
+    /// from the [`SharedConfig`](super::SharedConfig) to their LEDs, the overrides of the group
+    /// are applied last and are left untouched.
+    pub fn render(&self, led_count: usize) -> Vec<LedConfiguration> {
+        render(
+            self.leds(),
+            &self.overrides,
+            self.tint.as_ref(),
+            self.background(),
+            self.brightness,
+            led_count,
+        )
+    }
+
+    /// The color of the LEDs which aren't turned on by any effect, if they shouldn't be off.
+    fn background(&self) -> Option<&Color> {
+        self.pit_lane_background
+            .as_ref()
+            .filter(|_| self.in_pit_lane)
+    }
+
+    /// Serialize the rendered state of the first `led_count` LEDs of this group into a compact
+    /// JSON array, see [`GroupState::render()`].
+    ///
+    /// Every LED is represented as an object containing the zero-based `index` of the LED, the
+    /// `r`, `g`, and `b` color components, and whether the LED is turned `on`. This allows
+    /// processes which don't link to this crate, like a web UI, to display the LEDs.
+    pub fn to_frame_json(&self, led_count: usize) -> serde_json::Value {
+        let leds = self
+            .render(led_count)
+            .into_iter()
+            .enumerate()
+            .map(|(index, led)| {
+                let ([r, g, b, _], on) = match led {
+                    LedConfiguration::On { color, .. } => (color.to_rgba8(), true),
+                    LedConfiguration::Off => ([0; 4], false),
+                };
+
+                json!({ "index": index, "r": r, "g": g, "b": b, "on": on })
+            })
+            .collect();
+
+        serde_json::Value::Array(leds)
+    }
+
+    /// Take a snapshot of the current LED states of this group.
+    ///
+    /// The snapshot is cheap to clone and can be sent to other threads, this allows a single
+    /// updater to feed multiple outputs, for example the LEDs and a display mirroring them.
+    pub fn snapshot(&self) -> EffectSnapshot {
+        EffectSnapshot {
+            states: self.leds().cloned().collect(),
+            overrides: Arc::new(self.overrides.clone()),
+            tint: self.tint.clone(),
+            background: self.background().cloned(),
+            brightness: self.brightness,
         }
     }
 
-    fn update_states(&mut self, sim_state: &dyn Moment) {
+    /// Set a color which is multiplied, channel by channel, into the colors the effects of this
+    /// group produce when rendering, see [`GroupState::render()`].
+    ///
+    /// This allows the palette of a whole profile to be adjusted at runtime, a white tint leaves
+    /// the colors untouched. Passing `None` removes the tint.
+    pub fn set_tint(&mut self, tint: Option<Color>) {
+        self.tint = tint;
+    }
+
+    /// Get the tint of this group, see [`GroupState::set_tint()`].
+    pub fn tint(&self) -> Option<&Color> {
+        self.tint.as_ref()
+    }
+
+    /// Set a background color for the LED bar which is shown while the car is in the pit lane,
+    /// see [`MomentExt::is_in_pit_lane()`].
+    ///
+    /// Only the LEDs which aren't turned on by any of the effects take on the color, lit LEDs are
+    /// left as they are, this way effects like the speed limiter animation remain visible in the
+    /// pit lane. Unlike the [tint](GroupState::set_tint()) the color isn't blended into the
+    /// colors of the effects. Passing `None` removes the pit lane background.
+    pub fn set_pit_lane_background(&mut self, background: Option<Color>) {
+        self.pit_lane_background = background;
+    }
+
+    /// Get the pit lane background of this group, see [`GroupState::set_pit_lane_background()`].
+    pub fn pit_lane_background(&self) -> Option<&Color> {
+        self.pit_lane_background.as_ref()
+    }
+
+    /// Force the LED with the given zero-based index to the given configuration when rendering,
+    /// regardless of what the effects of this group produce.
+    ///
+    /// Returns the previous override of the LED, if there was one.
+    pub fn set_override(
+        &mut self,
+        index: usize,
+        led_config: LedConfiguration,
+    ) -> Option<LedConfiguration> {
+        self.overrides.insert(index, led_config)
+    }
+
+    /// Remove the override of the LED with the given zero-based index.
+    pub fn remove_override(&mut self, index: usize) -> Option<LedConfiguration> {
+        self.overrides.remove(&index)
+    }
+
+    /// Restart the blink cycles of all the effects in this group, including the ones in nested
+    /// groups, at the given instant.
+    ///
+    /// Effects which started blinking at different times will blink in unison afterwards.
+    pub fn sync_blink_phases(&mut self, now: Instant) {
         for state in &mut self.states {
-            state.update(sim_state);
+            state.sync_blink_phase(now)
         }
     }
 
     pub fn update(&mut self, sim_state: &dyn Moment) {
+        self.update_ctx(&UpdateContext::new(sim_state))
+    }
+
+    /// Update the group using the given [`UpdateContext`].
+    ///
+    /// The effects of a [`GroupCondition::CarStarted`] group are updated with the fraction of the
+    /// group duration that has elapsed, see [`UpdateContext::progress`].
+    pub fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        let sim_state = ctx.sim_state;
+        self.in_pit_lane = sim_state.is_in_pit_lane();
+
+        if let Some(config) = ctx.config {
+            self.brightness = config.brightness;
+        }
+
         match &mut self.condition {
             // TODO: Once simetry exposes if the game has started or not, use that information to
             // guard the `GameStarted` condition.
-            GroupCondition::AlwaysOn | GroupCondition::GameStarted => self.update_states(sim_state),
-            GroupCondition::CarStarted { duration, state } => match state {
-                SimpleConditionstate::Waiting => {
-                    if sim_state.is_engine_running() {
-                        *state = SimpleConditionstate::Triggered {
-                            trigger_time: Instant::now(),
-                        };
-                        self.update_states(sim_state);
+            GroupCondition::AlwaysOn | GroupCondition::GameStarted => self.update_states(ctx),
+            GroupCondition::CarStarted {
+                duration,
+                state,
+                re_arm_policy,
+                was_in_pit_lane,
+            } => {
+                let in_pit_lane = self.in_pit_lane;
+                let entered_pit_lane = in_pit_lane && !*was_in_pit_lane;
+                *was_in_pit_lane = in_pit_lane;
+
+                match state {
+                    SimpleConditionstate::Waiting => {
+                        if sim_state.is_engine_running() {
+                            *state = SimpleConditionstate::Triggered {
+                                trigger_time: ctx.now,
+                            };
+                            self.update_states(&ctx.with_progress(0.0));
+                        }
                     }
-                }
-                SimpleConditionstate::Triggered { trigger_time } => {
-                    if &trigger_time.elapsed() >= duration {
-                        *state = SimpleConditionstate::Expired;
-                        self.disable();
-                    } else {
-                        self.update_states(sim_state);
+                    SimpleConditionstate::Triggered { trigger_time } => {
+                        let elapsed = ctx.now.saturating_duration_since(*trigger_time);
+
+                        if &elapsed >= duration {
+                            *state = SimpleConditionstate::Expired;
+                            self.disable();
+                        } else {
+                            let progress = elapsed.as_secs_f64() / duration.as_secs_f64();
+                            self.update_states(&ctx.with_progress(progress));
+                        }
                     }
-                }
-                SimpleConditionstate::Expired => {
-                    if !sim_state.is_engine_running() {
-                        *state = SimpleConditionstate::Waiting;
+                    SimpleConditionstate::Expired => {
+                        let re_arm = match re_arm_policy {
+                            ReArmPolicy::OnEngineStop => !sim_state.is_engine_running(),
+                            ReArmPolicy::OnPitLaneEntry => entered_pit_lane,
+                            ReArmPolicy::Never => false,
+                        };
+
+                        if re_arm {
+                            *state = SimpleConditionstate::Waiting;
+                        }
                     }
                 }
-            },
-            // TODO: Support ncalc style expressions.
+            }
+            // TODO: Support ncalc style expressions. The gear property of a formula needs to use
+            // `MomentExt::gear_label()`, so formulas see the same labels the gear display shows.
             GroupCondition::Conditional { .. } => (),
         }
     }
 }
 
-impl LedEffect for GroupState {
+/// Get the LEDs an effect draws on, from the lowest to the highest LED any of its LED states
+/// cover, or `None` if the effect doesn't have any LEDs.
+///
+/// The LED count of a nested group is the sum of its effects, which doesn't tell us which LEDs
+/// the group covers if its effects are layered on top of each other.
+fn covered_leds(effect: &impl LedEffect) -> Option<RangeInclusive<usize>> {
+    effect
+        .leds()
+        .filter(|led_state| !led_state.leds().is_empty())
+        .map(|led_state| {
+            let start = led_state.start_position().get();
+            start..=start.saturating_add(led_state.leds().len() - 1)
+        })
+        .reduce(|covered, range| {
+            *covered.start().min(range.start())..=*covered.end().max(range.end())
+        })
+}
+
+pub(super) fn render<'a>(
+    states: impl Iterator<Item = &'a LedState>,
+    overrides: &HashMap<usize, LedConfiguration>,
+    tint: Option<&Color>,
+    background: Option<&Color>,
+    brightness: f64,
+    led_count: usize,
+) -> Vec<LedConfiguration> {
+    let mut leds = vec![LedConfiguration::Off; led_count];
+
+    for state in states {
+        for (index, led_config) in state.covering_leds() {
+            let Some(led) = leds.get_mut(index) else {
+                break;
+            };
+
+            *led = match led_config {
+                LedConfiguration::On {
+                    color,
+                    brightness: led_brightness,
+                } => LedConfiguration::On {
+                    color: match tint {
+                        Some(tint) => Color::new(
+                            color.r * tint.r,
+                            color.g * tint.g,
+                            color.b * tint.b,
+                            color.a,
+                        ),
+                        None => color.clone(),
+                    },
+                    brightness: led_brightness * brightness,
+                },
+                LedConfiguration::Off => LedConfiguration::Off,
+            };
+        }
+    }
+
+    if let Some(background) = background {
+        for led in leds.iter_mut().filter(|led| **led == LedConfiguration::Off) {
+            *led = LedConfiguration::On {
+                color: background.clone(),
+                brightness,
+            };
+        }
+    }
+
+    for (index, led_config) in overrides {
+        if let Some(led) = leds.get_mut(*index) {
+            *led = led_config.clone();
+        }
+    }
+
+    leds
+}
+
+/// Flatten the LED states into `led_count` LEDs as they are, without any overrides, tint,
+/// background or brightness applied.
+pub(super) fn render_plain<'a>(
+    states: impl Iterator<Item = &'a LedState>,
+    led_count: usize,
+) -> Vec<LedConfiguration> {
+    render(states, &HashMap::new(), None, None, 1.0, led_count)
+}
+
+/// A snapshot of the LED states of a [`GroupState`], taken after the group was updated.
+#[derive(Debug, Clone)]
+pub struct EffectSnapshot {
+    states: Arc<[LedState]>,
+    overrides: Arc<HashMap<usize, LedConfiguration>>,
+    tint: Option<Color>,
+    background: Option<Color>,
+    brightness: f64,
+}
+
+impl EffectSnapshot {
+    /// Get the LED states of the snapshot.
+    pub fn leds(&self) -> impl Iterator<Item = &LedState> {
+        self.states.iter()
+    }
+
+    /// Flatten the LED states of the snapshot into a single list of LEDs, see
+    /// [`GroupState::render()`].
+    pub fn render(&self, led_count: usize) -> Vec<LedConfiguration> {
+        render(
+            self.leds(),
+            &self.overrides,
+            self.tint.as_ref(),
+            self.background.as_ref(),
+            self.brightness,
+            led_count,
+        )
+    }
+}
+
+impl<E: LedEffect> LedEffect for GroupState<E> {
     fn update(&mut self, sim_state: &dyn Moment) {
         self.update(sim_state)
     }
 
+    fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        self.update_ctx(ctx)
+    }
+
     fn start_led(&self) -> NonZeroUsize {
         self.start_position
     }
@@ -235,17 +780,43 @@ impl LedEffect for GroupState {
         }
     }
 
+    fn pause(&mut self, now: Instant) {
+        for state in &mut self.states {
+            state.pause(now)
+        }
+    }
+
+    fn resume(&mut self, now: Instant) {
+        for state in &mut self.states {
+            state.resume(now)
+        }
+    }
+
+    fn sync_blink_phase(&mut self, state_change: Instant) {
+        self.sync_blink_phases(state_change)
+    }
+
     fn led_count(&self) -> usize {
         self.states.iter().map(|state| state.led_count()).sum()
     }
+
+    fn flattened(&self, total: usize) -> Vec<LedConfiguration> {
+        self.render(total)
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use serde_json::json;
     use similar_asserts::assert_eq;
+    use uom::si::{angular_velocity::revolution_per_minute, f64::AngularVelocity};
 
-    use crate::{led::state::flag::test::SimState, leds};
+    use crate::{
+        led,
+        led::state::{
+            effect::Effect, flag::test::SimState, rpm::gradient::test::RpmSimState, SharedConfig,
+        },
+        leds,
+    };
 
     use super::*;
 
@@ -294,76 +865,1589 @@ mod test {
         )
     }
 
+    fn profile() -> LedProfile {
+        let GroupContainer::Simple(group) = container(false) else {
+            unreachable!("The test container should always be a simple group");
+        };
+
+        let profile = json!({
+            "Name": "White flags",
+            "ProfileId": "9b2f5b6e-3c9c-4a53-8f1e-0a0c5f6e7d21",
+            "GlobalBrightness": 1.0,
+            "UseProfileBrightness": false,
+            "LedContainers": [],
+        });
+
+        let mut profile: LedProfile =
+            serde_json::from_value(profile).expect("We should be able to deserialize the profile");
+        profile.led_containers = group.led_containers;
+
+        profile
+    }
+
     #[test]
-    fn white_flag() {
-        let container = container(false);
-        let mut state = GroupState::new(container);
+    fn root_at_base_position() {
         let mut flags = SimState::new();
+        flags.inner.white = true;
 
+        let mut state = GroupState::root(profile());
         state.update(&flags);
 
         assert_eq!(
-            &leds![3; off; 3],
-            state.states[0].leds().next().unwrap(),
-            "The LEDs should stay off if no flag is waving"
+            state.leds().collect::<Vec<_>>(),
+            vec![&leds![1; "White"; 3], &leds![14; "White"; 3]],
+            "The default root group should use the positions from the profile"
         );
 
+        let mut state = GroupState::root_at(profile(), NonZeroUsize::new(5).unwrap());
+        state.update(&flags);
+
         assert_eq!(
-            &leds![16; off; 3],
-            state.states[1].leds().next().unwrap(),
-            "The LEDs should stay off if no flag is waving"
+            state.leds().collect::<Vec<_>>(),
+            vec![&leds![5; "White"; 3], &leds![18; "White"; 3]],
+            "All the containers should be offset by the base position"
         );
+    }
 
-        flags.inner.white = true;
-        state.update(&flags);
+    #[test]
+    fn root_at_base_position_nested() {
+        let profile = flags_profile(vec![
+            flag_container(1, 2),
+            json!({
+                "LedContainers": [
+                    flag_container(1, 3),
+                    {
+                        "LedContainers": [flag_container(2, 2)],
+                        "StartPosition": 10,
+                        "ContainerType": "GroupContainer",
+                        "IsEnabled": true
+                    },
+                ],
+                "StartPosition": 4,
+                "ContainerType": "GroupContainer",
+                "IsEnabled": true
+            }),
+        ]);
+
+        let positions = |state: GroupState| {
+            state
+                .leds()
+                .map(|led_state| led_state.start_position().get())
+                .collect::<Vec<_>>()
+        };
 
         assert_eq!(
-            &leds![3; "White"; 3],
-            state.states[0].leds().next().unwrap(),
-            "The yellow flag should turn all the LEDs on"
+            positions(GroupState::root(profile.clone())),
+            [1, 4, 11],
+            "The default root group should use the positions from the profile"
         );
-
         assert_eq!(
-            &leds![16; "White"; 3],
-            state.states[1].leds().next().unwrap(),
-            "The yellow flag should turn all the LEDs on"
+            positions(GroupState::root_at(profile, NonZeroUsize::new(5).unwrap())),
+            [5, 8, 15],
+            "The containers of nested groups should be offset by the base position as well"
         );
     }
 
     #[test]
-    fn white_flag_left_to_right_stacking() {
-        let container = container(true);
-        let mut state = GroupState::new(container);
-
+    fn root_stacking() {
         let mut flags = SimState::new();
+        flags.inner.white = true;
 
-        state.update(&flags);
+        let mut profile = profile();
+        assert_eq!(profile.stacking_type, StackingType::Layered);
+
+        let mut layered = GroupState::root(profile.clone());
+        layered.update(&flags);
 
         assert_eq!(
-            &leds![3; off; 3],
-            state.states[0].leds().next().unwrap(),
-            "The LEDs should stay off if no flag is waving"
+            layered.leds().collect::<Vec<_>>(),
+            vec![&leds![1; "White"; 3], &leds![14; "White"; 3]],
+            "The layered root should use the start positions of the containers"
         );
 
+        profile.stacking_type = StackingType::LeftToRight;
+
+        let mut left_to_right = GroupState::root_at(profile, NonZeroUsize::new(2).unwrap());
+        left_to_right.update(&flags);
+
         assert_eq!(
-            &leds![6; off; 3],
-            state.states[1].leds().next().unwrap(),
-            "The LEDs should stay off if no flag is waving"
+            left_to_right.leds().collect::<Vec<_>>(),
+            vec![&leds![2; "White"; 3], &leds![5; "White"; 3]],
+            "The left to right root should place the containers one after another, starting at \
+             the base position"
         );
+    }
 
-        flags.inner.white = true;
-        state.update(&flags);
+    fn flag_container(start_position: usize, led_count: usize) -> serde_json::Value {
+        json!({
+            "LedCount": led_count,
+            "Color": "White",
+            "BlinkEnabled": false,
+            "StartPosition": start_position,
+            "ContainerType": "WhiteFlagContainer",
+            "IsEnabled": true
+        })
+    }
+
+    fn flags_profile(containers: Vec<serde_json::Value>) -> LedProfile {
+        let profile = json!({
+            "Name": "Overlapping flags",
+            "ProfileId": "6d1c2d1e-5a0f-4f3c-9d8e-1b7b8c4e2f10",
+            "GlobalBrightness": 1.0,
+            "UseProfileBrightness": false,
+            "LedContainers": containers,
+        });
+
+        serde_json::from_value(profile).expect("We should be able to deserialize the profile")
+    }
+
+    #[test]
+    fn layout_matches_runtime_positions() {
+        let mut disabled = flag_container(4, 1);
+        disabled["IsEnabled"] = false.into();
+
+        let mut profile = flags_profile(vec![
+            flag_container(1, 2),
+            json!({
+                "LedContainers": [
+                    flag_container(1, 3),
+                    {
+                        "LedCount": 8,
+                        "Color": "Red",
+                        "DualBlinkTimingEnabled": false,
+                        "OffDelay": 100,
+                        "OnDelay": 200,
+                        "StartPosition": 1,
+                        "ContainerType": "RedlineReachedContainer",
+                        "Description": "Redline",
+                        "IsEnabled": true
+                    },
+                    flag_container(7, 2),
+                    {
+                        "LedContainers": [flag_container(2, 2), flag_container(1, 1)],
+                        "StartPosition": 20,
+                        "ContainerType": "GroupContainer",
+                        "IsEnabled": false
+                    },
+                    flag_container(1, 1),
+                ],
+                "StackLeftToRight": true,
+                "StartPosition": 6,
+                "ContainerType": "GroupContainer",
+                "IsEnabled": true
+            }),
+            json!({ "ContainerType": "FancyNewContainer", "StartPosition": 40 }),
+            disabled,
+        ]);
+
+        for stacking_type in [StackingType::Layered, StackingType::LeftToRight] {
+            profile.stacking_type = stacking_type;
+
+            let layout = profile.layout();
+            let state = GroupState::root(profile.clone());
+
+            assert_eq!(
+                layout
+                    .iter()
+                    .map(|placement| (placement.start.get(), placement.count))
+                    .collect::<Vec<_>>(),
+                state
+                    .leds()
+                    .map(|led_state| (led_state.start_position().get(), led_state.leds().len()))
+                    .collect::<Vec<_>>(),
+                "The layout should place the effects where the group places them, using the \
+                 {stacking_type:?} stacking"
+            );
+        }
 
         assert_eq!(
-            &leds![3; "White"; 3],
-            state.states[0].leds().next().unwrap(),
-            "The yellow flag should turn all the LEDs on"
+            profile
+                .layout()
+                .iter()
+                .map(|placement| placement.enabled)
+                .collect::<Vec<_>>(),
+            [true, true, true, false, false, true, false],
+            "Effects of a disabled group should be reported as disabled"
+        );
+        assert!(
+            profile
+                .layout()
+                .iter()
+                .all(|placement| placement.kind == "WhiteFlagContainer"),
+            "The unknown and the redline container shouldn't be part of the layout"
         );
-
+        assert_eq!(
+            profile
+                .layout()
+                .iter()
+                .map(|placement| placement.start.get())
+                .collect::<Vec<_>>(),
+            [1, 6, 9, 21, 20, 14, 12],
+            "The redline container shouldn't take up any LEDs in a group stacking from left to \
+             right"
+        );
+    }
+
+    /// Create the root group for the given profile and get the overlaps between its containers.
+    fn overlaps(profile: LedProfile) -> Vec<BuildWarning> {
+        let (_, warnings) = GroupState::root_with_warnings(profile);
+
+        warnings
+            .into_iter()
+            .filter(|warning| matches!(warning.kind, BuildWarningKind::Overlap { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn overlapping_containers() {
+        let overlap = |start_position: usize, leds| BuildWarning {
+            container_type: "WhiteFlagContainer".to_owned(),
+            start_position: NonZeroUsize::new(start_position).unwrap(),
+            kind: BuildWarningKind::Overlap {
+                other_container_type: "WhiteFlagContainer".to_owned(),
+                leds,
+            },
+        };
+
+        let profile = flags_profile(vec![flag_container(1, 4), flag_container(3, 4)]);
+        let warnings = overlaps(profile);
+
+        assert_eq!(
+            warnings,
+            [overlap(3, 3..=4)],
+            "The two flag containers cover the third and fourth LED"
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "The WhiteFlagContainer starting at LED 3 is drawn on top of the WhiteFlagContainer \
+             on the LEDs 3 to 4"
+        );
+
+        let profile = flags_profile(vec![
+            flag_container(1, 2),
+            flag_container(5, 2),
+            flag_container(2, 5),
+        ]);
+
+        assert_eq!(
+            overlaps(profile),
+            [overlap(2, 2..=2), overlap(2, 5..=6)],
+            "The last container should overlap both of the earlier ones"
+        );
+    }
+
+    #[test]
+    fn adjacent_containers_dont_overlap() {
+        let profile = flags_profile(vec![flag_container(1, 4), flag_container(5, 4)]);
+
+        assert!(
+            overlaps(profile).is_empty(),
+            "Containers which are next to each other shouldn't overlap"
+        );
+
+        let mut profile = flags_profile(vec![flag_container(1, 4), flag_container(1, 4)]);
+        profile.stacking_type = StackingType::LeftToRight;
+
+        assert!(
+            overlaps(profile).is_empty(),
+            "Containers stacked left to right should never overlap"
+        );
+    }
+
+    #[test]
+    fn nested_layered_group_overlaps() {
+        let profile = flags_profile(vec![
+            json!({
+                "LedContainers": [flag_container(1, 4), flag_container(1, 4)],
+                "StackLeftToRight": false,
+                "StartPosition": 1,
+                "ContainerType": "GroupContainer",
+                "IsEnabled": true
+            }),
+            flag_container(6, 2),
+        ]);
+
+        assert_eq!(
+            overlaps(profile)
+                .iter()
+                .map(|warning| warning.start_position.get())
+                .collect::<Vec<_>>(),
+            [1],
+            "Only the layered flags inside the group should overlap, the group itself only \
+             covers the first four LEDs"
+        );
+
+        let profile = flags_profile(vec![
+            json!({
+                "LedContainers": [flag_container(1, 4), flag_container(1, 4)],
+                "StackLeftToRight": false,
+                "StartPosition": 1,
+                "ContainerType": "GroupContainer",
+                "IsEnabled": true
+            }),
+            flag_container(4, 2),
+        ]);
+        let warnings = overlaps(profile);
+
+        assert_eq!(
+            warnings.last().map(|warning| &warning.kind),
+            Some(&BuildWarningKind::Overlap {
+                other_container_type: "GroupContainer".to_owned(),
+                leds: 4..=4,
+            }),
+            "A sibling covering the last LED of the group should overlap the group"
+        );
+    }
+
+    #[test]
+    fn nested_group_start_position() {
+        let profile = flags_profile(vec![json!({
+            "LedContainers": [flag_container(1, 3), flag_container(1, 2)],
+            "StackLeftToRight": true,
+            "StartPosition": 6,
+            "ContainerType": "GroupContainer",
+            "IsEnabled": true
+        })]);
+        let group = GroupState::root(profile);
+
+        let nested = group
+            .states
+            .first()
+            .expect("The root group should contain the nested group");
+
+        assert_eq!(
+            nested.start_led().get(),
+            6,
+            "A group stacking its containers from left to right should start at its own start \
+             position, not after its last container"
+        );
+    }
+
+    #[test]
+    fn car_overrides() {
+        let profile = json!({
+            "Name": "Car overrides",
+            "ProfileId": "0f1e7f0c-4a8e-4c1a-a3c3-28d0f9a1c4be",
+            "GlobalBrightness": 1.0,
+            "UseProfileBrightness": false,
+            "LedContainers": [
+                {
+                    "LedCount": 2,
+                    "Color": "White",
+                    "BlinkEnabled": false,
+                    "StartPosition": 1,
+                    "ContainerType": "WhiteFlagContainer",
+                    "IsEnabled": true
+                }
+            ],
+            "CarOverrides": {
+                "bmw_m4_gt3": [
+                    {
+                        "LedCount": 4,
+                        "Color": "Red",
+                        "BlinkEnabled": false,
+                        "StartPosition": 5,
+                        "ContainerType": "WhiteFlagContainer",
+                        "IsEnabled": true
+                    }
+                ]
+            }
+        });
+
+        let profile: LedProfile =
+            serde_json::from_value(profile).expect("We should be able to deserialize the profile");
+
+        let mut flags = SimState::new();
+        flags.inner.white = true;
+
+        let mut state = GroupState::for_car(profile.clone(), "bmw_m4_gt3");
+        state.update(&flags);
+
+        assert_eq!(
+            state.leds().collect::<Vec<_>>(),
+            vec![&leds![5; "Red"; 4]],
+            "The containers of the car override should be used"
+        );
+
+        let mut state = GroupState::for_car(profile, "porsche_991ii_gt3_r");
+        state.update(&flags);
+
+        assert_eq!(
+            state.leds().collect::<Vec<_>>(),
+            vec![&leds![1; "White"; 2]],
+            "The base containers should be used if the car doesn't have an override"
+        );
+    }
+
+    #[test]
+    fn render() {
+        let mut state = GroupState::root(profile());
+        let mut flags = SimState::new();
+        flags.inner.white = true;
+
+        state.update(&flags);
+
+        let mut expected = leds![off; 18];
+        expected.leds_mut()[..3].fill(led!("White"));
+        expected.leds_mut()[13..16].fill(led!("White"));
+
+        assert_eq!(
+            state.render(18),
+            expected.leds(),
+            "The two flag containers should be rendered at their positions, the rest should be off"
+        );
+
+        assert_eq!(
+            state.render(2),
+            leds!["White"; 2].leds(),
+            "LEDs past the requested LED count should be dropped"
+        );
+    }
+
+    #[test]
+    fn flattened() {
+        let mut profile = profile();
+        profile.led_containers = vec![
+            LedContainer::Group(container(true)),
+            LedContainer::Group(container(false)),
+        ];
+
+        let mut flags = SimState::new();
+        flags.inner.white = true;
+
+        let mut state: GroupState<Box<dyn LedEffect>> = GroupState::root(profile);
+        state.update(&flags);
+
+        let mut expected = vec![LedConfiguration::Off; 20];
+
+        for led_state in state.leds() {
+            let start = led_state.start_position().get() - 1;
+
+            for (offset, led) in led_state.leds().iter().enumerate() {
+                if let Some(expected) = expected.get_mut(start + offset) {
+                    *expected = led.clone();
+                }
+            }
+        }
+
+        let effect: &dyn LedEffect = &state;
+
+        assert_eq!(
+            effect.flattened(20),
+            expected,
+            "Flattening the effect should be the same as flattening its LED states manually"
+        );
+        assert_eq!(
+            effect.flattened(20),
+            state.render(20),
+            "Flattening a group should render it"
+        );
+
+        let nested: Vec<_> = state
+            .states
+            .iter()
+            .map(|state| state.flattened(20))
+            .collect();
+        assert_eq!(
+            nested[0]
+                .iter()
+                .filter(|led| **led != LedConfiguration::Off)
+                .count(),
+            6,
+            "Every nested group should flatten its own LED states"
+        );
+    }
+
+    #[test]
+    fn render_tint() {
+        let mut flags = SimState::new();
+        flags.inner.white = true;
+
+        let mut state = GroupState::root(flags_profile(vec![flag_container(1, 2)]));
+        state.update(&flags);
+        state.set_override(3, led!("white"));
+
+        assert_eq!(state.tint(), None);
+
+        state.set_tint(Some(Color::new(0.0, 0.5, 1.0, 1.0)));
+        let expected = vec![
+            led!((0.0, 0.5, 1.0)),
+            led!((0.0, 0.5, 1.0)),
+            led!(off),
+            led!("white"),
+        ];
+
+        assert_eq!(
+            state.render(4),
+            expected,
+            "The white LEDs of the flag should take on the tint, the override shouldn't be tinted"
+        );
+        assert_eq!(
+            state.snapshot().render(4),
+            expected,
+            "Snapshots should be rendered using the same tint"
+        );
+
+        let dimmed = LedState::from_configs(
+            NonZeroUsize::MIN,
+            vec![LedConfiguration::On {
+                color: Color::new(0.5, 0.8, 0.2, 1.0),
+                brightness: 0.5,
+            }],
+        );
+        let tint = Color::new(0.5, 0.25, 1.0, 1.0);
+
+        assert_eq!(
+            super::render(
+                std::iter::once(&dimmed),
+                &HashMap::new(),
+                Some(&tint),
+                None,
+                1.0,
+                1
+            ),
+            [LedConfiguration::On {
+                color: Color::new(0.25, 0.2, 0.2, 1.0),
+                brightness: 0.5,
+            }],
+            "Every channel should be multiplied by the channel of the tint, the brightness \
+             should be kept"
+        );
+
+        state.set_tint(None);
+
+        assert_eq!(
+            state.render(4),
+            vec![led!("white"), led!("white"), led!(off), led!("white")],
+            "Removing the tint should restore the original colors"
+        );
+    }
+
+    #[test]
+    fn shared_brightness() {
+        let mut flags = SimState::new();
+        flags.inner.white = true;
+
+        let mut profile = flags_profile(vec![flag_container(1, 2)]);
+        profile.global_brightness = 0.25;
+
+        assert_eq!(
+            SharedConfig::from_profile(&profile),
+            SharedConfig::default(),
+            "The global brightness should be ignored unless the profile says it should be used"
+        );
+
+        profile.use_profile_brightness = true;
+        let config = SharedConfig::from_profile(&profile);
+
+        let mut state = GroupState::root(profile);
+        state.update_ctx(&UpdateContext::new(&flags).with_config(&config));
+        state.set_override(3, led!("white"));
+
+        let dimmed = LedConfiguration::On {
+            color: Color::from_html("white").unwrap(),
+            brightness: 0.25,
+        };
+
+        assert_eq!(
+            state.render(4),
+            [dimmed.clone(), dimmed.clone(), led!(off), led!("white")],
+            "The LEDs of the effects should be rendered with the brightness of the profile, the \
+             override should be left untouched"
+        );
+
+        state.update(&flags);
+
+        assert_eq!(
+            state.snapshot().render(2),
+            [dimmed.clone(), dimmed],
+            "The brightness should be kept for updates which don't carry a shared configuration"
+        );
+    }
+
+    #[test]
+    fn build_warnings() {
+        let profile = json!({
+            "Name": "Problematic",
+            "ProfileId": "5e2d8b1a-3c4f-4a7e-9b6d-1f0e2c3a4b5d",
+            "GlobalBrightness": 1.0,
+            "UseProfileBrightness": false,
+            "LedContainers": [
+                {
+                    "LedCount": 5,
+                    "StartColor": "Lime",
+                    "EndColor": "Red",
+                    "BlinkDelay": 100,
+                    "StartPosition": 1,
+                    "ContainerType": "RPMContainer",
+                    "IsEnabled": true
+                },
+                {
+                    "LedCount": 3,
+                    "Color": "Yellow",
+                    "BlinkEnabled": false,
+                    "StartPosition": 4,
+                    "ContainerType": "YellowFlagContainer",
+                    "IsEnabled": true
+                },
+                {
+                    "StartPosition": 8,
+                    "ContainerType": "JavascriptContainer",
+                    "IsEnabled": true
+                },
+                {
+                    "SegmentsCount": 0,
+                    "Segments": [],
+                    "StartPosition": 10,
+                    "ContainerType": "RPMSegmentsContainer",
+                    "IsEnabled": true
+                },
+                {
+                    "LedCount": 2,
+                    "Color": "Blue",
+                    "BlinkEnabled": false,
+                    "StartPosition": usize::MAX,
+                    "ContainerType": "BlueFlagContainer",
+                    "IsEnabled": true
+                },
+                {
+                    "LedContainers": [
+                        {
+                            "StartPosition": 1,
+                            "ContainerType": "JavascriptContainer",
+                            "IsEnabled": true
+                        }
+                    ],
+                    "StackLeftToRight": false,
+                    "StartPosition": 20,
+                    "ContainerType": "GroupContainer",
+                    "IsEnabled": true
+                }
+            ]
+        });
+        let profile: LedProfile =
+            serde_json::from_value(profile).expect("We should be able to deserialize the profile");
+
+        let (group, warnings) = GroupState::root_with_warnings(profile.clone());
+
+        let warning = |container_type: &str, start_position: usize, kind| BuildWarning {
+            container_type: container_type.to_owned(),
+            start_position: NonZeroUsize::new(start_position).unwrap(),
+            kind,
+        };
+
+        assert_eq!(
+            warnings,
+            [
+                warning(
+                    "YellowFlagContainer",
+                    4,
+                    BuildWarningKind::Overlap {
+                        other_container_type: "RPMContainer".to_owned(),
+                        leds: 4..=5,
+                    }
+                ),
+                warning("JavascriptContainer", 8, BuildWarningKind::Unsupported),
+                warning("RPMSegmentsContainer", 10, BuildWarningKind::NoLeds),
+                warning(
+                    "BlueFlagContainer",
+                    usize::MAX,
+                    BuildWarningKind::PositionOverflow
+                ),
+                warning("JavascriptContainer", 20, BuildWarningKind::Unsupported),
+            ],
+            "The warnings should be reported in the order of the containers, including the ones \
+             of nested groups"
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "The YellowFlagContainer starting at LED 4 is drawn on top of the RPMContainer on \
+             the LEDs 4 to 5"
+        );
+        assert_eq!(
+            group.led_count(),
+            GroupState::root(profile).led_count(),
+            "Collecting the warnings shouldn't change the created group"
+        );
+    }
+
+    #[test]
+    fn overflowing_segments() {
+        const START_POSITION: usize = usize::MAX - 20;
+
+        let segment = json!({
+            "StartValue": 50.0,
+            "EndValue": 80.0,
+            "NormalColor": "Lime",
+            "BlinkingColor": "Blue",
+            "UseBlinkingColor": true,
+            "LedCount": 2,
+            "SampleResult": { "Width": 0, "Position": 0, "Columns": 0 }
+        });
+        let container = |segment_count: usize| {
+            json!({
+                "SegmentsCount": segment_count,
+                "Segments": vec![segment.clone(); segment_count],
+                "StartPosition": START_POSITION,
+                "ContainerType": "RPMSegmentsContainer",
+                "IsEnabled": true
+            })
+        };
+
+        let profile = flags_profile(vec![container(10), container(11)]);
+        let (_, warnings) = GroupState::root_with_warnings(profile);
+
+        assert_eq!(
+            warnings
+                .iter()
+                .filter(|warning| warning.kind == BuildWarningKind::PositionOverflow)
+                .map(|warning| (
+                    warning.container_type.as_str(),
+                    warning.start_position.get()
+                ))
+                .collect::<Vec<_>>(),
+            [("RPMSegmentsContainer", START_POSITION)],
+            "Only the segments which would be placed past the last possible LED position should \
+             be reported"
+        );
+    }
+
+    #[test]
+    fn transparent_segment_overlay() {
+        const MAX_RPM: f64 = 8000.0;
+
+        let profile = |transparent: bool| -> LedProfile {
+            let profile = json!({
+                "Name": "Layered redline",
+                "ProfileId": "0b7c2e4d-9a1f-4c3e-8d5b-6f2a1e9c7d3b",
+                "GlobalBrightness": 1.0,
+                "UseProfileBrightness": false,
+                "LedContainers": [
+                    {
+                        "UsePercent": true,
+                        "PercentMin": 80.0,
+                        "PercentMax": 95.0,
+                        "BlinkDelay": 200,
+                        "StartColor": "Lime",
+                        "EndColor": "Red",
+                        "LedCount": 5,
+                        "StartPosition": 1,
+                        "ContainerType": "RPMContainer",
+                        "IsEnabled": true
+                    },
+                    {
+                        "SegmentsCount": 1,
+                        "TransparentWhenOff": transparent,
+                        "StartPosition": 1,
+                        "ContainerType": "RPMSegmentsContainer",
+                        "IsEnabled": true,
+                        "Segments": [
+                            {
+                                "StartValue": 95.0,
+                                "EndValue": 100.0,
+                                "NormalColor": "Blue",
+                                "BlinkingColor": "Blue",
+                                "UseBlinkingColor": false,
+                                "LedCount": 5,
+                                "SampleResult": { "Width": 0, "Position": 0, "Columns": 0 }
+                            }
+                        ]
+                    }
+                ]
+            });
+
+            serde_json::from_value(profile).expect("We should be able to deserialize the profile")
+        };
+
+        let mut sim_state = RpmSimState::new(MAX_RPM * 0.91, MAX_RPM);
+
+        let mut opaque = GroupState::root(profile(false));
+        opaque.update(&sim_state);
+
+        assert_eq!(
+            opaque.render(5),
+            leds![off; 5].leds(),
+            "An opaque segment which is turned off should hide the gradient below it"
+        );
+
+        let mut layered = GroupState::root(profile(true));
+        layered.update(&sim_state);
+
+        assert_eq!(
+            layered.render(5),
+            leds!["lime", (0.25, 0.75, 0.0), (0.5, 0.5, 0.0), off, off].leds(),
+            "The gradient should show through a transparent segment which is turned off"
+        );
+
+        sim_state.update_rpm(MAX_RPM * 0.96);
+        layered.update(&sim_state);
+
+        assert_eq!(
+            layered.render(5),
+            leds!["blue"; 5].leds(),
+            "The redline segment should be drawn on top of the gradient once it's turned on"
+        );
+        assert_eq!(
+            layered.snapshot().render(5),
+            layered.render(5),
+            "Snapshots should respect the transparency as well"
+        );
+    }
+
+    #[test]
+    fn pit_lane_background() {
+        #[derive(Default)]
+        struct PitLaneState {
+            in_pit_lane: bool,
+            limiter_engaged: bool,
+        }
+
+        impl Moment for PitLaneState {
+            fn is_vehicle_in_pit_lane(&self) -> Option<bool> {
+                Some(self.in_pit_lane)
+            }
+
+            fn is_pit_limiter_engaged(&self) -> Option<bool> {
+                Some(self.limiter_engaged)
+            }
+        }
+
+        let profile = json!({
+            "Name": "Speed limiter",
+            "ProfileId": "6a1d9c3e-4b2f-4e8a-9d7c-5f3e2a1b0c9d",
+            "GlobalBrightness": 1.0,
+            "UseProfileBrightness": false,
+            "LedContainers": [
+                {
+                    "LedCount": 4,
+                    "Color1Alternate": "Red",
+                    "Color2Alternate": "Blue",
+                    "Color1SingleColor": "Yellow",
+                    "Color2SingleColor": "Black",
+                    "AlternateDelay": 50,
+                    "AlternateEnabled": false,
+                    "BlinkColor1Delay": 50,
+                    "LimiterBehavior": 1,
+                    "UseAlternate2": false,
+                    "UseAlternate": false,
+                    "Alternate2BlanckBackground": false,
+                    "StartPosition": 2,
+                    "ContainerType": "SpeedLimiterAnimationContainer",
+                    "Description": "Speed limiter animation",
+                    "IsEnabled": true
+                }
+            ]
+        });
+        let profile: LedProfile =
+            serde_json::from_value(profile).expect("We should be able to deserialize the profile");
+
+        let mut state = GroupState::root(profile);
+        state.set_pit_lane_background(Some(Color::from_html("blue").unwrap()));
+
+        assert_eq!(
+            state.pit_lane_background(),
+            Some(&Color::from_html("blue").unwrap())
+        );
+
+        let mut sim_state = PitLaneState {
+            in_pit_lane: true,
+            limiter_engaged: false,
+        };
+        state.update(&sim_state);
+
+        assert_eq!(
+            state.render(6),
+            leds!["blue"; 6].leds(),
+            "All the LEDs should take on the pit lane background while in the pit lane"
+        );
+
+        sim_state.limiter_engaged = true;
+        state.update(&sim_state);
+
+        assert_eq!(
+            state.render(6),
+            leds!["blue", "yellow", "yellow", "yellow", "yellow", "blue"].leds(),
+            "The speed limiter animation should be drawn on top of the pit lane background"
+        );
+        assert_eq!(
+            state.snapshot().render(6),
+            state.render(6),
+            "Snapshots should be rendered using the pit lane background as well"
+        );
+
+        sim_state.in_pit_lane = false;
+        sim_state.limiter_engaged = false;
+        state.update(&sim_state);
+
+        assert_eq!(
+            state.render(6),
+            leds![off; 6].leds(),
+            "The pit lane background should be removed once the car leaves the pit lane"
+        );
+    }
+
+    #[test]
+    fn render_overrides() {
+        struct RpmSimState;
+
+        impl Moment for RpmSimState {
+            fn vehicle_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+                Some(AngularVelocity::new::<revolution_per_minute>(8000.0))
+            }
+
+            fn vehicle_max_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+                Some(AngularVelocity::new::<revolution_per_minute>(9000.0))
+            }
+        }
+
+        let profile = json!({
+            "Name": "RPM gradient",
+            "ProfileId": "6d1b8c3e-93e4-4a51-a0f4-7a3f2b5c9e10",
+            "GlobalBrightness": 1.0,
+            "UseProfileBrightness": false,
+            "LedContainers": [
+                {
+                    "UsePercent": false,
+                    "PercentMin": 85.0,
+                    "PercentMax": 95.0,
+                    "RPMMin": 1000.0,
+                    "RPMMax": 8000.0,
+                    "BlinkDelay": 200,
+                    "StartColor": "Lime",
+                    "EndColor": "Red",
+                    "LedCount": 5,
+                    "StartPosition": 1,
+                    "ContainerType": "RPMContainer",
+                    "IsEnabled": true
+                }
+            ]
+        });
+        let profile: LedProfile =
+            serde_json::from_value(profile).expect("We should be able to deserialize the profile");
+
+        let mut state = GroupState::root(profile);
+        state.update(&RpmSimState);
+
+        let mut expected = state.render(5);
+
+        assert_eq!(state.set_override(2, led!("blue")), None);
+        assert_eq!(state.set_override(4, led!(off)), None);
+        assert_eq!(state.set_override(7, led!("blue")), None);
+
+        expected[2] = led!("blue");
+        expected[4] = led!(off);
+
+        assert_eq!(
+            state.render(5),
+            expected,
+            "Only the overridden LEDs should differ from the RPM gradient, overrides past the LED \
+             count should be ignored"
+        );
+
+        assert_eq!(state.remove_override(2), Some(led!("blue")));
+        expected[2] = state.states[0].leds().next().unwrap().leds()[2].clone();
+
+        assert_eq!(
+            state.render(5),
+            expected,
+            "Removing an override should restore the LED of the gradient"
+        );
+    }
+
+    #[test]
+    fn frame_json() {
+        let profile = json!({
+            "Name": "Yellow flag",
+            "ProfileId": "4c3fca87-2ba4-46e0-9e5e-ac1a8ba1fd1f",
+            "GlobalBrightness": 1.0,
+            "UseProfileBrightness": false,
+            "LedContainers": [
+                {
+                    "LedCount": 2,
+                    "Color": "Yellow",
+                    "BlinkEnabled": false,
+                    "StartPosition": 3,
+                    "ContainerType": "YellowFlagContainer",
+                    "IsEnabled": true
+                },
+                {
+                    "LedCount": 1,
+                    "Color": "Blue",
+                    "BlinkEnabled": false,
+                    "StartPosition": 1,
+                    "ContainerType": "BlueFlagContainer",
+                    "IsEnabled": true
+                }
+            ]
+        });
+        let profile: LedProfile =
+            serde_json::from_value(profile).expect("We should be able to deserialize the profile");
+
+        let mut state = GroupState::root(profile);
+        let mut flags = SimState::new();
+        flags.inner.yellow = true;
+
+        state.update(&flags);
+        state.set_override(3, led!("red"));
+
+        assert_eq!(
+            state.to_frame_json(5),
+            json!([
+                { "index": 0, "r": 0, "g": 0, "b": 0, "on": false },
+                { "index": 1, "r": 0, "g": 0, "b": 0, "on": false },
+                { "index": 2, "r": 255, "g": 255, "b": 0, "on": true },
+                { "index": 3, "r": 255, "g": 0, "b": 0, "on": true },
+                { "index": 4, "r": 0, "g": 0, "b": 0, "on": false },
+            ]),
+            "Every LED should be serialized once, in order, with the overrides applied"
+        );
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut state = GroupState::root(profile());
+        let mut flags = SimState::new();
+        flags.inner.white = true;
+
+        state.update(&flags);
+        state.set_override(5, led!("blue"));
+
+        let snapshot = state.snapshot();
+        let expected = state.render(18);
+
+        // Updating the group after the snapshot was taken must not modify the snapshot.
+        state.update(&SimState::new());
+
+        let consumers: Vec<_> = (0..2)
+            .map(|_| {
+                let snapshot = snapshot.clone();
+                std::thread::spawn(move || snapshot.render(18))
+            })
+            .collect();
+
+        for consumer in consumers {
+            assert_eq!(
+                consumer
+                    .join()
+                    .expect("The consumer thread should not panic"),
+                expected,
+                "Every consumer should see the LEDs the group had when the snapshot was taken"
+            );
+        }
+
+        assert_eq!(
+            snapshot.leds().collect::<Vec<_>>(),
+            vec![&leds![1; "White"; 3], &leds![14; "White"; 3]],
+        );
+    }
+
+    #[test]
+    fn white_flag() {
+        let container = container(false);
+        let mut state = GroupState::new(container);
+        let mut flags = SimState::new();
+
+        state.update(&flags);
+
+        assert_eq!(
+            &leds![3; off; 3],
+            state.states[0].leds().next().unwrap(),
+            "The LEDs should stay off if no flag is waving"
+        );
+
+        assert_eq!(
+            &leds![16; off; 3],
+            state.states[1].leds().next().unwrap(),
+            "The LEDs should stay off if no flag is waving"
+        );
+
+        flags.inner.white = true;
+        state.update(&flags);
+
+        assert_eq!(
+            &leds![3; "White"; 3],
+            state.states[0].leds().next().unwrap(),
+            "The yellow flag should turn all the LEDs on"
+        );
+
+        assert_eq!(
+            &leds![16; "White"; 3],
+            state.states[1].leds().next().unwrap(),
+            "The yellow flag should turn all the LEDs on"
+        );
+    }
+
+    #[test]
+    fn white_flag_left_to_right_stacking() {
+        let container = container(true);
+        let mut state = GroupState::new(container);
+
+        let mut flags = SimState::new();
+
+        state.update(&flags);
+
+        assert_eq!(
+            &leds![3; off; 3],
+            state.states[0].leds().next().unwrap(),
+            "The LEDs should stay off if no flag is waving"
+        );
+
+        assert_eq!(
+            &leds![6; off; 3],
+            state.states[1].leds().next().unwrap(),
+            "The LEDs should stay off if no flag is waving"
+        );
+
+        flags.inner.white = true;
+        state.update(&flags);
+
+        assert_eq!(
+            &leds![3; "White"; 3],
+            state.states[0].leds().next().unwrap(),
+            "The yellow flag should turn all the LEDs on"
+        );
+
         assert_eq!(
             &leds![6; "White"; 3],
             state.states[1].leds().next().unwrap(),
             "The yellow flag should turn all the LEDs on"
         );
     }
+
+    struct StartState {
+        engine_running: bool,
+        in_pit_lane: bool,
+    }
+
+    impl Moment for StartState {
+        fn vehicle_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+            let rpm = if self.engine_running { 3000.0 } else { 0.0 };
+            Some(AngularVelocity::new::<revolution_per_minute>(rpm))
+        }
+
+        fn is_starter_on(&self) -> Option<bool> {
+            Some(false)
+        }
+
+        fn is_vehicle_in_pit_lane(&self) -> Option<bool> {
+            Some(self.in_pit_lane)
+        }
+
+        fn flags(&self) -> Option<simetry::RacingFlags> {
+            Some(simetry::RacingFlags {
+                white: true,
+                ..Default::default()
+            })
+        }
+    }
+
+    fn car_started_group(re_arm_policy: &str) -> GroupState {
+        let container = json!({
+            "LedContainers": [
+                {
+                    "LedCount": 2,
+                    "Color": "White",
+                    "BlinkEnabled": false,
+                    "StartPosition": 1,
+                    "ContainerType": "WhiteFlagContainer",
+                    "IsEnabled": true
+                }
+            ],
+            "Duration": 0,
+            "ReArmPolicy": re_arm_policy,
+            "StartPosition": 1,
+            "IsEnabled": true
+        });
+
+        GroupState::new(GroupContainer::CarStarted(
+            serde_json::from_value(container)
+                .expect("We should be able to deserialize the car started group"),
+        ))
+    }
+
+    /// Start the engine and let the zero length duration of the group expire, returns if the
+    /// LEDs were turned on when the engine was started.
+    fn run_once(group: &mut GroupState, sim_state: &mut StartState) -> bool {
+        sim_state.engine_running = true;
+        group.update(sim_state);
+        let triggered = group.leds().next() == Some(&leds!["White"; 2]);
+        group.update(sim_state);
+
+        triggered
+    }
+
+    #[test]
+    fn car_started_re_arm_on_engine_stop() {
+        let mut group = car_started_group("OnEngineStop");
+        let mut sim_state = StartState {
+            engine_running: false,
+            in_pit_lane: false,
+        };
+
+        assert!(
+            run_once(&mut group, &mut sim_state),
+            "Starting the engine should trigger the group"
+        );
+        assert!(
+            !run_once(&mut group, &mut sim_state),
+            "The group should not trigger again while the engine keeps running"
+        );
+
+        sim_state.engine_running = false;
+        group.update(&sim_state);
+
+        assert!(
+            run_once(&mut group, &mut sim_state),
+            "Stopping the engine should re-arm the group"
+        );
+    }
+
+    #[test]
+    fn car_started_re_arm_on_pit_lane_entry() {
+        let mut group = car_started_group("OnPitLaneEntry");
+        let mut sim_state = StartState {
+            engine_running: false,
+            in_pit_lane: true,
+        };
+
+        assert!(
+            run_once(&mut group, &mut sim_state),
+            "Starting the engine should trigger the group"
+        );
+
+        sim_state.engine_running = false;
+        group.update(&sim_state);
+
+        assert!(
+            !run_once(&mut group, &mut sim_state),
+            "Stopping the engine, or staying in the pit lane, should not re-arm the group"
+        );
+
+        sim_state.in_pit_lane = false;
+        group.update(&sim_state);
+        sim_state.in_pit_lane = true;
+        group.update(&sim_state);
+
+        assert!(
+            run_once(&mut group, &mut sim_state),
+            "Entering the pit lane should re-arm the group"
+        );
+    }
+
+    #[test]
+    fn update_paths_agree() {
+        let mut flags = SimState::new();
+        flags.inner.white = true;
+
+        let mut old = GroupState::new(container(true));
+        let mut new = GroupState::new(container(true));
+
+        for _ in 0..3 {
+            old.update(&flags);
+            new.update_ctx(&UpdateContext::new(&flags));
+
+            assert_eq!(
+                old.leds().collect::<Vec<_>>(),
+                new.leds().collect::<Vec<_>>(),
+                "Updating the group with a context should produce the same LEDs as the old update"
+            );
+        }
+
+        let mut state = GroupState::new(container(true));
+        let effect: &mut dyn LedEffect = &mut state;
+        effect.update_ctx(&UpdateContext::new(&flags));
+
+        assert_eq!(
+            old.leds().collect::<Vec<_>>(),
+            state.leds().collect::<Vec<_>>(),
+            "Updating the group through the trait should produce the same LEDs"
+        );
+    }
+
+    #[test]
+    fn pausing_the_group() {
+        let mut flags = SimState::new();
+        flags.inner.white = true;
+
+        let delay = Duration::from_millis(500);
+        let start = Instant::now();
+
+        let mut boxed = GroupState::root(profile());
+        let mut static_group = GroupState::<Effect>::from_profile_at(profile(), NonZeroUsize::MIN);
+
+        boxed.update_ctx(&UpdateContext::at(&flags, start));
+        static_group.update_ctx(&UpdateContext::at(&flags, start));
+
+        boxed.pause(start + delay / 2);
+        static_group.pause(start + delay / 2);
+
+        let resumed = start + delay * 10;
+
+        boxed.resume(resumed);
+        static_group.resume(resumed);
+
+        for (now, expected, message) in [
+            (
+                resumed + delay / 4,
+                [leds![1; "White"; 3], leds![14; "White"; 3]],
+                "The flags should still be in the first half of the blink cycle",
+            ),
+            (
+                resumed + delay / 2,
+                [leds![1; off; 3], leds![14; off; 3]],
+                "The flags should have finished the blink cycle they were paused in",
+            ),
+        ] {
+            boxed.update_ctx(&UpdateContext::at(&flags, now));
+            static_group.update_ctx(&UpdateContext::at(&flags, now));
+
+            crate::assert_led_states_eq!(boxed.leds(), &expected, "{message}");
+            crate::assert_led_states_eq!(static_group.leds(), &expected, "{message}");
+        }
+    }
+
+    #[test]
+    fn syncing_blink_phases() {
+        let blinking = |start_position, color: &str| {
+            let mut container = flag_container(start_position, 2);
+            container["Color"] = color.into();
+            container["ContainerType"] = format!("{color}FlagContainer").into();
+            container["BlinkEnabled"] = true.into();
+            container["BlinkDelay"] = 50.into();
+            container
+        };
+
+        let mut state = GroupState::root(flags_profile(vec![
+            blinking(1, "White"),
+            blinking(3, "Yellow"),
+        ]));
+
+        let delay = Duration::from_millis(50);
+        let start = Instant::now();
+        let mut flags = SimState::new();
+
+        flags.inner.white = true;
+        state.update_ctx(&UpdateContext::at(&flags, start));
+
+        flags.inner.yellow = true;
+        state.update_ctx(&UpdateContext::at(&flags, start + delay / 2));
+        state.update_ctx(&UpdateContext::at(&flags, start + delay));
+
+        crate::assert_led_states_eq!(
+            state.leds(),
+            &[leds![1; off; 2], leds![3; "Yellow"; 2]],
+            "The flags should blink out of phase since they started waving at different times"
+        );
+
+        let synced = start + delay * 3 / 2;
+        state.sync_blink_phases(synced);
+
+        for (now, expected, message) in [
+            (
+                synced,
+                [leds![1; "White"; 2], leds![3; "Yellow"; 2]],
+                "Both flags should be turned on after their blink phases were synced",
+            ),
+            (
+                synced + delay,
+                [leds![1; off; 2], leds![3; off; 2]],
+                "Both flags should turn off at the same time",
+            ),
+            (
+                synced + delay * 2,
+                [leds![1; "White"; 2], leds![3; "Yellow"; 2]],
+                "Both flags should turn on again at the same time",
+            ),
+        ] {
+            state.update_ctx(&UpdateContext::at(&flags, now));
+            crate::assert_led_states_eq!(state.leds(), &expected, "{message}");
+        }
+    }
+
+    #[test]
+    fn car_started_progress() {
+        use std::{cell::RefCell, rc::Rc};
+
+        #[derive(Debug)]
+        struct ProgressEffect {
+            progress: Rc<RefCell<Vec<f64>>>,
+        }
+
+        impl LedEffect for ProgressEffect {
+            fn leds(&self) -> Box<dyn Iterator<Item = &LedState> + '_> {
+                Box::new(std::iter::empty())
+            }
+
+            fn update(&mut self, _: &dyn Moment) {
+                unreachable!("The children of a car started group should receive the progress")
+            }
+
+            fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+                let progress = ctx
+                    .progress
+                    .expect("The children of a car started group should receive the progress");
+                self.progress.borrow_mut().push(progress);
+            }
+
+            fn disable(&mut self) {}
+
+            fn start_led(&self) -> NonZeroUsize {
+                NonZeroUsize::MIN
+            }
+
+            fn description(&self) -> &str {
+                ""
+            }
+        }
+
+        let container = json!({
+            "LedContainers": [],
+            "Duration": 1000,
+            "StartPosition": 1,
+            "IsEnabled": true
+        });
+
+        let mut group = GroupState::new(GroupContainer::CarStarted(
+            serde_json::from_value(container)
+                .expect("We should be able to deserialize the car started group"),
+        ));
+        let progress = Rc::new(RefCell::new(Vec::new()));
+        group.states.push(Box::new(ProgressEffect {
+            progress: progress.clone(),
+        }));
+
+        let sim_state = StartState {
+            engine_running: true,
+            in_pit_lane: false,
+        };
+
+        let start = Instant::now();
+
+        for elapsed in [0, 250, 500, 900, 1000, 1200] {
+            group.update_ctx(&UpdateContext::at(
+                &sim_state,
+                start + Duration::from_millis(elapsed),
+            ));
+        }
+
+        assert_eq!(
+            *progress.borrow(),
+            vec![0.0, 0.25, 0.5, 0.9],
+            "The children should receive the elapsed fraction of the duration until it expires"
+        );
+    }
+
+    #[test]
+    fn car_started_never_re_arm() {
+        let mut group = car_started_group("Never");
+        let mut sim_state = StartState {
+            engine_running: false,
+            in_pit_lane: false,
+        };
+
+        assert!(
+            run_once(&mut group, &mut sim_state),
+            "Starting the engine should trigger the group"
+        );
+
+        sim_state.engine_running = false;
+        sim_state.in_pit_lane = true;
+        group.update(&sim_state);
+
+        assert!(
+            !run_once(&mut group, &mut sim_state),
+            "The group should never be re-armed"
+        );
+    }
+
+    /// Load the profile into a root [`GroupState`], sweep the RPM through the points of the
+    /// expected frames, and compare the rendered LEDs to the expected frame at each point.
+    ///
+    /// Every frame is a pair of the RPM and the expected LEDs, the number of rendered LEDs is
+    /// taken from the expected LEDs.
+    fn assert_profile_sweep(
+        profile: LedProfile,
+        max_rpm: f64,
+        expected_frames: &[(f64, LedState)],
+    ) {
+        let mut group = GroupState::root(profile);
+        let mut sim_state = RpmSimState::new(0.0, max_rpm);
+
+        for (rpm, expected) in expected_frames {
+            sim_state.update_rpm(*rpm);
+            group.update(&sim_state);
+
+            assert_eq!(
+                group.render(expected.leds().len()),
+                expected.leds(),
+                "The rendered LEDs don't match the expected frame at {rpm} RPM"
+            );
+        }
+    }
+
+    #[test]
+    fn rpm_sweep_profile() {
+        const MAX_RPM: f64 = 8000.0;
+
+        let profile: LedProfile = serde_json::from_str(include_str!(
+            "../../../tests/fixtures/profiles/rpm_sweep.json"
+        ))
+        .expect("We should be able to deserialize the RPM sweep profile");
+
+        assert_profile_sweep(
+            profile,
+            MAX_RPM,
+            &[
+                (MAX_RPM * 0.50, leds![off; 8]),
+                (
+                    MAX_RPM * 0.75,
+                    leds![off, off, off, off, off, "yellow", "yellow", off],
+                ),
+                (
+                    MAX_RPM * 0.84,
+                    leds!["lime", off, off, off, off, "yellow", "yellow", off],
+                ),
+                (
+                    MAX_RPM * 0.87,
+                    leds![
+                        "lime",
+                        (0.25, 0.75, 0.0),
+                        off,
+                        off,
+                        off,
+                        "yellow",
+                        "yellow",
+                        off
+                    ],
+                ),
+                (
+                    MAX_RPM * 0.91,
+                    leds![
+                        "lime",
+                        (0.25, 0.75, 0.0),
+                        (0.5, 0.5, 0.0),
+                        off,
+                        off,
+                        "yellow",
+                        "yellow",
+                        "blue"
+                    ],
+                ),
+                (
+                    MAX_RPM * 0.96,
+                    leds![
+                        "lime",
+                        (0.25, 0.75, 0.0),
+                        (0.5, 0.5, 0.0),
+                        (0.75, 0.25, 0.0),
+                        "red",
+                        "yellow",
+                        "yellow",
+                        "blue"
+                    ],
+                ),
+                (MAX_RPM * 0.50, leds![off; 8]),
+            ],
+        );
+    }
 }
@@ -0,0 +1,192 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+
+use csscolorparser::Color;
+use simetry::Moment;
+
+use super::{LedConfiguration, LedEffect, LedState, UpdateContext};
+
+/// An ambient rainbow animation which is shown while no telemetry is being received, so the
+/// LEDs don't stay frozen on the last frame of the profile.
+#[derive(Debug)]
+pub struct IdleLedState {
+    state: LedState,
+    /// How long it takes for a color to travel through the whole rainbow.
+    cycle: Duration,
+    started: Instant,
+}
+
+impl IdleLedState {
+    pub fn new(start_position: NonZeroUsize, led_count: NonZeroUsize, cycle: Duration) -> Self {
+        Self {
+            state: LedState::new(start_position, led_count),
+            cycle,
+            started: Instant::now(),
+        }
+    }
+
+    /// Set the LEDs to the colors of the animation after the given amount of time has passed
+    /// since the animation was started.
+    fn update_at(&mut self, elapsed: Duration) {
+        let led_count = self.state.leds.len() as f64;
+        let progress = if self.cycle.is_zero() {
+            0.0
+        } else {
+            elapsed.as_secs_f64() / self.cycle.as_secs_f64()
+        };
+
+        for (led_number, led) in self.state.leds.iter_mut().enumerate() {
+            // Spread the rainbow over all the LEDs and rotate it as time passes.
+            let hue = ((progress + led_number as f64 / led_count) * 360.0) % 360.0;
+
+            *led = LedConfiguration::on(Color::from_hsva(hue, 1.0, 1.0, 1.0));
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.update_at(self.started.elapsed())
+    }
+
+    pub fn state(&self) -> &LedState {
+        &self.state
+    }
+}
+
+impl LedEffect for IdleLedState {
+    fn update(&mut self, _: &dyn Moment) {
+        self.update()
+    }
+
+    fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        self.update_at(ctx.now.saturating_duration_since(self.started))
+    }
+
+    fn start_led(&self) -> NonZeroUsize {
+        self.state.start_position()
+    }
+
+    fn description(&self) -> &str {
+        "Rainbow animation shown while no telemetry is received"
+    }
+
+    fn leds(&self) -> Box<dyn Iterator<Item = &LedState> + '_> {
+        Box::new(std::iter::once(&self.state))
+    }
+
+    fn disable(&mut self) {
+        for led in &mut self.state.leds {
+            *led = LedConfiguration::Off;
+        }
+    }
+}
+
+/// Keeps track of when telemetry was last received, to decide when the [`IdleLedState`] should
+/// take over from the LED profile.
+#[derive(Debug)]
+pub struct IdleDetector {
+    timeout: Duration,
+    last_data: Instant,
+}
+
+impl IdleDetector {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            last_data: Instant::now(),
+        }
+    }
+
+    /// Record that telemetry was received at the given point in time.
+    pub fn data_received(&mut self, now: Instant) {
+        self.last_data = now;
+    }
+
+    /// Get the point in time the last telemetry was received.
+    pub fn last_data(&self) -> Instant {
+        self.last_data
+    }
+
+    /// Has no telemetry been received for longer than the timeout at the given point in time?
+    pub fn is_idle(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.last_data) >= self.timeout
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use similar_asserts::assert_eq;
+
+    use crate::led;
+
+    use super::*;
+
+    #[test]
+    fn rainbow() {
+        let mut idle = IdleLedState::new(
+            NonZeroUsize::MIN,
+            NonZeroUsize::new(3).unwrap(),
+            Duration::from_secs(3),
+        );
+
+        idle.update_at(Duration::ZERO);
+        assert_eq!(
+            idle.state.leds(),
+            [led!("red"), led!("lime"), led!("blue")],
+            "The rainbow should be spread over all the LEDs"
+        );
+
+        idle.update_at(Duration::from_secs(1));
+        assert_eq!(
+            idle.state.leds(),
+            [led!("lime"), led!("blue"), led!("red")],
+            "The rainbow should rotate as time passes"
+        );
+    }
+
+    #[test]
+    fn detector() {
+        let timeout = Duration::from_secs(2);
+        let start = Instant::now();
+
+        let mut detector = IdleDetector::new(timeout);
+        detector.data_received(start);
+
+        assert!(
+            !detector.is_idle(start + Duration::from_secs(1)),
+            "The detector shouldn't be idle before the timeout expires"
+        );
+        assert!(
+            detector.is_idle(start + timeout),
+            "The detector should be idle once no data was received for the whole timeout"
+        );
+
+        detector.data_received(start + Duration::from_secs(3));
+        assert!(
+            !detector.is_idle(start + Duration::from_secs(4)),
+            "Receiving data should switch back from the idle state"
+        );
+        assert!(detector.is_idle(start + Duration::from_secs(5)));
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::{num::NonZeroUsize, time::Instant};
+
+use csscolorparser::Color;
+use simetry::Moment;
+
+use super::{LedConfiguration, LedEffect, LedState, UpdateContext};
+
+/// LED effect which inverts the output of another effect.
+///
+/// Every LED the wrapped effect turns on is turned off and vice versa. Since LEDs which are turned
+/// off don't have a color, the LEDs which get turned on use the configured color.
+#[derive(Debug)]
+pub struct InvertEffect<E> {
+    inner: E,
+    color: Color,
+    states: Vec<LedState>,
+}
+
+impl<E: LedEffect> InvertEffect<E> {
+    /// Wrap the given effect, LEDs the effect turns off will be turned on using the given color.
+    pub fn new(inner: E, color: Color) -> Self {
+        let mut effect = Self {
+            inner,
+            color,
+            states: Vec::new(),
+        };
+
+        effect.invert();
+
+        effect
+    }
+
+    /// Get a reference to the effect whose output is being inverted.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    fn invert_led(&self, led: &LedConfiguration) -> LedConfiguration {
+        match led {
+            LedConfiguration::On { .. } => LedConfiguration::Off,
+            LedConfiguration::Off => LedConfiguration::on(self.color.clone()),
+        }
+    }
+
+    fn invert(&mut self) {
+        let states = self
+            .inner
+            .leds()
+            .map(|state| {
+                let leds = state
+                    .leds()
+                    .iter()
+                    .map(|led| self.invert_led(led))
+                    .collect();
+                LedState::from_configs(state.start_position(), leds)
+            })
+            .collect();
+
+        self.states = states;
+    }
+}
+
+impl<E: LedEffect> LedEffect for InvertEffect<E> {
+    fn leds(&self) -> Box<dyn Iterator<Item = &LedState> + '_> {
+        Box::new(self.states.iter())
+    }
+
+    fn update(&mut self, sim_state: &dyn Moment) {
+        self.inner.update(sim_state);
+        self.invert();
+    }
+
+    fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        self.inner.update_ctx(ctx);
+        self.invert();
+    }
+
+    fn disable(&mut self) {
+        self.inner.disable();
+
+        // A disabled effect should turn its LEDs off, instead of turning all of them on.
+        for state in &mut self.states {
+            state.leds_mut().fill(LedConfiguration::Off);
+        }
+    }
+
+    fn pause(&mut self, now: Instant) {
+        self.inner.pause(now)
+    }
+
+    fn resume(&mut self, now: Instant) {
+        self.inner.resume(now)
+    }
+
+    fn sync_blink_phase(&mut self, state_change: Instant) {
+        self.inner.sync_blink_phase(state_change)
+    }
+
+    fn start_led(&self) -> NonZeroUsize {
+        self.inner.start_led()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn led_count(&self) -> usize {
+        self.inner.led_count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use similar_asserts::assert_eq;
+
+    use super::*;
+    use crate::{
+        led::state::rpm::gradient::{
+            test::{container, RpmSimState},
+            RpmLedState,
+        },
+        leds,
+    };
+
+    #[test]
+    fn inverted_rpm_gradient() {
+        const MAX_RPM: f64 = 9000.0;
+
+        let mut sim_state = RpmSimState::new(0.0, MAX_RPM);
+        let mut effect = InvertEffect::new(
+            RpmLedState::new(container()),
+            Color::from_html("blue").unwrap(),
+        );
+
+        assert_eq!(
+            effect.leds().collect::<Vec<_>>(),
+            vec![&leds!["blue"; 5]],
+            "All the LEDs should be on before the first update, since the gradient is off"
+        );
+
+        sim_state.update_rpm(MAX_RPM * 0.87);
+        effect.update(&sim_state);
+
+        assert_eq!(
+            effect.inner().leds().collect::<Vec<_>>(),
+            vec![&leds!["lime", off, off, off, off]],
+            "The wrapped gradient should not be modified"
+        );
+        assert_eq!(
+            effect.leds().collect::<Vec<_>>(),
+            vec![&leds![off, "blue", "blue", "blue", "blue"]],
+            "The LED the gradient turned on should be off, the other ones on"
+        );
+
+        sim_state.update_rpm(MAX_RPM);
+        effect.update(&sim_state);
+
+        assert_eq!(
+            effect.leds().collect::<Vec<_>>(),
+            vec![&leds![off; 5]],
+            "All the LEDs should be off once the gradient turned all of them on"
+        );
+
+        sim_state.update_rpm(MAX_RPM * 0.87);
+        effect.update(&sim_state);
+        effect.disable();
+
+        assert_eq!(
+            effect.leds().collect::<Vec<_>>(),
+            vec![&leds![off; 5]],
+            "Disabling the effect should turn all the LEDs off"
+        );
+    }
+}
@@ -19,24 +19,227 @@
 // SOFTWARE.
 
 use csscolorparser::Color;
-use std::{fmt::Debug, num::NonZeroUsize, time::Instant};
+use std::{
+    fmt::Debug,
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
 
 use simetry::Moment;
 
+use crate::led::profiles::LedProfile;
+
+pub mod assist;
+pub mod effect;
 pub mod flag;
 pub mod groups;
+pub mod idle;
+pub mod invert;
+pub mod offset;
 pub mod rpm;
+pub mod speed_limiter;
 
 pub trait LedEffect: Debug {
     fn leds(&self) -> Box<dyn Iterator<Item = &LedState> + '_>;
     fn update(&mut self, sim_state: &dyn Moment);
+
+    /// Update the effect using the given [`UpdateContext`].
+    ///
+    /// Effects which only need the state of the simulator are updated using
+    /// [`LedEffect::update()`].
+    fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        self.update(ctx.sim_state);
+    }
     fn disable(&mut self);
+
+    /// Freeze the blink timers of the effect, for example because the game has been paused.
+    ///
+    /// Updates which happen while the effect is paused don't advance the blink timers.
+    fn pause(&mut self, _now: Instant) {}
+
+    /// Unfreeze the blink timers of the effect, the time the effect spent paused is not counted
+    /// towards the blink cycle, so the effect continues at the same point of the cycle.
+    fn resume(&mut self, _now: Instant) {}
+
+    /// Restart the blink cycle of the effect at the given instant, see [`BlinkState::synced_to()`].
+    fn sync_blink_phase(&mut self, _state_change: Instant) {}
+
     fn start_led(&self) -> NonZeroUsize;
     fn description(&self) -> &str;
 
     fn led_count(&self) -> usize {
         self.leds().map(|led_state| led_state.leds().len()).sum()
     }
+
+    /// Flatten the current LED states of the effect into the configurations of `total` LEDs,
+    /// starting from the first LED of the device.
+    ///
+    /// Later LED states are drawn on top of earlier ones, LEDs which aren't covered by any LED
+    /// state are off and LEDs past `total` are dropped.
+    fn flattened(&self, total: usize) -> Vec<LedConfiguration> {
+        groups::render_plain(self.leds(), total)
+    }
+}
+
+impl<T: LedEffect + ?Sized> LedEffect for Box<T> {
+    fn leds(&self) -> Box<dyn Iterator<Item = &LedState> + '_> {
+        (**self).leds()
+    }
+
+    fn update(&mut self, sim_state: &dyn Moment) {
+        (**self).update(sim_state)
+    }
+
+    fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        (**self).update_ctx(ctx)
+    }
+
+    fn disable(&mut self) {
+        (**self).disable()
+    }
+
+    fn pause(&mut self, now: Instant) {
+        (**self).pause(now)
+    }
+
+    fn resume(&mut self, now: Instant) {
+        (**self).resume(now)
+    }
+
+    fn sync_blink_phase(&mut self, state_change: Instant) {
+        (**self).sync_blink_phase(state_change)
+    }
+
+    fn start_led(&self) -> NonZeroUsize {
+        (**self).start_led()
+    }
+
+    fn description(&self) -> &str {
+        (**self).description()
+    }
+
+    fn led_count(&self) -> usize {
+        (**self).led_count()
+    }
+
+    fn flattened(&self, total: usize) -> Vec<LedConfiguration> {
+        (**self).flattened(total)
+    }
+}
+
+/// The data which is passed to a [`LedEffect`] when it gets updated.
+#[derive(Clone, Copy)]
+pub struct UpdateContext<'a> {
+    /// The current state of the simulator.
+    pub sim_state: &'a dyn Moment,
+    /// The time of the update, effects should use this instead of [`Instant::now()`] so the time
+    /// can be injected.
+    pub now: Instant,
+    /// The fraction of the duration of a timed animation that has elapsed, going from `0.0` to
+    /// `1.0`. Only set for the effects of a [`groups::GroupCondition::CarStarted`] group.
+    pub progress: Option<f64>,
+    /// The time at which the runner last applied a frame to the output. If the runner doesn't
+    /// keep track of it, every update is assumed to be applied.
+    pub last_applied: Option<Instant>,
+    /// The state of the driver assists, if the telemetry source reports it. The [`Moment`] doesn't
+    /// contain the state of the driver assists, so it needs to be passed in separately, see
+    /// [`MomentSource::driver_assists()`].
+    ///
+    /// [`MomentSource::driver_assists()`]: crate::dashboard::sim_dashboard::MomentSource::driver_assists
+    pub assists: Option<DriverAssists>,
+    /// The configuration shared by all the effects of the profile, if the runner provides one.
+    pub config: Option<&'a SharedConfig>,
+}
+
+/// Configuration which is shared by all the effects of a profile, see
+/// [`UpdateContext::with_config()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedConfig {
+    /// The brightness all the LEDs are rendered with, between `0.0` and `1.0`. It's multiplied
+    /// into the brightness of the individual LEDs.
+    pub brightness: f64,
+}
+
+impl Default for SharedConfig {
+    fn default() -> Self {
+        Self { brightness: 1.0 }
+    }
+}
+
+impl SharedConfig {
+    /// Get the shared configuration of the given profile.
+    ///
+    /// The global brightness of the profile is only used if the profile says so, see
+    /// [`LedProfile::use_profile_brightness`].
+    pub fn from_profile(profile: &LedProfile) -> Self {
+        let brightness = if profile.use_profile_brightness {
+            profile.global_brightness.clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        Self { brightness }
+    }
+}
+
+/// The state of the driver assists of the car, see [`UpdateContext::with_assists()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DriverAssists {
+    /// Is the anti-lock braking system currently engaged.
+    pub abs_active: bool,
+    /// Is the traction control currently engaged.
+    pub tc_active: bool,
+}
+
+impl<'a> UpdateContext<'a> {
+    /// Create a new [`UpdateContext`] for an update happening right now.
+    pub fn new(sim_state: &'a dyn Moment) -> Self {
+        Self::at(sim_state, Instant::now())
+    }
+
+    /// Create a new [`UpdateContext`] for an update happening at the given time.
+    pub fn at(sim_state: &'a dyn Moment, now: Instant) -> Self {
+        Self {
+            sim_state,
+            now,
+            progress: None,
+            last_applied: None,
+            assists: None,
+            config: None,
+        }
+    }
+
+    /// Set the time at which the runner last applied a frame to the output.
+    pub fn with_last_applied(self, last_applied: Instant) -> Self {
+        Self {
+            last_applied: Some(last_applied),
+            ..self
+        }
+    }
+
+    /// Set the configuration shared by all the effects of the profile.
+    pub fn with_config(self, config: &'a SharedConfig) -> Self {
+        Self {
+            config: Some(config),
+            ..self
+        }
+    }
+
+    /// Set the state of the driver assists reported by the telemetry source.
+    pub fn with_assists(self, assists: DriverAssists) -> Self {
+        Self {
+            assists: Some(assists),
+            ..self
+        }
+    }
+
+    /// Set the progress of the timed animation the update is part of.
+    pub fn with_progress(self, progress: f64) -> Self {
+        Self {
+            progress: Some(progress),
+            ..self
+        }
+    }
 }
 
 pub trait MomentExt: Moment {
@@ -69,6 +272,26 @@ pub trait MomentExt: Moment {
 
         !is_starting && rpm.value > 0.0
     }
+
+    /// Is the car in the pit lane, cars for which the telemetry doesn't tell us are considered to
+    /// be on the track.
+    fn is_in_pit_lane(&self) -> bool {
+        self.is_vehicle_in_pit_lane().unwrap_or_default()
+    }
+
+    /// Get the currently selected gear the way it's usually displayed in a car, `R` for the
+    /// reverse gear, `N` for neutral and `-` if the telemetry doesn't tell us the gear.
+    ///
+    /// This is the canonical text form of the gear, anything showing or comparing the gear as
+    /// text should use it instead of formatting [`Moment::vehicle_gear()`] itself.
+    fn gear_label(&self) -> String {
+        match self.vehicle_gear() {
+            Some(gear) if gear < 0 => "R".to_owned(),
+            Some(0) => "N".to_owned(),
+            Some(gear) => gear.to_string(),
+            None => "-".to_owned(),
+        }
+    }
 }
 
 impl<T> MomentExt for T where T: Moment + ?Sized {}
@@ -85,10 +308,112 @@ pub enum BlinkState {
     },
 }
 
+impl BlinkState {
+    /// Advance the blinking by a single frame.
+    ///
+    /// The LEDs stay on for the `on_delay` and off for the `off_delay`. At most one phase change
+    /// happens per call, and the phase only changes once a frame showing it was applied to the
+    /// output, see [`UpdateContext::last_applied`]. This way every phase is shown for at least
+    /// one frame, even if the delays are shorter than the time between two applied frames.
+    pub fn next(
+        self,
+        on_delay: Duration,
+        off_delay: Duration,
+        now: Instant,
+        last_applied: Option<Instant>,
+    ) -> Self {
+        let was_applied = |state_change: Instant| match last_applied {
+            Some(last_applied) => last_applied >= state_change,
+            None => true,
+        };
+
+        match self {
+            BlinkState::NotBlinking => BlinkState::LedsTurnedOn { state_change: now },
+            BlinkState::LedsTurnedOff { state_change } => {
+                if was_applied(state_change)
+                    && now.saturating_duration_since(state_change) >= off_delay
+                {
+                    BlinkState::LedsTurnedOn { state_change: now }
+                } else {
+                    self
+                }
+            }
+            BlinkState::LedsTurnedOn { state_change } => {
+                if was_applied(state_change)
+                    && now.saturating_duration_since(state_change) >= on_delay
+                {
+                    BlinkState::LedsTurnedOff { state_change: now }
+                } else {
+                    self
+                }
+            }
+        }
+    }
+
+    /// Move the time of the last state change forward by the given duration.
+    ///
+    /// This is used to resume a paused effect, the state changes as if the time in between never
+    /// passed.
+    pub fn delayed_by(self, duration: Duration) -> Self {
+        match self {
+            BlinkState::NotBlinking => BlinkState::NotBlinking,
+            BlinkState::LedsTurnedOff { state_change } => BlinkState::LedsTurnedOff {
+                state_change: state_change + duration,
+            },
+            BlinkState::LedsTurnedOn { state_change } => BlinkState::LedsTurnedOn {
+                state_change: state_change + duration,
+            },
+        }
+    }
+
+    /// Restart the blink cycle with the LEDs turned on at the given instant.
+    ///
+    /// Effects which aren't blinking stay that way, this is used to bring the blink cycles of
+    /// multiple effects into the same phase.
+    pub fn synced_to(self, state_change: Instant) -> Self {
+        match self {
+            BlinkState::NotBlinking => BlinkState::NotBlinking,
+            BlinkState::LedsTurnedOff { .. } | BlinkState::LedsTurnedOn { .. } => {
+                BlinkState::LedsTurnedOn { state_change }
+            }
+        }
+    }
+}
+
+/// Keeps track of when an effect was paused, see [`LedEffect::pause()`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PauseState {
+    paused_at: Option<Instant>,
+}
+
+impl PauseState {
+    /// Mark the effect as paused, pausing an already paused effect keeps the original time.
+    pub fn pause(&mut self, now: Instant) {
+        self.paused_at.get_or_insert(now);
+    }
+
+    /// Mark the effect as running again, returns how long the effect was paused.
+    pub fn resume(&mut self, now: Instant) -> Duration {
+        self.paused_at
+            .take()
+            .map(|paused_at| now.saturating_duration_since(paused_at))
+            .unwrap_or_default()
+    }
+
+    /// Get the time the blink timers of the effect should use, the timers stand still while the
+    /// effect is paused.
+    pub fn now(&self, now: Instant) -> Instant {
+        self.paused_at.unwrap_or(now)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct LedState {
     start_position: NonZeroUsize,
     leds: Vec<LedConfiguration>,
+    /// Should the LEDs which are turned off let the LEDs of the effects below this one show
+    /// through, instead of turning them off?
+    transparent_when_off: bool,
 }
 
 impl LedState {
@@ -96,13 +421,24 @@ impl LedState {
         Self {
             start_position,
             leds: vec![LedConfiguration::default(); led_count.get()],
+            transparent_when_off: false,
         }
     }
 
     pub fn with_color(color: Color, start_position: NonZeroUsize, led_count: NonZeroUsize) -> Self {
         Self {
             start_position,
-            leds: vec![LedConfiguration::On { color }; led_count.get()],
+            leds: vec![LedConfiguration::on(color); led_count.get()],
+            transparent_when_off: false,
+        }
+    }
+
+    /// Create a new [`LedState`] from a list of LED configurations computed elsewhere.
+    pub fn from_configs(start_position: NonZeroUsize, leds: Vec<LedConfiguration>) -> Self {
+        Self {
+            start_position,
+            leds,
+            transparent_when_off: false,
         }
     }
 
@@ -113,6 +449,58 @@ impl LedState {
     pub fn leds(&self) -> &[LedConfiguration] {
         &self.leds
     }
+
+    /// Get a mutable reference to the LED configurations, to modify them in place.
+    pub fn leds_mut(&mut self) -> &mut [LedConfiguration] {
+        &mut self.leds
+    }
+
+    /// Change the number of LEDs of this [`LedState`], keeping the start position.
+    ///
+    /// The configurations of the LEDs that fit into the new length are preserved, newly added
+    /// LEDs are turned off.
+    pub fn resize(&mut self, led_count: NonZeroUsize) {
+        self.leds.resize(led_count.get(), LedConfiguration::Off);
+    }
+
+    /// Should the LEDs of this state which are turned off be transparent when this state is
+    /// layered on top of other LED states?
+    ///
+    /// By default, a LED which is turned off turns off the LED of the effects below it.
+    pub fn is_transparent_when_off(&self) -> bool {
+        self.transparent_when_off
+    }
+
+    /// Set whether the LEDs of this state which are turned off should be transparent, see
+    /// [`LedState::is_transparent_when_off()`].
+    pub fn set_transparent_when_off(&mut self, transparent: bool) {
+        self.transparent_when_off = transparent;
+    }
+
+    /// Get the LEDs of this state which cover the LEDs below them, together with their zero-based
+    /// index.
+    pub fn covering_leds(&self) -> impl Iterator<Item = (usize, &LedConfiguration)> {
+        let start = self.start_position.get() - 1;
+
+        self.leds
+            .iter()
+            .enumerate()
+            .filter(|(_, led)| !(self.transparent_when_off && **led == LedConfiguration::Off))
+            .map(move |(offset, led)| (start + offset, led))
+    }
+}
+
+/// Sort the given LED states by their start position.
+///
+/// This is useful to compare the LED states of effects without depending on the order the effects
+/// produce them in.
+pub fn sorted_by_start_position<'a>(
+    states: impl IntoIterator<Item = &'a LedState>,
+) -> Vec<&'a LedState> {
+    let mut states: Vec<_> = states.into_iter().collect();
+    states.sort_by_key(|state| state.start_position());
+
+    states
 }
 
 // TODO: This should be an enum with On/Off variants.
@@ -120,56 +508,289 @@ impl LedState {
 pub enum LedConfiguration {
     On {
         color: Color,
+        /// The brightness of the LED, between `0.0` and `1.0`.
+        brightness: f64,
     },
     #[default]
     Off,
 }
 
+impl LedConfiguration {
+    /// Create a LED which is turned on with the given color, using the full brightness.
+    pub fn on(color: Color) -> Self {
+        Self::On {
+            color,
+            brightness: 1.0,
+        }
+    }
+}
+
+/// Create a single [`LedConfiguration`].
+///
+/// The LED can either be turned off, or turned on with a color given as a `(r, g, b)` tuple of
+/// floats or as a HTML color string.
+///
+/// ```
+/// use csscolorparser::Color;
+/// use lmx_cli::{led, led::state::LedConfiguration};
+///
+/// let off = led!(off);
+/// let yellow = led!((0.5, 0.5, 0.0));
+/// let red = led!("red");
+///
+/// assert_eq!(off, LedConfiguration::Off);
+/// assert_eq!(yellow, LedConfiguration::on(Color::new(0.5, 0.5, 0.0, 1.0)));
+/// assert_eq!(red, LedConfiguration::on(Color::new(1.0, 0.0, 0.0, 1.0)));
+/// ```
+#[macro_export]
+macro_rules! led {
+    (off) => {
+        $crate::led::state::LedConfiguration::Off
+    };
+    (($r:expr, $g:expr, $b:expr)) => {
+        $crate::led::state::LedConfiguration::on(::csscolorparser::Color::new($r, $g, $b, 1.0))
+    };
+    ($color:expr) => {
+        $crate::led::state::LedConfiguration::on(
+            ::csscolorparser::Color::from_html($color).unwrap(),
+        )
+    };
+}
+
+/// Create a [`LedState`], the LEDs are defined using the same syntax the [`led!`] macro uses.
+///
+/// The start position of the [`LedState`] can optionally be given as the first argument, followed
+/// by a semicolon, otherwise the first LED is used as the start position.
+///
+/// ```
+/// use lmx_cli::{led, leds};
+///
+/// // Three red LEDs, starting at the first LED.
+/// let red = leds!["red"; 3];
+/// // Five LEDs which are turned off, starting at the fourth LED.
+/// let off = leds![4; off; 5];
+/// // A list of LEDs with different colors, starting at the second LED.
+/// let mixed = leds![2; "lime", (0.5, 0.5, 0.0), off];
+///
+/// assert_eq!(red.start_position().get(), 1);
+/// assert_eq!(red.leds(), [led!("red"), led!("red"), led!("red")]);
+/// assert_eq!(off.start_position().get(), 4);
+/// assert_eq!(off.leds().len(), 5);
+/// assert_eq!(mixed.start_position().get(), 2);
+/// assert_eq!(mixed.leds(), [led!("lime"), led!((0.5, 0.5, 0.0)), led!(off)]);
+/// ```
+#[macro_export]
+macro_rules! leds {
+    ($start_position:expr; $color:tt; $n:expr) => {
+        $crate::led::state::LedState::from_configs(
+            ::std::num::NonZeroUsize::new($start_position)
+                .expect("Invalid start position, must be non-zero"),
+            vec![$crate::led!($color); $n],
+        )
+    };
+
+    ($color:tt; $n:expr) => {
+        $crate::leds![1; $color; $n]
+    };
+
+    ($start_position:expr; $($color:tt),+ $(,)?) => {
+        $crate::led::state::LedState::from_configs(
+            ::std::num::NonZeroUsize::new($start_position)
+                .expect("Invalid start position, must be non-zero"),
+            vec![$($crate::led!($color)),+],
+        )
+    };
+
+    ($($color:tt),+ $(,)?) => {
+        $crate::leds![1; $($color),+]
+    };
+}
+
 #[cfg(test)]
 mod test {
+    use similar_asserts::assert_eq;
+
+    use super::*;
+
+    /// Assert that two collections of [`LedState`]s are equal, independently of the order of the
+    /// LED states.
+    ///
+    /// On failure, the start position of the first mismatching LED state is reported.
     #[macro_export]
-    macro_rules! led {
-        (off) => {
-            $crate::led::state::LedConfiguration::Off
+    macro_rules! assert_led_states_eq {
+        ($left:expr, $right:expr $(,)?) => {
+            $crate::assert_led_states_eq!($left, $right, "The LED states don't match")
         };
-        (($r:expr, $g:expr, $b:expr)) => {
-            $crate::led::state::LedConfiguration::On {
-                color: ::csscolorparser::Color::new($r, $g, $b, 1.0),
-            }
-        };
-        ($color:expr) => {
-            $crate::led::state::LedConfiguration::On {
-                color: ::csscolorparser::Color::from_html($color).unwrap(),
+        ($left:expr, $right:expr, $($arg:tt)+) => {
+            // Match on the expressions so temporaries live until the end of the comparison.
+            match ($left, $right) {
+                (left, right) => {
+                    let left = $crate::led::state::sorted_by_start_position(left);
+                    let right = $crate::led::state::sorted_by_start_position(right);
+
+                    let left_positions: Vec<_> = left.iter().map(|s| s.start_position()).collect();
+                    let right_positions: Vec<_> = right.iter().map(|s| s.start_position()).collect();
+
+                    ::similar_asserts::assert_eq!(
+                        left_positions,
+                        right_positions,
+                        "{}: The LED states don't start at the same positions",
+                        format_args!($($arg)+)
+                    );
+
+                    for (left, right) in left.iter().zip(right.iter()) {
+                        ::similar_asserts::assert_eq!(
+                            left,
+                            right,
+                            "{}: The LED state starting at position {} doesn't match",
+                            format_args!($($arg)+),
+                            left.start_position()
+                        );
+                    }
+                }
             }
         };
     }
 
-    #[macro_export]
-    macro_rules! leds {
-        ($start_position:expr; $color:tt; $n:expr) => {
-            $crate::led::state::LedState {
-                start_position: ::std::num::NonZeroUsize::new($start_position).expect("Invalid start position, must be non-zero"),
-                leds: vec![$crate::led!($color); $n],
-            }
-        };
+    #[test]
+    fn from_configs() {
+        let start_position = NonZeroUsize::new(4).unwrap();
+        let mut state =
+            LedState::from_configs(start_position, vec![led!("red"), led!(off), led!("lime")]);
 
-        ($color:tt; $n:expr) => {
-            leds![1; $color; $n]
-        };
+        assert_eq!(state, leds![4; "red", off, "lime"]);
+
+        state.leds_mut()[1] = led!("blue");
+        state.leds_mut()[2] = LedConfiguration::Off;
+
+        assert_eq!(
+            state,
+            leds![4; "red", "blue", off],
+            "The LEDs should have been modified in place"
+        );
+    }
+
+    #[test]
+    fn grow() {
+        let mut state = leds![3; "red", "lime"];
+
+        state.resize(NonZeroUsize::new(4).unwrap());
 
-        ($start_position:expr; $($color:tt),+ $(,)?) => {{
-            let leds = vec![
-                $($crate::led!($color)),+
-            ];
+        assert_eq!(
+            state,
+            leds![3; "red", "lime", off, off],
+            "The existing LEDs should be kept and the new ones should be turned off"
+        );
+    }
+
+    #[test]
+    fn shrink() {
+        let mut state = leds![3; "red", "lime", "blue"];
+
+        state.resize(NonZeroUsize::new(1).unwrap());
+        assert_eq!(
+            state,
+            leds![3; "red"; 1],
+            "Only the first LED should be kept"
+        );
+
+        state.resize(NonZeroUsize::new(1).unwrap());
+        assert_eq!(
+            state,
+            leds![3; "red"; 1],
+            "Resizing to the same length should be a no-op"
+        );
+    }
 
-            LedState {
-                start_position: ::std::num::NonZeroUsize::new($start_position).expect("Invalid start position, must be non-zero"),
-                leds
+    #[test]
+    fn order_independent_comparison() {
+        let states = [leds![14; "red"; 2], leds![1; off; 3], leds![5; "lime"; 4]];
+        let expected = [leds![1; off; 3], leds![5; "lime"; 4], leds![14; "red"; 2]];
+
+        assert_led_states_eq!(&states, &expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "The LED state starting at position 5 doesn't match")]
+    fn mismatching_start_position_is_reported() {
+        let states = [leds![14; "red"; 2], leds![5; "blue"; 4]];
+        let expected = [leds![5; "lime"; 4], leds![14; "red"; 2]];
+
+        assert_led_states_eq!(&states, &expected);
+    }
+
+    #[test]
+    fn sub_frame_blink_delay() {
+        let delay = Duration::from_micros(1);
+        let frame = Duration::from_millis(16);
+        let start = Instant::now();
+
+        let mut state = BlinkState::NotBlinking;
+        let mut phases = Vec::new();
+
+        for frame_number in 0..6 {
+            state = state.next(delay, delay, start + frame * frame_number, None);
+            phases.push(matches!(state, BlinkState::LedsTurnedOn { .. }));
+        }
+
+        assert_eq!(
+            phases,
+            [true, false, true, false, true, false],
+            "Every phase should be shown for one frame, even if the delay is shorter than a frame"
+        );
+    }
+
+    #[test]
+    fn asymmetric_blink_delay() {
+        let on_delay = Duration::from_millis(10);
+        let off_delay = Duration::from_millis(30);
+        let start = Instant::now();
+
+        let state = BlinkState::NotBlinking.next(on_delay, off_delay, start, None);
+        assert!(matches!(state, BlinkState::LedsTurnedOn { .. }));
+
+        let state = state.next(on_delay, off_delay, start + on_delay, None);
+        assert!(
+            matches!(state, BlinkState::LedsTurnedOff { .. }),
+            "The LEDs should be turned off once the on delay passed"
+        );
+
+        let state = state.next(on_delay, off_delay, start + on_delay * 2, None);
+        assert!(
+            matches!(state, BlinkState::LedsTurnedOff { .. }),
+            "The LEDs should stay off until the off delay passed"
+        );
+
+        let state = state.next(on_delay, off_delay, start + on_delay + off_delay, None);
+        assert!(
+            matches!(state, BlinkState::LedsTurnedOn { .. }),
+            "The LEDs should be turned on again once the off delay passed"
+        );
+    }
+
+    #[test]
+    fn gear_label() {
+        struct GearSimState(Option<i8>);
+
+        impl Moment for GearSimState {
+            fn vehicle_gear(&self) -> Option<i8> {
+                self.0
             }
-        }};
+        }
 
-        ($($color:tt),+ $(,)?) => {{
-            leds![1; $($color),+]
-        }};
+        for (gear, expected) in [
+            (Some(-1), "R"),
+            (Some(0), "N"),
+            (Some(1), "1"),
+            (Some(2), "2"),
+            (Some(7), "7"),
+            (None, "-"),
+        ] {
+            assert_eq!(
+                GearSimState(gear).gear_label(),
+                expected,
+                "The gear {gear:?} should have the label {expected}"
+            );
+        }
     }
 }
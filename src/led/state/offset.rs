@@ -0,0 +1,182 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::{num::NonZeroUsize, time::Instant};
+
+use simetry::Moment;
+
+use super::{LedEffect, LedState, UpdateContext};
+
+/// LED effect which moves the output of another effect further along the device.
+///
+/// The start position of every LED state the wrapped effect produces is increased by the offset,
+/// this allows an effect, or a whole group of effects, to be relocated without rebuilding it.
+#[derive(Debug)]
+pub struct OffsetEffect<E> {
+    inner: E,
+    offset: usize,
+    states: Vec<LedState>,
+}
+
+impl<E: LedEffect> OffsetEffect<E> {
+    /// Wrap the given effect, its LEDs will be moved by the given number of positions.
+    pub fn new(inner: E, offset: usize) -> Self {
+        let mut effect = Self {
+            inner,
+            offset,
+            states: Vec::new(),
+        };
+
+        effect.shift();
+
+        effect
+    }
+
+    /// Get a reference to the effect which is being moved.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Get the number of positions the LEDs of the wrapped effect are moved by.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Move the LEDs of the wrapped effect by a different number of positions.
+    pub fn set_offset(&mut self, offset: usize) {
+        self.offset = offset;
+        self.shift();
+    }
+
+    fn shift(&mut self) {
+        let states = self
+            .inner
+            .leds()
+            .map(|state| {
+                let mut state = state.clone();
+                state.start_position = state.start_position.saturating_add(self.offset);
+                state
+            })
+            .collect();
+
+        self.states = states;
+    }
+}
+
+impl<E: LedEffect> LedEffect for OffsetEffect<E> {
+    fn leds(&self) -> Box<dyn Iterator<Item = &LedState> + '_> {
+        Box::new(self.states.iter())
+    }
+
+    fn update(&mut self, sim_state: &dyn Moment) {
+        self.inner.update(sim_state);
+        self.shift();
+    }
+
+    fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        self.inner.update_ctx(ctx);
+        self.shift();
+    }
+
+    fn disable(&mut self) {
+        self.inner.disable();
+        self.shift();
+    }
+
+    fn pause(&mut self, now: Instant) {
+        self.inner.pause(now)
+    }
+
+    fn resume(&mut self, now: Instant) {
+        self.inner.resume(now)
+    }
+
+    fn sync_blink_phase(&mut self, state_change: Instant) {
+        self.inner.sync_blink_phase(state_change)
+    }
+
+    fn start_led(&self) -> NonZeroUsize {
+        self.inner.start_led().saturating_add(self.offset)
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn led_count(&self) -> usize {
+        self.inner.led_count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use similar_asserts::assert_eq;
+
+    use super::*;
+    use crate::{
+        led::state::rpm::gradient::{
+            test::{container, RpmSimState},
+            RpmLedState,
+        },
+        leds,
+    };
+
+    #[test]
+    fn offset_rpm_gradient() {
+        const MAX_RPM: f64 = 9000.0;
+
+        let mut sim_state = RpmSimState::new(0.0, MAX_RPM);
+        let mut effect = OffsetEffect::new(RpmLedState::new(container()), 3);
+
+        assert_eq!(
+            effect.start_led().get(),
+            4,
+            "The effect should start three LEDs after the wrapped effect"
+        );
+
+        sim_state.update_rpm(MAX_RPM * 0.87);
+        effect.update(&sim_state);
+
+        assert_eq!(
+            effect.inner().leds().collect::<Vec<_>>(),
+            vec![&leds!["lime", off, off, off, off]],
+            "The wrapped gradient should not be modified"
+        );
+        assert_eq!(
+            effect.leds().collect::<Vec<_>>(),
+            vec![&leds![4; "lime", off, off, off, off]],
+            "The LEDs of the gradient should be moved by the offset"
+        );
+
+        effect.set_offset(10);
+
+        assert_eq!(
+            effect.leds().collect::<Vec<_>>(),
+            vec![&leds![11; "lime", off, off, off, off]],
+            "Changing the offset should move the LEDs without an update"
+        );
+
+        assert_eq!(
+            &effect.flattened(16)[9..],
+            leds![off, "lime", off, off, off, off, off].leds(),
+            "The flattened LEDs should be placed at the moved position"
+        );
+    }
+}
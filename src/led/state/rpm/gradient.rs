@@ -19,41 +19,120 @@
 // SOFTWARE.
 
 use colorgrad::{CustomGradient, Gradient};
+use csscolorparser::Color;
 use simetry::Moment;
 use std::num::NonZeroUsize;
 use std::time::Instant;
-use uom::si::{f64::AngularVelocity, ratio::ratio};
+use uom::si::{angular_velocity::revolution_per_minute, f64::AngularVelocity, ratio::ratio};
 
-use crate::led::profiles::rpm::RpmContainer;
-use crate::led::state::{BlinkState, LedConfiguration, LedEffect, LedState, MomentExt};
+use crate::led::profiles::rpm::{AboveMaxBehavior, RpmContainer};
+use crate::led::state::{
+    BlinkState, LedConfiguration, LedEffect, LedState, MomentExt, PauseState, UpdateContext,
+};
 
 // TODO: Support LED dimming, aka the [`RpmContainer::use_led_dimming`] setting.
 
+/// The settings of a [`RpmContainer`] which decide how many LEDs get turned on for a given RPM,
+/// see [`leds_to_turn_on()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RpmFillParams {
+    /// The number of LEDs which can be turned on.
+    pub led_count: usize,
+    /// Are the [`RpmFillParams::min`] and [`RpmFillParams::max`] values percentages of the
+    /// maximum RPM instead of absolute RPM values.
+    pub use_percent: bool,
+    /// The value at which the first LED starts to turn on.
+    pub min: f64,
+    /// The value at which all the LEDs are turned on.
+    pub max: f64,
+}
+
+impl RpmFillParams {
+    /// Get the fill parameters of the given [`RpmContainer`].
+    pub fn new(container: &RpmContainer) -> Self {
+        let (min, max) = if container.use_percent {
+            (
+                container.percent_min.get::<ratio>(),
+                container.percent_max.get::<ratio>(),
+            )
+        } else {
+            (
+                container.rpm_min.get::<revolution_per_minute>(),
+                container.rpm_max.get::<revolution_per_minute>(),
+            )
+        };
+
+        Self {
+            led_count: container.led_count.get(),
+            use_percent: container.use_percent,
+            min,
+            max,
+        }
+    }
+}
+
+/// Calculate how many LEDs should be turned on for the given RPM and maximum RPM of the car, both
+/// in revolutions per minute.
+///
+/// The LEDs are turned on linearly between the minimum and the maximum of the [`RpmFillParams`],
+/// the result is always between zero and [`RpmFillParams::led_count`].
+pub fn leds_to_turn_on(rpm: f64, max_rpm: f64, params: &RpmFillParams) -> usize {
+    let value = fill_value(rpm, max_rpm, params);
+    let fraction = (value - params.min) / (params.max - params.min);
+    let leds = (fraction * params.led_count as f64).floor();
+
+    // A NaN can only happen if both the value and the range are zero, don't turn any LEDs on in
+    // that case.
+    if leds.is_nan() {
+        0
+    } else {
+        leds.clamp(0.0, params.led_count as f64) as usize
+    }
+}
+
+/// Is the given RPM past the maximum of the [`RpmFillParams`], both RPM values are in revolutions
+/// per minute.
+pub fn is_above_max(rpm: f64, max_rpm: f64, params: &RpmFillParams) -> bool {
+    fill_value(rpm, max_rpm, params) > params.max
+}
+
+/// Get the value which is compared to the minimum and maximum of the [`RpmFillParams`].
+///
+/// Some simulators report a maximum RPM of zero before the car is loaded, the percentage is zero
+/// in that case instead of an infinite or NaN value.
+fn fill_value(rpm: f64, max_rpm: f64, params: &RpmFillParams) -> f64 {
+    if params.use_percent {
+        if max_rpm > 0.0 {
+            rpm / max_rpm * 100.0
+        } else {
+            0.0
+        }
+    } else {
+        rpm
+    }
+}
+
 #[derive(Debug)]
 pub struct RpmLedState {
     container: RpmContainer,
-    gradient: Gradient,
+    /// The colors of the gradient, one for every position on the gradient, computed once so the
+    /// gradient doesn't need to be evaluated for every LED on every update.
+    gradient_colors: Vec<Color>,
     state: LedState,
     blink_state: BlinkState,
+    pause_state: PauseState,
+    /// The RPM after the low-pass filter was applied, see [`RpmContainer::rpm_smoothing`].
+    filtered_rpm: Option<AngularVelocity>,
 }
 
 impl RpmLedState {
     pub fn with_start_position(container: RpmContainer, start_position: NonZeroUsize) -> Self {
-        let led_count = container.led_count.get();
-
-        let gradient = CustomGradient::new()
-            .colors(&[container.start_color.clone(), container.end_color.clone()])
-            .domain(&[0.0, (led_count - 1) as f64])
-            .build()
-            .expect(
-                "We should always be able to create a gradient from two parsed Color \
-                 types and a domain that's guaranteed to be at lest 0 -> 0",
-            );
-
         Self {
             state: LedState::new(start_position, container.led_count),
-            gradient,
+            gradient_colors: Self::gradient_colors(&container),
             blink_state: Default::default(),
+            pause_state: PauseState::default(),
+            filtered_rpm: None,
             container,
         }
     }
@@ -64,36 +143,81 @@ impl RpmLedState {
         Self::with_start_position(container, start_position)
     }
 
-    fn calculate_how_many_leds_to_turn_on(
-        &self,
-        rpm: AngularVelocity,
-        max_rpm: AngularVelocity,
-    ) -> usize {
-        let led_count = self.state.leds.len();
+    /// Get the configuration this effect was created from.
+    pub fn container(&self) -> &RpmContainer {
+        &self.container
+    }
 
-        let percentage_of_leds_to_turn_on = if self.container.use_percent {
-            let rpm_percentage = rpm / max_rpm * 100.0;
+    /// Replace the configuration of this effect.
+    ///
+    /// The gradient is rebuilt and the LEDs are resized to match the new [`RpmContainer`], the
+    /// start position of the effect stays the same. The blink state is kept unless the new
+    /// configuration disables blinking. The new configuration will be shown on the next update.
+    pub fn set_container(&mut self, container: RpmContainer) {
+        self.gradient_colors = Self::gradient_colors(&container);
+        self.state.resize(container.led_count);
+
+        if !container.blink_enabled && container.above_max_behavior != AboveMaxBehavior::Blink {
+            self.blink_state = BlinkState::NotBlinking;
+        }
 
-            let percentage_min = self.container.percent_min;
-            let percentage_max = self.container.percent_max;
+        self.container = container;
+    }
 
-            (rpm_percentage - percentage_min) / (percentage_max - percentage_min)
-        } else {
-            let rpm_min = self.container.rpm_min;
-            let rpm_max = self.container.rpm_max;
+    fn gradient_colors(container: &RpmContainer) -> Vec<Color> {
+        let gradient = Self::gradient(container);
 
-            (rpm - rpm_min) / (rpm_max - rpm_min)
-        };
+        (0..container.led_count.get())
+            .map(|position| gradient.at(position as f64))
+            .collect()
+    }
+
+    fn gradient(container: &RpmContainer) -> Gradient {
+        let led_count = container.led_count.get();
+
+        CustomGradient::new()
+            .colors(&[container.start_color.clone(), container.end_color.clone()])
+            .domain(&[0.0, (led_count - 1) as f64])
+            .build()
+            .expect(
+                "We should always be able to create a gradient from two parsed Color \
+                 types and a domain that's guaranteed to be at lest 0 -> 0",
+            )
+    }
+
+    /// Get the color at the given position on the gradient.
+    ///
+    /// Positions past the end of the gradient get the end color, the same as
+    /// [`Gradient::at()`] would return.
+    fn gradient_color(gradient_colors: &[Color], position: usize) -> Color {
+        let last = gradient_colors.len().saturating_sub(1);
+        gradient_colors[position.min(last)].clone()
+    }
 
-        (percentage_of_leds_to_turn_on * led_count as f64)
-            .floor::<ratio>()
-            .get::<ratio>() as usize
+    fn calculate_how_many_leds_to_turn_on(
+        &self,
+        rpm: AngularVelocity,
+        max_rpm: AngularVelocity,
+    ) -> usize {
+        leds_to_turn_on(
+            rpm.get::<revolution_per_minute>(),
+            max_rpm.get::<revolution_per_minute>(),
+            &RpmFillParams::new(&self.container),
+        )
     }
 
-    fn calculate_next_blink_state(&self, sim_state: &dyn Moment) -> BlinkState {
-        let redline_reached = sim_state.redline_reached();
-        let blink_enabled = self.container.blink_enabled;
+    fn is_above_max(&self, rpm: AngularVelocity, max_rpm: AngularVelocity) -> bool {
+        is_above_max(
+            rpm.get::<revolution_per_minute>(),
+            max_rpm.get::<revolution_per_minute>(),
+            &RpmFillParams::new(&self.container),
+        )
+    }
 
+    /// Should the LEDs blink because the redline was reached in the given sim state.
+    ///
+    /// Blinking at the redline takes precedence over the [`RpmContainer::above_max_behavior`].
+    fn blinks_at_redline(&self, sim_state: &dyn Moment) -> bool {
         let blink = if self.container.blink_on_last_gear {
             true
         } else {
@@ -101,70 +225,67 @@ impl RpmLedState {
             sim_state.vehicle_gear() != Some(6)
         };
 
-        if redline_reached && blink_enabled && blink {
-            match &self.blink_state {
-                BlinkState::NotBlinking => BlinkState::LedsTurnedOn {
-                    state_change: Instant::now(),
-                },
-                BlinkState::LedsTurnedOff { state_change } => {
-                    if state_change.elapsed() >= self.container.blink_delay {
-                        BlinkState::LedsTurnedOn {
-                            state_change: Instant::now(),
-                        }
-                    } else {
-                        self.blink_state
-                    }
-                }
-                BlinkState::LedsTurnedOn { state_change } => {
-                    if state_change.elapsed() >= self.container.blink_delay {
-                        BlinkState::LedsTurnedOff {
-                            state_change: Instant::now(),
-                        }
-                    } else {
-                        self.blink_state
-                    }
-                }
-            }
+        sim_state.redline_reached() && self.container.blink_enabled && blink
+    }
+
+    /// Should all the LEDs be turned off because the RPM is above the maximum of the container,
+    /// see [`AboveMaxBehavior::Off`].
+    fn turned_off_above_max(&self, sim_state: &dyn Moment, above_max: bool) -> bool {
+        above_max
+            && self.container.above_max_behavior == AboveMaxBehavior::Off
+            && !self.blinks_at_redline(sim_state)
+    }
+
+    /// Calculate the next blink state, blinking at the redline takes precedence over the
+    /// [`RpmContainer::above_max_behavior`].
+    fn calculate_next_blink_state(
+        &self,
+        sim_state: &dyn Moment,
+        above_max: bool,
+        now: Instant,
+        last_applied: Option<Instant>,
+    ) -> BlinkState {
+        let blink_above_max =
+            above_max && self.container.above_max_behavior == AboveMaxBehavior::Blink;
+
+        if self.blinks_at_redline(sim_state) || blink_above_max {
+            let delay = self.container.blink_delay;
+            self.blink_state.next(delay, delay, now, last_applied)
         } else {
             BlinkState::NotBlinking
         }
     }
 
-    pub fn update(&mut self, sim_state: &dyn Moment) {
-        let Some(rpm) = sim_state.vehicle_engine_rotation_speed() else {
-            return;
-        };
-        let Some(max_rpm) = sim_state.vehicle_max_engine_rotation_speed() else {
-            return;
-        };
+    /// Set the LEDs to the state given by the number of LEDs which should be turned on and the
+    /// state of the blinking.
+    fn fill_leds(
+        container: &RpmContainer,
+        gradient_colors: &[Color],
+        leds: &mut [LedConfiguration],
+        leds_to_turn_on: usize,
+        blink_state: &BlinkState,
+    ) {
+        let led_order = container.fill_direction().led_order(leds.len());
 
-        let next_blink_state = self.calculate_next_blink_state(sim_state);
-        let leds_to_turn_on = self.calculate_how_many_leds_to_turn_on(rpm, max_rpm);
+        for (led_number, &index) in led_order.iter().enumerate() {
+            let led = &mut leds[index];
 
-        let led_iterator: Box<dyn Iterator<Item = &mut LedConfiguration>> =
-            if self.container.right_to_left {
-                Box::new(self.state.leds.iter_mut().rev())
-            } else {
-                Box::new(self.state.leds.iter_mut())
-            };
-
-        for (led_number, led) in led_iterator.enumerate() {
             // If we're using the [`RpmContainer::gradient_on_all`] setting, we're going to pick
             // the color of the active LED that is rightmost on the gradient for all LEDs,
             // otherwise, each LED will get their color from the position on the gradient.
-            let gradient_position = if self.container.gradient_on_all {
+            let gradient_position = if container.gradient_on_all {
                 leds_to_turn_on
             } else {
                 led_number
             };
 
-            let enabled = match next_blink_state {
+            let enabled = match blink_state {
                 BlinkState::NotBlinking => {
                     // If the [`RpmContainer::gradient_on_all`] and [`RpmContainer::fill_all_leds`]
                     // settings are on, then all LEDs will be turned on and only the color of the
                     // LEDs will change. Otherwise, only the LEDs that match a certain RPM value
                     // will be turned on.
-                    if self.container.gradient_on_all && self.container.fill_all_leds {
+                    if container.gradient_on_all && container.fill_all_leds {
                         true
                     } else {
                         led_number < leds_to_turn_on
@@ -175,22 +296,137 @@ impl RpmLedState {
             };
 
             *led = if enabled {
-                let color = self.gradient.at(gradient_position as f64);
-                LedConfiguration::On { color }
+                let color = match (blink_state, &container.shift_flash_color) {
+                    (BlinkState::LedsTurnedOn { .. }, Some(flash_color)) => flash_color.clone(),
+                    _ => Self::gradient_color(gradient_colors, gradient_position),
+                };
+
+                LedConfiguration::on(color)
             } else {
                 LedConfiguration::Off
             };
         }
+    }
+
+    /// Calculate the state the LEDs would have for the given RPM values, without modifying the
+    /// state of the effect.
+    ///
+    /// Blinking isn't taken into account, the returned LEDs are the ones that would be shown if
+    /// the LEDs weren't blinking.
+    pub fn preview(&self, rpm: AngularVelocity, max_rpm: AngularVelocity) -> Vec<LedConfiguration> {
+        let leds_to_turn_on = self.calculate_how_many_leds_to_turn_on(rpm, max_rpm);
+        let mut leds = vec![LedConfiguration::Off; self.state.leds.len()];
+
+        let sim_state = PreviewState { rpm, max_rpm };
+
+        if self.turned_off_above_max(&sim_state, self.is_above_max(rpm, max_rpm)) {
+            return leds;
+        }
+
+        Self::fill_leds(
+            &self.container,
+            &self.gradient_colors,
+            &mut leds,
+            leds_to_turn_on,
+            &BlinkState::NotBlinking,
+        );
+
+        leds
+    }
+
+    /// Apply the exponential moving average filter to the given RPM value, if the filter is
+    /// enabled.
+    fn filter_rpm(&mut self, rpm: AngularVelocity) -> AngularVelocity {
+        let Some(smoothing) = self.container.rpm_smoothing else {
+            return rpm;
+        };
+
+        let smoothing = smoothing.clamp(0.0, 1.0);
+
+        let filtered_rpm = match self.filtered_rpm {
+            Some(previous) => previous + (rpm - previous) * smoothing,
+            None => rpm,
+        };
+
+        self.filtered_rpm = Some(filtered_rpm);
+
+        filtered_rpm
+    }
+
+    pub fn update(&mut self, sim_state: &dyn Moment) {
+        self.update_ctx(&UpdateContext::new(sim_state))
+    }
+
+    pub fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        let sim_state = ctx.sim_state;
+
+        let Some(rpm) = sim_state.vehicle_engine_rotation_speed() else {
+            return;
+        };
+        let rpm = self.filter_rpm(rpm);
+        let Some(max_rpm) = sim_state.vehicle_max_engine_rotation_speed() else {
+            return;
+        };
+
+        let in_neutral_or_reverse = matches!(sim_state.vehicle_gear(), Some(gear) if gear <= 0);
+
+        if self.container.off_in_neutral_and_reverse && in_neutral_or_reverse {
+            self.blink_state = BlinkState::NotBlinking;
+            self.state.leds.fill(LedConfiguration::Off);
+
+            return;
+        }
+
+        let now = self.pause_state.now(ctx.now);
+        let above_max = self.is_above_max(rpm, max_rpm);
+        let next_blink_state =
+            self.calculate_next_blink_state(sim_state, above_max, now, ctx.last_applied);
+        let leds_to_turn_on = self.calculate_how_many_leds_to_turn_on(rpm, max_rpm);
+
+        if self.turned_off_above_max(sim_state, above_max) {
+            self.blink_state = next_blink_state;
+            self.state.leds.fill(LedConfiguration::Off);
+
+            return;
+        }
+
+        Self::fill_leds(
+            &self.container,
+            &self.gradient_colors,
+            &mut self.state.leds,
+            leds_to_turn_on,
+            &next_blink_state,
+        );
 
         self.blink_state = next_blink_state;
     }
 }
 
+/// The sim state used by [`RpmLedState::preview()`], which only knows about the RPM.
+struct PreviewState {
+    rpm: AngularVelocity,
+    max_rpm: AngularVelocity,
+}
+
+impl Moment for PreviewState {
+    fn vehicle_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+        Some(self.rpm)
+    }
+
+    fn vehicle_max_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+        Some(self.max_rpm)
+    }
+}
+
 impl LedEffect for RpmLedState {
     fn update(&mut self, sim_state: &dyn Moment) {
         self.update(sim_state)
     }
 
+    fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        self.update_ctx(ctx)
+    }
+
     fn start_led(&self) -> NonZeroUsize {
         self.state.start_position()
     }
@@ -205,41 +441,60 @@ impl LedEffect for RpmLedState {
 
     fn disable(&mut self) {
         self.blink_state = BlinkState::NotBlinking;
+        self.filtered_rpm = None;
 
         for led in &mut self.state.leds {
             *led = LedConfiguration::Off;
         }
     }
 
+    fn pause(&mut self, now: Instant) {
+        self.pause_state.pause(now);
+    }
+
+    fn resume(&mut self, now: Instant) {
+        let paused_for = self.pause_state.resume(now);
+        self.blink_state = self.blink_state.delayed_by(paused_for);
+    }
+
+    fn sync_blink_phase(&mut self, state_change: Instant) {
+        self.blink_state = self.blink_state.synced_to(state_change);
+    }
+
     fn led_count(&self) -> usize {
         self.state.leds.len()
     }
 }
 
 #[cfg(test)]
-mod test {
+pub mod test {
+    use std::time::Duration;
+
     use serde_json::json;
     use similar_asserts::assert_eq;
-    use uom::si::{angular_velocity::revolution_per_minute, f64::AngularVelocity};
+    use uom::si::{
+        angular_velocity::revolution_per_minute,
+        f64::{AngularVelocity, Ratio},
+    };
 
-    use crate::leds;
+    use crate::{led::profiles::rpm::FillDirection, leds};
 
     use super::*;
 
-    struct RpmSimState {
+    pub struct RpmSimState {
         rpm: AngularVelocity,
         max_rpm: AngularVelocity,
     }
 
     impl RpmSimState {
-        fn new(rpm: f64, rpm_max: f64) -> Self {
+        pub fn new(rpm: f64, rpm_max: f64) -> Self {
             Self {
                 rpm: AngularVelocity::new::<revolution_per_minute>(rpm),
                 max_rpm: AngularVelocity::new::<revolution_per_minute>(rpm_max),
             }
         }
 
-        fn update_rpm(&mut self, rpm: f64) {
+        pub fn update_rpm(&mut self, rpm: f64) {
             self.rpm = AngularVelocity::new::<revolution_per_minute>(rpm);
         }
     }
@@ -254,7 +509,7 @@ mod test {
         }
     }
 
-    fn container() -> RpmContainer {
+    pub fn container() -> RpmContainer {
         let container = json!({
             "UsePercent": true,
             "PercentMin": 85.0,
@@ -356,6 +611,62 @@ mod test {
         );
     }
 
+    #[test]
+    fn leds_to_turn_on_table() {
+        let percent = RpmFillParams {
+            led_count: 10,
+            use_percent: true,
+            min: 50.0,
+            max: 100.0,
+        };
+        let absolute = RpmFillParams {
+            led_count: 10,
+            use_percent: false,
+            min: 4000.0,
+            max: 8000.0,
+        };
+        let empty_range = RpmFillParams {
+            min: 8000.0,
+            ..absolute
+        };
+
+        let cases = [
+            // Percentages of the maximum RPM.
+            (0.0, 8000.0, &percent, 0),
+            (3999.0, 8000.0, &percent, 0),
+            (4000.0, 8000.0, &percent, 0),
+            (4399.0, 8000.0, &percent, 0),
+            (4400.0, 8000.0, &percent, 1),
+            (6000.0, 8000.0, &percent, 5),
+            (7999.0, 8000.0, &percent, 9),
+            (8000.0, 8000.0, &percent, 10),
+            (9000.0, 8000.0, &percent, 10),
+            (4000.0, 0.0, &percent, 0),
+            (0.0, 0.0, &percent, 0),
+            // Absolute RPM values, the maximum RPM of the car doesn't matter.
+            (0.0, 8000.0, &absolute, 0),
+            (4000.0, 8000.0, &absolute, 0),
+            (4400.0, 8000.0, &absolute, 1),
+            (6000.0, 0.0, &absolute, 5),
+            (7999.0, 8000.0, &absolute, 9),
+            (8000.0, 8000.0, &absolute, 10),
+            (12000.0, 8000.0, &absolute, 10),
+            (-100.0, 8000.0, &absolute, 0),
+            // A range without any width acts as a switch.
+            (7999.0, 8000.0, &empty_range, 0),
+            (8000.0, 8000.0, &empty_range, 0),
+            (8001.0, 8000.0, &empty_range, 10),
+        ];
+
+        for (rpm, max_rpm, params, expected) in cases {
+            assert_eq!(
+                leds_to_turn_on(rpm, max_rpm, params),
+                expected,
+                "Unexpected number of LEDs for {rpm} RPM out of {max_rpm} RPM with {params:?}"
+            );
+        }
+    }
+
     #[test]
     fn rpm_values() {
         const MAX_RPM: f64 = 9000.0;
@@ -550,6 +861,227 @@ mod test {
         );
     }
 
+    #[test]
+    fn shift_flash_color() {
+        const MAX_RPM: f64 = 9000.0;
+        let mut container = container();
+        container.blink_enabled = true;
+        container.shift_flash_color = Some(Color::from_html("blue").unwrap());
+
+        let mut sim_state = RpmSimState::new(MAX_RPM * 0.9, MAX_RPM);
+        let mut rpm_led_state = RpmLedState::new(container);
+        let start = Instant::now();
+        let delay = rpm_led_state.container.blink_delay;
+
+        rpm_led_state.update_ctx(&UpdateContext::at(&sim_state, start));
+
+        assert_eq!(
+            &leds!["lime", (0.25, 0.75, 0.0), off, off, off],
+            &rpm_led_state.state,
+            "The gradient should be shown below the redline"
+        );
+
+        sim_state.update_rpm(MAX_RPM);
+        rpm_led_state.update_ctx(&UpdateContext::at(&sim_state, start));
+
+        assert_eq!(
+            &leds!["blue"; 5],
+            &rpm_led_state.state,
+            "The flash color should override the gradient on all the LEDs at the redline"
+        );
+
+        rpm_led_state.update_ctx(&UpdateContext::at(&sim_state, start + delay));
+
+        assert_eq!(
+            &leds![off; 5],
+            &rpm_led_state.state,
+            "The LEDs should be turned off between the flashes"
+        );
+
+        rpm_led_state.update_ctx(&UpdateContext::at(&sim_state, start + delay * 2));
+
+        assert_eq!(
+            &leds!["blue"; 5],
+            &rpm_led_state.state,
+            "The LEDs should flash again after the blink delay"
+        );
+
+        sim_state.update_rpm(MAX_RPM * 0.9);
+        rpm_led_state.update_ctx(&UpdateContext::at(&sim_state, start + delay * 3));
+
+        assert_eq!(
+            &leds!["lime", (0.25, 0.75, 0.0), off, off, off],
+            &rpm_led_state.state,
+            "The gradient should come back once the RPM drops below the redline"
+        );
+    }
+
+    #[test]
+    fn preview() {
+        const MAX_RPM: f64 = 9000.0;
+        let mut container = container();
+        container.right_to_left = true;
+
+        let mut sim_state = RpmSimState::new(0.0, MAX_RPM);
+        let mut rpm_led_state = RpmLedState::new(container);
+
+        for percentage in [0.0, 0.86, 0.9, 0.93, 0.95, 1.0] {
+            let rpm = MAX_RPM * percentage;
+
+            let preview = rpm_led_state.preview(
+                AngularVelocity::new::<revolution_per_minute>(rpm),
+                sim_state.max_rpm,
+            );
+
+            assert_eq!(
+                rpm_led_state.state.leds(),
+                &leds![off; 5].leds,
+                "Previewing the LEDs should not modify the state of the effect"
+            );
+
+            sim_state.update_rpm(rpm);
+            rpm_led_state.update(&sim_state);
+
+            assert_eq!(
+                preview, rpm_led_state.state.leds,
+                "The preview should match the LEDs after an update at {rpm} RPM"
+            );
+
+            rpm_led_state.disable();
+        }
+    }
+
+    fn gradient_leds(
+        rpm_led_state: &RpmLedState,
+        positions: &[Option<usize>],
+    ) -> Vec<LedConfiguration> {
+        let gradient = RpmLedState::gradient(&rpm_led_state.container);
+
+        positions
+            .iter()
+            .map(|position| match position {
+                Some(position) => LedConfiguration::on(gradient.at(*position as f64)),
+                None => LedConfiguration::Off,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn center_out() {
+        const MAX_RPM: f64 = 9000.0;
+
+        for (led_count, partially_filled, fully_filled) in [
+            (
+                5,
+                vec![None, Some(1), Some(0), None, None],
+                vec![Some(3), Some(1), Some(0), Some(2), Some(4)],
+            ),
+            (
+                4,
+                vec![None, Some(0), None, None],
+                vec![Some(2), Some(0), Some(1), Some(3)],
+            ),
+        ] {
+            let mut container = container();
+            container.use_percent = false;
+            container.led_count = NonZeroUsize::new(led_count).unwrap();
+            container.fill_direction = Some(FillDirection::CenterOut);
+
+            let mut sim_state = RpmSimState::new(0.0, MAX_RPM);
+            let mut rpm_led_state = RpmLedState::new(container);
+
+            // 40% of the way between the minimum and maximum RPM.
+            sim_state.update_rpm(3900.0);
+            rpm_led_state.update(&sim_state);
+
+            assert_eq!(
+                rpm_led_state.state.leds(),
+                gradient_leds(&rpm_led_state, &partially_filled),
+                "At 40% only the center LEDs should be turned on for {led_count} LEDs",
+            );
+
+            sim_state.update_rpm(8000.0);
+            rpm_led_state.update(&sim_state);
+
+            assert_eq!(
+                rpm_led_state.state.leds(),
+                gradient_leds(&rpm_led_state, &fully_filled),
+                "At 100% all LEDs should be turned on, the gradient should start in the center for \
+                 {led_count} LEDs",
+            );
+        }
+    }
+
+    #[test]
+    fn edges_in() {
+        let mut container = container();
+        container.fill_direction = Some(FillDirection::EdgesIn);
+
+        assert_eq!(container.fill_direction().led_order(5), [0, 4, 1, 3, 2]);
+        assert_eq!(container.fill_direction().led_order(4), [0, 3, 1, 2]);
+
+        container.fill_direction = None;
+        container.right_to_left = true;
+
+        assert_eq!(
+            container.fill_direction(),
+            FillDirection::RightToLeft,
+            "The legacy right to left setting should be used if no fill direction is set"
+        );
+    }
+
+    #[test]
+    fn rpm_smoothing() {
+        const MAX_RPM: f64 = 9000.0;
+
+        // Count how often the number of turned on LEDs changes for a noisy RPM signal that
+        // hovers around the threshold of the third LED.
+        let count_changes = |rpm_smoothing: Option<f64>| {
+            let mut container = container();
+            container.use_percent = false;
+            container.rpm_smoothing = rpm_smoothing;
+
+            let mut sim_state = RpmSimState::new(0.0, MAX_RPM);
+            let mut rpm_led_state = RpmLedState::new(container);
+
+            let mut previous_count = None;
+            let mut changes = 0;
+
+            for step in 0..20 {
+                let noise = if step % 2 == 0 { -300.0 } else { 300.0 };
+                sim_state.update_rpm(5350.0 + noise);
+                rpm_led_state.update(&sim_state);
+
+                let count = rpm_led_state
+                    .state
+                    .leds()
+                    .iter()
+                    .filter(|led| matches!(led, LedConfiguration::On { .. }))
+                    .count();
+
+                if previous_count.is_some_and(|previous| previous != count) {
+                    changes += 1;
+                }
+
+                previous_count = Some(count);
+            }
+
+            changes
+        };
+
+        let unfiltered = count_changes(None);
+        let filtered = count_changes(Some(0.2));
+
+        assert_eq!(
+            unfiltered, 19,
+            "Without smoothing, the leading LED should follow every bit of noise"
+        );
+        assert!(
+            filtered < unfiltered / 4,
+            "Smoothing the RPM should reduce the flickering of the leading LED, {filtered} changes"
+        );
+    }
+
     #[test]
     fn reverse() {
         const MAX_RPM: f64 = 9000.0;
@@ -577,4 +1109,364 @@ mod test {
             "Getting to 0.9 of the MAX RPM should turn on another LED, from the right side",
         );
     }
+
+    #[test]
+    fn read_back_container() {
+        let mut container = container();
+        container.led_count = NonZeroUsize::new(7).unwrap();
+        container.end_color = Color::from_html("blue").unwrap();
+
+        let state = RpmLedState::with_start_position(container, NonZeroUsize::new(3).unwrap());
+
+        assert_eq!(state.container().led_count.get(), 7);
+        assert_eq!(
+            state.container().start_color,
+            Color::from_html("lime").unwrap()
+        );
+        assert_eq!(
+            state.container().end_color,
+            Color::from_html("blue").unwrap(),
+            "The container should be returned as it was passed to the effect"
+        );
+        assert_eq!(
+            state.container().start_position.get(),
+            1,
+            "The start position of the container should not be replaced by the start position \
+             of the effect"
+        );
+    }
+
+    #[test]
+    fn cached_gradient_colors() {
+        for led_count in [1, 2, 5, 16] {
+            let mut container = container();
+            container.led_count = NonZeroUsize::new(led_count).unwrap();
+
+            let gradient = RpmLedState::gradient(&container);
+            let state = RpmLedState::new(container);
+
+            assert_eq!(state.gradient_colors.len(), led_count);
+
+            // Positions past the end of the gradient are used by the `GradientOnAll` setting once
+            // the RPM goes past the maximum.
+            for position in 0..led_count + 3 {
+                assert_eq!(
+                    RpmLedState::gradient_color(&state.gradient_colors, position),
+                    gradient.at(position as f64),
+                    "The cached color at position {position} of a gradient with {led_count} LEDs \
+                     should match the gradient"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn set_container() {
+        const MAX_RPM: f64 = 9000.0;
+        let sim_state = RpmSimState::new(MAX_RPM * 0.95, MAX_RPM);
+        let mut rpm_led_state =
+            RpmLedState::with_start_position(container(), NonZeroUsize::new(4).unwrap());
+
+        rpm_led_state.update(&sim_state);
+
+        assert_eq!(
+            &leds![4; "lime", (0.25, 0.75, 0.0), (0.5, 0.5, 0.0), (0.75, 0.25, 0.0), "red"],
+            &rpm_led_state.state,
+            "Getting to 0.95 of the MAX RPM should turn on all LEDs",
+        );
+
+        let mut container = container();
+        container.led_count = NonZeroUsize::new(3).unwrap();
+        container.start_color = Color::from_html("blue").unwrap();
+        rpm_led_state.set_container(container);
+
+        assert_eq!(rpm_led_state.led_count(), 3);
+        assert_eq!(
+            rpm_led_state.start_led().get(),
+            4,
+            "Replacing the container should not move the effect"
+        );
+
+        rpm_led_state.update(&sim_state);
+
+        assert_eq!(
+            &leds![4; "blue", (0.5, 0.0, 0.5), "red"],
+            &rpm_led_state.state,
+            "The next update should use the new LED count and the new gradient",
+        );
+    }
+
+    #[test]
+    fn set_container_keeps_blinking() {
+        const MAX_RPM: f64 = 9000.0;
+        let mut container = container();
+        container.blink_enabled = true;
+        container.blink_delay = Duration::from_secs(60);
+
+        let sim_state = RpmSimState::new(MAX_RPM, MAX_RPM);
+        let mut rpm_led_state = RpmLedState::new(container.clone());
+
+        rpm_led_state.update(&sim_state);
+
+        let BlinkState::LedsTurnedOn { state_change } = rpm_led_state.blink_state else {
+            panic!("Reaching the redline should start the blinking");
+        };
+
+        container.end_color = Color::from_html("blue").unwrap();
+        rpm_led_state.set_container(container.clone());
+
+        assert!(
+            matches!(
+                rpm_led_state.blink_state,
+                BlinkState::LedsTurnedOn { state_change: s } if s == state_change
+            ),
+            "Changing the colors should not restart the blinking"
+        );
+
+        rpm_led_state.update(&sim_state);
+
+        assert_eq!(
+            &leds![
+                "lime",
+                (0.0, 0.75, 0.25),
+                (0.0, 0.5, 0.5),
+                (0.0, 0.25, 0.75),
+                "blue"
+            ],
+            &rpm_led_state.state,
+            "The blinking LEDs should use the new colors",
+        );
+
+        container.blink_enabled = false;
+        rpm_led_state.set_container(container);
+
+        assert!(
+            matches!(rpm_led_state.blink_state, BlinkState::NotBlinking),
+            "Disabling blinking should stop the blinking"
+        );
+    }
+
+    #[test]
+    fn zero_max_rpm() {
+        let sim_state = RpmSimState::new(4000.0, 0.0);
+
+        let mut container = container();
+        container.percent_min = Ratio::new::<ratio>(0.0);
+        let mut rpm_led_state = RpmLedState::new(container);
+
+        rpm_led_state.update(&sim_state);
+
+        assert_eq!(
+            &leds![off; 5],
+            &rpm_led_state.state,
+            "All the LEDs should stay off if the simulator reports a maximum RPM of zero"
+        );
+    }
+
+    /// The LEDs of the default [`container()`] with all of them turned on.
+    fn full_gradient() -> LedState {
+        leds![
+            "lime",
+            (0.25, 0.75, 0.0),
+            (0.5, 0.5, 0.0),
+            (0.75, 0.25, 0.0),
+            "red"
+        ]
+    }
+
+    #[test]
+    fn above_max_hold_on() {
+        const MAX_RPM: f64 = 9000.0;
+        let sim_state = RpmSimState::new(MAX_RPM * 0.96, MAX_RPM);
+
+        assert!(
+            !sim_state.redline_reached(),
+            "96% of the MAX RPM should be above the maximum of the container but below the redline"
+        );
+
+        let mut rpm_led_state = RpmLedState::new(container());
+        let start = Instant::now();
+
+        for now in [start, start + Duration::from_millis(250)] {
+            rpm_led_state.update_ctx(&UpdateContext::at(&sim_state, now));
+
+            assert_eq!(
+                &full_gradient(),
+                &rpm_led_state.state,
+                "All the LEDs should stay on above the maximum by default"
+            );
+        }
+    }
+
+    #[test]
+    fn above_max_blink() {
+        const MAX_RPM: f64 = 9000.0;
+        let mut sim_state = RpmSimState::new(MAX_RPM * 0.96, MAX_RPM);
+
+        let mut container = container();
+        container.above_max_behavior = AboveMaxBehavior::Blink;
+        let mut rpm_led_state = RpmLedState::new(container);
+        let start = Instant::now();
+
+        for (now, expected, message) in [
+            (
+                start,
+                full_gradient(),
+                "The LEDs should be turned on at the start of the blink cycle",
+            ),
+            (
+                start + Duration::from_millis(250),
+                leds![off; 5],
+                "The LEDs should be turned off after the blink delay",
+            ),
+            (
+                start + Duration::from_millis(500),
+                full_gradient(),
+                "The LEDs should be turned on again after another blink delay",
+            ),
+        ] {
+            rpm_led_state.update_ctx(&UpdateContext::at(&sim_state, now));
+            assert_eq!(&expected, &rpm_led_state.state, "{message}");
+        }
+
+        sim_state.update_rpm(MAX_RPM * 0.94);
+        rpm_led_state.update_ctx(&UpdateContext::at(
+            &sim_state,
+            start + Duration::from_millis(750),
+        ));
+
+        assert!(
+            matches!(rpm_led_state.blink_state, BlinkState::NotBlinking),
+            "The LEDs should stop blinking once the RPM drops below the maximum"
+        );
+    }
+
+    #[test]
+    fn above_max_off() {
+        const MAX_RPM: f64 = 9000.0;
+        let mut sim_state = RpmSimState::new(MAX_RPM * 0.96, MAX_RPM);
+
+        let mut container = container();
+        container.above_max_behavior = AboveMaxBehavior::Off;
+        let mut rpm_led_state = RpmLedState::new(container);
+
+        rpm_led_state.update(&sim_state);
+
+        assert_eq!(
+            &leds![off; 5],
+            &rpm_led_state.state,
+            "All the LEDs should be turned off above the maximum"
+        );
+
+        assert_eq!(
+            rpm_led_state.preview(sim_state.rpm, sim_state.max_rpm),
+            vec![LedConfiguration::Off; 5],
+            "The preview should turn the LEDs off above the maximum as well"
+        );
+
+        sim_state.update_rpm(MAX_RPM * 0.95);
+        rpm_led_state.update(&sim_state);
+
+        assert_eq!(
+            &full_gradient(),
+            &rpm_led_state.state,
+            "All the LEDs should be on when the RPM is exactly at the maximum"
+        );
+    }
+
+    #[test]
+    fn above_max_off_at_redline() {
+        const MAX_RPM: f64 = 9000.0;
+        let sim_state = RpmSimState::new(MAX_RPM, MAX_RPM);
+
+        let mut container = container();
+        container.above_max_behavior = AboveMaxBehavior::Off;
+        container.blink_enabled = true;
+        let mut rpm_led_state = RpmLedState::new(container);
+
+        rpm_led_state.update(&sim_state);
+
+        assert_eq!(
+            &full_gradient(),
+            &rpm_led_state.state,
+            "Blinking at the redline should take precedence over turning the LEDs off"
+        );
+        assert_eq!(
+            rpm_led_state.preview(sim_state.rpm, sim_state.max_rpm),
+            full_gradient().leds(),
+            "The preview should give the redline the same precedence"
+        );
+    }
+
+    #[test]
+    fn off_in_neutral_and_reverse() {
+        struct GearSimState {
+            rpm: RpmSimState,
+            gear: i8,
+        }
+
+        impl Moment for GearSimState {
+            fn vehicle_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+                self.rpm.vehicle_engine_rotation_speed()
+            }
+
+            fn vehicle_max_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+                self.rpm.vehicle_max_engine_rotation_speed()
+            }
+
+            fn vehicle_gear(&self) -> Option<i8> {
+                Some(self.gear)
+            }
+        }
+
+        const MAX_RPM: f64 = 9000.0;
+        let mut sim_state = GearSimState {
+            rpm: RpmSimState::new(MAX_RPM * 0.95, MAX_RPM),
+            gear: 0,
+        };
+
+        let mut rpm_led_state = RpmLedState::new(container());
+        rpm_led_state.update(&sim_state);
+
+        assert_eq!(
+            rpm_led_state
+                .state
+                .leds()
+                .iter()
+                .filter(|led| **led != LedConfiguration::Off)
+                .count(),
+            5,
+            "The LEDs should be turned on in neutral if the option isn't enabled"
+        );
+
+        let mut container = container();
+        container.off_in_neutral_and_reverse = true;
+        let mut rpm_led_state = RpmLedState::new(container);
+
+        for gear in [0, -1] {
+            sim_state.gear = gear;
+            rpm_led_state.update(&sim_state);
+
+            assert_eq!(
+                &leds![off; 5],
+                &rpm_led_state.state,
+                "The LEDs should stay off at high RPM in gear {gear}"
+            );
+        }
+
+        sim_state.gear = 3;
+        rpm_led_state.update(&sim_state);
+
+        assert_eq!(
+            &leds![
+                "lime",
+                (0.25, 0.75, 0.0),
+                (0.5, 0.5, 0.0),
+                (0.75, 0.25, 0.0),
+                "red"
+            ],
+            &rpm_led_state.state,
+            "The LEDs should be turned on again once a gear is engaged"
+        );
+    }
 }
@@ -19,3 +19,4 @@
 // SOFTWARE.
 
 pub mod gradient;
+pub mod segments;
@@ -0,0 +1,498 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+
+use simetry::Moment;
+use uom::si::{
+    f64::{AngularVelocity, Ratio},
+    ratio::ratio,
+};
+
+use crate::led::profiles::rpm::{LedSegment, RpmSegmentsContainer};
+use crate::led::state::{
+    BlinkState, LedConfiguration, LedEffect, LedState, PauseState, UpdateContext,
+};
+
+/// How a single segment of a [`RpmSegmentsContainer`] should blink.
+#[derive(Debug, Clone, Copy)]
+struct BlinkConfiguration {
+    /// How long the LEDs of the segment stay on and off when blinking.
+    delay: Duration,
+    blink_state: BlinkState,
+}
+
+impl BlinkConfiguration {
+    fn next_blink_state(
+        &self,
+        blinking: bool,
+        now: Instant,
+        last_applied: Option<Instant>,
+    ) -> BlinkState {
+        if !blinking {
+            return BlinkState::NotBlinking;
+        }
+
+        self.blink_state
+            .next(self.delay, self.delay, now, last_applied)
+    }
+}
+
+#[derive(Debug)]
+struct SegmentState {
+    segment: LedSegment,
+    state: LedState,
+    blink: BlinkConfiguration,
+}
+
+impl SegmentState {
+    fn update(
+        &mut self,
+        rpm_percentage: Ratio,
+        blink_enabled: bool,
+        now: Instant,
+        last_applied: Option<Instant>,
+    ) {
+        let enabled = rpm_percentage >= self.segment.start_value;
+        let blinking = blink_enabled && rpm_percentage >= self.segment.end_value;
+
+        let next_blink_state = self.blink.next_blink_state(blinking, now, last_applied);
+
+        let color = match next_blink_state {
+            BlinkState::NotBlinking if enabled => Some(&self.segment.normal_color),
+            BlinkState::NotBlinking | BlinkState::LedsTurnedOff { .. } => None,
+            BlinkState::LedsTurnedOn { .. } => {
+                if self.segment.use_blinking_color {
+                    Some(&self.segment.blinking_color)
+                } else {
+                    Some(&self.segment.normal_color)
+                }
+            }
+        };
+
+        for led in &mut self.state.leds {
+            *led = match color {
+                Some(color) => LedConfiguration::on(color.clone()),
+                None => LedConfiguration::Off,
+            };
+        }
+
+        self.blink.blink_state = next_blink_state;
+    }
+
+    fn disable(&mut self) {
+        self.blink.blink_state = BlinkState::NotBlinking;
+
+        for led in &mut self.state.leds {
+            *led = LedConfiguration::Off;
+        }
+    }
+}
+
+/// LED effect which turns on segments of LEDs based on the RPM of the engine.
+///
+/// A segment is turned on once the RPM reaches the [`LedSegment::start_value`] percentage of the
+/// maximum RPM, and starts to blink once the [`LedSegment::end_value`] percentage is reached.
+/// Every segment blinks using its own timer.
+#[derive(Debug)]
+pub struct RpmSegmentsLedState {
+    container: RpmSegmentsContainer,
+    segments: Vec<SegmentState>,
+    pause_state: PauseState,
+}
+
+impl RpmSegmentsLedState {
+    /// Create the effect, placing the segments one after the other starting at the given
+    /// position.
+    ///
+    /// Segments which would be placed past [`usize::MAX`] are capped at it, such containers are
+    /// reported with a [`BuildWarningKind::PositionOverflow`] when the effects of a profile are
+    /// built.
+    ///
+    /// [`BuildWarningKind::PositionOverflow`]: crate::led::state::groups::BuildWarningKind::PositionOverflow
+    pub fn with_start_position(
+        container: RpmSegmentsContainer,
+        start_position: NonZeroUsize,
+    ) -> Self {
+        let mut segment_position = start_position;
+
+        let segments = container
+            .segments
+            .iter()
+            .map(|segment| {
+                let mut state = LedState::new(segment_position, segment.led_count);
+                state.set_transparent_when_off(container.transparent_when_off);
+                segment_position = segment_position.saturating_add(segment.led_count.get());
+
+                SegmentState {
+                    segment: segment.clone(),
+                    state,
+                    blink: BlinkConfiguration {
+                        delay: segment.blink_delay_or(container.blink_delay),
+                        blink_state: BlinkState::default(),
+                    },
+                }
+            })
+            .collect();
+
+        Self {
+            container,
+            segments,
+            pause_state: PauseState::default(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn new(container: RpmSegmentsContainer) -> Self {
+        let start_position = container.start_position;
+        Self::with_start_position(container, start_position)
+    }
+
+    /// Get the configuration this effect was created from.
+    pub fn container(&self) -> &RpmSegmentsContainer {
+        &self.container
+    }
+
+    fn blink_enabled(&self, sim_state: &dyn Moment) -> bool {
+        let blink = if self.container.blink_on_last_gear {
+            true
+        } else {
+            // TODO: How do we figure out what max gear the car supports?
+            sim_state.vehicle_gear() != Some(6)
+        };
+
+        self.container.blink_enabled && blink
+    }
+
+    /// Get the RPM as a percentage of the given maximum RPM, a maximum RPM of zero, which some
+    /// simulators report before the car is loaded, results in 0%.
+    fn rpm_percentage(rpm: AngularVelocity, max_rpm: AngularVelocity) -> Ratio {
+        if max_rpm.value > 0.0 {
+            rpm / max_rpm * 100.0
+        } else {
+            Ratio::new::<ratio>(0.0)
+        }
+    }
+
+    pub fn update(&mut self, sim_state: &dyn Moment) {
+        self.update_ctx(&UpdateContext::new(sim_state))
+    }
+
+    pub fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        let sim_state = ctx.sim_state;
+
+        let Some(rpm) = sim_state.vehicle_engine_rotation_speed() else {
+            return;
+        };
+        let Some(max_rpm) = sim_state.vehicle_max_engine_rotation_speed() else {
+            return;
+        };
+
+        let reference_rpm = if self.container.relative_to_redline {
+            sim_state.shift_point().unwrap_or(max_rpm)
+        } else {
+            max_rpm
+        };
+
+        let rpm_percentage = Self::rpm_percentage(rpm, reference_rpm);
+        let blink_enabled = self.blink_enabled(sim_state);
+
+        let now = self.pause_state.now(ctx.now);
+
+        for segment in &mut self.segments {
+            segment.update(rpm_percentage, blink_enabled, now, ctx.last_applied);
+        }
+    }
+}
+
+impl LedEffect for RpmSegmentsLedState {
+    fn update(&mut self, sim_state: &dyn Moment) {
+        self.update(sim_state)
+    }
+
+    fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        self.update_ctx(ctx)
+    }
+
+    fn start_led(&self) -> NonZeroUsize {
+        self.segments
+            .first()
+            .map(|s| s.state.start_position())
+            .unwrap_or(self.container.start_position)
+    }
+
+    fn description(&self) -> &str {
+        &self.container.description
+    }
+
+    fn leds(&self) -> Box<dyn Iterator<Item = &LedState> + '_> {
+        Box::new(self.segments.iter().map(|s| &s.state))
+    }
+
+    fn disable(&mut self) {
+        for segment in &mut self.segments {
+            segment.disable();
+        }
+    }
+
+    fn pause(&mut self, now: Instant) {
+        self.pause_state.pause(now);
+    }
+
+    fn resume(&mut self, now: Instant) {
+        let paused_for = self.pause_state.resume(now);
+
+        for segment in &mut self.segments {
+            segment.blink.blink_state = segment.blink.blink_state.delayed_by(paused_for);
+        }
+    }
+
+    fn sync_blink_phase(&mut self, state_change: Instant) {
+        for segment in &mut self.segments {
+            segment.blink.blink_state = segment.blink.blink_state.synced_to(state_change);
+        }
+    }
+
+    fn led_count(&self) -> usize {
+        self.container.total_led_count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use uom::si::angular_velocity::revolution_per_minute;
+
+    use crate::{assert_led_states_eq, leds};
+
+    use super::*;
+
+    struct RpmSimState {
+        rpm: AngularVelocity,
+        max_rpm: AngularVelocity,
+    }
+
+    impl RpmSimState {
+        fn new(rpm: f64, max_rpm: f64) -> Self {
+            Self {
+                rpm: AngularVelocity::new::<revolution_per_minute>(rpm),
+                max_rpm: AngularVelocity::new::<revolution_per_minute>(max_rpm),
+            }
+        }
+    }
+
+    impl Moment for RpmSimState {
+        fn vehicle_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+            Some(self.rpm)
+        }
+
+        fn vehicle_max_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+            Some(self.max_rpm)
+        }
+    }
+
+    fn container() -> RpmSegmentsContainer {
+        let container = json!({
+            "SegmentsCount": 2,
+            "BlinkEnabled": true,
+            "BlinkDelay": 300,
+            "BlinkOnLastGear": true,
+            "StartPosition": 1,
+            "ContainerType": "RPMSegmentsContainer",
+            "Description": "Turn on segments of LEDs based on the RPM",
+            "IsEnabled": true,
+            "Segments": [
+                {
+                    "StartValue": 50.0,
+                    "EndValue": 80.0,
+                    "NormalColor": "Lime",
+                    "BlinkingColor": "Blue",
+                    "UseBlinkingColor": true,
+                    "LedCount": 2,
+                    "BlinkDelay": 100,
+                    "SampleResult": { "Width": 0, "Position": 0, "Columns": 0 }
+                },
+                {
+                    "StartValue": 70.0,
+                    "EndValue": 80.0,
+                    "NormalColor": "Red",
+                    "BlinkingColor": "Red",
+                    "UseBlinkingColor": false,
+                    "LedCount": 3,
+                    "SampleResult": { "Width": 0, "Position": 0, "Columns": 0 }
+                }
+            ]
+        });
+
+        serde_json::from_value(container)
+            .expect("We should be able to deserialize the RPM segments container")
+    }
+
+    #[test]
+    fn segments() {
+        let mut effect = RpmSegmentsLedState::new(container());
+
+        effect.update(&RpmSimState::new(1000.0, 9000.0));
+        assert_led_states_eq!(effect.leds(), &[leds![off; 2], leds![3; off; 3]]);
+
+        effect.update(&RpmSimState::new(5000.0, 9000.0));
+        assert_led_states_eq!(effect.leds(), &[leds!["lime"; 2], leds![3; off; 3]]);
+
+        effect.update(&RpmSimState::new(6500.0, 9000.0));
+        assert_led_states_eq!(effect.leds(), &[leds!["lime"; 2], leds![3; "red"; 3]]);
+    }
+
+    #[test]
+    fn relative_to_redline() {
+        struct RedlineSimState {
+            rpm: RpmSimState,
+            redline: Option<AngularVelocity>,
+        }
+
+        impl Moment for RedlineSimState {
+            fn vehicle_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+                self.rpm.vehicle_engine_rotation_speed()
+            }
+
+            fn vehicle_max_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+                self.rpm.vehicle_max_engine_rotation_speed()
+            }
+
+            fn shift_point(&self) -> Option<AngularVelocity> {
+                self.redline
+            }
+        }
+
+        let sim_state = |rpm: f64, redline: Option<f64>| RedlineSimState {
+            rpm: RpmSimState::new(rpm, 10000.0),
+            redline: redline.map(AngularVelocity::new::<revolution_per_minute>),
+        };
+
+        let mut effect = RpmSegmentsLedState::new(container());
+        effect.update(&sim_state(5600.0, Some(8000.0)));
+
+        assert_led_states_eq!(
+            effect.leds(),
+            &[leds!["lime"; 2], leds![3; off; 3]],
+            "Without the setting, the segments should be relative to the maximum RPM"
+        );
+
+        let mut container = container();
+        container.relative_to_redline = true;
+        let mut effect = RpmSegmentsLedState::new(container);
+
+        effect.update(&sim_state(5600.0, Some(8000.0)));
+        assert_led_states_eq!(
+            effect.leds(),
+            &[leds!["lime"; 2], leds![3; "red"; 3]],
+            "5600 RPM is 70% of the redline, which should turn on the second segment"
+        );
+
+        effect.update(&sim_state(3600.0, Some(8000.0)));
+        assert_led_states_eq!(
+            effect.leds(),
+            &[leds![off; 2], leds![3; off; 3]],
+            "3600 RPM is 45% of the redline, which shouldn't turn on any segment"
+        );
+
+        effect.update(&sim_state(5600.0, None));
+        assert_led_states_eq!(
+            effect.leds(),
+            &[leds!["lime"; 2], leds![3; off; 3]],
+            "Cars without a redline should fall back to the maximum RPM"
+        );
+    }
+
+    #[test]
+    fn zero_max_rpm() {
+        let mut effect = RpmSegmentsLedState::new(container());
+
+        effect.update(&RpmSimState::new(5000.0, 0.0));
+        assert_led_states_eq!(
+            effect.leds(),
+            &[leds![off; 2], leds![3; off; 3]],
+            "All the LEDs should stay off if the simulator reports a maximum RPM of zero"
+        );
+
+        let mut container = container();
+        container.relative_to_redline = true;
+        let mut effect = RpmSegmentsLedState::new(container);
+
+        effect.update(&RpmSimState::new(5000.0, 0.0));
+        assert_led_states_eq!(
+            effect.leds(),
+            &[leds![off; 2], leds![3; off; 3]],
+            "Cars without a redline and a maximum RPM of zero should keep the LEDs off"
+        );
+    }
+
+    #[test]
+    fn read_back_container() {
+        let effect = RpmSegmentsLedState::new(container());
+
+        assert_eq!(effect.container().segments_count, 2);
+        assert_eq!(effect.container().segments.len(), 2);
+        assert_eq!(effect.container().start_position.get(), 1);
+        assert_eq!(
+            effect.container().total_led_count(),
+            effect.led_count(),
+            "The effect should cover all the LEDs of the container"
+        );
+    }
+
+    #[test]
+    fn independent_blink_timers() {
+        let container = container();
+        let container_delay = container.blink_delay;
+        let segment_delay = container.segments[0]
+            .blink_delay
+            .expect("The first segment should have its own blink delay");
+
+        let mut effect = RpmSegmentsLedState::new(container);
+        let sim_state = RpmSimState::new(9000.0, 9000.0);
+        let start = Instant::now();
+
+        effect.update_ctx(&UpdateContext::at(&sim_state, start));
+        assert_led_states_eq!(
+            effect.leds(),
+            &[leds!["blue"; 2], leds![3; "red"; 3]],
+            "Both segments should start blinking in the turned on state"
+        );
+
+        effect.update_ctx(&UpdateContext::at(&sim_state, start + segment_delay));
+        assert_led_states_eq!(
+            effect.leds(),
+            &[leds![off; 2], leds![3; "red"; 3]],
+            "Only the first segment should have been turned off, the second one uses the longer \
+             delay of the container"
+        );
+
+        effect.update_ctx(&UpdateContext::at(&sim_state, start + container_delay));
+        assert_led_states_eq!(
+            effect.leds(),
+            &[leds!["blue"; 2], leds![3; off; 3]],
+            "The first segment should have been turned back on while the second one has now been \
+             turned off"
+        );
+    }
+}
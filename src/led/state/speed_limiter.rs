@@ -0,0 +1,415 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+
+use simetry::Moment;
+
+use crate::led::profiles::speed_limiter::{LimiterBehavior, SpeedLimiterAnimationContainer};
+
+use super::{
+    BlinkState, LedConfiguration, LedEffect, LedState, MomentExt, PauseState, UpdateContext,
+};
+
+#[derive(Debug)]
+pub struct SpeedLimiterLedState {
+    container: SpeedLimiterAnimationContainer,
+    state: LedState,
+    /// The animation switches between two phases, the [`BlinkState::LedsTurnedOn`] state is used
+    /// for the first phase, the [`BlinkState::LedsTurnedOff`] state for the second one.
+    blink_state: BlinkState,
+    /// How many times the sweeping LED moved since the animation started, every phase change
+    /// moves the sweeping LED by one.
+    sweep_step: usize,
+    pause_state: PauseState,
+}
+
+impl SpeedLimiterLedState {
+    pub fn with_start_position(
+        container: SpeedLimiterAnimationContainer,
+        start_position: NonZeroUsize,
+    ) -> Self {
+        Self {
+            state: LedState::new(start_position, container.led_count),
+            container,
+            blink_state: BlinkState::default(),
+            sweep_step: 0,
+            pause_state: PauseState::default(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn new(container: SpeedLimiterAnimationContainer) -> Self {
+        let start_position = container.start_position;
+        Self::with_start_position(container, start_position)
+    }
+
+    /// Get the configuration this effect was created from.
+    pub fn container(&self) -> &SpeedLimiterAnimationContainer {
+        &self.container
+    }
+
+    fn is_active(&self, sim_state: &dyn Moment) -> bool {
+        match self.container.limiter_behavior {
+            LimiterBehavior::AlwaysOn => true,
+            LimiterBehavior::OnWhenLimiterEngaged => {
+                sim_state.is_pit_limiter_engaged().unwrap_or_default()
+            }
+            LimiterBehavior::OnInPitLane => sim_state.is_in_pit_lane(),
+        }
+    }
+
+    fn is_sweeping(&self) -> bool {
+        self.container.use_alternate || self.container.use_alternate_2
+    }
+
+    /// Get the index of the sweeping LED, the LED bounces between the first and the last LED.
+    fn sweep_position(&self) -> usize {
+        let last_led = self.state.leds.len() - 1;
+
+        if last_led == 0 {
+            0
+        } else {
+            let step = self.sweep_step % (2 * last_led);
+            last_led - step.abs_diff(last_led)
+        }
+    }
+
+    fn phase_duration(&self) -> Duration {
+        if self.container.alternate_enabled || self.is_sweeping() {
+            self.container.alternate_delay
+        } else {
+            self.container.blink_color_1_delay
+        }
+    }
+
+    fn calculate_next_blink_state(
+        &self,
+        is_active: bool,
+        now: Instant,
+        last_applied: Option<Instant>,
+    ) -> BlinkState {
+        if !is_active {
+            return BlinkState::NotBlinking;
+        }
+
+        let delay = self.phase_duration();
+
+        self.blink_state.next(delay, delay, now, last_applied)
+    }
+
+    pub fn update(&mut self, sim_state: &dyn Moment) {
+        self.update_ctx(&UpdateContext::new(sim_state))
+    }
+
+    pub fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        let is_active = self.is_active(ctx.sim_state);
+        let now = self.pause_state.now(ctx.now);
+        let next_blink_state = self.calculate_next_blink_state(is_active, now, ctx.last_applied);
+
+        let first_phase = match next_blink_state {
+            BlinkState::NotBlinking => {
+                self.disable();
+                return;
+            }
+            BlinkState::LedsTurnedOn { .. } => true,
+            BlinkState::LedsTurnedOff { .. } => false,
+        };
+
+        self.sweep_step = match self.blink_state {
+            BlinkState::NotBlinking => 0,
+            previous if first_phase != matches!(previous, BlinkState::LedsTurnedOn { .. }) => {
+                self.sweep_step.wrapping_add(1)
+            }
+            _ => self.sweep_step,
+        };
+
+        let is_sweeping = self.is_sweeping();
+        let sweep_position = self.sweep_position();
+
+        for (led_number, led) in self.state.leds.iter_mut().enumerate() {
+            if is_sweeping {
+                *led = if led_number == sweep_position {
+                    LedConfiguration::on(self.container.color_1_alternate.clone())
+                } else if self.container.alternate_2_blank_background {
+                    LedConfiguration::Off
+                } else {
+                    LedConfiguration::on(self.container.color_2_alternate.clone())
+                };
+
+                continue;
+            }
+
+            let color = if self.container.alternate_enabled {
+                // Neighbouring LEDs use different colors, the colors get swapped in every phase.
+                if (led_number % 2 == 0) == first_phase {
+                    &self.container.color_1_alternate
+                } else {
+                    &self.container.color_2_alternate
+                }
+            } else if first_phase {
+                &self.container.color_1_single_color
+            } else {
+                &self.container.color_2_single_color
+            };
+
+            *led = LedConfiguration::on(color.clone());
+        }
+
+        self.blink_state = next_blink_state;
+    }
+}
+
+impl LedEffect for SpeedLimiterLedState {
+    fn update(&mut self, sim_state: &dyn Moment) {
+        self.update(sim_state)
+    }
+
+    fn update_ctx(&mut self, ctx: &UpdateContext<'_>) {
+        self.update_ctx(ctx)
+    }
+
+    fn start_led(&self) -> NonZeroUsize {
+        self.state.start_position()
+    }
+
+    fn description(&self) -> &str {
+        &self.container.description
+    }
+
+    fn leds(&self) -> Box<dyn Iterator<Item = &LedState> + '_> {
+        Box::new(std::iter::once(&self.state))
+    }
+
+    fn disable(&mut self) {
+        self.blink_state = BlinkState::NotBlinking;
+
+        for led in &mut self.state.leds {
+            *led = LedConfiguration::Off;
+        }
+    }
+
+    fn pause(&mut self, now: Instant) {
+        self.pause_state.pause(now);
+    }
+
+    fn resume(&mut self, now: Instant) {
+        let paused_for = self.pause_state.resume(now);
+        self.blink_state = self.blink_state.delayed_by(paused_for);
+    }
+
+    fn sync_blink_phase(&mut self, state_change: Instant) {
+        self.blink_state = self.blink_state.synced_to(state_change);
+    }
+
+    fn led_count(&self) -> usize {
+        self.state.leds.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use similar_asserts::assert_eq;
+
+    use crate::leds;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct LimiterSimState {
+        pit_limiter_engaged: bool,
+    }
+
+    impl Moment for LimiterSimState {
+        fn is_pit_limiter_engaged(&self) -> Option<bool> {
+            Some(self.pit_limiter_engaged)
+        }
+    }
+
+    fn container() -> SpeedLimiterAnimationContainer {
+        let container = json!({
+            "LedCount": 4,
+            "Color1Alternate": "Red",
+            "Color2Alternate": "Blue",
+            "Color1SingleColor": "Yellow",
+            "Color2SingleColor": "Black",
+            "AlternateDelay": 50,
+            "AlternateEnabled": false,
+            "BlinkColor1Delay": 50,
+            "LimiterBehavior": 1,
+            "UseAlternate2": false,
+            "UseAlternate": false,
+            "Alternate2BlanckBackground": false,
+            "StartPosition": 2,
+            "ContainerType": "SpeedLimiterAnimationContainer",
+            "Description": "Speed limiter animation",
+            "IsEnabled": true
+        });
+
+        serde_json::from_value(container)
+            .expect("We should be able to deserialize the speed limiter container")
+    }
+
+    #[test]
+    fn read_back_container() {
+        let state = SpeedLimiterLedState::new(container());
+
+        assert_eq!(state.container().led_count.get(), 4);
+        assert_eq!(state.container().start_position.get(), 2);
+        assert_eq!(state.container().alternate_delay, Duration::from_millis(50));
+    }
+
+    #[test]
+    fn single_color() {
+        let mut sim_state = LimiterSimState::default();
+        let mut state = SpeedLimiterLedState::new(container());
+
+        state.update(&sim_state);
+
+        assert_eq!(
+            &leds![2; off; 4],
+            &state.state,
+            "The LEDs should stay off while the pit limiter isn't engaged"
+        );
+
+        sim_state.pit_limiter_engaged = true;
+        state.update(&sim_state);
+
+        assert_eq!(
+            &leds![2; "yellow"; 4],
+            &state.state,
+            "Engaging the pit limiter should turn on the LEDs using the first color"
+        );
+
+        std::thread::sleep(state.container.blink_color_1_delay);
+        state.update(&sim_state);
+
+        assert_eq!(
+            &leds![2; "black"; 4],
+            &state.state,
+            "The second color should be used after the delay has passed"
+        );
+
+        sim_state.pit_limiter_engaged = false;
+        state.update(&sim_state);
+
+        assert_eq!(
+            &leds![2; off; 4],
+            &state.state,
+            "The LEDs should be turned off once the pit limiter has been disengaged"
+        );
+    }
+
+    #[test]
+    fn alternate() {
+        let mut container = container();
+        container.alternate_enabled = true;
+        container.limiter_behavior = LimiterBehavior::AlwaysOn;
+
+        let sim_state = LimiterSimState::default();
+        let mut state = SpeedLimiterLedState::new(container);
+
+        state.update(&sim_state);
+
+        assert_eq!(&leds![2; "red", "blue", "red", "blue"], &state.state);
+
+        std::thread::sleep(state.container.alternate_delay);
+        state.update(&sim_state);
+
+        assert_eq!(
+            &leds![2; "blue", "red", "blue", "red"],
+            &state.state,
+            "The colors should be swapped after the alternate delay has passed"
+        );
+    }
+
+    #[test]
+    fn sweep() {
+        let mut container = container();
+        container.use_alternate = true;
+        container.limiter_behavior = LimiterBehavior::AlwaysOn;
+
+        let sim_state = LimiterSimState::default();
+        let mut state = SpeedLimiterLedState::new(container);
+        let start = Instant::now();
+
+        let expected = [
+            leds![2; "red", "blue", "blue", "blue"],
+            leds![2; "blue", "red", "blue", "blue"],
+            leds![2; "blue", "blue", "red", "blue"],
+            leds![2; "blue", "blue", "blue", "red"],
+            leds![2; "blue", "blue", "red", "blue"],
+            leds![2; "blue", "red", "blue", "blue"],
+            leds![2; "red", "blue", "blue", "blue"],
+        ];
+
+        for (step, expected) in expected.iter().enumerate() {
+            let now = start + state.container.alternate_delay * step as u32;
+            state.update_ctx(&UpdateContext::at(&sim_state, now));
+
+            assert_eq!(
+                expected, &state.state,
+                "The lit LED should move by one LED every alternate delay, step {step}"
+            );
+        }
+
+        let now = start + state.container.alternate_delay * 6 + Duration::from_millis(10);
+        state.update_ctx(&UpdateContext::at(&sim_state, now));
+
+        assert_eq!(
+            &leds![2; "red", "blue", "blue", "blue"],
+            &state.state,
+            "The lit LED shouldn't move before the alternate delay has passed"
+        );
+    }
+
+    #[test]
+    fn sweep_blank_background() {
+        let mut container = container();
+        container.use_alternate_2 = true;
+        container.alternate_2_blank_background = true;
+        container.limiter_behavior = LimiterBehavior::AlwaysOn;
+
+        let sim_state = LimiterSimState::default();
+        let mut state = SpeedLimiterLedState::new(container);
+        let start = Instant::now();
+
+        state.update_ctx(&UpdateContext::at(&sim_state, start));
+
+        assert_eq!(
+            &leds![2; "red", off, off, off],
+            &state.state,
+            "The LEDs outside of the sweeping LED should be off with a blank background"
+        );
+
+        let now = start + state.container.alternate_delay;
+        state.update_ctx(&UpdateContext::at(&sim_state, now));
+
+        assert_eq!(
+            &leds![2; off, "red", off, off],
+            &state.state,
+            "The lit LED should have moved to the next LED"
+        );
+    }
+}
@@ -18,21 +18,23 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use std::{fs::File, io::BufReader, path::PathBuf};
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context as _, Result};
 use cairo::{Format, ImageSurface};
 use clap::{Parser, Subcommand};
-use led::state::groups::GroupState;
-
-use crate::{
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
+use simetry::assetto_corsa_competizione::Client;
+
+use lmx_cli::{
+    dashboard::{
+        gear_speed::{GearSpeedWidget, SpeedUnit},
+        seven_segment::SevenSegmentStyle,
+    },
     devices::{LmxWheel, USBD480Display},
-    led::profiles::LedProfile,
+    led::{profiles::LedProfile, state::LedConfiguration},
 };
 
-mod devices;
-mod led;
-
 #[derive(Debug, Parser)]
 struct Cli {
     #[command(subcommand)]
@@ -43,15 +45,31 @@ struct Cli {
 enum CliCommand {
     Draw,
     ShowDeviceDetails,
-    GetConfigValue,
-    SetButtonColor { red: u8, green: u8, blue: u8 },
-    SetBrightness { brightness: u8 },
-    RpmTest { profile: PathBuf },
+    GetConfigValue {
+        parameter_id: u16,
+    },
+    GetBrightness,
+    SetButtonColor {
+        red: u8,
+        green: u8,
+        blue: u8,
+    },
+    SetBrightness {
+        brightness: u8,
+    },
+    RpmTest {
+        profile: PathBuf,
+    },
+    /// Run the LED profile on the RPM LEDs and show the gear and speed on the display.
+    Dashboard {
+        profile: PathBuf,
+    },
+    /// Print the state of the RPM LEDs this process knows about.
+    DumpLeds,
 }
 
 fn draw_letter(display: &USBD480Display) -> Result<()> {
-    let width = USBD480Display::WIDTH;
-    let height = USBD480Display::HEIGHT;
+    let Size { width, height } = display.size();
 
     let mut surface = ImageSurface::create(Format::Rgb16_565, width as i32, height as i32)
         .expect("Can't create surface with RGB565 format");
@@ -85,10 +103,55 @@ fn draw_letter(display: &USBD480Display) -> Result<()> {
     Ok(())
 }
 
+/// The widget the dashboard uses to show the gear and speed on the display.
+fn dashboard_widget() -> GearSpeedWidget {
+    GearSpeedWidget {
+        gear_position: Point::new(40, 36),
+        gear_style: SevenSegmentStyle {
+            character_size: Size::new(120, 200),
+            thickness: 20,
+            color: Rgb565::WHITE,
+        },
+        speed_position: Point::new(240, 136),
+        speed_style: SevenSegmentStyle {
+            character_size: Size::new(50, 100),
+            thickness: 10,
+            color: Rgb565::YELLOW,
+        },
+        speed_unit: SpeedUnit::KilometersPerHour,
+    }
+}
+
+/// Load a LED profile, the format of the profile is picked based on the extension of the file.
+///
+/// Files with an unknown extension are parsed as JSON.
+fn load_profile(path: &Path) -> Result<LedProfile> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        #[cfg(feature = "yaml")]
+        Some("yaml" | "yml") => {
+            let profile = std::fs::read_to_string(path).context("Couldn't read the LED profile")?;
+            LedProfile::from_yaml(&profile).context("Could not deserialize the YAML LED profile")
+        }
+        #[cfg(feature = "ron")]
+        Some("ron") => {
+            let profile = std::fs::read_to_string(path).context("Couldn't read the LED profile")?;
+            LedProfile::from_ron(&profile).context("Could not deserialize the RON LED profile")
+        }
+        _ => LedProfile::from_path(path),
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    // Libraries using this crate install their own subscriber, the binary only logs to stderr if
+    // the logging feature is enabled, which it is by default.
+    #[cfg(feature = "logging")]
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+
     let mut lmx = LmxWheel::open()?;
 
     match cli.command {
@@ -102,21 +165,44 @@ async fn main() -> Result<()> {
         CliCommand::SetBrightness { brightness } => {
             lmx.display().set_brightness(brightness)?;
         }
-        CliCommand::GetConfigValue => {
-            lmx.display().get_config_value()?;
+        CliCommand::GetConfigValue { parameter_id } => {
+            let value = lmx.display().get_config_value(parameter_id)?;
+            println!("Config value {parameter_id}: {value}");
+        }
+        CliCommand::GetBrightness => {
+            let brightness = lmx.display().get_brightness()?;
+            println!("Brightness: {brightness}");
         }
         CliCommand::SetButtonColor { red, green, blue } => {
             lmx.buttons().set_color(red, green, blue)?;
         }
+        CliCommand::DumpLeds => {
+            for (number, led) in lmx.rpm_leds().led_configurations().iter().enumerate() {
+                match led {
+                    LedConfiguration::On { color, .. } => {
+                        println!("LED {}: {}", number + 1, color.to_hex_string())
+                    }
+                    LedConfiguration::Off => println!("LED {}: off", number + 1),
+                }
+            }
+        }
         CliCommand::RpmTest { profile } => {
-            let profile = File::open(profile).context("Couldn't open the LED profile")?;
-            let reader = BufReader::new(profile);
-
-            let profile: LedProfile =
-                serde_json::from_reader(reader).context("Could not deserialize the LED profile")?;
-            let root_group = GroupState::root(profile);
+            let profile = load_profile(&profile)?;
 
-            lmx.rpm_leds_mut().run_led_profile(root_group).await?;
+            lmx.rpm_leds_mut().run_led_profile(profile).await?;
+        }
+        CliCommand::Dashboard { profile } => {
+            let profile = load_profile(&profile)?;
+            let client = Client::try_connect()
+                .await
+                .context("Could not connect to the Assetto Corsa Competizione SHM file")?;
+
+            let stats = lmx
+                .into_dashboard(profile, client, dashboard_widget())
+                .run()
+                .await?;
+
+            println!("The game stopped sending data: {stats:?}");
         }
     }
 
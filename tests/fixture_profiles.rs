@@ -0,0 +1,206 @@
+// Copyright (c) 2024 Damir Jelić
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Load the LED profiles in the `tests/fixtures/profiles` directory, build their effects and
+//! render them, this catches breakage across all the container types at once.
+
+use std::path::{Path, PathBuf};
+
+use lmx_cli::led::{
+    profiles::{LedContainer, LedProfile},
+    state::{
+        groups::{BuildWarningKind, GroupState},
+        LedConfiguration,
+    },
+};
+use simetry::{Moment, RacingFlags};
+use similar_asserts::assert_eq;
+use uom::si::{angular_velocity::revolution_per_minute, f64::AngularVelocity};
+
+const MAX_RPM: f64 = 8000.0;
+
+/// The LEDs every fixture profile turns on for the quiet and for the busy sim state of
+/// [`fixture_profiles()`], see [`lit_leds()`].
+const EXPECTED_LEDS: &[(&str, &str, &str)] = &[
+    ("flags.json", "................", "################"),
+    ("pit_lane.json", "############....", "################"),
+    ("rpm_sweep.json", "........", "########"),
+];
+
+#[derive(Default)]
+struct SimState {
+    rpm: f64,
+    flags: RacingFlags,
+    in_pit_lane: bool,
+}
+
+impl Moment for SimState {
+    fn vehicle_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+        Some(AngularVelocity::new::<revolution_per_minute>(self.rpm))
+    }
+
+    fn vehicle_max_engine_rotation_speed(&self) -> Option<AngularVelocity> {
+        Some(AngularVelocity::new::<revolution_per_minute>(MAX_RPM))
+    }
+
+    fn flags(&self) -> Option<RacingFlags> {
+        Some(self.flags.clone())
+    }
+
+    fn is_pit_limiter_engaged(&self) -> Option<bool> {
+        Some(self.in_pit_lane)
+    }
+
+    fn is_vehicle_in_pit_lane(&self) -> Option<bool> {
+        Some(self.in_pit_lane)
+    }
+
+    fn is_starter_on(&self) -> Option<bool> {
+        Some(false)
+    }
+}
+
+fn fixture_paths() -> Vec<PathBuf> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/profiles");
+
+    let mut paths: Vec<_> = std::fs::read_dir(&directory)
+        .expect("We should be able to list the fixture profiles")
+        .map(|entry| {
+            entry
+                .expect("We should be able to read the directory entry")
+                .path()
+        })
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+
+    paths
+}
+
+/// Collect the types of the containers, including the ones nested in groups, which weren't
+/// recognized.
+fn unknown_container_types(containers: &[LedContainer]) -> Vec<String> {
+    containers
+        .iter()
+        .flat_map(|container| match container {
+            LedContainer::Unknown { container_type, .. } => vec![container_type.clone()],
+            LedContainer::Group(group) => unknown_container_types(group.led_containers()),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// Render the profile after updating it with the given sim state, as a string with one character
+/// per LED, `.` for LEDs which are off and `#` for LEDs which are on.
+fn lit_leds(group: &mut GroupState, sim_state: &SimState, led_count: usize) -> String {
+    group.update(sim_state);
+
+    group
+        .render(led_count)
+        .iter()
+        .map(|led| match led {
+            LedConfiguration::Off => '.',
+            LedConfiguration::On { .. } => '#',
+        })
+        .collect()
+}
+
+/// Load every fixture profile, build the effects of the profile and render them for a quiet sim
+/// state, and for a busy one with all the flags waving, the engine at the redline and the car in
+/// the pit lane.
+#[test]
+fn fixture_profiles() {
+    let paths = fixture_paths();
+    assert!(!paths.is_empty(), "There should be some fixture profiles");
+
+    let mut unknown_containers = Vec::new();
+
+    for path in paths {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .expect("The fixture profiles should have UTF-8 file names");
+
+        let profile = LedProfile::from_path(&path)
+            .unwrap_or_else(|e| panic!("The fixture profile {name} should load: {e:?}"));
+
+        let unknown = unknown_container_types(&profile.led_containers);
+
+        if !unknown.is_empty() {
+            unknown_containers.push((name.to_owned(), unknown));
+        }
+
+        let led_count = profile.max_led_index();
+        let (mut group, warnings) = GroupState::root_with_warnings(profile);
+
+        assert_eq!(
+            warnings
+                .iter()
+                .filter(|warning| matches!(
+                    warning.kind,
+                    BuildWarningKind::Unsupported | BuildWarningKind::PositionOverflow
+                ))
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            Vec::<String>::new(),
+            "All the containers of the fixture profile {name} should be turned into effects"
+        );
+
+        let &(_, quiet_leds, busy_leds) = EXPECTED_LEDS
+            .iter()
+            .find(|(fixture, _, _)| *fixture == name)
+            .unwrap_or_else(|| panic!("The expected LEDs of {name} should be in EXPECTED_LEDS"));
+
+        let quiet = SimState {
+            rpm: MAX_RPM / 2.0,
+            ..Default::default()
+        };
+        let busy = SimState {
+            rpm: MAX_RPM,
+            flags: RacingFlags {
+                blue: true,
+                white: true,
+                yellow: true,
+                ..Default::default()
+            },
+            in_pit_lane: true,
+        };
+
+        assert_eq!(
+            lit_leds(&mut group, &quiet, led_count),
+            quiet_leds,
+            "The fixture profile {name} should render the expected LEDs for the quiet sim state"
+        );
+        assert_eq!(
+            lit_leds(&mut group, &busy, led_count),
+            busy_leds,
+            "The fixture profile {name} should render the expected LEDs for the busy sim state"
+        );
+    }
+
+    assert_eq!(
+        unknown_containers,
+        Vec::<(String, Vec<String>)>::new(),
+        "The fixture profiles shouldn't contain any unknown containers"
+    );
+}